@@ -790,6 +790,7 @@ pub struct WorkspaceCommandEnvironment {
     immutable_heads_expression: Rc<UserRevsetExpression>,
     short_prefixes_expression: Option<Rc<UserRevsetExpression>>,
     conflict_marker_style: ConflictMarkerStyle,
+    stdout_term_width: Option<usize>,
 }
 
 impl WorkspaceCommandEnvironment {
@@ -812,6 +813,7 @@ impl WorkspaceCommandEnvironment {
             immutable_heads_expression: RevsetExpression::root(),
             short_prefixes_expression: None,
             conflict_marker_style: settings.get("ui.conflict-marker-style")?,
+            stdout_term_width: ui.stdout_term_width(),
         };
         env.immutable_heads_expression = env.load_immutable_heads_expression(ui)?;
         env.short_prefixes_expression = env.load_short_prefixes_expression(ui)?;
@@ -997,7 +999,7 @@ impl WorkspaceCommandEnvironment {
         repo: &'a dyn Repo,
         id_prefix_context: &'a IdPrefixContext,
     ) -> CommitTemplateLanguage<'a> {
-        CommitTemplateLanguage::new(
+        let mut language = CommitTemplateLanguage::new(
             repo,
             &self.path_converter,
             &self.workspace_name,
@@ -1006,12 +1008,20 @@ impl WorkspaceCommandEnvironment {
             self.immutable_expression(),
             self.conflict_marker_style,
             &self.command.data.commit_template_extensions,
-        )
+        );
+        language.set_term_width(self.stdout_term_width);
+        language
     }
 
     pub fn operation_template_extensions(&self) -> &[Arc<dyn OperationTemplateLanguageExtension>] {
         &self.command.data.operation_template_extensions
     }
+
+    /// Terminal width detected when this environment was created. `None` if
+    /// stdout isn't connected to a terminal and `$COLUMNS` isn't set.
+    pub fn stdout_term_width(&self) -> Option<usize> {
+        self.stdout_term_width
+    }
 }
 
 /// Provides utilities for writing a command that works on a [`Workspace`]
@@ -1767,11 +1777,13 @@ to the current parents may contain changes from multiple commits.
 
     /// Creates operation template language environment for this workspace.
     pub fn operation_template_language(&self) -> OperationTemplateLanguage {
-        OperationTemplateLanguage::new(
+        let mut language = OperationTemplateLanguage::new(
             self.workspace.repo_loader(),
             Some(self.repo().op_id()),
             self.env.operation_template_extensions(),
-        )
+        );
+        language.set_term_width(self.env.stdout_term_width());
+        language
     }
 
     /// Template for one-line summary of a commit.
@@ -657,10 +657,16 @@ impl From<UserRevsetEvaluationError> for CommandError {
 
 impl From<TemplateParseError> for CommandError {
     fn from(err: TemplateParseError) -> Self {
-        let hint = template_parse_error_hint(&err);
+        // The attached hint is also taken from the bottom error, which is
+        // usually the root cause.
+        let bottom_err = iter::successors(Some(&err), |e| e.origin()).last().unwrap();
+        let hints = template_parse_error_hint(&err)
+            .into_iter()
+            .chain(bottom_err.hint().map(ToOwned::to_owned))
+            .collect_vec();
         let mut cmd_err =
             user_error_with_message(format!("Failed to parse template: {}", err.kind()), err);
-        cmd_err.extend_hints(hint);
+        cmd_err.extend_hints(hints);
         cmd_err
     }
 }
@@ -890,6 +896,14 @@ fn template_parse_error_hint(err: &TemplateParseError) -> Option<String> {
         TemplateParseErrorKind::InvalidArguments { .. } | TemplateParseErrorKind::Expression(_) => {
             find_source_parse_error_hint(bottom_err)
         }
+        TemplateParseErrorKind::IncomparableTypes { lhs, rhs } => {
+            let types = (lhs.as_str(), rhs.as_str());
+            matches!(types, ("String", "Integer") | ("Integer", "String")).then(|| {
+                "Use `.parse_int()` to convert the String to Integer, or `.to_string()` to convert \
+                 the Integer to String"
+                    .to_owned()
+            })
+        }
         _ => None,
     }
 }
@@ -79,7 +79,8 @@ pub fn cmd_config_list(
     args: &ConfigListArgs,
 ) -> Result<(), CommandError> {
     let template = {
-        let language = config_template_language(command.settings());
+        let mut language = config_template_language(command.settings());
+        language.set_term_width(ui.stdout_term_width());
         let text = match &args.template {
             Some(value) => value.to_owned(),
             None => command.settings().get_string("templates.config_list")?,
@@ -126,11 +126,12 @@ fn do_op_log(
     let template;
     let op_node_template;
     {
-        let language = OperationTemplateLanguage::new(
+        let mut language = OperationTemplateLanguage::new(
             repo_loader,
             Some(current_op.id()),
             workspace_env.operation_template_extensions(),
         );
+        language.set_term_width(workspace_env.stdout_term_width());
         let text = match &args.template {
             Some(value) => value.to_owned(),
             None => settings.get_string("templates.op_log")?,
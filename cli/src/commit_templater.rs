@@ -20,6 +20,7 @@ use std::io;
 use std::rc::Rc;
 
 use bstr::BString;
+use chrono::TimeDelta;
 use futures::stream::BoxStream;
 use futures::StreamExt as _;
 use futures::TryStreamExt as _;
@@ -27,6 +28,7 @@ use itertools::Itertools as _;
 use jj_lib::backend::BackendResult;
 use jj_lib::backend::ChangeId;
 use jj_lib::backend::CommitId;
+use jj_lib::backend::Timestamp;
 use jj_lib::backend::TreeValue;
 use jj_lib::commit::Commit;
 use jj_lib::conflicts;
@@ -75,7 +77,9 @@ use crate::formatter::Formatter;
 use crate::revset_util;
 use crate::template_builder;
 use crate::template_builder::expect_plain_text_expression;
+use crate::template_builder::make_cached_property_fn;
 use crate::template_builder::merge_fn_map;
+use crate::template_builder::sorted_fn_names;
 use crate::template_builder::BuildContext;
 use crate::template_builder::CoreTemplateBuildFnTable;
 use crate::template_builder::CoreTemplatePropertyKind;
@@ -90,7 +94,10 @@ use crate::template_parser::TemplateParseError;
 use crate::template_parser::TemplateParseResult;
 use crate::templater;
 use crate::templater::BoxedTemplateProperty;
+use crate::templater::ListEnumerated;
+use crate::templater::ListGroup;
 use crate::templater::PlainTextFormattedProperty;
+use crate::templater::PropertyCacheScope;
 use crate::templater::SizeHint;
 use crate::templater::Template;
 use crate::templater::TemplateFormatter;
@@ -120,6 +127,7 @@ pub struct CommitTemplateLanguage<'repo> {
     build_fn_table: CommitTemplateBuildFnTable<'repo>,
     keyword_cache: CommitKeywordCache<'repo>,
     cache_extensions: ExtensionsMap,
+    term_width: Option<usize>,
 }
 
 impl<'repo> CommitTemplateLanguage<'repo> {
@@ -157,8 +165,14 @@ impl<'repo> CommitTemplateLanguage<'repo> {
             build_fn_table,
             keyword_cache: CommitKeywordCache::default(),
             cache_extensions,
+            term_width: None,
         }
     }
+
+    /// Sets the terminal width returned by `term_width()`.
+    pub fn set_term_width(&mut self, width: Option<usize>) {
+        self.term_width = width;
+    }
 }
 
 impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo> {
@@ -168,6 +182,10 @@ impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo> {
         self.repo.base_repo().settings()
     }
 
+    fn term_width(&self) -> Option<usize> {
+        self.term_width
+    }
+
     fn build_function(
         &self,
         diagnostics: &mut TemplateDiagnostics,
@@ -203,6 +221,27 @@ impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo> {
                 let inner_property = property.try_unwrap(type_name).into_dyn();
                 build(self, diagnostics, build_ctx, inner_property, function)
             }
+            CommitTemplatePropertyKind::CommitList(property) if function.name == "group_by" => {
+                template_builder::build_group_by_operation(
+                    self,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    Self::Property::wrap_commit,
+                    Self::Property::wrap_commit_group_list,
+                )
+            }
+            CommitTemplatePropertyKind::CommitList(property) if function.name == "enumerate" => {
+                template_builder::build_enumerate_operation(
+                    self,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    Self::Property::wrap_commit_enumerated_list,
+                )
+            }
             CommitTemplatePropertyKind::CommitList(property) => {
                 // TODO: migrate to table?
                 template_builder::build_unformattable_list_method(
@@ -212,9 +251,58 @@ impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo> {
                     property,
                     function,
                     Self::Property::wrap_commit,
+                    Self::Property::wrap_commit_opt,
                     Self::Property::wrap_commit_list,
                 )
             }
+            CommitTemplatePropertyKind::CommitGroup(property) => {
+                template_builder::build_list_group_method(
+                    type_name,
+                    property,
+                    function,
+                    Self::Property::wrap_commit_list,
+                )
+            }
+            CommitTemplatePropertyKind::CommitGroupList(property) => {
+                template_builder::build_unformattable_list_method(
+                    self,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    Self::Property::wrap_commit_group,
+                    |property| {
+                        let type_name = "Group<Commit>";
+                        Self::Property::wrap_commit_group(property.try_unwrap(type_name).into_dyn())
+                    },
+                    Self::Property::wrap_commit_group_list,
+                )
+            }
+            CommitTemplatePropertyKind::CommitEnumerated(property) => {
+                template_builder::build_list_enumerated_method(
+                    type_name,
+                    property,
+                    function,
+                    Self::Property::wrap_commit,
+                )
+            }
+            CommitTemplatePropertyKind::CommitEnumeratedList(property) => {
+                template_builder::build_unformattable_list_method(
+                    self,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    Self::Property::wrap_commit_enumerated,
+                    |property| {
+                        let type_name = "Enumerated<Commit>";
+                        Self::Property::wrap_commit_enumerated(
+                            property.try_unwrap(type_name).into_dyn(),
+                        )
+                    },
+                    Self::Property::wrap_commit_enumerated_list,
+                )
+            }
             CommitTemplatePropertyKind::CommitRef(property) => {
                 let table = &self.build_fn_table.commit_ref_methods;
                 let build = template_parser::lookup_method(type_name, table, function)?;
@@ -236,6 +324,7 @@ impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo> {
                     property,
                     function,
                     Self::Property::wrap_commit_ref,
+                    Self::Property::wrap_commit_ref_opt,
                     Self::Property::wrap_commit_ref_list,
                 )
             }
@@ -280,6 +369,12 @@ impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo> {
                     property,
                     function,
                     Self::Property::wrap_tree_diff_entry,
+                    |property| {
+                        let type_name = "TreeDiffEntry";
+                        Self::Property::wrap_tree_diff_entry(
+                            property.try_unwrap(type_name).into_dyn(),
+                        )
+                    },
                     Self::Property::wrap_tree_diff_entry_list,
                 )
             }
@@ -331,12 +426,48 @@ impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo> {
                         property,
                         function,
                         Self::Property::wrap_trailer,
+                        |property| {
+                            let type_name = "Trailer";
+                            Self::Property::wrap_trailer(property.try_unwrap(type_name).into_dyn())
+                        },
                         Self::Property::wrap_trailer_list,
                     )
                 }
             }
         }
     }
+
+    fn method_names(&self, type_name: &str) -> Option<Vec<&'static str>> {
+        let table = &self.build_fn_table;
+        let names = match type_name {
+            "Commit" | "Option<Commit>" => sorted_fn_names(&table.commit_methods),
+            "List<Commit>" => {
+                template_builder::list_method_names(false, &["enumerate", "group_by"])
+            }
+            "Group<Commit>" => template_builder::LIST_GROUP_METHOD_NAMES.to_vec(),
+            "List<Group<Commit>>" => template_builder::list_method_names(false, &[]),
+            "Enumerated<Commit>" => template_builder::LIST_ENUMERATED_METHOD_NAMES.to_vec(),
+            "List<Enumerated<Commit>>" => template_builder::list_method_names(false, &[]),
+            "CommitRef" | "Option<CommitRef>" => sorted_fn_names(&table.commit_ref_methods),
+            "List<CommitRef>" => template_builder::list_method_names(true, &[]),
+            "RepoPath" | "Option<RepoPath>" => sorted_fn_names(&table.repo_path_methods),
+            "CommitOrChangeId" => sorted_fn_names(&table.commit_or_change_id_methods),
+            "ShortestIdPrefix" => sorted_fn_names(&table.shortest_id_prefix_methods),
+            "TreeDiff" => sorted_fn_names(&table.tree_diff_methods),
+            "TreeDiffEntry" => sorted_fn_names(&table.tree_diff_entry_methods),
+            "List<TreeDiffEntry>" => template_builder::list_method_names(false, &[]),
+            "TreeEntry" => sorted_fn_names(&table.tree_entry_methods),
+            "DiffStats" => sorted_fn_names(&table.diff_stats_methods),
+            "CryptographicSignature" | "Option<CryptographicSignature>" => {
+                sorted_fn_names(&table.cryptographic_signature_methods)
+            }
+            "AnnotationLine" => sorted_fn_names(&table.annotation_line_methods),
+            "Trailer" => sorted_fn_names(&table.trailer_methods),
+            "List<Trailer>" => template_builder::list_method_names(true, &["contains_key"]),
+            _ => return table.core.method_names(type_name),
+        };
+        Some(names)
+    }
 }
 
 // If we need to add multiple languages that support Commit types, this can be
@@ -364,6 +495,10 @@ pub enum CommitTemplatePropertyKind<'repo> {
     Commit(BoxedTemplateProperty<'repo, Commit>),
     CommitOpt(BoxedTemplateProperty<'repo, Option<Commit>>),
     CommitList(BoxedTemplateProperty<'repo, Vec<Commit>>),
+    CommitGroup(BoxedTemplateProperty<'repo, ListGroup<Commit>>),
+    CommitGroupList(BoxedTemplateProperty<'repo, Vec<ListGroup<Commit>>>),
+    CommitEnumerated(BoxedTemplateProperty<'repo, ListEnumerated<Commit>>),
+    CommitEnumeratedList(BoxedTemplateProperty<'repo, Vec<ListEnumerated<Commit>>>),
     CommitRef(BoxedTemplateProperty<'repo, Rc<CommitRef>>),
     CommitRefOpt(BoxedTemplateProperty<'repo, Option<Rc<CommitRef>>>),
     CommitRefList(BoxedTemplateProperty<'repo, Vec<Rc<CommitRef>>>),
@@ -387,6 +522,10 @@ impl<'repo> CommitTemplatePropertyKind<'repo> {
         pub wrap_commit(Commit) => Commit,
         pub wrap_commit_opt(Option<Commit>) => CommitOpt,
         pub wrap_commit_list(Vec<Commit>) => CommitList,
+        pub wrap_commit_group(ListGroup<Commit>) => CommitGroup,
+        pub wrap_commit_group_list(Vec<ListGroup<Commit>>) => CommitGroupList,
+        pub wrap_commit_enumerated(ListEnumerated<Commit>) => CommitEnumerated,
+        pub wrap_commit_enumerated_list(Vec<ListEnumerated<Commit>>) => CommitEnumeratedList,
         pub wrap_commit_ref(Rc<CommitRef>) => CommitRef,
         pub wrap_commit_ref_opt(Option<Rc<CommitRef>>) => CommitRefOpt,
         pub wrap_commit_ref_list(Vec<Rc<CommitRef>>) => CommitRefList,
@@ -417,6 +556,10 @@ impl<'repo> CoreTemplatePropertyVar<'repo> for CommitTemplatePropertyKind<'repo>
             CommitTemplatePropertyKind::Commit(_) => "Commit",
             CommitTemplatePropertyKind::CommitOpt(_) => "Option<Commit>",
             CommitTemplatePropertyKind::CommitList(_) => "List<Commit>",
+            CommitTemplatePropertyKind::CommitGroup(_) => "Group<Commit>",
+            CommitTemplatePropertyKind::CommitGroupList(_) => "List<Group<Commit>>",
+            CommitTemplatePropertyKind::CommitEnumerated(_) => "Enumerated<Commit>",
+            CommitTemplatePropertyKind::CommitEnumeratedList(_) => "List<Enumerated<Commit>>",
             CommitTemplatePropertyKind::CommitRef(_) => "CommitRef",
             CommitTemplatePropertyKind::CommitRefOpt(_) => "Option<CommitRef>",
             CommitTemplatePropertyKind::CommitRefList(_) => "List<CommitRef>",
@@ -448,6 +591,14 @@ impl<'repo> CoreTemplatePropertyVar<'repo> for CommitTemplatePropertyKind<'repo>
             CommitTemplatePropertyKind::CommitList(property) => {
                 Some(property.map(|l| !l.is_empty()).into_dyn())
             }
+            CommitTemplatePropertyKind::CommitGroup(_) => None,
+            CommitTemplatePropertyKind::CommitGroupList(property) => {
+                Some(property.map(|l| !l.is_empty()).into_dyn())
+            }
+            CommitTemplatePropertyKind::CommitEnumerated(_) => None,
+            CommitTemplatePropertyKind::CommitEnumeratedList(property) => {
+                Some(property.map(|l| !l.is_empty()).into_dyn())
+            }
             CommitTemplatePropertyKind::CommitRef(_) => None,
             CommitTemplatePropertyKind::CommitRefOpt(property) => {
                 Some(property.map(|opt| opt.is_some()).into_dyn())
@@ -488,6 +639,34 @@ impl<'repo> CoreTemplatePropertyVar<'repo> for CommitTemplatePropertyKind<'repo>
         }
     }
 
+    fn try_into_float(self) -> Option<BoxedTemplateProperty<'repo, f64>> {
+        match self {
+            CommitTemplatePropertyKind::Core(property) => property.try_into_float(),
+            _ => None,
+        }
+    }
+
+    fn try_into_timestamp(self) -> Option<BoxedTemplateProperty<'repo, Timestamp>> {
+        match self {
+            CommitTemplatePropertyKind::Core(property) => property.try_into_timestamp(),
+            _ => None,
+        }
+    }
+
+    fn try_into_duration(self) -> Option<BoxedTemplateProperty<'repo, TimeDelta>> {
+        match self {
+            CommitTemplatePropertyKind::Core(property) => property.try_into_duration(),
+            _ => None,
+        }
+    }
+
+    fn try_into_string_list(self) -> Option<BoxedTemplateProperty<'repo, Vec<String>>> {
+        match self {
+            CommitTemplatePropertyKind::Core(property) => property.try_into_string_list(),
+            _ => None,
+        }
+    }
+
     fn try_into_plain_text(self) -> Option<BoxedTemplateProperty<'repo, String>> {
         match self {
             CommitTemplatePropertyKind::Core(property) => property.try_into_plain_text(),
@@ -504,6 +683,10 @@ impl<'repo> CoreTemplatePropertyVar<'repo> for CommitTemplatePropertyKind<'repo>
             CommitTemplatePropertyKind::Commit(_) => None,
             CommitTemplatePropertyKind::CommitOpt(_) => None,
             CommitTemplatePropertyKind::CommitList(_) => None,
+            CommitTemplatePropertyKind::CommitGroup(_) => None,
+            CommitTemplatePropertyKind::CommitGroupList(_) => None,
+            CommitTemplatePropertyKind::CommitEnumerated(_) => None,
+            CommitTemplatePropertyKind::CommitEnumeratedList(_) => None,
             CommitTemplatePropertyKind::CommitRef(property) => Some(property.into_template()),
             CommitTemplatePropertyKind::CommitRefOpt(property) => Some(property.into_template()),
             CommitTemplatePropertyKind::CommitRefList(property) => Some(property.into_template()),
@@ -536,6 +719,10 @@ impl<'repo> CoreTemplatePropertyVar<'repo> for CommitTemplatePropertyKind<'repo>
             (CommitTemplatePropertyKind::Commit(_), _) => None,
             (CommitTemplatePropertyKind::CommitOpt(_), _) => None,
             (CommitTemplatePropertyKind::CommitList(_), _) => None,
+            (CommitTemplatePropertyKind::CommitGroup(_), _) => None,
+            (CommitTemplatePropertyKind::CommitGroupList(_), _) => None,
+            (CommitTemplatePropertyKind::CommitEnumerated(_), _) => None,
+            (CommitTemplatePropertyKind::CommitEnumeratedList(_), _) => None,
             (CommitTemplatePropertyKind::CommitRef(_), _) => None,
             (CommitTemplatePropertyKind::CommitRefOpt(_), _) => None,
             (CommitTemplatePropertyKind::CommitRefList(_), _) => None,
@@ -564,6 +751,10 @@ impl<'repo> CoreTemplatePropertyVar<'repo> for CommitTemplatePropertyKind<'repo>
             (CommitTemplatePropertyKind::Commit(_), _) => None,
             (CommitTemplatePropertyKind::CommitOpt(_), _) => None,
             (CommitTemplatePropertyKind::CommitList(_), _) => None,
+            (CommitTemplatePropertyKind::CommitGroup(_), _) => None,
+            (CommitTemplatePropertyKind::CommitGroupList(_), _) => None,
+            (CommitTemplatePropertyKind::CommitEnumerated(_), _) => None,
+            (CommitTemplatePropertyKind::CommitEnumeratedList(_), _) => None,
             (CommitTemplatePropertyKind::CommitRef(_), _) => None,
             (CommitTemplatePropertyKind::CommitRefOpt(_), _) => None,
             (CommitTemplatePropertyKind::CommitRefList(_), _) => None,
@@ -582,6 +773,88 @@ impl<'repo> CoreTemplatePropertyVar<'repo> for CommitTemplatePropertyKind<'repo>
             (CommitTemplatePropertyKind::TrailerList(_), _) => None,
         }
     }
+
+    fn try_into_cached(self, scope: &PropertyCacheScope) -> Option<Box<dyn Fn() -> Self + 'repo>> {
+        match self {
+            CommitTemplatePropertyKind::Core(property) => {
+                let make = property.try_into_cached(scope)?;
+                Some(Box::new(move || CommitTemplatePropertyKind::Core(make())))
+            }
+            CommitTemplatePropertyKind::Commit(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Commit))
+            }
+            CommitTemplatePropertyKind::CommitOpt(property) => {
+                Some(make_cached_property_fn(property, scope, Self::CommitOpt))
+            }
+            CommitTemplatePropertyKind::CommitList(property) => {
+                Some(make_cached_property_fn(property, scope, Self::CommitList))
+            }
+            CommitTemplatePropertyKind::CommitGroup(property) => {
+                Some(make_cached_property_fn(property, scope, Self::CommitGroup))
+            }
+            CommitTemplatePropertyKind::CommitGroupList(property) => Some(make_cached_property_fn(
+                property,
+                scope,
+                Self::CommitGroupList,
+            )),
+            CommitTemplatePropertyKind::CommitEnumerated(property) => Some(
+                make_cached_property_fn(property, scope, Self::CommitEnumerated),
+            ),
+            CommitTemplatePropertyKind::CommitEnumeratedList(property) => Some(
+                make_cached_property_fn(property, scope, Self::CommitEnumeratedList),
+            ),
+            CommitTemplatePropertyKind::CommitRef(property) => {
+                Some(make_cached_property_fn(property, scope, Self::CommitRef))
+            }
+            CommitTemplatePropertyKind::CommitRefOpt(property) => {
+                Some(make_cached_property_fn(property, scope, Self::CommitRefOpt))
+            }
+            CommitTemplatePropertyKind::CommitRefList(property) => Some(make_cached_property_fn(
+                property,
+                scope,
+                Self::CommitRefList,
+            )),
+            CommitTemplatePropertyKind::RepoPath(property) => {
+                Some(make_cached_property_fn(property, scope, Self::RepoPath))
+            }
+            CommitTemplatePropertyKind::RepoPathOpt(property) => {
+                Some(make_cached_property_fn(property, scope, Self::RepoPathOpt))
+            }
+            CommitTemplatePropertyKind::CommitOrChangeId(property) => Some(
+                make_cached_property_fn(property, scope, Self::CommitOrChangeId),
+            ),
+            // These types aren't cloneable.
+            CommitTemplatePropertyKind::ShortestIdPrefix(_) => None,
+            CommitTemplatePropertyKind::TreeDiff(_) => None,
+            CommitTemplatePropertyKind::TreeDiffEntry(property) => Some(make_cached_property_fn(
+                property,
+                scope,
+                Self::TreeDiffEntry,
+            )),
+            CommitTemplatePropertyKind::TreeDiffEntryList(property) => Some(
+                make_cached_property_fn(property, scope, Self::TreeDiffEntryList),
+            ),
+            CommitTemplatePropertyKind::TreeEntry(property) => {
+                Some(make_cached_property_fn(property, scope, Self::TreeEntry))
+            }
+            CommitTemplatePropertyKind::DiffStats(property) => {
+                Some(make_cached_property_fn(property, scope, Self::DiffStats))
+            }
+            // Not cloneable.
+            CommitTemplatePropertyKind::CryptographicSignatureOpt(_) => None,
+            CommitTemplatePropertyKind::AnnotationLine(property) => Some(make_cached_property_fn(
+                property,
+                scope,
+                Self::AnnotationLine,
+            )),
+            CommitTemplatePropertyKind::Trailer(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Trailer))
+            }
+            CommitTemplatePropertyKind::TrailerList(property) => {
+                Some(make_cached_property_fn(property, scope, Self::TrailerList))
+            }
+        }
+    }
 }
 
 /// Table of functions that translate method call node of self type `T`.
@@ -41,6 +41,15 @@ pub trait Formatter: Write {
     /// already formatted, such as in the graphical log.
     fn raw(&mut self) -> io::Result<Box<dyn Write + '_>>;
 
+    /// Returns the backing `Write` without applying the current labels. This is
+    /// useful for writing plain text inside labeled content.
+    ///
+    /// The default implementation is the same as `raw()`, which is correct for
+    /// formatters that don't translate labels to escape sequences.
+    fn raw_unlabeled(&mut self) -> io::Result<Box<dyn Write + '_>> {
+        self.raw()
+    }
+
     fn push_label(&mut self, label: &str) -> io::Result<()>;
 
     fn pop_label(&mut self) -> io::Result<()>;
@@ -570,6 +579,14 @@ impl<W: Write> Formatter for ColorFormatter<W> {
         Ok(Box::new(self.output.by_ref()))
     }
 
+    fn raw_unlabeled(&mut self) -> io::Result<Box<dyn Write + '_>> {
+        // Reset the style. It will be restored by the next write.
+        let labels = mem::take(&mut self.labels);
+        self.write_new_style()?;
+        self.labels = labels;
+        Ok(Box::new(self.output.by_ref()))
+    }
+
     fn push_label(&mut self, label: &str) -> io::Result<()> {
         self.labels.push(label.to_owned());
         Ok(())
@@ -644,26 +661,45 @@ impl FormatRecorder {
         &self,
         formatter: &mut dyn Formatter,
         mut write_data: impl FnMut(&mut dyn Formatter, Range<usize>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.replay_with_labels(formatter, |formatter, _labels, range| {
+            write_data(formatter, range)
+        })
+    }
+
+    /// Like `replay_with()`, but also passes the labels pushed to the
+    /// `formatter` while writing the data range.
+    pub fn replay_with_labels(
+        &self,
+        formatter: &mut dyn Formatter,
+        mut write_data: impl FnMut(&mut dyn Formatter, &[&str], Range<usize>) -> io::Result<()>,
     ) -> io::Result<()> {
         let mut last_pos = 0;
-        let mut flush_data = |formatter: &mut dyn Formatter, pos| -> io::Result<()> {
+        let mut labels = vec![];
+        let mut flush_data = |formatter: &mut dyn Formatter, labels: &[&str], pos| {
             if last_pos != pos {
-                write_data(formatter, last_pos..pos)?;
+                write_data(formatter, labels, last_pos..pos)?;
                 last_pos = pos;
             }
-            Ok(())
+            io::Result::Ok(())
         };
         for (pos, op) in &self.ops {
-            flush_data(formatter, *pos)?;
+            flush_data(formatter, &labels, *pos)?;
             match op {
-                FormatOp::PushLabel(label) => formatter.push_label(label)?,
-                FormatOp::PopLabel => formatter.pop_label()?,
+                FormatOp::PushLabel(label) => {
+                    formatter.push_label(label)?;
+                    labels.push(label.as_str());
+                }
+                FormatOp::PopLabel => {
+                    formatter.pop_label()?;
+                    labels.pop();
+                }
                 FormatOp::RawEscapeSequence(raw_escape_sequence) => {
                     formatter.raw()?.write_all(raw_escape_sequence)?;
                 }
             }
         }
-        flush_data(formatter, self.data.len())
+        flush_data(formatter, &labels, self.data.len())
     }
 }
 
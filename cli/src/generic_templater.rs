@@ -15,8 +15,13 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
+use bstr::ByteVec as _;
+use chrono::TimeDelta;
+use itertools::Itertools as _;
+use jj_lib::backend::Timestamp;
 use jj_lib::settings::UserSettings;
 
+use crate::formatter::PlainTextFormatter;
 use crate::template_builder;
 use crate::template_builder::BuildContext;
 use crate::template_builder::CoreTemplateBuildFnTable;
@@ -25,10 +30,16 @@ use crate::template_builder::CoreTemplatePropertyVar;
 use crate::template_builder::TemplateLanguage;
 use crate::template_parser;
 use crate::template_parser::FunctionCallNode;
+use crate::template_parser::TemplateAliasesMap;
 use crate::template_parser::TemplateDiagnostics;
+use crate::template_parser::TemplateParseError;
+use crate::template_parser::TemplateParseErrorKind;
 use crate::template_parser::TemplateParseResult;
 use crate::templater::BoxedTemplateProperty;
+use crate::templater::PropertyCacheScope;
 use crate::templater::Template;
+use crate::templater::TemplateRenderer;
+use crate::time_util::TemplateClock;
 
 /// General-purpose template language for basic value types.
 ///
@@ -36,15 +47,23 @@ use crate::templater::Template;
 /// the self type `C`.) The self type `C` is usually a tuple or struct of value
 /// types. It's cloned several times internally. Keyword functions need to be
 /// registered to extract properties from the self object.
-pub struct GenericTemplateLanguage<'a, C> {
+///
+/// The context `X` is a resource shared by all keyword and method functions,
+/// such as a repository handle. It defaults to `()`.
+pub struct GenericTemplateLanguage<'a, C, X = ()> {
     settings: UserSettings,
-    build_fn_table: GenericTemplateBuildFnTable<'a, C>,
+    context: X,
+    build_fn_table: GenericTemplateBuildFnTable<'a, C, X>,
+    aliases_map: TemplateAliasesMap,
+    clock: TemplateClock,
+    term_width: Option<usize>,
 }
 
 impl<'a, C> GenericTemplateLanguage<'a, C> {
     /// Sets up environment with no keywords.
     ///
-    /// New keyword functions can be registered by `add_keyword()`.
+    /// New keyword functions can be registered by `add_keyword()`, and
+    /// n-ary method functions can be registered by `add_method()`.
     pub fn new(settings: &UserSettings) -> Self {
         Self::with_keywords(HashMap::new(), settings)
     }
@@ -53,17 +72,53 @@ impl<'a, C> GenericTemplateLanguage<'a, C> {
     pub fn with_keywords(
         keywords: GenericTemplateBuildKeywordFnMap<'a, C>,
         settings: &UserSettings,
+    ) -> Self {
+        Self::with_context_and_keywords((), keywords, settings)
+    }
+}
+
+impl<'a, C, X> GenericTemplateLanguage<'a, C, X> {
+    /// Sets up environment with the shared `context` and no keywords.
+    ///
+    /// Keyword functions which need the context can be registered by
+    /// `add_context_keyword()`. Method functions can access the context
+    /// through `context()`.
+    pub fn with_context(context: X, settings: &UserSettings) -> Self {
+        Self::with_context_and_keywords(context, HashMap::new(), settings)
+    }
+
+    /// Sets up environment with the shared `context` and the given `keywords`
+    /// table.
+    pub fn with_context_and_keywords(
+        context: X,
+        keywords: GenericTemplateBuildKeywordFnMap<'a, C>,
+        settings: &UserSettings,
     ) -> Self {
         GenericTemplateLanguage {
             // Clone settings to keep lifetime simple. It's cheap.
             settings: settings.clone(),
+            context,
             build_fn_table: GenericTemplateBuildFnTable {
                 core: CoreTemplateBuildFnTable::builtin(),
-                keywords,
+                keywords: keywords
+                    .into_iter()
+                    .map(|(name, build)| (name, KeywordFn::Plain(build)))
+                    .collect(),
+                methods: HashMap::new(),
+                docs: HashMap::new(),
+                fallback: None,
             },
+            aliases_map: TemplateAliasesMap::new(),
+            clock: TemplateClock::SYSTEM,
+            term_width: None,
         }
     }
 
+    /// Shared context object.
+    pub fn context(&self) -> &X {
+        &self.context
+    }
+
     /// Registers new function that translates keyword to property.
     ///
     /// A keyword function returns `Self::Property`, which is basically a
@@ -83,17 +138,329 @@ impl<'a, C> GenericTemplateLanguage<'a, C> {
             ) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
             + 'a,
     {
-        self.build_fn_table.keywords.insert(name, Box::new(build));
+        let build = KeywordFn::Plain(Box::new(build));
+        self.build_fn_table.keywords.insert(name, build);
+    }
+
+    /// Registers new keyword function with the documentation string.
+    ///
+    /// The `doc` can be looked up by `doc()`, and is shown in "no such
+    /// keyword" errors if the keyword is suggested as a candidate.
+    ///
+    /// ```ignore
+    /// language.add_keyword_with_doc("name", "Name of the person", |self_property| {
+    ///     let out_property = self_property.map(|v| v.name);
+    ///     Ok(GenericTemplateLanguage::wrap_string(out_property.into_dyn()))
+    /// });
+    /// ```
+    pub fn add_keyword_with_doc<F>(&mut self, name: &'static str, doc: &'static str, build: F)
+    where
+        F: Fn(
+                BoxedTemplateProperty<'a, C>,
+            ) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
+            + 'a,
+    {
+        self.add_keyword(name, build);
+        self.build_fn_table.docs.insert(name, doc);
+    }
+
+    /// Registers new function that translates keyword to property with access
+    /// to the shared context.
+    ///
+    /// The returned property can't borrow from the context reference, so
+    /// resources needed at evaluation time should be cloned from it. If the
+    /// context is a reference or `Rc`, the cloning is cheap.
+    ///
+    /// ```ignore
+    /// language.add_context_keyword("owner", |repo, self_property| {
+    ///     let repo = repo.clone();
+    ///     let out_property = self_property.map(move |v| repo.owner_of(&v));
+    ///     Ok(GenericTemplateLanguage::wrap_string(out_property.into_dyn()))
+    /// });
+    /// ```
+    pub fn add_context_keyword<F>(&mut self, name: &'static str, build: F)
+    where
+        F: Fn(
+                &X,
+                BoxedTemplateProperty<'a, C>,
+            ) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
+            + 'a,
+    {
+        let build = move |_settings: &UserSettings, context: &X, self_property| {
+            build(context, self_property)
+        };
+        let build = KeywordFn::Context(Box::new(build));
+        self.build_fn_table.keywords.insert(name, build);
+    }
+
+    /// Registers new function that translates keyword to property with access
+    /// to the user settings.
+    ///
+    /// The settings are looked up when the keyword is built, so the keyword
+    /// function doesn't have to capture a clone of them.
+    ///
+    /// ```ignore
+    /// language.add_settings_keyword("me", |settings, _self_property| {
+    ///     let name = settings.user_name().to_owned();
+    ///     Ok(GenericTemplateLanguage::wrap_string(Literal(name).into_dyn()))
+    /// });
+    /// ```
+    pub fn add_settings_keyword<F>(&mut self, name: &'static str, build: F)
+    where
+        F: Fn(
+                &UserSettings,
+                BoxedTemplateProperty<'a, C>,
+            ) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
+            + 'a,
+    {
+        let build = move |settings: &UserSettings, _context: &X, self_property| {
+            build(settings, self_property)
+        };
+        let build = KeywordFn::Context(Box::new(build));
+        self.build_fn_table.keywords.insert(name, build);
+    }
+
+    /// Registers multiple keyword functions at once.
+    ///
+    /// Keyword functions for plain field accessors can be generated by the
+    /// `generic_keywords!()` macro.
+    ///
+    /// ```ignore
+    /// language.add_keywords(generic_keywords!(Person { name: String, age: i64 }));
+    /// language.add_keywords([
+    ///     ("greeting", new_keyword_fn(|self_property| { ... })),
+    /// ]);
+    /// ```
+    pub fn add_keywords<I>(&mut self, keywords: I)
+    where
+        I: IntoIterator<Item = (&'static str, GenericTemplateBuildKeywordFn<'a, C>)>,
+    {
+        let keywords = keywords
+            .into_iter()
+            .map(|(name, build)| (name, KeywordFn::Plain(build)));
+        self.build_fn_table.keywords.extend(keywords);
+    }
+
+    /// Registers new function that translates method call node of the self
+    /// type to property.
+    ///
+    /// Unlike keyword functions, a method function can take arguments, which
+    /// can be built by the `template_builder::expect_*_expression()` helpers.
+    /// It can also report non-fatal warnings, such as deprecation, to the
+    /// `diagnostics`. Since a keyword is a 0-ary method of the self type, a
+    /// method function can be referenced as a keyword.
+    ///
+    /// ```ignore
+    /// language.add_method("at", |language, diagnostics, build_ctx, self_property, function| {
+    ///     let [index_node] = function.expect_exact_arguments()?;
+    ///     let index = template_builder::expect_usize_expression(
+    ///         language, diagnostics, build_ctx, index_node)?;
+    ///     let out_property = (self_property, index).map(|(v, i)| v.items[i].clone());
+    ///     Ok(GenericTemplateLanguage::wrap_string(out_property.into_dyn()))
+    /// });
+    /// language.add_method("old_name", |_language, diagnostics, _build_ctx, self_property, function| {
+    ///     function.expect_no_arguments()?;
+    ///     diagnostics.add_warning(TemplateParseError::expression(
+    ///         "old_name is deprecated; use name instead",
+    ///         function.name_span,
+    ///     ));
+    ///     let out_property = self_property.map(|v| v.name.clone());
+    ///     Ok(GenericTemplateLanguage::wrap_string(out_property.into_dyn()))
+    /// });
+    /// ```
+    pub fn add_method<F>(&mut self, name: &'static str, build: F)
+    where
+        F: Fn(
+                &Self,
+                &mut TemplateDiagnostics,
+                &BuildContext<GenericTemplatePropertyKind<'a, C>>,
+                BoxedTemplateProperty<'a, C>,
+                &FunctionCallNode,
+            ) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
+            + 'a,
+    {
+        self.build_fn_table.methods.insert(name, Box::new(build));
+    }
+
+    /// Registers function that resolves methods of the self type which aren't
+    /// registered as keywords or methods.
+    ///
+    /// This is useful if the self type has dynamic fields, such as a map-like
+    /// record. The `resolve` function receives the method call node, and
+    /// returns `None` if the method name is unknown, in which case the usual
+    /// "no such method" error is reported. Since a keyword is a 0-ary method of
+    /// the self type, the fallback function is also called for unknown
+    /// keywords. Only one fallback function can be registered.
+    ///
+    /// ```ignore
+    /// language.add_fallback_method(|_language, _diagnostics, _build_ctx, self_property, function| {
+    ///     function.expect_no_arguments()?;
+    ///     let name = function.name.to_owned();
+    ///     let out_property = self_property.map(move |v| v.fields.get(&name).cloned());
+    ///     Ok(Some(GenericTemplateLanguage::wrap_string_opt(out_property.into_dyn())))
+    /// });
+    /// ```
+    pub fn add_fallback_method<F>(&mut self, resolve: F)
+    where
+        F: Fn(
+                &Self,
+                &mut TemplateDiagnostics,
+                &BuildContext<GenericTemplatePropertyKind<'a, C>>,
+                BoxedTemplateProperty<'a, C>,
+                &FunctionCallNode,
+            ) -> TemplateParseResult<Option<GenericTemplatePropertyKind<'a, C>>>
+            + 'a,
+    {
+        self.build_fn_table.fallback = Some(Box::new(resolve));
+    }
+
+    /// Iterates over names of the registered keyword functions.
+    ///
+    /// Similar keyword names are suggested in the "no such keyword" error.
+    pub fn keyword_names(&self) -> impl Iterator<Item = &'static str> + use<'_, 'a, C, X> {
+        self.build_fn_table.keywords.keys().copied()
+    }
+
+    /// Looks up the documentation string of the keyword `name`.
+    pub fn doc(&self, name: &str) -> Option<&'static str> {
+        self.build_fn_table.docs.get(name).copied()
+    }
+
+    /// Registers new template alias that can be used as a symbol or function.
+    ///
+    /// The alias definition `defn` is parsed when the alias is expanded, so it
+    /// may refer to keywords registered later. Parse errors in the definition
+    /// are reported as errors in alias expansion.
+    ///
+    /// ```ignore
+    /// language.add_alias("summary", "name ++ ': ' ++ value")?;
+    /// language.add_alias("quoted(x)", "'\"' ++ x ++ '\"'")?;
+    /// ```
+    pub fn add_alias(
+        &mut self,
+        decl: impl AsRef<str>,
+        defn: impl Into<String>,
+    ) -> TemplateParseResult<()> {
+        self.aliases_map.insert(decl, defn)
+    }
+
+    /// Template aliases registered by `add_alias()`.
+    ///
+    /// This should be passed to `template_builder::parse()` to expand the
+    /// aliases.
+    pub fn aliases_map(&self) -> &TemplateAliasesMap {
+        &self.aliases_map
+    }
+
+    /// Replaces the clock used by `now()`, `.ago()`, and `.local()`.
+    ///
+    /// The system clock is used by default. Tests can freeze the time and the
+    /// local time zone to get stable output.
+    pub fn set_clock(&mut self, clock: TemplateClock) {
+        self.clock = clock;
+    }
+
+    /// Sets the terminal width returned by `term_width()`.
+    ///
+    /// If unset, `ui.default-term-width` is used.
+    pub fn set_term_width(&mut self, width: Option<usize>) {
+        self.term_width = width;
+    }
+}
+
+impl<'a, C, X> GenericTemplateLanguage<'a, C, X> {
+    /// Attaches documentation of the suggested candidates to the "no such
+    /// method" error as a hint.
+    fn attach_candidate_docs(&self, err: TemplateParseError) -> TemplateParseError {
+        let TemplateParseErrorKind::NoSuchMethod { candidates, .. } = err.kind() else {
+            return err;
+        };
+        let docs = candidates
+            .iter()
+            .filter_map(|name| Some(format!("`{name}`: {}", self.doc(name)?)))
+            .join("\n");
+        if docs.is_empty() {
+            err
+        } else {
+            err.with_hint(docs)
+        }
+    }
+}
+
+impl<'a, C: Clone + 'a, X> GenericTemplateLanguage<'a, C, X> {
+    /// Parses `template_text` into a renderer for the self type. Aliases
+    /// registered by `add_alias()` are expanded.
+    ///
+    /// The renderer writes the output incrementally to the formatter, so the
+    /// rendered text isn't buffered as a whole. `List` values are evaluated
+    /// eagerly, but a keyword can return a lazily evaluated list by
+    /// `wrap_string_stream()`, whose items are generated while `.map()` or
+    /// `.join()` is rendered. The renderer can be reused for multiple self
+    /// values.
+    ///
+    /// ```ignore
+    /// let template = language.parse_template(&mut diagnostics, "name ++ '\n'")?;
+    /// let mut formatter = PlainTextFormatter::new(io::stdout().lock());
+    /// for person in &people {
+    ///     template.format(person, &mut formatter)?;
+    /// }
+    /// ```
+    pub fn parse_template(
+        &self,
+        diagnostics: &mut TemplateDiagnostics,
+        template_text: &str,
+    ) -> TemplateParseResult<TemplateRenderer<'a, C>> {
+        template_builder::parse(
+            self,
+            diagnostics,
+            template_text,
+            &self.aliases_map,
+            GenericTemplatePropertyKind::wrap_self,
+        )
+    }
+
+    /// Parses `template_text` and renders it for the given `self_value` as
+    /// plain text.
+    ///
+    /// This is a shorthand for `parse_template()` followed by
+    /// `TemplateRenderer::format()`, which is mainly useful for testing
+    /// keyword and method functions. Evaluation errors are rendered inline.
+    ///
+    /// ```ignore
+    /// let mut diagnostics = TemplateDiagnostics::new();
+    /// let text = language.render_to_string(&mut diagnostics, "name.upper()", &person)?;
+    /// ```
+    pub fn render_to_string(
+        &self,
+        diagnostics: &mut TemplateDiagnostics,
+        template_text: &str,
+        self_value: &C,
+    ) -> TemplateParseResult<String> {
+        let template = self.parse_template(diagnostics, template_text)?;
+        let mut output = Vec::new();
+        template
+            .format(self_value, &mut PlainTextFormatter::new(&mut output))
+            .expect("write() to PlainTextFormatter should never fail");
+        // Template output is usually UTF-8, but it can contain arbitrary bytes.
+        Ok(output.into_string_lossy())
     }
 }
 
-impl<'a, C> TemplateLanguage<'a> for GenericTemplateLanguage<'a, C> {
+impl<'a, C, X> TemplateLanguage<'a> for GenericTemplateLanguage<'a, C, X> {
     type Property = GenericTemplatePropertyKind<'a, C>;
 
     fn settings(&self) -> &UserSettings {
         &self.settings
     }
 
+    fn clock(&self) -> &TemplateClock {
+        &self.clock
+    }
+
+    fn term_width(&self) -> Option<usize> {
+        self.term_width
+    }
+
     fn build_function(
         &self,
         diagnostics: &mut TemplateDiagnostics,
@@ -118,14 +485,45 @@ impl<'a, C> TemplateLanguage<'a> for GenericTemplateLanguage<'a, C> {
                 table.build_method(self, diagnostics, build_ctx, property, function)
             }
             GenericTemplatePropertyKind::Self_(property) => {
+                let methods = &self.build_fn_table.methods;
+                if let Some(build) = methods.get(function.name) {
+                    return build(self, diagnostics, build_ctx, property, function);
+                }
                 let table = &self.build_fn_table.keywords;
-                let build = template_parser::lookup_method(type_name, table, function)?;
-                // For simplicity, only 0-ary method is supported.
+                let build = match template_parser::lookup_method(type_name, table, function) {
+                    Ok(build) => build,
+                    Err(err) => {
+                        if let Some(resolve) = &self.build_fn_table.fallback {
+                            let resolved =
+                                resolve(self, diagnostics, build_ctx, property, function)?;
+                            if let Some(out_property) = resolved {
+                                return Ok(out_property);
+                            }
+                        }
+                        let err = err.extend_method_candidates(methods.keys());
+                        return Err(self.attach_candidate_docs(err));
+                    }
+                };
+                // Keyword function is 0-ary method.
                 function.expect_no_arguments()?;
-                build(property)
+                match build {
+                    KeywordFn::Plain(build) => build(property),
+                    KeywordFn::Context(build) => build(&self.settings, &self.context, property),
+                }
             }
         }
     }
+
+    fn method_names(&self, type_name: &str) -> Option<Vec<&'static str>> {
+        match type_name {
+            "Self" => {
+                let keywords = self.build_fn_table.keywords.keys();
+                let methods = self.build_fn_table.methods.keys();
+                Some(keywords.chain(methods).copied().sorted_unstable().collect())
+            }
+            _ => self.build_fn_table.core.method_names(type_name),
+        }
+    }
 }
 
 pub enum GenericTemplatePropertyKind<'a, C> {
@@ -163,6 +561,34 @@ impl<'a, C> CoreTemplatePropertyVar<'a> for GenericTemplatePropertyKind<'a, C> {
         }
     }
 
+    fn try_into_float(self) -> Option<BoxedTemplateProperty<'a, f64>> {
+        match self {
+            GenericTemplatePropertyKind::Core(property) => property.try_into_float(),
+            GenericTemplatePropertyKind::Self_(_) => None,
+        }
+    }
+
+    fn try_into_timestamp(self) -> Option<BoxedTemplateProperty<'a, Timestamp>> {
+        match self {
+            GenericTemplatePropertyKind::Core(property) => property.try_into_timestamp(),
+            GenericTemplatePropertyKind::Self_(_) => None,
+        }
+    }
+
+    fn try_into_duration(self) -> Option<BoxedTemplateProperty<'a, TimeDelta>> {
+        match self {
+            GenericTemplatePropertyKind::Core(property) => property.try_into_duration(),
+            GenericTemplatePropertyKind::Self_(_) => None,
+        }
+    }
+
+    fn try_into_string_list(self) -> Option<BoxedTemplateProperty<'a, Vec<String>>> {
+        match self {
+            GenericTemplatePropertyKind::Core(property) => property.try_into_string_list(),
+            GenericTemplatePropertyKind::Self_(_) => None,
+        }
+    }
+
     fn try_into_plain_text(self) -> Option<BoxedTemplateProperty<'a, String>> {
         match self {
             GenericTemplatePropertyKind::Core(property) => property.try_into_plain_text(),
@@ -196,13 +622,26 @@ impl<'a, C> CoreTemplatePropertyVar<'a> for GenericTemplatePropertyKind<'a, C> {
             (GenericTemplatePropertyKind::Self_(_), _) => None,
         }
     }
+
+    fn try_into_cached(self, scope: &PropertyCacheScope) -> Option<Box<dyn Fn() -> Self + 'a>> {
+        match self {
+            GenericTemplatePropertyKind::Core(property) => {
+                let make = property.try_into_cached(scope)?;
+                Some(Box::new(move || GenericTemplatePropertyKind::Core(make())))
+            }
+            // The self type isn't necessarily cloneable.
+            GenericTemplatePropertyKind::Self_(_) => None,
+        }
+    }
 }
 
 /// Function that translates keyword (or 0-ary method call node of the self type
 /// `C`.)
 ///
-/// Because the `GenericTemplateLanguage` doesn't provide a way to pass around
-/// global resources, the keyword function is allowed to capture resources.
+/// The keyword function is allowed to capture resources. Keyword functions
+/// which need the user settings or the shared context can be registered by
+/// `GenericTemplateLanguage::add_settings_keyword()` or
+/// `add_context_keyword()`.
 pub type GenericTemplateBuildKeywordFn<'a, C> = Box<
     dyn Fn(BoxedTemplateProperty<'a, C>) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
         + 'a,
@@ -212,8 +651,116 @@ pub type GenericTemplateBuildKeywordFn<'a, C> = Box<
 pub type GenericTemplateBuildKeywordFnMap<'a, C> =
     HashMap<&'static str, GenericTemplateBuildKeywordFn<'a, C>>;
 
+/// Boxes keyword function.
+pub fn new_keyword_fn<'a, C, F>(build: F) -> GenericTemplateBuildKeywordFn<'a, C>
+where
+    F: Fn(BoxedTemplateProperty<'a, C>) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
+        + 'a,
+{
+    Box::new(build)
+}
+
+/// Registered keyword function.
+enum KeywordFn<'a, C, X> {
+    Plain(GenericTemplateBuildKeywordFn<'a, C>),
+    Context(ContextKeywordFn<'a, C, X>),
+}
+
+/// Keyword function that also takes the user settings and the shared context
+/// `X`.
+type ContextKeywordFn<'a, C, X> = Box<
+    dyn Fn(
+            &UserSettings,
+            &X,
+            BoxedTemplateProperty<'a, C>,
+        ) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
+        + 'a,
+>;
+
+/// Generates `(name, keyword_fn)` pairs of field accessors of the self type.
+///
+/// The field type must be `String`, `i64`, or `bool`. The generated list can be
+/// registered by `GenericTemplateLanguage::add_keywords()`.
+///
+/// ```ignore
+/// language.add_keywords(generic_keywords!(Person {
+///     name: String,
+///     age: i64,
+///     is_admin: bool,
+/// }));
+/// ```
+#[macro_export]
+macro_rules! generic_keywords {
+    ($self_ty:ty { $($field:ident: $field_ty:ident),* $(,)? }) => {
+        [$(
+            (
+                stringify!($field),
+                $crate::generic_templater::new_keyword_fn(
+                    |self_property| {
+                        let out_property = $crate::templater::TemplatePropertyExt::map(
+                            self_property,
+                            |value: $self_ty| value.$field,
+                        );
+                        Ok($crate::generic_keywords!(
+                            @wrap $field_ty,
+                            $crate::templater::TemplatePropertyExt::into_dyn(out_property)
+                        ))
+                    },
+                ),
+            )
+        ),*]
+    };
+    (@wrap String, $property:expr) => {
+        $crate::template_builder::CoreTemplatePropertyVar::wrap_string($property)
+    };
+    (@wrap i64, $property:expr) => {
+        $crate::template_builder::CoreTemplatePropertyVar::wrap_integer($property)
+    };
+    (@wrap bool, $property:expr) => {
+        $crate::template_builder::CoreTemplatePropertyVar::wrap_boolean($property)
+    };
+}
+
+/// Function that translates method call node of the self type `C`.
+///
+/// Like keyword functions, the method function is allowed to capture
+/// resources.
+pub type GenericTemplateBuildMethodFn<'a, C, X = ()> = Box<
+    dyn Fn(
+            &GenericTemplateLanguage<'a, C, X>,
+            &mut TemplateDiagnostics,
+            &BuildContext<GenericTemplatePropertyKind<'a, C>>,
+            BoxedTemplateProperty<'a, C>,
+            &FunctionCallNode,
+        ) -> TemplateParseResult<GenericTemplatePropertyKind<'a, C>>
+        + 'a,
+>;
+
+/// Function that resolves method call node of the self type `C` which isn't
+/// registered.
+///
+/// The function returns `None` if the method doesn't exist.
+pub type GenericTemplateBuildFallbackFn<'a, C, X = ()> = Box<
+    dyn Fn(
+            &GenericTemplateLanguage<'a, C, X>,
+            &mut TemplateDiagnostics,
+            &BuildContext<GenericTemplatePropertyKind<'a, C>>,
+            BoxedTemplateProperty<'a, C>,
+            &FunctionCallNode,
+        ) -> TemplateParseResult<Option<GenericTemplatePropertyKind<'a, C>>>
+        + 'a,
+>;
+
+/// Table of functions that translate method call node of the self type `C`.
+pub type GenericTemplateBuildMethodFnMap<'a, C, X = ()> =
+    HashMap<&'static str, GenericTemplateBuildMethodFn<'a, C, X>>;
+
 /// Symbol table of methods available in the general-purpose template.
-struct GenericTemplateBuildFnTable<'a, C> {
-    core: CoreTemplateBuildFnTable<'a, GenericTemplateLanguage<'a, C>>,
-    keywords: GenericTemplateBuildKeywordFnMap<'a, C>,
+struct GenericTemplateBuildFnTable<'a, C, X> {
+    core: CoreTemplateBuildFnTable<'a, GenericTemplateLanguage<'a, C, X>>,
+    keywords: HashMap<&'static str, KeywordFn<'a, C, X>>,
+    methods: GenericTemplateBuildMethodFnMap<'a, C, X>,
+    /// Documentation strings of the keyword functions.
+    docs: HashMap<&'static str, &'static str>,
+    fallback: Option<GenericTemplateBuildFallbackFn<'a, C, X>>,
 }
@@ -17,7 +17,9 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::io;
 
+use chrono::TimeDelta;
 use itertools::Itertools as _;
+use jj_lib::backend::Timestamp;
 use jj_lib::extensions_map::ExtensionsMap;
 use jj_lib::object_id::ObjectId as _;
 use jj_lib::op_store::OperationId;
@@ -26,7 +28,9 @@ use jj_lib::repo::RepoLoader;
 use jj_lib::settings::UserSettings;
 
 use crate::template_builder;
+use crate::template_builder::make_cached_property_fn;
 use crate::template_builder::merge_fn_map;
+use crate::template_builder::sorted_fn_names;
 use crate::template_builder::BuildContext;
 use crate::template_builder::CoreTemplateBuildFnTable;
 use crate::template_builder::CoreTemplatePropertyKind;
@@ -39,6 +43,7 @@ use crate::template_parser::TemplateDiagnostics;
 use crate::template_parser::TemplateParseResult;
 use crate::templater::BoxedTemplateProperty;
 use crate::templater::PlainTextFormattedProperty;
+use crate::templater::PropertyCacheScope;
 use crate::templater::Template;
 use crate::templater::TemplateFormatter;
 use crate::templater::TemplatePropertyExt as _;
@@ -55,6 +60,7 @@ pub struct OperationTemplateLanguage {
     current_op_id: Option<OperationId>,
     build_fn_table: OperationTemplateBuildFnTable,
     cache_extensions: ExtensionsMap,
+    term_width: Option<usize>,
 }
 
 impl OperationTemplateLanguage {
@@ -81,8 +87,14 @@ impl OperationTemplateLanguage {
             current_op_id: current_op_id.cloned(),
             build_fn_table,
             cache_extensions,
+            term_width: None,
         }
     }
+
+    /// Sets the terminal width returned by `term_width()`.
+    pub fn set_term_width(&mut self, width: Option<usize>) {
+        self.term_width = width;
+    }
 }
 
 impl TemplateLanguage<'static> for OperationTemplateLanguage {
@@ -92,6 +104,10 @@ impl TemplateLanguage<'static> for OperationTemplateLanguage {
         self.repo_loader.settings()
     }
 
+    fn term_width(&self) -> Option<usize> {
+        self.term_width
+    }
+
     fn build_function(
         &self,
         diagnostics: &mut TemplateDiagnostics,
@@ -127,6 +143,16 @@ impl TemplateLanguage<'static> for OperationTemplateLanguage {
             }
         }
     }
+
+    fn method_names(&self, type_name: &str) -> Option<Vec<&'static str>> {
+        let table = &self.build_fn_table;
+        let names = match type_name {
+            "Operation" => sorted_fn_names(&table.operation_methods),
+            "OperationId" => sorted_fn_names(&table.operation_id_methods),
+            _ => return table.core.method_names(type_name),
+        };
+        Some(names)
+    }
 }
 
 impl OperationTemplateLanguage {
@@ -174,6 +200,34 @@ impl CoreTemplatePropertyVar<'static> for OperationTemplatePropertyKind {
         }
     }
 
+    fn try_into_float(self) -> Option<BoxedTemplateProperty<'static, f64>> {
+        match self {
+            OperationTemplatePropertyKind::Core(property) => property.try_into_float(),
+            _ => None,
+        }
+    }
+
+    fn try_into_timestamp(self) -> Option<BoxedTemplateProperty<'static, Timestamp>> {
+        match self {
+            OperationTemplatePropertyKind::Core(property) => property.try_into_timestamp(),
+            _ => None,
+        }
+    }
+
+    fn try_into_duration(self) -> Option<BoxedTemplateProperty<'static, TimeDelta>> {
+        match self {
+            OperationTemplatePropertyKind::Core(property) => property.try_into_duration(),
+            _ => None,
+        }
+    }
+
+    fn try_into_string_list(self) -> Option<BoxedTemplateProperty<'static, Vec<String>>> {
+        match self {
+            OperationTemplatePropertyKind::Core(property) => property.try_into_string_list(),
+            _ => None,
+        }
+    }
+
     fn try_into_plain_text(self) -> Option<BoxedTemplateProperty<'static, String>> {
         match self {
             OperationTemplatePropertyKind::Core(property) => property.try_into_plain_text(),
@@ -215,6 +269,26 @@ impl CoreTemplatePropertyVar<'static> for OperationTemplatePropertyKind {
             (OperationTemplatePropertyKind::OperationId(_), _) => None,
         }
     }
+
+    fn try_into_cached(
+        self,
+        scope: &PropertyCacheScope,
+    ) -> Option<Box<dyn Fn() -> Self + 'static>> {
+        match self {
+            OperationTemplatePropertyKind::Core(property) => {
+                let make = property.try_into_cached(scope)?;
+                Some(Box::new(
+                    move || OperationTemplatePropertyKind::Core(make()),
+                ))
+            }
+            OperationTemplatePropertyKind::Operation(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Operation))
+            }
+            OperationTemplatePropertyKind::OperationId(property) => {
+                Some(make_cached_property_fn(property, scope, Self::OperationId))
+            }
+        }
+    }
 }
 
 /// Table of functions that translate method call node of self type `T`.
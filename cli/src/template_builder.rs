@@ -12,21 +12,32 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::io;
 use std::iter;
 
+use base64::prelude::BASE64_STANDARD;
+use base64::prelude::BASE64_URL_SAFE;
+use base64::Engine as _;
+use chrono::TimeDelta;
 use itertools::Itertools as _;
+use jj_lib::backend::MillisSinceEpoch;
 use jj_lib::backend::Signature;
 use jj_lib::backend::Timestamp;
+use jj_lib::config::ConfigGetResultExt as _;
 use jj_lib::config::ConfigNamePathBuf;
 use jj_lib::config::ConfigValue;
 use jj_lib::dsl_util::AliasExpandError as _;
+use jj_lib::dsl_util::InvalidArguments;
+use jj_lib::settings::HumanByteSize;
 use jj_lib::settings::UserSettings;
-use jj_lib::time_util::DatePattern;
 use serde::de::IntoDeserializer as _;
 use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation as _;
+use unicode_width::UnicodeWidthStr as _;
 
 use crate::formatter::FormatRecorder;
 use crate::formatter::Formatter;
@@ -36,6 +47,7 @@ use crate::template_parser::ExpressionKind;
 use crate::template_parser::ExpressionNode;
 use crate::template_parser::FunctionCallNode;
 use crate::template_parser::LambdaNode;
+use crate::template_parser::LetNode;
 use crate::template_parser::TemplateAliasesMap;
 use crate::template_parser::TemplateDiagnostics;
 use crate::template_parser::TemplateParseError;
@@ -43,20 +55,29 @@ use crate::template_parser::TemplateParseErrorKind;
 use crate::template_parser::TemplateParseResult;
 use crate::template_parser::UnaryOp;
 use crate::templater::BoxedTemplateProperty;
+use crate::templater::CacheScopeTemplate;
+use crate::templater::CachedProperty;
+use crate::templater::CachedTemplate;
 use crate::templater::CoalesceTemplate;
 use crate::templater::ConcatTemplate;
 use crate::templater::ConditionalTemplate;
 use crate::templater::Email;
 use crate::templater::LabelTemplate;
+use crate::templater::ListEnumerated;
+use crate::templater::ListGroup;
 use crate::templater::ListPropertyTemplate;
+use crate::templater::ListStream;
 use crate::templater::ListTemplate;
 use crate::templater::Literal;
+use crate::templater::OptionalPropertyTemplate;
 use crate::templater::PlainTextFormattedProperty;
+use crate::templater::PropertyCacheScope;
 use crate::templater::PropertyPlaceholder;
 use crate::templater::RawEscapeSequenceTemplate;
 use crate::templater::ReformatTemplate;
 use crate::templater::SeparateTemplate;
 use crate::templater::SizeHint;
+use crate::templater::TablePropertyTemplate;
 use crate::templater::Template;
 use crate::templater::TemplateProperty;
 use crate::templater::TemplatePropertyError;
@@ -65,6 +86,7 @@ use crate::templater::TemplateRenderer;
 use crate::templater::TimestampRange;
 use crate::text_util;
 use crate::time_util;
+use crate::time_util::TemplateClock;
 
 /// Callbacks to build language-specific evaluation objects from AST nodes.
 pub trait TemplateLanguage<'a> {
@@ -90,6 +112,26 @@ pub trait TemplateLanguage<'a> {
         property: Self::Property,
         function: &FunctionCallNode,
     ) -> TemplateParseResult<Self::Property>;
+
+    /// Returns the names of the methods available for the given property
+    /// `type_name`, or `None` if the type is unknown.
+    ///
+    /// This is read-only metadata that can be used to implement completion.
+    /// This should be delegated to `CoreTemplateBuildFnTable::method_names()`
+    /// for the core types. The default implementation provides no metadata.
+    fn method_names(&self, _type_name: &str) -> Option<Vec<&'static str>> {
+        None
+    }
+
+    /// Clock to get the current time and the local time zone from.
+    fn clock(&self) -> &TemplateClock {
+        &TemplateClock::SYSTEM
+    }
+
+    /// Width of the terminal the output is written to, or `None` if unknown.
+    fn term_width(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// Implements `CoreTemplatePropertyVar::wrap_<type>()` functions.
@@ -104,16 +146,29 @@ macro_rules! impl_core_wrap_property_fns {
         $crate::template_builder::impl_wrap_property_fns!(
             $a, $crate::template_builder::CoreTemplatePropertyKind, $outer, {
                 wrap_string(String) => String,
+                wrap_string_opt(Option<String>) => StringOpt,
                 wrap_string_list(Vec<String>) => StringList,
+                wrap_string_list_list(Vec<Vec<String>>) => StringListList,
+                wrap_string_group($crate::templater::ListGroup<String>) => StringGroup,
+                wrap_string_group_list(Vec<$crate::templater::ListGroup<String>>)
+                    => StringGroupList,
+                wrap_string_enumerated($crate::templater::ListEnumerated<String>)
+                    => StringEnumerated,
+                wrap_string_enumerated_list(Vec<$crate::templater::ListEnumerated<String>>)
+                    => StringEnumeratedList,
+                wrap_string_stream($crate::templater::ListStream<$a, String>) => StringStream,
                 wrap_boolean(bool) => Boolean,
                 wrap_integer(i64) => Integer,
                 wrap_integer_opt(Option<i64>) => IntegerOpt,
+                wrap_float(f64) => Float,
                 wrap_config_value(jj_lib::config::ConfigValue) => ConfigValue,
                 wrap_signature(jj_lib::backend::Signature) => Signature,
                 wrap_email($crate::templater::Email) => Email,
                 wrap_size_hint($crate::templater::SizeHint) => SizeHint,
                 wrap_timestamp(jj_lib::backend::Timestamp) => Timestamp,
                 wrap_timestamp_range($crate::templater::TimestampRange) => TimestampRange,
+                wrap_duration(chrono::TimeDelta) => Duration,
+                wrap_bytes(Vec<u8>) => Bytes,
             }
         );
         fn wrap_template(
@@ -156,16 +211,28 @@ pub(crate) use impl_wrap_property_fns;
 /// Wrapper for the core template property types.
 pub trait CoreTemplatePropertyVar<'a> {
     fn wrap_string(property: BoxedTemplateProperty<'a, String>) -> Self;
+    fn wrap_string_opt(property: BoxedTemplateProperty<'a, Option<String>>) -> Self;
     fn wrap_string_list(property: BoxedTemplateProperty<'a, Vec<String>>) -> Self;
+    fn wrap_string_list_list(property: BoxedTemplateProperty<'a, Vec<Vec<String>>>) -> Self;
+    fn wrap_string_group(property: BoxedTemplateProperty<'a, ListGroup<String>>) -> Self;
+    fn wrap_string_group_list(property: BoxedTemplateProperty<'a, Vec<ListGroup<String>>>) -> Self;
+    fn wrap_string_enumerated(property: BoxedTemplateProperty<'a, ListEnumerated<String>>) -> Self;
+    fn wrap_string_enumerated_list(
+        property: BoxedTemplateProperty<'a, Vec<ListEnumerated<String>>>,
+    ) -> Self;
+    fn wrap_string_stream(property: BoxedTemplateProperty<'a, ListStream<'a, String>>) -> Self;
     fn wrap_boolean(property: BoxedTemplateProperty<'a, bool>) -> Self;
     fn wrap_integer(property: BoxedTemplateProperty<'a, i64>) -> Self;
     fn wrap_integer_opt(property: BoxedTemplateProperty<'a, Option<i64>>) -> Self;
+    fn wrap_float(property: BoxedTemplateProperty<'a, f64>) -> Self;
     fn wrap_config_value(property: BoxedTemplateProperty<'a, ConfigValue>) -> Self;
     fn wrap_signature(property: BoxedTemplateProperty<'a, Signature>) -> Self;
     fn wrap_email(property: BoxedTemplateProperty<'a, Email>) -> Self;
     fn wrap_size_hint(property: BoxedTemplateProperty<'a, SizeHint>) -> Self;
     fn wrap_timestamp(property: BoxedTemplateProperty<'a, Timestamp>) -> Self;
     fn wrap_timestamp_range(property: BoxedTemplateProperty<'a, TimestampRange>) -> Self;
+    fn wrap_duration(property: BoxedTemplateProperty<'a, TimeDelta>) -> Self;
+    fn wrap_bytes(property: BoxedTemplateProperty<'a, Vec<u8>>) -> Self;
 
     fn wrap_template(template: Box<dyn Template + 'a>) -> Self;
     fn wrap_list_template(template: Box<dyn ListTemplate + 'a>) -> Self;
@@ -175,6 +242,11 @@ pub trait CoreTemplatePropertyVar<'a> {
 
     fn try_into_boolean(self) -> Option<BoxedTemplateProperty<'a, bool>>;
     fn try_into_integer(self) -> Option<BoxedTemplateProperty<'a, i64>>;
+    /// Converts integer types to float as well.
+    fn try_into_float(self) -> Option<BoxedTemplateProperty<'a, f64>>;
+    fn try_into_timestamp(self) -> Option<BoxedTemplateProperty<'a, Timestamp>>;
+    fn try_into_duration(self) -> Option<BoxedTemplateProperty<'a, TimeDelta>>;
+    fn try_into_string_list(self) -> Option<BoxedTemplateProperty<'a, Vec<String>>>;
 
     fn try_into_plain_text(self) -> Option<BoxedTemplateProperty<'a, String>>;
     fn try_into_template(self) -> Option<Box<dyn Template + 'a>>;
@@ -184,20 +256,42 @@ pub trait CoreTemplatePropertyVar<'a> {
 
     /// Transforms into a property that will evaluate to an [`Ordering`].
     fn try_into_cmp(self, other: Self) -> Option<BoxedTemplateProperty<'a, Ordering>>;
+
+    /// Transforms into a function that creates properties sharing the value
+    /// evaluated at most once within the `scope`.
+    ///
+    /// No types can be cached by default. Implementations should opt in
+    /// variants of cloneable types by `make_cached_property_fn()`.
+    fn try_into_cached(self, _scope: &PropertyCacheScope) -> Option<Box<dyn Fn() -> Self + 'a>>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
 
 pub enum CoreTemplatePropertyKind<'a> {
     String(BoxedTemplateProperty<'a, String>),
+    StringOpt(BoxedTemplateProperty<'a, Option<String>>),
     StringList(BoxedTemplateProperty<'a, Vec<String>>),
+    StringListList(BoxedTemplateProperty<'a, Vec<Vec<String>>>),
+    StringGroup(BoxedTemplateProperty<'a, ListGroup<String>>),
+    StringGroupList(BoxedTemplateProperty<'a, Vec<ListGroup<String>>>),
+    StringEnumerated(BoxedTemplateProperty<'a, ListEnumerated<String>>),
+    StringEnumeratedList(BoxedTemplateProperty<'a, Vec<ListEnumerated<String>>>),
+    StringStream(BoxedTemplateProperty<'a, ListStream<'a, String>>),
     Boolean(BoxedTemplateProperty<'a, bool>),
     Integer(BoxedTemplateProperty<'a, i64>),
     IntegerOpt(BoxedTemplateProperty<'a, Option<i64>>),
+    Float(BoxedTemplateProperty<'a, f64>),
     ConfigValue(BoxedTemplateProperty<'a, ConfigValue>),
     Signature(BoxedTemplateProperty<'a, Signature>),
     Email(BoxedTemplateProperty<'a, Email>),
     SizeHint(BoxedTemplateProperty<'a, SizeHint>),
     Timestamp(BoxedTemplateProperty<'a, Timestamp>),
     TimestampRange(BoxedTemplateProperty<'a, TimestampRange>),
+    Duration(BoxedTemplateProperty<'a, TimeDelta>),
+    Bytes(BoxedTemplateProperty<'a, Vec<u8>>),
 
     // Both TemplateProperty and Template can represent a value to be evaluated
     // dynamically, which suggests that `Box<dyn Template + 'a>` could be
@@ -219,16 +313,26 @@ impl<'a> CoreTemplatePropertyVar<'a> for CoreTemplatePropertyKind<'a> {
     fn type_name(&self) -> &'static str {
         match self {
             CoreTemplatePropertyKind::String(_) => "String",
+            CoreTemplatePropertyKind::StringOpt(_) => "Option<String>",
             CoreTemplatePropertyKind::StringList(_) => "List<String>",
+            CoreTemplatePropertyKind::StringListList(_) => "List<List<String>>",
+            CoreTemplatePropertyKind::StringGroup(_) => "Group<String>",
+            CoreTemplatePropertyKind::StringGroupList(_) => "List<Group<String>>",
+            CoreTemplatePropertyKind::StringEnumerated(_) => "Enumerated<String>",
+            CoreTemplatePropertyKind::StringEnumeratedList(_) => "List<Enumerated<String>>",
+            CoreTemplatePropertyKind::StringStream(_) => "Stream<String>",
             CoreTemplatePropertyKind::Boolean(_) => "Boolean",
             CoreTemplatePropertyKind::Integer(_) => "Integer",
             CoreTemplatePropertyKind::IntegerOpt(_) => "Option<Integer>",
+            CoreTemplatePropertyKind::Float(_) => "Float",
             CoreTemplatePropertyKind::ConfigValue(_) => "ConfigValue",
             CoreTemplatePropertyKind::Signature(_) => "Signature",
             CoreTemplatePropertyKind::Email(_) => "Email",
             CoreTemplatePropertyKind::SizeHint(_) => "SizeHint",
             CoreTemplatePropertyKind::Timestamp(_) => "Timestamp",
             CoreTemplatePropertyKind::TimestampRange(_) => "TimestampRange",
+            CoreTemplatePropertyKind::Duration(_) => "Duration",
+            CoreTemplatePropertyKind::Bytes(_) => "Bytes",
             CoreTemplatePropertyKind::Template(_) => "Template",
             CoreTemplatePropertyKind::ListTemplate(_) => "ListTemplate",
         }
@@ -239,14 +343,32 @@ impl<'a> CoreTemplatePropertyVar<'a> for CoreTemplatePropertyKind<'a> {
             CoreTemplatePropertyKind::String(property) => {
                 Some(property.map(|s| !s.is_empty()).into_dyn())
             }
+            CoreTemplatePropertyKind::StringOpt(property) => {
+                Some(property.map(|opt| opt.is_some()).into_dyn())
+            }
             CoreTemplatePropertyKind::StringList(property) => {
                 Some(property.map(|l| !l.is_empty()).into_dyn())
             }
+            CoreTemplatePropertyKind::StringListList(property) => {
+                Some(property.map(|l| !l.is_empty()).into_dyn())
+            }
+            CoreTemplatePropertyKind::StringGroup(_) => None,
+            CoreTemplatePropertyKind::StringGroupList(property) => {
+                Some(property.map(|l| !l.is_empty()).into_dyn())
+            }
+            CoreTemplatePropertyKind::StringEnumerated(_) => None,
+            CoreTemplatePropertyKind::StringEnumeratedList(property) => {
+                Some(property.map(|l| !l.is_empty()).into_dyn())
+            }
+            CoreTemplatePropertyKind::StringStream(property) => {
+                Some(property.map(|mut items| items.next().is_some()).into_dyn())
+            }
             CoreTemplatePropertyKind::Boolean(property) => Some(property),
             CoreTemplatePropertyKind::Integer(_) => None,
             CoreTemplatePropertyKind::IntegerOpt(property) => {
                 Some(property.map(|opt| opt.is_some()).into_dyn())
             }
+            CoreTemplatePropertyKind::Float(_) => None,
             CoreTemplatePropertyKind::ConfigValue(_) => None,
             CoreTemplatePropertyKind::Signature(_) => None,
             CoreTemplatePropertyKind::Email(property) => {
@@ -255,6 +377,10 @@ impl<'a> CoreTemplatePropertyVar<'a> for CoreTemplatePropertyKind<'a> {
             CoreTemplatePropertyKind::SizeHint(_) => None,
             CoreTemplatePropertyKind::Timestamp(_) => None,
             CoreTemplatePropertyKind::TimestampRange(_) => None,
+            CoreTemplatePropertyKind::Duration(_) => None,
+            CoreTemplatePropertyKind::Bytes(property) => {
+                Some(property.map(|b| !b.is_empty()).into_dyn())
+            }
             // Template types could also be evaluated to boolean, but it's less likely
             // to apply label() or .map() and use the result as conditional. It's also
             // unclear whether ListTemplate should behave as a "list" or a "template".
@@ -273,6 +399,37 @@ impl<'a> CoreTemplatePropertyVar<'a> for CoreTemplatePropertyKind<'a> {
         }
     }
 
+    fn try_into_float(self) -> Option<BoxedTemplateProperty<'a, f64>> {
+        match self {
+            CoreTemplatePropertyKind::Float(property) => Some(property),
+            _ => {
+                let property = self.try_into_integer()?;
+                Some(property.map(|v| v as f64).into_dyn())
+            }
+        }
+    }
+
+    fn try_into_timestamp(self) -> Option<BoxedTemplateProperty<'a, Timestamp>> {
+        match self {
+            CoreTemplatePropertyKind::Timestamp(property) => Some(property),
+            _ => None,
+        }
+    }
+
+    fn try_into_duration(self) -> Option<BoxedTemplateProperty<'a, TimeDelta>> {
+        match self {
+            CoreTemplatePropertyKind::Duration(property) => Some(property),
+            _ => None,
+        }
+    }
+
+    fn try_into_string_list(self) -> Option<BoxedTemplateProperty<'a, Vec<String>>> {
+        match self {
+            CoreTemplatePropertyKind::StringList(property) => Some(property),
+            _ => None,
+        }
+    }
+
     fn try_into_plain_text(self) -> Option<BoxedTemplateProperty<'a, String>> {
         match self {
             CoreTemplatePropertyKind::String(property) => Some(property),
@@ -286,16 +443,32 @@ impl<'a> CoreTemplatePropertyVar<'a> for CoreTemplatePropertyKind<'a> {
     fn try_into_template(self) -> Option<Box<dyn Template + 'a>> {
         match self {
             CoreTemplatePropertyKind::String(property) => Some(property.into_template()),
+            CoreTemplatePropertyKind::StringOpt(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::StringList(property) => Some(property.into_template()),
+            CoreTemplatePropertyKind::StringListList(_) => None,
+            CoreTemplatePropertyKind::StringGroup(_) => None,
+            CoreTemplatePropertyKind::StringGroupList(_) => None,
+            CoreTemplatePropertyKind::StringEnumerated(_) => None,
+            CoreTemplatePropertyKind::StringEnumeratedList(_) => None,
+            CoreTemplatePropertyKind::StringStream(property) => {
+                let template =
+                    ListPropertyTemplate::new(property, Literal(" "), |formatter, item| {
+                        item.format(formatter)
+                    });
+                Some(Box::new(template))
+            }
             CoreTemplatePropertyKind::Boolean(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::Integer(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::IntegerOpt(property) => Some(property.into_template()),
+            CoreTemplatePropertyKind::Float(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::ConfigValue(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::Signature(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::Email(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::SizeHint(_) => None,
             CoreTemplatePropertyKind::Timestamp(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::TimestampRange(property) => Some(property.into_template()),
+            CoreTemplatePropertyKind::Duration(property) => Some(property.into_template()),
+            CoreTemplatePropertyKind::Bytes(_) => None,
             CoreTemplatePropertyKind::Template(template) => Some(template),
             CoreTemplatePropertyKind::ListTemplate(template) => Some(template.into_template()),
         }
@@ -315,23 +488,48 @@ impl<'a> CoreTemplatePropertyVar<'a> for CoreTemplatePropertyKind<'a> {
             (CoreTemplatePropertyKind::Integer(lhs), CoreTemplatePropertyKind::Integer(rhs)) => {
                 Some((lhs, rhs).map(|(l, r)| l == r).into_dyn())
             }
+            (CoreTemplatePropertyKind::Integer(lhs), CoreTemplatePropertyKind::Float(rhs)) => {
+                Some((lhs, rhs).map(|(l, r)| l as f64 == r).into_dyn())
+            }
+            (CoreTemplatePropertyKind::Float(lhs), CoreTemplatePropertyKind::Float(rhs)) => {
+                Some((lhs, rhs).map(|(l, r)| l == r).into_dyn())
+            }
+            (CoreTemplatePropertyKind::Float(lhs), CoreTemplatePropertyKind::Integer(rhs)) => {
+                Some((lhs, rhs).map(|(l, r)| l == r as f64).into_dyn())
+            }
             (CoreTemplatePropertyKind::Email(lhs), CoreTemplatePropertyKind::Email(rhs)) => {
                 Some((lhs, rhs).map(|(l, r)| l == r).into_dyn())
             }
             (CoreTemplatePropertyKind::Email(lhs), CoreTemplatePropertyKind::String(rhs)) => {
                 Some((lhs, rhs).map(|(l, r)| l.0 == r).into_dyn())
             }
+            (CoreTemplatePropertyKind::Duration(lhs), CoreTemplatePropertyKind::Duration(rhs)) => {
+                Some((lhs, rhs).map(|(l, r)| l == r).into_dyn())
+            }
+            (CoreTemplatePropertyKind::Bytes(lhs), CoreTemplatePropertyKind::Bytes(rhs)) => {
+                Some((lhs, rhs).map(|(l, r)| l == r).into_dyn())
+            }
             (CoreTemplatePropertyKind::String(_), _) => None,
+            (CoreTemplatePropertyKind::StringOpt(_), _) => None,
             (CoreTemplatePropertyKind::StringList(_), _) => None,
+            (CoreTemplatePropertyKind::StringListList(_), _) => None,
+            (CoreTemplatePropertyKind::StringGroup(_), _) => None,
+            (CoreTemplatePropertyKind::StringGroupList(_), _) => None,
+            (CoreTemplatePropertyKind::StringEnumerated(_), _) => None,
+            (CoreTemplatePropertyKind::StringEnumeratedList(_), _) => None,
+            (CoreTemplatePropertyKind::StringStream(_), _) => None,
             (CoreTemplatePropertyKind::Boolean(_), _) => None,
             (CoreTemplatePropertyKind::Integer(_), _) => None,
             (CoreTemplatePropertyKind::IntegerOpt(_), _) => None,
+            (CoreTemplatePropertyKind::Float(_), _) => None,
             (CoreTemplatePropertyKind::ConfigValue(_), _) => None,
             (CoreTemplatePropertyKind::Signature(_), _) => None,
             (CoreTemplatePropertyKind::Email(_), _) => None,
             (CoreTemplatePropertyKind::SizeHint(_), _) => None,
             (CoreTemplatePropertyKind::Timestamp(_), _) => None,
             (CoreTemplatePropertyKind::TimestampRange(_), _) => None,
+            (CoreTemplatePropertyKind::Duration(_), _) => None,
+            (CoreTemplatePropertyKind::Bytes(_), _) => None,
             (CoreTemplatePropertyKind::Template(_), _) => None,
             (CoreTemplatePropertyKind::ListTemplate(_), _) => None,
         }
@@ -342,21 +540,144 @@ impl<'a> CoreTemplatePropertyVar<'a> for CoreTemplatePropertyKind<'a> {
             (CoreTemplatePropertyKind::Integer(lhs), CoreTemplatePropertyKind::Integer(rhs)) => {
                 Some((lhs, rhs).map(|(l, r)| l.cmp(&r)).into_dyn())
             }
+            (CoreTemplatePropertyKind::Integer(lhs), CoreTemplatePropertyKind::Float(rhs)) => {
+                let lhs = lhs.map(|l| l as f64);
+                Some((lhs, rhs).and_then(cmp_float).into_dyn())
+            }
+            (CoreTemplatePropertyKind::Float(lhs), CoreTemplatePropertyKind::Float(rhs)) => {
+                Some((lhs, rhs).and_then(cmp_float).into_dyn())
+            }
+            (CoreTemplatePropertyKind::Float(lhs), CoreTemplatePropertyKind::Integer(rhs)) => {
+                let rhs = rhs.map(|r| r as f64);
+                Some((lhs, rhs).and_then(cmp_float).into_dyn())
+            }
+            (CoreTemplatePropertyKind::Duration(lhs), CoreTemplatePropertyKind::Duration(rhs)) => {
+                Some((lhs, rhs).map(|(l, r)| l.cmp(&r)).into_dyn())
+            }
             (CoreTemplatePropertyKind::String(_), _) => None,
+            (CoreTemplatePropertyKind::StringOpt(_), _) => None,
             (CoreTemplatePropertyKind::StringList(_), _) => None,
+            (CoreTemplatePropertyKind::StringListList(_), _) => None,
+            (CoreTemplatePropertyKind::StringGroup(_), _) => None,
+            (CoreTemplatePropertyKind::StringGroupList(_), _) => None,
+            (CoreTemplatePropertyKind::StringEnumerated(_), _) => None,
+            (CoreTemplatePropertyKind::StringEnumeratedList(_), _) => None,
+            (CoreTemplatePropertyKind::StringStream(_), _) => None,
             (CoreTemplatePropertyKind::Boolean(_), _) => None,
             (CoreTemplatePropertyKind::Integer(_), _) => None,
             (CoreTemplatePropertyKind::IntegerOpt(_), _) => None,
+            (CoreTemplatePropertyKind::Float(_), _) => None,
             (CoreTemplatePropertyKind::ConfigValue(_), _) => None,
             (CoreTemplatePropertyKind::Signature(_), _) => None,
             (CoreTemplatePropertyKind::Email(_), _) => None,
             (CoreTemplatePropertyKind::SizeHint(_), _) => None,
             (CoreTemplatePropertyKind::Timestamp(_), _) => None,
             (CoreTemplatePropertyKind::TimestampRange(_), _) => None,
+            (CoreTemplatePropertyKind::Duration(_), _) => None,
+            (CoreTemplatePropertyKind::Bytes(_), _) => None,
             (CoreTemplatePropertyKind::Template(_), _) => None,
             (CoreTemplatePropertyKind::ListTemplate(_), _) => None,
         }
     }
+
+    fn try_into_cached(self, scope: &PropertyCacheScope) -> Option<Box<dyn Fn() -> Self + 'a>> {
+        match self {
+            CoreTemplatePropertyKind::String(property) => {
+                Some(make_cached_property_fn(property, scope, Self::String))
+            }
+            CoreTemplatePropertyKind::StringOpt(property) => {
+                Some(make_cached_property_fn(property, scope, Self::StringOpt))
+            }
+            CoreTemplatePropertyKind::StringList(property) => {
+                Some(make_cached_property_fn(property, scope, Self::StringList))
+            }
+            CoreTemplatePropertyKind::StringListList(property) => Some(make_cached_property_fn(
+                property,
+                scope,
+                Self::StringListList,
+            )),
+            CoreTemplatePropertyKind::StringGroup(property) => {
+                Some(make_cached_property_fn(property, scope, Self::StringGroup))
+            }
+            CoreTemplatePropertyKind::StringGroupList(property) => Some(make_cached_property_fn(
+                property,
+                scope,
+                Self::StringGroupList,
+            )),
+            CoreTemplatePropertyKind::StringEnumerated(property) => Some(make_cached_property_fn(
+                property,
+                scope,
+                Self::StringEnumerated,
+            )),
+            CoreTemplatePropertyKind::StringEnumeratedList(property) => Some(
+                make_cached_property_fn(property, scope, Self::StringEnumeratedList),
+            ),
+            // The stream would have to be collected to share the items, which
+            // defeats the purpose of the stream.
+            CoreTemplatePropertyKind::StringStream(_) => None,
+            CoreTemplatePropertyKind::Boolean(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Boolean))
+            }
+            CoreTemplatePropertyKind::Integer(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Integer))
+            }
+            CoreTemplatePropertyKind::IntegerOpt(property) => {
+                Some(make_cached_property_fn(property, scope, Self::IntegerOpt))
+            }
+            CoreTemplatePropertyKind::Float(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Float))
+            }
+            CoreTemplatePropertyKind::ConfigValue(property) => {
+                Some(make_cached_property_fn(property, scope, Self::ConfigValue))
+            }
+            CoreTemplatePropertyKind::Signature(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Signature))
+            }
+            CoreTemplatePropertyKind::Email(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Email))
+            }
+            CoreTemplatePropertyKind::SizeHint(property) => {
+                Some(make_cached_property_fn(property, scope, Self::SizeHint))
+            }
+            CoreTemplatePropertyKind::Timestamp(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Timestamp))
+            }
+            CoreTemplatePropertyKind::TimestampRange(property) => Some(make_cached_property_fn(
+                property,
+                scope,
+                Self::TimestampRange,
+            )),
+            CoreTemplatePropertyKind::Duration(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Duration))
+            }
+            CoreTemplatePropertyKind::Bytes(property) => {
+                Some(make_cached_property_fn(property, scope, Self::Bytes))
+            }
+            CoreTemplatePropertyKind::Template(template) => {
+                let template = CachedTemplate::new(template, scope.clone());
+                Some(Box::new(move || Self::Template(Box::new(template.clone()))))
+            }
+            // ListTemplate could be cached as a template, but then it would
+            // lose the list methods.
+            CoreTemplatePropertyKind::ListTemplate(_) => None,
+        }
+    }
+}
+
+/// Wraps `property` in [`CachedProperty`], and returns a function to create
+/// properties sharing the cached value.
+pub fn make_cached_property_fn<'a, O: Clone + 'a, P>(
+    property: BoxedTemplateProperty<'a, O>,
+    scope: &PropertyCacheScope,
+    wrap: impl Fn(BoxedTemplateProperty<'a, O>) -> P + 'a,
+) -> Box<dyn Fn() -> P + 'a> {
+    let property = CachedProperty::new(property, scope.clone());
+    Box::new(move || wrap(property.clone().into_dyn()))
+}
+
+fn cmp_float((lhs, rhs): (f64, f64)) -> Result<Ordering, TemplatePropertyError> {
+    lhs.partial_cmp(&rhs)
+        .ok_or_else(|| TemplatePropertyError("Cannot compare NaN".into()))
 }
 
 /// Function that translates global function call node.
@@ -396,12 +717,51 @@ pub struct CoreTemplateBuildFnTable<'a, L: TemplateLanguage<'a> + ?Sized> {
     pub string_methods: TemplateBuildMethodFnMap<'a, L, String>,
     pub boolean_methods: TemplateBuildMethodFnMap<'a, L, bool>,
     pub integer_methods: TemplateBuildMethodFnMap<'a, L, i64>,
+    pub float_methods: TemplateBuildMethodFnMap<'a, L, f64>,
     pub config_value_methods: TemplateBuildMethodFnMap<'a, L, ConfigValue>,
     pub email_methods: TemplateBuildMethodFnMap<'a, L, Email>,
     pub signature_methods: TemplateBuildMethodFnMap<'a, L, Signature>,
     pub size_hint_methods: TemplateBuildMethodFnMap<'a, L, SizeHint>,
     pub timestamp_methods: TemplateBuildMethodFnMap<'a, L, Timestamp>,
     pub timestamp_range_methods: TemplateBuildMethodFnMap<'a, L, TimestampRange>,
+    pub duration_methods: TemplateBuildMethodFnMap<'a, L, TimeDelta>,
+    pub bytes_methods: TemplateBuildMethodFnMap<'a, L, Vec<u8>>,
+}
+
+/// Returns the function names registered in the `table` in sorted order.
+pub fn sorted_fn_names<'s, F>(table: &HashMap<&'s str, F>) -> Vec<&'s str> {
+    table.keys().copied().sorted_unstable().collect()
+}
+
+/// Names of the methods defined for all list types by
+/// [`build_formattable_list_method()`] and
+/// [`build_unformattable_list_method()`].
+const LIST_METHOD_NAMES: &[&str] = &[
+    "all", "any", "filter", "first", "is_empty", "last", "len", "map", "max", "min", "nth",
+    "reverse", "skip", "sort", "sort_by", "sum", "table", "take", "unique",
+];
+
+/// Names of the methods defined by [`build_list_group_method()`].
+pub const LIST_GROUP_METHOD_NAMES: &[&str] = &["items", "key"];
+
+/// Names of the methods defined by [`build_list_enumerated_method()`].
+pub const LIST_ENUMERATED_METHOD_NAMES: &[&str] = &["index", "item"];
+
+/// Names of the methods defined by [`build_list_stream_method()`].
+pub const LIST_STREAM_METHOD_NAMES: &[&str] = &["join", "map"];
+
+/// Returns the names of the list methods in sorted order. `join` is included
+/// if the list items are `formattable`. `extra_names` are the methods
+/// specific to the list type.
+pub fn list_method_names(formattable: bool, extra_names: &[&'static str]) -> Vec<&'static str> {
+    let join = formattable.then_some("join");
+    LIST_METHOD_NAMES
+        .iter()
+        .copied()
+        .chain(join)
+        .chain(extra_names.iter().copied())
+        .sorted_unstable()
+        .collect()
 }
 
 pub fn merge_fn_map<'s, F>(base: &mut HashMap<&'s str, F>, extension: HashMap<&'s str, F>) {
@@ -418,14 +778,17 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
         CoreTemplateBuildFnTable {
             functions: builtin_functions(),
             string_methods: builtin_string_methods(),
-            boolean_methods: HashMap::new(),
-            integer_methods: HashMap::new(),
+            boolean_methods: builtin_boolean_methods(),
+            integer_methods: builtin_integer_methods(),
+            float_methods: builtin_float_methods(),
             config_value_methods: builtin_config_value_methods(),
             signature_methods: builtin_signature_methods(),
             email_methods: builtin_email_methods(),
             size_hint_methods: builtin_size_hint_methods(),
             timestamp_methods: builtin_timestamp_methods(),
             timestamp_range_methods: builtin_timestamp_range_methods(),
+            duration_methods: builtin_duration_methods(),
+            bytes_methods: builtin_bytes_methods(),
         }
     }
 
@@ -435,12 +798,15 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
             string_methods: HashMap::new(),
             boolean_methods: HashMap::new(),
             integer_methods: HashMap::new(),
+            float_methods: HashMap::new(),
             config_value_methods: HashMap::new(),
             signature_methods: HashMap::new(),
             email_methods: HashMap::new(),
             size_hint_methods: HashMap::new(),
             timestamp_methods: HashMap::new(),
             timestamp_range_methods: HashMap::new(),
+            duration_methods: HashMap::new(),
+            bytes_methods: HashMap::new(),
         }
     }
 
@@ -450,24 +816,30 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
             string_methods,
             boolean_methods,
             integer_methods,
+            float_methods,
             config_value_methods,
             signature_methods,
             email_methods,
             size_hint_methods,
             timestamp_methods,
             timestamp_range_methods,
+            duration_methods,
+            bytes_methods,
         } = extension;
 
         merge_fn_map(&mut self.functions, functions);
         merge_fn_map(&mut self.string_methods, string_methods);
         merge_fn_map(&mut self.boolean_methods, boolean_methods);
         merge_fn_map(&mut self.integer_methods, integer_methods);
+        merge_fn_map(&mut self.float_methods, float_methods);
         merge_fn_map(&mut self.config_value_methods, config_value_methods);
         merge_fn_map(&mut self.signature_methods, signature_methods);
         merge_fn_map(&mut self.email_methods, email_methods);
         merge_fn_map(&mut self.size_hint_methods, size_hint_methods);
         merge_fn_map(&mut self.timestamp_methods, timestamp_methods);
         merge_fn_map(&mut self.timestamp_range_methods, timestamp_range_methods);
+        merge_fn_map(&mut self.duration_methods, duration_methods);
+        merge_fn_map(&mut self.bytes_methods, bytes_methods);
     }
 
     /// Translates the function call node `function` by using this symbol table.
@@ -500,6 +872,54 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
                 let build = template_parser::lookup_method(type_name, table, function)?;
                 build(language, diagnostics, build_ctx, property, function)
             }
+            CoreTemplatePropertyKind::StringOpt(property) if function.name == "map_or" => {
+                build_map_or_operation(
+                    language,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    L::Property::wrap_string,
+                )
+            }
+            CoreTemplatePropertyKind::StringOpt(property) => {
+                let type_name = "String";
+                let table = &self.string_methods;
+                let build = template_parser::lookup_method(type_name, table, function)?;
+                let inner_property = property.try_unwrap(type_name).into_dyn();
+                build(language, diagnostics, build_ctx, inner_property, function)
+            }
+            CoreTemplatePropertyKind::StringList(property) if function.name == "chunk" => {
+                build_chunk_operation(
+                    language,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    L::Property::wrap_string_list_list,
+                )
+            }
+            CoreTemplatePropertyKind::StringList(property) if function.name == "group_by" => {
+                build_group_by_operation(
+                    language,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    L::Property::wrap_string,
+                    L::Property::wrap_string_group_list,
+                )
+            }
+            CoreTemplatePropertyKind::StringList(property) if function.name == "enumerate" => {
+                build_enumerate_operation(
+                    language,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    L::Property::wrap_string_enumerated_list,
+                )
+            }
             CoreTemplatePropertyKind::StringList(property) => {
                 // TODO: migrate to table?
                 build_formattable_list_method(
@@ -509,9 +929,77 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
                     property,
                     function,
                     L::Property::wrap_string,
+                    L::Property::wrap_string_opt,
                     L::Property::wrap_string_list,
                 )
             }
+            CoreTemplatePropertyKind::StringListList(property) if function.name == "flatten" => {
+                build_flatten_operation::<L, _>(property, function, L::Property::wrap_string_list)
+            }
+            CoreTemplatePropertyKind::StringListList(property) => build_unformattable_list_method(
+                language,
+                diagnostics,
+                build_ctx,
+                property,
+                function,
+                L::Property::wrap_string_list,
+                |property| {
+                    let type_name = "List<String>";
+                    L::Property::wrap_string_list(property.try_unwrap(type_name).into_dyn())
+                },
+                L::Property::wrap_string_list_list,
+            ),
+            CoreTemplatePropertyKind::StringGroup(property) => build_list_group_method(
+                type_name,
+                property,
+                function,
+                L::Property::wrap_string_list,
+            ),
+            CoreTemplatePropertyKind::StringGroupList(property) => build_unformattable_list_method(
+                language,
+                diagnostics,
+                build_ctx,
+                property,
+                function,
+                L::Property::wrap_string_group,
+                |property| {
+                    let type_name = "Group<String>";
+                    L::Property::wrap_string_group(property.try_unwrap(type_name).into_dyn())
+                },
+                L::Property::wrap_string_group_list,
+            ),
+            CoreTemplatePropertyKind::StringEnumerated(property) => build_list_enumerated_method(
+                type_name,
+                property,
+                function,
+                L::Property::wrap_string,
+            ),
+            CoreTemplatePropertyKind::StringEnumeratedList(property) => {
+                build_unformattable_list_method(
+                    language,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    L::Property::wrap_string_enumerated,
+                    |property| {
+                        let type_name = "Enumerated<String>";
+                        L::Property::wrap_string_enumerated(
+                            property.try_unwrap(type_name).into_dyn(),
+                        )
+                    },
+                    L::Property::wrap_string_enumerated_list,
+                )
+            }
+            CoreTemplatePropertyKind::StringStream(property) => build_list_stream_method(
+                language,
+                diagnostics,
+                build_ctx,
+                type_name,
+                property,
+                function,
+                L::Property::wrap_string,
+            ),
             CoreTemplatePropertyKind::Boolean(property) => {
                 let table = &self.boolean_methods;
                 let build = template_parser::lookup_method(type_name, table, function)?;
@@ -522,6 +1010,16 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
                 let build = template_parser::lookup_method(type_name, table, function)?;
                 build(language, diagnostics, build_ctx, property, function)
             }
+            CoreTemplatePropertyKind::IntegerOpt(property) if function.name == "map_or" => {
+                build_map_or_operation(
+                    language,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    L::Property::wrap_integer,
+                )
+            }
             CoreTemplatePropertyKind::IntegerOpt(property) => {
                 let type_name = "Integer";
                 let table = &self.integer_methods;
@@ -529,6 +1027,11 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
                 let inner_property = property.try_unwrap(type_name).into_dyn();
                 build(language, diagnostics, build_ctx, inner_property, function)
             }
+            CoreTemplatePropertyKind::Float(property) => {
+                let table = &self.float_methods;
+                let build = template_parser::lookup_method(type_name, table, function)?;
+                build(language, diagnostics, build_ctx, property, function)
+            }
             CoreTemplatePropertyKind::ConfigValue(property) => {
                 let table = &self.config_value_methods;
                 let build = template_parser::lookup_method(type_name, table, function)?;
@@ -559,9 +1062,19 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
                 let build = template_parser::lookup_method(type_name, table, function)?;
                 build(language, diagnostics, build_ctx, property, function)
             }
-            CoreTemplatePropertyKind::Template(_) => {
+            CoreTemplatePropertyKind::Duration(property) => {
+                let table = &self.duration_methods;
+                let build = template_parser::lookup_method(type_name, table, function)?;
+                build(language, diagnostics, build_ctx, property, function)
+            }
+            CoreTemplatePropertyKind::Bytes(property) => {
+                let table = &self.bytes_methods;
+                let build = template_parser::lookup_method(type_name, table, function)?;
+                build(language, diagnostics, build_ctx, property, function)
+            }
+            CoreTemplatePropertyKind::Template(template) => {
                 // TODO: migrate to table?
-                Err(TemplateParseError::no_such_method(type_name, function))
+                build_template_method(language, diagnostics, build_ctx, template, function)
             }
             CoreTemplatePropertyKind::ListTemplate(template) => {
                 // TODO: migrate to table?
@@ -569,6 +1082,45 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
             }
         }
     }
+
+    /// Returns the names of the methods available for the given core
+    /// `type_name`, or `None` if the type is unknown.
+    pub fn method_names(&self, type_name: &str) -> Option<Vec<&'static str>> {
+        let with_map_or = |names: Vec<&'static str>| {
+            names
+                .into_iter()
+                .chain(["map_or"])
+                .sorted_unstable()
+                .collect()
+        };
+        let names = match type_name {
+            "String" => sorted_fn_names(&self.string_methods),
+            "Option<String>" => with_map_or(sorted_fn_names(&self.string_methods)),
+            "List<String>" => list_method_names(true, &["chunk", "enumerate", "group_by"]),
+            "List<List<String>>" => list_method_names(false, &["flatten"]),
+            "Group<String>" => LIST_GROUP_METHOD_NAMES.to_vec(),
+            "List<Group<String>>" => list_method_names(false, &[]),
+            "Enumerated<String>" => LIST_ENUMERATED_METHOD_NAMES.to_vec(),
+            "List<Enumerated<String>>" => list_method_names(false, &[]),
+            "Stream<String>" => LIST_STREAM_METHOD_NAMES.to_vec(),
+            "Boolean" => sorted_fn_names(&self.boolean_methods),
+            "Integer" => sorted_fn_names(&self.integer_methods),
+            "Option<Integer>" => with_map_or(sorted_fn_names(&self.integer_methods)),
+            "Float" => sorted_fn_names(&self.float_methods),
+            "ConfigValue" => sorted_fn_names(&self.config_value_methods),
+            "Signature" => sorted_fn_names(&self.signature_methods),
+            "Email" => sorted_fn_names(&self.email_methods),
+            "SizeHint" => sorted_fn_names(&self.size_hint_methods),
+            "Timestamp" => sorted_fn_names(&self.timestamp_methods),
+            "TimestampRange" => sorted_fn_names(&self.timestamp_range_methods),
+            "Duration" => sorted_fn_names(&self.duration_methods),
+            "Bytes" => sorted_fn_names(&self.bytes_methods),
+            "Template" => vec!["surround"],
+            "ListTemplate" => vec!["join"],
+            _ => return None,
+        };
+        Some(names)
+    }
 }
 
 /// Opaque struct that represents a template value.
@@ -602,6 +1154,22 @@ impl<'a, P: CoreTemplatePropertyVar<'a>> Expression<P> {
         self.property.try_into_integer()
     }
 
+    pub fn try_into_float(self) -> Option<BoxedTemplateProperty<'a, f64>> {
+        self.property.try_into_float()
+    }
+
+    pub fn try_into_timestamp(self) -> Option<BoxedTemplateProperty<'a, Timestamp>> {
+        self.property.try_into_timestamp()
+    }
+
+    pub fn try_into_duration(self) -> Option<BoxedTemplateProperty<'a, TimeDelta>> {
+        self.property.try_into_duration()
+    }
+
+    pub fn try_into_string_list(self) -> Option<BoxedTemplateProperty<'a, Vec<String>>> {
+        self.property.try_into_string_list()
+    }
+
     pub fn try_into_plain_text(self) -> Option<BoxedTemplateProperty<'a, String>> {
         self.property.try_into_plain_text()
     }
@@ -632,8 +1200,12 @@ pub struct BuildContext<'i, P> {
     /// This could be `local_variables["self"]`, but keyword lookup shouldn't be
     /// overridden by a user-defined `self` variable.
     self_variable: &'i (dyn Fn() -> P),
+    /// Map of built nodes to their types, recorded only for debugging.
+    node_types: Option<&'i NodeTypesMap>,
 }
 
+type NodeTypesMap = RefCell<HashMap<*const (), &'static str>>;
+
 fn build_keyword<'a, L: TemplateLanguage<'a> + ?Sized>(
     language: &L,
     diagnostics: &mut TemplateDiagnostics,
@@ -659,7 +1231,7 @@ fn build_keyword<'a, L: TemplateLanguage<'a> + ?Sized>(
                     // TODO: filter methods by arity?
                     candidates: candidates.clone(),
                 };
-                TemplateParseError::with_span(kind, name_span)
+                err.with_kind(kind, name_span)
             }
             // Since keyword is a 0-ary method, any argument errors mean there's
             // no such keyword.
@@ -689,12 +1261,19 @@ fn build_unary_operation<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::Property::wrap_boolean(arg.map(|v| !v).into_dyn()))
         }
         UnaryOp::Negate => {
-            let arg = expect_integer_expression(language, diagnostics, build_ctx, arg_node)?;
-            let out = arg.and_then(|v| {
-                v.checked_neg()
-                    .ok_or_else(|| TemplatePropertyError("Attempt to negate with overflow".into()))
-            });
-            Ok(L::Property::wrap_integer(out.into_dyn()))
+            match expect_numeric_expression(language, diagnostics, build_ctx, arg_node)? {
+                NumericProperty::Integer(arg) => {
+                    let out = arg.and_then(|v| {
+                        v.checked_neg().ok_or_else(|| {
+                            TemplatePropertyError("Attempt to negate with overflow".into())
+                        })
+                    });
+                    Ok(L::Property::wrap_integer(out.into_dyn()))
+                }
+                NumericProperty::Float(arg) => {
+                    Ok(L::Property::wrap_float(arg.map(|v| -v).into_dyn()))
+                }
+            }
         }
     }
 }
@@ -727,8 +1306,11 @@ fn build_binary_operation<'a, L: TemplateLanguage<'a> + ?Sized>(
             let lty = lhs.type_name();
             let rty = rhs.type_name();
             let eq = lhs.try_into_eq(rhs).ok_or_else(|| {
-                let message = format!("Cannot compare expressions of type `{lty}` and `{rty}`");
-                TemplateParseError::expression(message, span)
+                let kind = TemplateParseErrorKind::IncomparableTypes {
+                    lhs: lty.to_owned(),
+                    rhs: rty.to_owned(),
+                };
+                TemplateParseError::with_span(kind, span)
             })?;
             let out = match op {
                 BinaryOp::Eq => eq.into_dyn(),
@@ -743,8 +1325,11 @@ fn build_binary_operation<'a, L: TemplateLanguage<'a> + ?Sized>(
             let lty = lhs.type_name();
             let rty = rhs.type_name();
             let cmp = lhs.try_into_cmp(rhs).ok_or_else(|| {
-                let message = format!("Cannot compare expressions of type `{lty}` and `{rty}`");
-                TemplateParseError::expression(message, span)
+                let kind = TemplateParseErrorKind::IncomparableTypes {
+                    lhs: lty.to_owned(),
+                    rhs: rty.to_owned(),
+                };
+                TemplateParseError::with_span(kind, span)
             })?;
             let out = match op {
                 BinaryOp::Ge => cmp.map(|ordering| ordering.is_ge()).into_dyn(),
@@ -755,6 +1340,129 @@ fn build_binary_operation<'a, L: TemplateLanguage<'a> + ?Sized>(
             };
             Ok(L::Property::wrap_boolean(out))
         }
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+            let lhs = expect_arithmetic_expression(language, diagnostics, build_ctx, lhs_node)?;
+            let rhs = expect_arithmetic_expression(language, diagnostics, build_ctx, rhs_node)?;
+            match (lhs, rhs) {
+                (
+                    ArithmeticProperty::Numeric(NumericProperty::Integer(lhs)),
+                    ArithmeticProperty::Numeric(NumericProperty::Integer(rhs)),
+                ) => {
+                    let out = (lhs, rhs).and_then(move |(l, r)| integer_arithmetic(op, l, r));
+                    Ok(L::Property::wrap_integer(out.into_dyn()))
+                }
+                (ArithmeticProperty::Numeric(lhs), ArithmeticProperty::Numeric(rhs)) => {
+                    let lhs = lhs.into_float();
+                    let rhs = rhs.into_float();
+                    let out = (lhs, rhs).and_then(move |(l, r)| float_arithmetic(op, l, r));
+                    Ok(L::Property::wrap_float(out.into_dyn()))
+                }
+                (ArithmeticProperty::Timestamp(lhs), ArithmeticProperty::Timestamp(rhs))
+                    if op == BinaryOp::Sub =>
+                {
+                    let out = (lhs, rhs).and_then(|(l, r)| {
+                        l.timestamp
+                            .0
+                            .checked_sub(r.timestamp.0)
+                            .and_then(TimeDelta::try_milliseconds)
+                            .ok_or_else(|| {
+                                TemplatePropertyError("Attempt to subtract with overflow".into())
+                            })
+                    });
+                    Ok(L::Property::wrap_duration(out.into_dyn()))
+                }
+                (ArithmeticProperty::Timestamp(lhs), ArithmeticProperty::Duration(rhs))
+                    if matches!(op, BinaryOp::Add | BinaryOp::Sub) =>
+                {
+                    let out = (lhs, rhs).and_then(move |(l, r)| timestamp_arithmetic(op, l, r));
+                    Ok(L::Property::wrap_timestamp(out.into_dyn()))
+                }
+                (ArithmeticProperty::Duration(lhs), ArithmeticProperty::Timestamp(rhs))
+                    if op == BinaryOp::Add =>
+                {
+                    let out = (rhs, lhs).and_then(move |(r, l)| timestamp_arithmetic(op, r, l));
+                    Ok(L::Property::wrap_timestamp(out.into_dyn()))
+                }
+                (ArithmeticProperty::Duration(lhs), ArithmeticProperty::Duration(rhs))
+                    if matches!(op, BinaryOp::Add | BinaryOp::Sub) =>
+                {
+                    let out = (lhs, rhs).and_then(move |(l, r)| duration_arithmetic(op, l, r));
+                    Ok(L::Property::wrap_duration(out.into_dyn()))
+                }
+                (lhs, rhs) => {
+                    let message = format!(
+                        "Cannot apply arithmetic operator to expressions of type `{}` and `{}`",
+                        lhs.type_name(),
+                        rhs.type_name()
+                    );
+                    Err(TemplateParseError::expression(message, span))
+                }
+            }
+        }
+    }
+}
+
+fn integer_arithmetic(op: BinaryOp, lhs: i64, rhs: i64) -> Result<i64, TemplatePropertyError> {
+    let (value, op_desc) = match op {
+        BinaryOp::Add => (lhs.checked_add(rhs), "add"),
+        BinaryOp::Sub => (lhs.checked_sub(rhs), "subtract"),
+        BinaryOp::Mul => (lhs.checked_mul(rhs), "multiply"),
+        BinaryOp::Div | BinaryOp::Rem if rhs == 0 => {
+            return Err(TemplatePropertyError("Attempt to divide by zero".into()));
+        }
+        BinaryOp::Div => (lhs.checked_div(rhs), "divide"),
+        BinaryOp::Rem => (lhs.checked_rem(rhs), "calculate the remainder"),
+        _ => unreachable!(),
+    };
+    value.ok_or_else(|| TemplatePropertyError(format!("Attempt to {op_desc} with overflow").into()))
+}
+
+fn timestamp_arithmetic(
+    op: BinaryOp,
+    lhs: Timestamp,
+    rhs: TimeDelta,
+) -> Result<Timestamp, TemplatePropertyError> {
+    let (millis, op_desc) = match op {
+        BinaryOp::Add => (lhs.timestamp.0.checked_add(rhs.num_milliseconds()), "add"),
+        BinaryOp::Sub => (
+            lhs.timestamp.0.checked_sub(rhs.num_milliseconds()),
+            "subtract",
+        ),
+        _ => unreachable!(),
+    };
+    let millis = millis.ok_or_else(|| {
+        TemplatePropertyError(format!("Attempt to {op_desc} with overflow").into())
+    })?;
+    Ok(Timestamp {
+        timestamp: MillisSinceEpoch(millis),
+        tz_offset: lhs.tz_offset,
+    })
+}
+
+fn duration_arithmetic(
+    op: BinaryOp,
+    lhs: TimeDelta,
+    rhs: TimeDelta,
+) -> Result<TimeDelta, TemplatePropertyError> {
+    let (value, op_desc) = match op {
+        BinaryOp::Add => (lhs.checked_add(&rhs), "add"),
+        BinaryOp::Sub => (lhs.checked_sub(&rhs), "subtract"),
+        _ => unreachable!(),
+    };
+    value.ok_or_else(|| TemplatePropertyError(format!("Attempt to {op_desc} with overflow").into()))
+}
+
+fn float_arithmetic(op: BinaryOp, lhs: f64, rhs: f64) -> Result<f64, TemplatePropertyError> {
+    match op {
+        BinaryOp::Add => Ok(lhs + rhs),
+        BinaryOp::Sub => Ok(lhs - rhs),
+        BinaryOp::Mul => Ok(lhs * rhs),
+        BinaryOp::Div | BinaryOp::Rem if rhs == 0.0 => {
+            Err(TemplatePropertyError("Attempt to divide by zero".into()))
+        }
+        BinaryOp::Div => Ok(lhs / rhs),
+        BinaryOp::Rem => Ok(lhs % rhs),
+        _ => unreachable!(),
     }
 }
 
@@ -763,12 +1471,28 @@ fn builtin_string_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
     // Not using maplit::hashmap!{} or custom declarative macro here because
     // code completion inside macro is quite restricted.
     let mut map = TemplateBuildMethodFnMap::<L, String>::new();
-    map.insert(
-        "len",
+    let build_byte_len: TemplateBuildMethodFn<'a, L, String> =
         |_language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
             let out_property = self_property.and_then(|s| Ok(s.len().try_into()?));
             Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        };
+    map.insert("len", build_byte_len);
+    map.insert("byte_len", build_byte_len);
+    map.insert(
+        "is_empty",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| s.is_empty());
+            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "char_count",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(|s| Ok(s.chars().count().try_into()?));
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
         },
     );
     map.insert(
@@ -783,6 +1507,45 @@ fn builtin_string_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::Property::wrap_boolean(out_property.into_dyn()))
         },
     );
+    map.insert(
+        "contains_ignore_case",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [needle_node] = function.expect_exact_arguments()?;
+            let needle_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, needle_node)?;
+            let out_property = (self_property, needle_property)
+                .map(|(haystack, needle)| contains_ignore_case(&haystack, &needle));
+            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "matches",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            let [pattern_node] = function.expect_exact_arguments()?;
+            let regex = expect_regex_literal(pattern_node)?;
+            let out_property = self_property.map(move |haystack| regex.is_match(&haystack));
+            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "captures",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            let [pattern_node] = function.expect_exact_arguments()?;
+            let regex = expect_regex_literal(pattern_node)?;
+            let out_property = self_property.map(move |haystack| {
+                let Some(captures) = regex.captures(&haystack) else {
+                    return vec![];
+                };
+                // Groups that didn't participate in the match are mapped to
+                // empty strings so the indices stay aligned with the pattern.
+                captures
+                    .iter()
+                    .map(|m| m.map_or("", |m| m.as_str()).to_owned())
+                    .collect()
+            });
+            Ok(L::Property::wrap_string_list(out_property.into_dyn()))
+        },
+    );
     map.insert(
         "starts_with",
         |language, diagnostics, build_ctx, self_property, function| {
@@ -836,26 +1599,103 @@ fn builtin_string_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
         },
     );
     map.insert(
-        "trim",
-        |_language, _diagnostics, _build_ctx, self_property, function| {
-            function.expect_no_arguments()?;
-            let out_property = self_property.map(|s| s.trim().to_owned());
+        "replace",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [from_node, to_node] = function.expect_exact_arguments()?;
+            let from_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, from_node)?;
+            let to_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, to_node)?;
+            let out_property =
+                (self_property, from_property, to_property).map(|(haystack, from, to)| {
+                    // str::replace() would insert `to` at every char boundary
+                    if from.is_empty() {
+                        haystack
+                    } else {
+                        haystack.replace(&from, &to)
+                    }
+                });
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
     map.insert(
-        "trim_start",
-        |_language, _diagnostics, _build_ctx, self_property, function| {
-            function.expect_no_arguments()?;
-            let out_property = self_property.map(|s| s.trim_start().to_owned());
+        "replace_regex",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [pattern_node, replacement_node] = function.expect_exact_arguments()?;
+            let regex = expect_regex_literal(pattern_node)?;
+            let replacement_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, replacement_node)?;
+            let out_property =
+                (self_property, replacement_property).map(move |(haystack, replacement)| {
+                    regex.replace_all(&haystack, &replacement).into_owned()
+                });
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
     map.insert(
-        "trim_end",
-        |_language, _diagnostics, _build_ctx, self_property, function| {
-            function.expect_no_arguments()?;
-            let out_property = self_property.map(|s| s.trim_end().to_owned());
+        "count_matches",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [needle_node] = function.expect_exact_arguments()?;
+            let needle_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, needle_node)?;
+            let out_property = (self_property, needle_property).and_then(|(haystack, needle)| {
+                // str::matches() would match at every char boundary
+                if needle.is_empty() {
+                    return Err(TemplatePropertyError("Pattern must not be empty".into()));
+                }
+                Ok(i64::try_from(haystack.matches(&needle).count())?)
+            });
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "count_matches_regex",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            let [pattern_node] = function.expect_exact_arguments()?;
+            let regex = expect_regex_literal(pattern_node)?;
+            if regex.as_str().is_empty() {
+                return Err(TemplateParseError::expression(
+                    "Pattern must not be empty",
+                    pattern_node.span,
+                ));
+            }
+            let out_property = self_property
+                .and_then(move |haystack| Ok(i64::try_from(regex.find_iter(&haystack).count())?));
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "trim",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| s.trim().to_owned());
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "trim_start",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| s.trim_start().to_owned());
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "trim_end",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| s.trim_end().to_owned());
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "trim_matches",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [chars_node] = function.expect_exact_arguments()?;
+            let chars_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, chars_node)?;
+            let out_property = (self_property, chars_property)
+                .map(|(s, chars)| s.trim_matches(|c| chars.contains(c)).to_owned());
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
@@ -877,6 +1717,153 @@ fn builtin_string_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
+    map.insert(
+        "pad_start",
+        |language, diagnostics, build_ctx, self_property, function| {
+            build_string_pad_method(
+                language,
+                diagnostics,
+                build_ctx,
+                self_property,
+                function,
+                |n| (n, 0),
+            )
+        },
+    );
+    map.insert(
+        "pad_end",
+        |language, diagnostics, build_ctx, self_property, function| {
+            build_string_pad_method(
+                language,
+                diagnostics,
+                build_ctx,
+                self_property,
+                function,
+                |n| (0, n),
+            )
+        },
+    );
+    map.insert(
+        "pad_centered",
+        |language, diagnostics, build_ctx, self_property, function| {
+            build_string_pad_method(
+                language,
+                diagnostics,
+                build_ctx,
+                self_property,
+                function,
+                |n| (n / 2, n - n / 2),
+            )
+        },
+    );
+    map.insert(
+        "hash_to",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [count_node] = function.expect_exact_arguments()?;
+            let count_property =
+                expect_integer_expression(language, diagnostics, build_ctx, count_node)?;
+            let out_property = (self_property, count_property).and_then(|(s, count)| {
+                let count = u64::try_from(count)
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| {
+                        TemplatePropertyError("Bucket count must be greater than 0".into())
+                    })?;
+                // The result is less than count, which fits in i64
+                Ok((fnv1a_hash(s.as_bytes()) % count) as i64)
+            });
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "as_bool",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| !matches!(s.as_str(), "" | "0" | "false"));
+            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "parse_int",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [radix_node]) = function.expect_arguments()?;
+            let radix_property = match radix_node {
+                Some(node) => expect_integer_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(10).into_dyn(),
+            };
+            let out_property = (self_property, radix_property).and_then(|(s, radix)| {
+                let radix = u32::try_from(radix)
+                    .ok()
+                    .filter(|r| (2..=36).contains(r))
+                    .ok_or_else(|| {
+                        TemplatePropertyError("Radix must be between 2 and 36".into())
+                    })?;
+                i64::from_str_radix(&s, radix).map_err(|err| {
+                    TemplatePropertyError(format!("Failed to parse {s:?} as integer: {err}").into())
+                })
+            });
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "parse_timestamp",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            let [format_node] = function.expect_exact_arguments()?;
+            let format =
+                template_parser::expect_string_literal_with(format_node, |format, span| {
+                    time_util::FormattingItems::parse(format)
+                        .ok_or_else(|| TemplateParseError::expression("Invalid time format", span))
+                })?
+                .into_owned();
+            let out_property = self_property.and_then(move |s| {
+                time_util::parse_timestamp_with(&s, &format).map_err(|err| {
+                    let message = format!("Failed to parse {s:?} as timestamp: {err}");
+                    TemplatePropertyError(message.into())
+                })
+            });
+            Ok(L::Property::wrap_timestamp(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "indent_rest",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [prefix_node] = function.expect_exact_arguments()?;
+            let prefix_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, prefix_node)?;
+            let out_property = (self_property, prefix_property).map(|(s, prefix)| {
+                let mut out = String::with_capacity(s.len());
+                // Like indent(), empty lines aren't prefixed, and the trailing
+                // newline doesn't start a new line.
+                for (i, line) in s.split_inclusive('\n').enumerate() {
+                    if i > 0 && line != "\n" {
+                        out.push_str(&prefix);
+                    }
+                    out.push_str(line);
+                }
+                out
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "word_wrap",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [width_node] = function.expect_exact_arguments()?;
+            let width_property =
+                expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
+            let out_property = (self_property, width_property).map(|(s, width)| {
+                // Like fill(), words are separated by ASCII spaces, but long
+                // words are broken at the display width as a fallback.
+                let options = textwrap::Options::new(width)
+                    .word_separator(textwrap::WordSeparator::AsciiSpace)
+                    .word_splitter(textwrap::WordSplitter::NoHyphenation)
+                    .wrap_algorithm(textwrap::WrapAlgorithm::FirstFit)
+                    .break_words(true);
+                textwrap::wrap(&s, options).join("\n")
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
     map.insert(
         "first_line",
         |_language, _diagnostics, _build_ctx, self_property, function| {
@@ -894,6 +1881,112 @@ fn builtin_string_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::Property::wrap_string_list(out_property.into_dyn()))
         },
     );
+    map.insert(
+        "normalize_newlines",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [collapse_trailing_node]) =
+                function.expect_named_arguments(&["collapse_trailing"])?;
+            let collapse_trailing_property = match collapse_trailing_node {
+                Some(node) => expect_boolean_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(false).into_dyn(),
+            };
+            let out_property = (self_property, collapse_trailing_property)
+                .map(|(s, collapse_trailing)| normalize_newlines(&s, collapse_trailing));
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "split",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([separator_node], [limit_node]) = function.expect_arguments()?;
+            let separator_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, separator_node)?;
+            let limit_property = match limit_node {
+                Some(node) => expect_usize_expression(language, diagnostics, build_ctx, node)?
+                    .map(Some)
+                    .into_dyn(),
+                None => Literal(None).into_dyn(),
+            };
+            let out_property =
+                (self_property, separator_property, limit_property).map(|(s, separator, limit)| {
+                    // Empty string has no items, which is consistent with lines()
+                    if s.is_empty() {
+                        return vec![];
+                    }
+                    if let Some(limit) = limit {
+                        s.splitn(limit, &separator).map(|l| l.to_owned()).collect()
+                    } else {
+                        s.split(&separator).map(|l| l.to_owned()).collect()
+                    }
+                });
+            Ok(L::Property::wrap_string_list(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "reverse",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            // Reverse by grapheme cluster so combining characters and emoji
+            // sequences are kept intact.
+            let out_property = self_property.map(|s| s.graphemes(true).rev().collect());
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "truncate",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([count_node], [ellipsis_node]) = function.expect_arguments()?;
+            let count_property =
+                expect_usize_expression(language, diagnostics, build_ctx, count_node)?;
+            let ellipsis_property = match ellipsis_node {
+                Some(node) => expect_plain_text_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(String::new()).into_dyn(),
+            };
+            let out_property =
+                (self_property, count_property, ellipsis_property).map(|(s, count, ellipsis)| {
+                    if s.graphemes(true).nth(count).is_none() {
+                        return s;
+                    }
+                    // The ellipsis counts toward the budget. If the ellipsis
+                    // itself is too long, it is truncated.
+                    let ellipsis_count = ellipsis.graphemes(true).count();
+                    let kept_count = count.saturating_sub(ellipsis_count);
+                    s.graphemes(true)
+                        .take(kept_count)
+                        .chain(ellipsis.graphemes(true))
+                        .take(count)
+                        .collect()
+                });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "repeat",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [count_node] = function.expect_exact_arguments()?;
+            let count_property =
+                expect_usize_expression(language, diagnostics, build_ctx, count_node)?;
+            let out_property = (self_property, count_property).and_then(|(s, count)| {
+                if s.is_empty() {
+                    return Ok(s);
+                }
+                let len = s
+                    .len()
+                    .checked_mul(count)
+                    .filter(|&len| isize::try_from(len).is_ok())
+                    .ok_or_else(|| {
+                        TemplatePropertyError("Attempt to repeat with overflow".into())
+                    })?;
+                let mut out = String::new();
+                out.try_reserve_exact(len)?;
+                for _ in 0..count {
+                    out.push_str(&s);
+                }
+                Ok(out)
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
     map.insert(
         "upper",
         |_language, _diagnostics, _build_ctx, self_property, function| {
@@ -910,6 +2003,76 @@ fn builtin_string_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
+    map.insert(
+        "title_case",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| {
+                let mut out = String::with_capacity(s.len());
+                let mut at_word_start = true;
+                for c in s.chars() {
+                    if at_word_start {
+                        out.extend(c.to_uppercase());
+                    } else {
+                        out.push(c);
+                    }
+                    at_word_start = c.is_whitespace();
+                }
+                out
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "surround",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let template = self_property.into_template();
+            build_template_method(language, diagnostics, build_ctx, template, function)
+        },
+    );
+    map.insert(
+        "capitalize",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| {
+                let mut chars = s.chars();
+                match chars.next() {
+                    Some(c) => c.to_uppercase().chain(chars).collect(),
+                    None => s,
+                }
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "escape_control",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| escape_control_chars(&s));
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "strip_ansi",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| strip_ansi_escapes(&s));
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "escape_markdown",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [link_text_node]) = function.expect_named_arguments(&["link_text"])?;
+            let link_text_property = match link_text_node {
+                Some(node) => expect_boolean_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(false).into_dyn(),
+            };
+            let out_property = (self_property, link_text_property)
+                .map(|(s, link_text)| escape_markdown(&s, link_text));
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
     map.insert(
         "escape_json",
         |_language, _diagnostics, _build_ctx, self_property, function| {
@@ -918,6 +2081,33 @@ fn builtin_string_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
+    map.insert(
+        "shell_quote",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| format!("'{}'", s.replace('\'', r"'\''")));
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "csv_quote",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| format!(r#""{}""#, s.replace('"', r#""""#)));
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "default",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [fallback_node] = function.expect_exact_arguments()?;
+            let fallback =
+                expect_template_expression(language, diagnostics, build_ctx, fallback_node)?;
+            // Same as coalesce(self, fallback)
+            let template = CoalesceTemplate(vec![self_property.into_template(), fallback]);
+            Ok(L::Property::wrap_template(Box::new(template)))
+        },
+    );
     map
 }
 
@@ -937,81 +2127,545 @@ fn string_index_to_char_boundary(s: &str, i: isize) -> usize {
     }
 }
 
-fn builtin_config_value_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
-) -> TemplateBuildMethodFnMap<'a, L, ConfigValue> {
-    fn extract<'de, T: Deserialize<'de>>(value: ConfigValue) -> Result<T, TemplatePropertyError> {
-        T::deserialize(value.into_deserializer())
-            // map to err.message() because TomlError appends newline to it
-            .map_err(|err| TemplatePropertyError(err.message().into()))
-    }
+/// Builds `.pad_*(width[, fill_char])` method which pads the string to the
+/// given display width.
+///
+/// The `split_fill_width` function determines the numbers of leading and
+/// trailing fill characters.
+fn build_string_pad_method<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: BoxedTemplateProperty<'a, String>,
+    function: &FunctionCallNode,
+    split_fill_width: fn(usize) -> (usize, usize),
+) -> TemplateParseResult<L::Property> {
+    let ([width_node], [fill_char_node]) = function.expect_named_arguments(&["", "fill_char"])?;
+    let width_property = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
+    let fill_char_property = match fill_char_node {
+        Some(node) => expect_plain_text_expression(language, diagnostics, build_ctx, node)?,
+        None => Literal(" ".to_owned()).into_dyn(),
+    };
+    let out_property = (self_property, width_property, fill_char_property).and_then(
+        move |(s, width, fill_char)| {
+            if fill_char.width() != 1 {
+                return Err(TemplatePropertyError(
+                    format!("Fill char must be 1 column wide: {fill_char:?}").into(),
+                ));
+            }
+            let (start, end) = split_fill_width(width.saturating_sub(s.width()));
+            let len = fill_char
+                .len()
+                .checked_mul(start + end)
+                .and_then(|len| len.checked_add(s.len()))
+                .filter(|&len| isize::try_from(len).is_ok())
+                .ok_or_else(|| TemplatePropertyError("Attempt to pad with overflow".into()))?;
+            let mut out = String::new();
+            out.try_reserve_exact(len)?;
+            for _ in 0..start {
+                out.push_str(&fill_char);
+            }
+            out.push_str(&s);
+            for _ in 0..end {
+                out.push_str(&fill_char);
+            }
+            Ok(out)
+        },
+    );
+    Ok(L::Property::wrap_string(out_property.into_dyn()))
+}
 
+fn builtin_boolean_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, bool> {
     // Not using maplit::hashmap!{} or custom declarative macro here because
     // code completion inside macro is quite restricted.
-    let mut map = TemplateBuildMethodFnMap::<L, ConfigValue>::new();
-    // These methods are called "as_<type>", not "to_<type>" to clarify that
-    // they'll never convert types (e.g. integer to string.) Since templater
-    // doesn't provide binding syntax, there's no need to distinguish between
-    // reference and consuming access.
+    let mut map = TemplateBuildMethodFnMap::<L, bool>::new();
     map.insert(
-        "as_boolean",
+        "to_string",
         |_language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
-            let out_property = self_property.and_then(extract);
-            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+            let out_property = self_property.map(|v| v.to_string());
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
     map.insert(
-        "as_integer",
+        "yes_no",
         |_language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
-            let out_property = self_property.and_then(extract);
-            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+            let out_property = self_property.map(|v| if v { "yes" } else { "no" }.to_owned());
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
     map.insert(
-        "as_string",
+        "on_off",
         |_language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
-            let out_property = self_property.and_then(extract);
+            let out_property = self_property.map(|v| if v { "on" } else { "off" }.to_owned());
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
     map.insert(
-        "as_string_list",
+        "check_mark",
         |_language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
-            let out_property = self_property.and_then(extract);
-            Ok(L::Property::wrap_string_list(out_property.into_dyn()))
+            let out_property = self_property.map(|v| if v { "✓" } else { "✗" }.to_owned());
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "then_else",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [true_node, false_node] = function.expect_exact_arguments()?;
+            let true_template =
+                expect_template_expression(language, diagnostics, build_ctx, true_node)?;
+            let false_template =
+                expect_template_expression(language, diagnostics, build_ctx, false_node)?;
+            let template =
+                ConditionalTemplate::new(self_property, true_template, Some(false_template));
+            Ok(L::Property::wrap_template(Box::new(template)))
         },
     );
-    // TODO: add is_<type>() -> Boolean?
-    // TODO: add .get(key) -> ConfigValue or Option<ConfigValue>?
     map
 }
 
-fn builtin_signature_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
-) -> TemplateBuildMethodFnMap<'a, L, Signature> {
+fn builtin_integer_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, i64> {
     // Not using maplit::hashmap!{} or custom declarative macro here because
     // code completion inside macro is quite restricted.
-    let mut map = TemplateBuildMethodFnMap::<L, Signature>::new();
+    let mut map = TemplateBuildMethodFnMap::<L, i64>::new();
     map.insert(
-        "name",
+        "to_string",
         |_language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
-            let out_property = self_property.map(|signature| signature.name);
+            let out_property = self_property.map(|v| v.to_string());
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
     );
     map.insert(
-        "email",
+        "as_bool",
         |_language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
-            let out_property = self_property.map(|signature| signature.email.into());
-            Ok(L::Property::wrap_email(out_property.into_dyn()))
+            let out_property = self_property.map(|v| v != 0);
+            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
         },
     );
     map.insert(
-        "username",
+        "format_number",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [separator_node, group_size_node]) = function.expect_arguments()?;
+            let separator_property = match separator_node {
+                Some(node) => expect_plain_text_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(",".to_owned()).into_dyn(),
+            };
+            let group_size_property = match group_size_node {
+                Some(node) => expect_usize_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(3).into_dyn(),
+            };
+            let out_property = (self_property, separator_property, group_size_property).and_then(
+                |(value, separator, group_size)| {
+                    if group_size == 0 {
+                        return Err(TemplatePropertyError(
+                            "Group size must be greater than 0".into(),
+                        ));
+                    }
+                    Ok(format_number(value, &separator, group_size))
+                },
+            );
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "format_size",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [binary_node]) = function.expect_arguments()?;
+            let binary_property = match binary_node {
+                Some(node) => expect_boolean_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(true).into_dyn(),
+            };
+            let out_property =
+                (self_property, binary_property).map(|(value, binary)| format_size(value, binary));
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "abs",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(|v| {
+                v.checked_abs().ok_or_else(|| {
+                    TemplatePropertyError("Attempt to take absolute value with overflow".into())
+                })
+            });
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "signum",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|v| v.signum());
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "clamp",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [min_node, max_node] = function.expect_exact_arguments()?;
+            let min_property =
+                expect_integer_expression(language, diagnostics, build_ctx, min_node)?;
+            let max_property =
+                expect_integer_expression(language, diagnostics, build_ctx, max_node)?;
+            let out_property =
+                (self_property, min_property, max_property).and_then(|(value, min, max)| {
+                    if min > max {
+                        return Err(TemplatePropertyError(
+                            format!("Minimum {min} is greater than maximum {max}").into(),
+                        ));
+                    }
+                    Ok(value.clamp(min, max))
+                });
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "pluralize",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([singular_node, plural_node], [zero_node]) = function.expect_arguments()?;
+            let singular_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, singular_node)?;
+            let plural_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, plural_node)?;
+            let zero_property = match zero_node {
+                Some(node) => expect_plain_text_expression(language, diagnostics, build_ctx, node)?
+                    .map(Some)
+                    .into_dyn(),
+                None => Literal(None).into_dyn(),
+            };
+            let out_property = (
+                self_property,
+                singular_property,
+                plural_property,
+                zero_property,
+            )
+                .map(|(value, singular, plural, zero)| match (value, zero) {
+                    (0, Some(zero)) => zero,
+                    // English rule: singular for one, plural for the rest
+                    _ if value.unsigned_abs() == 1 => singular,
+                    _ => plural,
+                });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map
+}
+
+/// Formats `value` bytes with a unit prefix, e.g. "1.2 KiB" or "3.4 MB".
+///
+/// Sizes smaller than one kilobyte are printed as exact byte counts. Larger
+/// sizes are rounded to one decimal place.
+fn format_size(value: i64, binary: bool) -> String {
+    let (base, prefixes) = if binary {
+        (1024, ["Ki", "Mi", "Gi", "Ti", "Pi", "Ei"])
+    } else {
+        (1000, ["K", "M", "G", "T", "P", "E"])
+    };
+    let bytes = value.unsigned_abs();
+    if bytes < base {
+        return format!("{value} B");
+    }
+    let sign = if value < 0 { "-" } else { "" };
+    let base = base as f64;
+    let mut scaled = bytes as f64 / base;
+    let mut i = 0;
+    // Move to the next unit if the value would otherwise be rounded up to
+    // the base, e.g. "1024.0 KiB".
+    while (scaled * 10.0).round() / 10.0 >= base && i < prefixes.len() - 1 {
+        scaled /= base;
+        i += 1;
+    }
+    format!("{sign}{scaled:.1} {}B", prefixes[i])
+}
+
+/// Computes 64-bit FNV-1a hash of the `data`.
+///
+/// This is a stable hash function, so the result must not change across
+/// versions and platforms.
+fn fnv1a_hash(data: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x00000100000001b3;
+    data.iter().fold(OFFSET_BASIS, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(PRIME)
+    })
+}
+
+/// Replaces C0 control characters (except for tab and newline) and DEL with
+/// `\xNN` escape sequences so they don't affect the terminal state.
+fn escape_control_chars(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii_control() && !matches!(c, '\t' | '\n') {
+            write!(out, "\\x{:02x}", u32::from(c)).unwrap();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Converts `\r\n` and lone `\r` line endings to `\n`.
+///
+/// If `collapse_trailing` is true, trailing empty lines are removed so that the
+/// text ends with at most one newline.
+fn normalize_newlines(s: &str, collapse_trailing: bool) -> String {
+    let mut out = s.replace("\r\n", "\n").replace('\r', "\n");
+    if collapse_trailing && out.ends_with('\n') {
+        out.truncate(out.trim_end_matches('\n').len());
+        out.push('\n');
+    }
+    out
+}
+
+/// Escapes Markdown syntax characters with backslashes.
+///
+/// If `link_text` is true, only the characters that would terminate the text
+/// of `[text](url)` are escaped.
+fn escape_markdown(s: &str, link_text: bool) -> String {
+    let special_chars = if link_text {
+        r"\[]"
+    } else {
+        r"\`*_{}[]<>()#+-.!|~"
+    };
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special_chars.contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Removes ANSI escape sequences such as colors and cursor movements.
+///
+/// CSI sequences (`ESC [ ... final`), string sequences like OSC hyperlinks
+/// (`ESC ] ... ST`), and two-character escapes (`ESC 7`) are removed. Other
+/// control characters are preserved.
+fn strip_ansi_escapes(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameter and intermediate bytes followed by a final byte
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC, DCS, SOS, PM, APC: terminated by ST (ESC \), or BEL
+            Some(']' | 'P' | 'X' | '^' | '_') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // Other escapes: intermediate bytes followed by a final byte
+            Some(' '..='/') => {
+                for c in chars.by_ref() {
+                    if !(' '..='/').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(_) | None => {}
+        }
+    }
+    out
+}
+
+/// Inserts `separator` between every `group_size` digits of `value`, counted
+/// from the least significant digit.
+fn format_number(value: i64, separator: &str, group_size: usize) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() * (separator.len() + 1) + 1);
+    if value < 0 {
+        out.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % group_size == 0 {
+            out.push_str(separator);
+        }
+        out.push(digit);
+    }
+    out
+}
+
+fn builtin_float_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, f64> {
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = TemplateBuildMethodFnMap::<L, f64>::new();
+    map.insert(
+        "to_string",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|v| v.to_string());
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "round",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(|v| float_to_integer(v.round()));
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "floor",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(|v| float_to_integer(v.floor()));
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "ceil",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(|v| float_to_integer(v.ceil()));
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "fixed",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [precision_node] = function.expect_exact_arguments()?;
+            let precision_property =
+                expect_usize_expression(language, diagnostics, build_ctx, precision_node)?;
+            let out_property = (self_property, precision_property).and_then(|(v, precision)| {
+                // format!() panics if the precision doesn't fit in u16
+                if u16::try_from(precision).is_err() {
+                    return Err(TemplatePropertyError(
+                        format!("Precision {precision} is out of range").into(),
+                    ));
+                }
+                Ok(format!("{v:.precision$}"))
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map
+}
+
+/// Returns true if `needle` occurs in `haystack` ignoring case.
+///
+/// Characters are compared by their lowercase mappings without allocating
+/// lowercased copies of the strings.
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    fn lowercase_chars(s: &str) -> impl Iterator<Item = char> + '_ {
+        s.chars().flat_map(char::to_lowercase)
+    }
+    needle.is_empty()
+        || haystack.char_indices().any(|(start, _)| {
+            let mut rest = lowercase_chars(&haystack[start..]);
+            lowercase_chars(needle).all(|c| rest.next() == Some(c))
+        })
+}
+
+/// Converts integral float value to integer.
+fn float_to_integer(v: f64) -> Result<i64, TemplatePropertyError> {
+    // i64::MAX can't be represented in f64, and is rounded up to 2^63.
+    if (i64::MIN as f64..i64::MAX as f64).contains(&v) {
+        Ok(v as i64)
+    } else {
+        Err(TemplatePropertyError(
+            format!("Float {v} is out of range for Integer").into(),
+        ))
+    }
+}
+
+fn builtin_config_value_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, ConfigValue> {
+    fn extract<'de, T: Deserialize<'de>>(value: ConfigValue) -> Result<T, TemplatePropertyError> {
+        T::deserialize(value.into_deserializer())
+            // map to err.message() because TomlError appends newline to it
+            .map_err(|err| TemplatePropertyError(err.message().into()))
+    }
+
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = TemplateBuildMethodFnMap::<L, ConfigValue>::new();
+    // These methods are called "as_<type>", not "to_<type>" to clarify that
+    // they'll never convert types (e.g. integer to string.) Since templater
+    // doesn't provide binding syntax, there's no need to distinguish between
+    // reference and consuming access.
+    map.insert(
+        "as_boolean",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(extract);
+            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "as_integer",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(extract);
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "as_string",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(extract);
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "as_string_list",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(extract);
+            Ok(L::Property::wrap_string_list(out_property.into_dyn()))
+        },
+    );
+    // TODO: add is_<type>() -> Boolean?
+    // TODO: add .get(key) -> ConfigValue or Option<ConfigValue>?
+    map
+}
+
+fn builtin_signature_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, Signature> {
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = TemplateBuildMethodFnMap::<L, Signature>::new();
+    map.insert(
+        "name",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|signature| signature.name);
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "email",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|signature| signature.email.into());
+            Ok(L::Property::wrap_email(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "username",
         |_language, diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
             // TODO: Remove in jj 0.30+
@@ -1118,12 +2772,11 @@ fn builtin_timestamp_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
     let mut map = TemplateBuildMethodFnMap::<L, Timestamp>::new();
     map.insert(
         "ago",
-        |_language, _diagnostics, _build_ctx, self_property, function| {
+        |language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
-            let now = Timestamp::now();
-            let format = timeago::Formatter::new();
+            let now = language.clock().now();
             let out_property = self_property.and_then(move |timestamp| {
-                Ok(time_util::format_duration(&timestamp, &now, &format)?)
+                Ok(time_util::format_timestamp_relative_to(&timestamp, &now)?)
             });
             Ok(L::Property::wrap_string(out_property.into_dyn()))
         },
@@ -1158,14 +2811,24 @@ fn builtin_timestamp_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::Property::wrap_timestamp(out_property.into_dyn()))
         },
     );
+    map.insert(
+        "in_timezone",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [zone_node] = function.expect_exact_arguments()?;
+            let zone_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, zone_node)?;
+            let out_property = (self_property, zone_property).and_then(|(timestamp, zone)| {
+                let zone = time_util::TimeZoneSpec::parse(&zone)?;
+                Ok(zone.convert(&timestamp)?)
+            });
+            Ok(L::Property::wrap_timestamp(out_property.into_dyn()))
+        },
+    );
     map.insert(
         "local",
-        |_language, _diagnostics, _build_ctx, self_property, function| {
+        |language, _diagnostics, _build_ctx, self_property, function| {
             function.expect_no_arguments()?;
-            let tz_offset = std::env::var("JJ_TZ_OFFSET_MINS")
-                .ok()
-                .and_then(|tz_string| tz_string.parse::<i32>().ok())
-                .unwrap_or_else(|| chrono::Local::now().offset().local_minus_utc() / 60);
+            let tz_offset = language.clock().local_tz_offset();
             let out_property = self_property.map(move |mut timestamp| {
                 timestamp.tz_offset = tz_offset;
                 timestamp
@@ -1173,18 +2836,44 @@ fn builtin_timestamp_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::Property::wrap_timestamp(out_property.into_dyn()))
         },
     );
+    map.insert(
+        "same_day",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [other_node] = function.expect_exact_arguments()?;
+            let other_property =
+                expect_timestamp_expression(language, diagnostics, build_ctx, other_node)?;
+            let tz_offset = language.clock().local_tz_offset();
+            let out_property = (self_property, other_property)
+                .and_then(move |(lhs, rhs)| Ok(time_util::is_same_day(&lhs, &rhs, tz_offset)?));
+            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "is_today",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let tz_offset = language.clock().local_tz_offset();
+            let now = language.clock().now();
+            let out_property = self_property.and_then(move |timestamp| {
+                Ok(time_util::is_same_day(&timestamp, &now, tz_offset)?)
+            });
+            Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+        },
+    );
     map.insert(
         "after",
-        |_language, _diagnostics, _build_ctx, self_property, function| {
+        |language, _diagnostics, _build_ctx, self_property, function| {
             let [date_pattern_node] = function.expect_exact_arguments()?;
-            let now = chrono::Local::now();
+            let clock = language.clock();
             let date_pattern = template_parser::expect_string_literal_with(
                 date_pattern_node,
                 |date_pattern, span| {
-                    DatePattern::from_str_kind(date_pattern, function.name, now).map_err(|err| {
-                        TemplateParseError::expression("Invalid date pattern", span)
-                            .with_source(err)
-                    })
+                    clock
+                        .parse_date_pattern(date_pattern, function.name)
+                        .map_err(|err| {
+                            TemplateParseError::expression("Invalid date pattern", span)
+                                .with_source(err)
+                        })
                 },
             )?;
             let out_property = self_property.map(move |timestamp| date_pattern.matches(&timestamp));
@@ -1192,6 +2881,15 @@ fn builtin_timestamp_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
         },
     );
     map.insert("before", map["after"]);
+    map.insert(
+        "to_string",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property
+                .and_then(|timestamp| Ok(time_util::format_absolute_timestamp(&timestamp)?));
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
     map
 }
 
@@ -1227,35 +2925,208 @@ fn builtin_timestamp_range_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
     map
 }
 
-fn build_list_template_method<'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    self_template: Box<dyn ListTemplate + 'a>,
-    function: &FunctionCallNode,
-) -> TemplateParseResult<L::Property> {
-    let property = match function.name {
-        "join" => {
-            let [separator_node] = function.expect_exact_arguments()?;
-            let separator =
-                expect_template_expression(language, diagnostics, build_ctx, separator_node)?;
-            L::Property::wrap_template(self_template.join(separator))
-        }
-        _ => return Err(TemplateParseError::no_such_method("ListTemplate", function)),
-    };
-    Ok(property)
-}
-
-/// Builds method call expression for printable list property.
-pub fn build_formattable_list_method<'a, L, O>(
+fn builtin_duration_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, TimeDelta> {
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = TemplateBuildMethodFnMap::<L, TimeDelta>::new();
+    map.insert(
+        "format",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(time_util::format_duration_compact);
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "seconds",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|duration| duration.num_seconds());
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "humanize",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(time_util::format_duration_humanized);
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map
+}
+
+fn builtin_bytes_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, Vec<u8>> {
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = TemplateBuildMethodFnMap::<L, Vec<u8>>::new();
+    map.insert(
+        "len",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(|bytes| Ok(i64::try_from(bytes.len())?));
+            Ok(L::Property::wrap_integer(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "hex",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [uppercase_node]) = function.expect_named_arguments(&["uppercase"])?;
+            let uppercase_property = match uppercase_node {
+                Some(node) => expect_boolean_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(false).into_dyn(),
+            };
+            let out_property = (self_property, uppercase_property).map(|(bytes, uppercase)| {
+                if uppercase {
+                    hex::encode_upper(bytes)
+                } else {
+                    hex::encode(bytes)
+                }
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "base64",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [url_safe_node]) = function.expect_named_arguments(&["url_safe"])?;
+            let url_safe_property = match url_safe_node {
+                Some(node) => expect_boolean_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(false).into_dyn(),
+            };
+            let out_property = (self_property, url_safe_property).map(|(bytes, url_safe)| {
+                if url_safe {
+                    BASE64_URL_SAFE.encode(bytes)
+                } else {
+                    BASE64_STANDARD.encode(bytes)
+                }
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map.insert(
+        "hexdump",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [width_node]) = function.expect_arguments()?;
+            let width_property = match width_node {
+                Some(node) => expect_usize_expression(language, diagnostics, build_ctx, node)?,
+                None => Literal(16).into_dyn(),
+            };
+            let out_property = (self_property, width_property).and_then(|(bytes, width)| {
+                if width == 0 {
+                    return Err(TemplatePropertyError(
+                        "Bytes per line must be greater than 0".into(),
+                    ));
+                }
+                Ok(format_hexdump(&bytes, width))
+            });
+            Ok(L::Property::wrap_string(out_property.into_dyn()))
+        },
+    );
+    map
+}
+
+/// Formats `bytes` as lines of offset, hex, and ASCII columns, like
+/// `hexdump -C`.
+///
+/// The hex column of the last line is padded so the ASCII columns line up.
+/// If `bytes` fit in one line, the line isn't padded to the full `width`.
+/// Non-printable bytes are shown as `.` in the ASCII column.
+fn format_hexdump(bytes: &[u8], width: usize) -> String {
+    let mut out = String::new();
+    let hex_width = (width.min(bytes.len()) * 3).saturating_sub(1);
+    for (i, chunk) in bytes.chunks(width).enumerate() {
+        let hex = chunk.iter().map(|b| format!("{b:02x}")).join(" ");
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    char::from(b)
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let offset = i * width;
+        writeln!(out, "{offset:08x}  {hex:<hex_width$}  |{ascii}|").unwrap();
+    }
+    out
+}
+
+fn build_list_template_method<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_template: Box<dyn ListTemplate + 'a>,
+    function: &FunctionCallNode,
+) -> TemplateParseResult<L::Property> {
+    let property = match function.name {
+        "join" => {
+            let [separator_node] = function.expect_exact_arguments()?;
+            let separator =
+                expect_template_expression(language, diagnostics, build_ctx, separator_node)?;
+            L::Property::wrap_template(self_template.join(separator))
+        }
+        _ => return Err(TemplateParseError::no_such_method("ListTemplate", function)),
+    };
+    Ok(property)
+}
+
+fn build_template_method<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_template: Box<dyn Template + 'a>,
+    function: &FunctionCallNode,
+) -> TemplateParseResult<L::Property> {
+    let property = match function.name {
+        "surround" => {
+            let [prefix_node, suffix_node] = function.expect_exact_arguments()?;
+            let prefix = expect_template_expression(language, diagnostics, build_ctx, prefix_node)?;
+            let suffix = expect_template_expression(language, diagnostics, build_ctx, suffix_node)?;
+            L::Property::wrap_template(build_surround_template(prefix, suffix, self_template))
+        }
+        _ => return Err(TemplateParseError::no_such_method("Template", function)),
+    };
+    Ok(property)
+}
+
+/// Builds template that surrounds the `content` with `prefix` and `suffix`
+/// only if the `content` is non-empty.
+fn build_surround_template<'a>(
+    prefix: Box<dyn Template + 'a>,
+    suffix: Box<dyn Template + 'a>,
+    content: Box<dyn Template + 'a>,
+) -> Box<dyn Template + 'a> {
+    let template = ReformatTemplate::new(content, move |formatter, recorded| {
+        if recorded.data().is_empty() {
+            return Ok(());
+        }
+        prefix.format(formatter)?;
+        recorded.replay(formatter.as_mut())?;
+        suffix.format(formatter)?;
+        Ok(())
+    });
+    Box::new(template)
+}
+
+/// Builds method call expression for printable list property.
+#[expect(
+    clippy::too_many_arguments,
+    reason = "list item, optional item, and list types need separate wrap functions"
+)]
+pub fn build_formattable_list_method<'a, L, O>(
     language: &L,
     diagnostics: &mut TemplateDiagnostics,
     build_ctx: &BuildContext<L::Property>,
     self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
     function: &FunctionCallNode,
     // TODO: Generic L: WrapProperty<O> trait might be needed to support more
-    // list operations such as first()/slice().
+    // list operations such as slice().
     wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+    wrap_item_opt: impl Fn(BoxedTemplateProperty<'a, Option<O>>) -> L::Property,
     wrap_list: impl Fn(BoxedTemplateProperty<'a, Vec<O>>) -> L::Property,
 ) -> TemplateParseResult<L::Property>
 where
@@ -1268,6 +3139,45 @@ where
             let out_property = self_property.and_then(|items| Ok(items.len().try_into()?));
             L::Property::wrap_integer(out_property.into_dyn())
         }
+        "is_empty" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|items| items.is_empty());
+            L::Property::wrap_boolean(out_property.into_dyn())
+        }
+        "reverse" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|mut items| {
+                items.reverse();
+                items
+            });
+            wrap_list(out_property.into_dyn())
+        }
+        "first" | "last" | "nth" => build_index_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item_opt,
+        )?,
+        "unique" => build_unique_operation::<L, _>(self_property, function, wrap_item, wrap_list)?,
+        "sort" | "sort_by" => build_sort_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+            wrap_list,
+        )?,
+        "take" | "skip" => build_slice_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_list,
+        )?,
         "join" => {
             let [separator_node] = function.expect_exact_arguments()?;
             let separator =
@@ -1295,11 +3205,49 @@ where
             function,
             wrap_item,
         )?,
+        "table" => build_table_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+        )?,
+        "any" => build_any_all_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+            false,
+        )?,
+        "all" => build_any_all_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+            true,
+        )?,
+        "sum" | "min" | "max" => build_sum_min_max_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+        )?,
         _ => return Err(TemplateParseError::no_such_method("List", function)),
     };
     Ok(property)
 }
 
+#[expect(
+    clippy::too_many_arguments,
+    reason = "list item, optional item, and list types need separate wrap functions"
+)]
 pub fn build_unformattable_list_method<'a, L, O>(
     language: &L,
     diagnostics: &mut TemplateDiagnostics,
@@ -1307,6 +3255,7 @@ pub fn build_unformattable_list_method<'a, L, O>(
     self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
     function: &FunctionCallNode,
     wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+    wrap_item_opt: impl Fn(BoxedTemplateProperty<'a, Option<O>>) -> L::Property,
     wrap_list: impl Fn(BoxedTemplateProperty<'a, Vec<O>>) -> L::Property,
 ) -> TemplateParseResult<L::Property>
 where
@@ -1319,6 +3268,45 @@ where
             let out_property = self_property.and_then(|items| Ok(items.len().try_into()?));
             L::Property::wrap_integer(out_property.into_dyn())
         }
+        "is_empty" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|items| items.is_empty());
+            L::Property::wrap_boolean(out_property.into_dyn())
+        }
+        "reverse" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|mut items| {
+                items.reverse();
+                items
+            });
+            wrap_list(out_property.into_dyn())
+        }
+        "first" | "last" | "nth" => build_index_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item_opt,
+        )?,
+        "unique" => build_unique_operation::<L, _>(self_property, function, wrap_item, wrap_list)?,
+        "sort" | "sort_by" => build_sort_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+            wrap_list,
+        )?,
+        "take" | "skip" => build_slice_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_list,
+        )?,
         // No "join"
         "filter" => build_filter_operation(
             language,
@@ -1337,6 +3325,40 @@ where
             function,
             wrap_item,
         )?,
+        "table" => build_table_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+        )?,
+        "any" => build_any_all_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+            false,
+        )?,
+        "all" => build_any_all_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+            true,
+        )?,
+        "sum" | "min" | "max" => build_sum_min_max_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+        )?,
         _ => return Err(TemplateParseError::no_such_method("List", function)),
     };
     Ok(property)
@@ -1387,1329 +3409,5336 @@ where
     Ok(wrap_list(out_property.into_dyn()))
 }
 
-/// Builds expression that extracts iterable property and applies template to
-/// each item.
+/// Builds `group_by(|item| key)` expression that extracts list property and
+/// groups its items by the rendered `key`.
+///
+/// Groups are ordered by the first occurrence of each key, and items in each
+/// group keep their original order.
 ///
 /// `wrap_item()` is the function to wrap a list item of type `O` as a property.
-fn build_map_operation<'a, L, O, P>(
+/// `wrap_group_list()` is the function to wrap the list of groups.
+pub fn build_group_by_operation<'a, L, O>(
     language: &L,
     diagnostics: &mut TemplateDiagnostics,
     build_ctx: &BuildContext<L::Property>,
-    self_property: P,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
     function: &FunctionCallNode,
     wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+    wrap_group_list: impl Fn(BoxedTemplateProperty<'a, Vec<ListGroup<O>>>) -> L::Property,
 ) -> TemplateParseResult<L::Property>
 where
     L: TemplateLanguage<'a> + ?Sized,
-    P: TemplateProperty + 'a,
-    P::Output: IntoIterator<Item = O>,
     O: Clone + 'a,
 {
     let [lambda_node] = function.expect_exact_arguments()?;
     let item_placeholder = PropertyPlaceholder::new();
-    let item_template = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+    let item_key = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
         build_lambda_expression(
             build_ctx,
             lambda,
             &[&|| wrap_item(item_placeholder.clone().into_dyn())],
-            |build_ctx, body| expect_template_expression(language, diagnostics, build_ctx, body),
+            |build_ctx, body| expect_plain_text_expression(language, diagnostics, build_ctx, body),
         )
     })?;
-    let list_template = ListPropertyTemplate::new(
-        self_property,
-        Literal(" "), // separator
-        move |formatter, item| {
-            item_placeholder.with_value(item, || item_template.format(formatter))
-        },
-    );
-    Ok(L::Property::wrap_list_template(Box::new(list_template)))
-}
-
-/// Builds lambda expression to be evaluated with the provided arguments.
-/// `arg_fns` is usually an array of wrapped [`PropertyPlaceholder`]s.
-fn build_lambda_expression<'i, P, T>(
-    build_ctx: &BuildContext<'i, P>,
-    lambda: &LambdaNode<'i>,
-    arg_fns: &[&'i dyn Fn() -> P],
-    build_body: impl FnOnce(&BuildContext<'i, P>, &ExpressionNode<'i>) -> TemplateParseResult<T>,
-) -> TemplateParseResult<T> {
-    if lambda.params.len() != arg_fns.len() {
-        return Err(TemplateParseError::expression(
-            format!("Expected {} lambda parameters", arg_fns.len()),
-            lambda.params_span,
-        ));
-    }
-    let mut local_variables = build_ctx.local_variables.clone();
-    local_variables.extend(iter::zip(&lambda.params, arg_fns));
-    let inner_build_ctx = BuildContext {
-        local_variables,
-        self_variable: build_ctx.self_variable,
-    };
-    build_body(&inner_build_ctx, &lambda.body)
+    let out_property = self_property.and_then(move |items| {
+        let mut groups: Vec<ListGroup<O>> = Vec::new();
+        let mut group_indices: HashMap<String, usize> = HashMap::new();
+        for item in items {
+            let key = item_placeholder.with_value(item.clone(), || item_key.extract())?;
+            if let Some(&index) = group_indices.get(&key) {
+                groups[index].items.push(item);
+            } else {
+                group_indices.insert(key.clone(), groups.len());
+                groups.push(ListGroup {
+                    key,
+                    items: vec![item],
+                });
+            }
+        }
+        Ok(groups)
+    });
+    Ok(wrap_group_list(out_property.into_dyn()))
 }
 
-fn builtin_functions<'a, L: TemplateLanguage<'a> + ?Sized>() -> TemplateBuildFunctionFnMap<'a, L> {
-    // Not using maplit::hashmap!{} or custom declarative macro here because
-    // code completion inside macro is quite restricted.
-    let mut map = TemplateBuildFunctionFnMap::<L>::new();
-    map.insert("fill", |language, diagnostics, build_ctx, function| {
-        let [width_node, content_node] = function.expect_exact_arguments()?;
-        let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
-        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-        let template =
-            ReformatTemplate::new(content, move |formatter, recorded| match width.extract() {
-                Ok(width) => text_util::write_wrapped(formatter.as_mut(), recorded, width),
-                Err(err) => formatter.handle_error(err),
-            });
-        Ok(L::Property::wrap_template(Box::new(template)))
-    });
-    map.insert("indent", |language, diagnostics, build_ctx, function| {
-        let [prefix_node, content_node] = function.expect_exact_arguments()?;
-        let prefix = expect_template_expression(language, diagnostics, build_ctx, prefix_node)?;
-        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-        let template = ReformatTemplate::new(content, move |formatter, recorded| {
-            let rewrap = formatter.rewrap_fn();
-            text_util::write_indented(formatter.as_mut(), recorded, |formatter| {
-                prefix.format(&mut rewrap(formatter))
+/// Builds method call expression for a group of list items.
+///
+/// `wrap_list()` is the function to wrap the list of group items.
+pub fn build_list_group_method<'a, P, O>(
+    type_name: &str,
+    self_property: impl TemplateProperty<Output = ListGroup<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_list: impl Fn(BoxedTemplateProperty<'a, Vec<O>>) -> P,
+) -> TemplateParseResult<P>
+where
+    P: CoreTemplatePropertyVar<'a>,
+    O: 'a,
+{
+    let property = match function.name {
+        "key" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|group| group.key);
+            P::wrap_string(out_property.into_dyn())
+        }
+        "items" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|group| group.items);
+            wrap_list(out_property.into_dyn())
+        }
+        _ => return Err(TemplateParseError::no_such_method(type_name, function)),
+    };
+    Ok(property)
+}
+
+/// Builds `enumerate([start])` expression that pairs list items with their
+/// positions counted from `start`.
+///
+/// `wrap_enumerated_list()` is the function to wrap the list of pairs.
+pub fn build_enumerate_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_enumerated_list: impl Fn(BoxedTemplateProperty<'a, Vec<ListEnumerated<O>>>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: 'a,
+{
+    let ([], [start_node]) = function.expect_arguments()?;
+    let start_property = match start_node {
+        Some(node) => expect_integer_expression(language, diagnostics, build_ctx, node)?,
+        None => Literal(0).into_dyn(),
+    };
+    let out_property = (self_property, start_property).and_then(|(items, start)| {
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(offset, item)| {
+                let index = i64::try_from(offset)
+                    .ok()
+                    .and_then(|offset| start.checked_add(offset))
+                    .ok_or_else(|| TemplatePropertyError("Attempt to add with overflow".into()))?;
+                Ok(ListEnumerated { index, item })
             })
-        });
-        Ok(L::Property::wrap_template(Box::new(template)))
-    });
-    map.insert("pad_start", |language, diagnostics, build_ctx, function| {
-        let ([width_node, content_node], [fill_char_node]) =
-            function.expect_named_arguments(&["", "", "fill_char"])?;
-        let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
-        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-        let fill_char = fill_char_node
-            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-            .transpose()?;
-        let template = new_pad_template(content, fill_char, width, text_util::write_padded_start);
-        Ok(L::Property::wrap_template(template))
-    });
-    map.insert("pad_end", |language, diagnostics, build_ctx, function| {
-        let ([width_node, content_node], [fill_char_node]) =
-            function.expect_named_arguments(&["", "", "fill_char"])?;
-        let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
-        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-        let fill_char = fill_char_node
-            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-            .transpose()?;
-        let template = new_pad_template(content, fill_char, width, text_util::write_padded_end);
-        Ok(L::Property::wrap_template(template))
-    });
-    map.insert(
-        "pad_centered",
-        |language, diagnostics, build_ctx, function| {
-            let ([width_node, content_node], [fill_char_node]) =
-                function.expect_named_arguments(&["", "", "fill_char"])?;
-            let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
-            let content =
-                expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-            let fill_char = fill_char_node
-                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-                .transpose()?;
-            let template =
-                new_pad_template(content, fill_char, width, text_util::write_padded_centered);
-            Ok(L::Property::wrap_template(template))
-        },
-    );
-    map.insert(
-        "truncate_start",
-        |language, diagnostics, build_ctx, function| {
-            let ([width_node, content_node], [ellipsis_node]) =
-                function.expect_named_arguments(&["", "", "ellipsis"])?;
-            let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
-            let content =
-                expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-            let ellipsis = ellipsis_node
-                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-                .transpose()?;
-            let template =
-                new_truncate_template(content, ellipsis, width, text_util::write_truncated_start);
-            Ok(L::Property::wrap_template(template))
-        },
-    );
-    map.insert(
-        "truncate_end",
-        |language, diagnostics, build_ctx, function| {
-            let ([width_node, content_node], [ellipsis_node]) =
-                function.expect_named_arguments(&["", "", "ellipsis"])?;
-            let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
-            let content =
-                expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-            let ellipsis = ellipsis_node
-                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-                .transpose()?;
-            let template =
-                new_truncate_template(content, ellipsis, width, text_util::write_truncated_end);
-            Ok(L::Property::wrap_template(template))
-        },
-    );
-    map.insert("label", |language, diagnostics, build_ctx, function| {
-        let [label_node, content_node] = function.expect_exact_arguments()?;
-        let label_property =
-            expect_plain_text_expression(language, diagnostics, build_ctx, label_node)?;
-        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-        let labels =
-            label_property.map(|s| s.split_whitespace().map(ToString::to_string).collect());
-        Ok(L::Property::wrap_template(Box::new(LabelTemplate::new(
-            content, labels,
-        ))))
-    });
-    map.insert(
-        "raw_escape_sequence",
-        |language, diagnostics, build_ctx, function| {
-            let [content_node] = function.expect_exact_arguments()?;
-            let content =
-                expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-            Ok(L::Property::wrap_template(Box::new(
-                RawEscapeSequenceTemplate(content),
-            )))
-        },
-    );
-    map.insert("stringify", |language, diagnostics, build_ctx, function| {
-        let [content_node] = function.expect_exact_arguments()?;
-        let content = expect_plain_text_expression(language, diagnostics, build_ctx, content_node)?;
-        Ok(L::Property::wrap_string(content))
-    });
-    map.insert("if", |language, diagnostics, build_ctx, function| {
-        let ([condition_node, true_node], [false_node]) = function.expect_arguments()?;
-        let condition =
-            expect_boolean_expression(language, diagnostics, build_ctx, condition_node)?;
-        let true_template =
-            expect_template_expression(language, diagnostics, build_ctx, true_node)?;
-        let false_template = false_node
-            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-            .transpose()?;
-        let template = ConditionalTemplate::new(condition, true_template, false_template);
-        Ok(L::Property::wrap_template(Box::new(template)))
-    });
-    map.insert("coalesce", |language, diagnostics, build_ctx, function| {
-        let contents = function
-            .args
-            .iter()
-            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-            .try_collect()?;
-        Ok(L::Property::wrap_template(Box::new(CoalesceTemplate(
-            contents,
-        ))))
-    });
-    map.insert("concat", |language, diagnostics, build_ctx, function| {
-        let contents = function
-            .args
-            .iter()
-            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-            .try_collect()?;
-        Ok(L::Property::wrap_template(Box::new(ConcatTemplate(
-            contents,
-        ))))
-    });
-    map.insert("separate", |language, diagnostics, build_ctx, function| {
-        let ([separator_node], content_nodes) = function.expect_some_arguments()?;
-        let separator =
-            expect_template_expression(language, diagnostics, build_ctx, separator_node)?;
-        let contents = content_nodes
-            .iter()
-            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-            .try_collect()?;
-        Ok(L::Property::wrap_template(Box::new(SeparateTemplate::new(
-            separator, contents,
-        ))))
-    });
-    map.insert("surround", |language, diagnostics, build_ctx, function| {
-        let [prefix_node, suffix_node, content_node] = function.expect_exact_arguments()?;
-        let prefix = expect_template_expression(language, diagnostics, build_ctx, prefix_node)?;
-        let suffix = expect_template_expression(language, diagnostics, build_ctx, suffix_node)?;
-        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
-        let template = ReformatTemplate::new(content, move |formatter, recorded| {
-            if recorded.data().is_empty() {
-                return Ok(());
-            }
-            prefix.format(formatter)?;
-            recorded.replay(formatter.as_mut())?;
-            suffix.format(formatter)?;
-            Ok(())
-        });
-        Ok(L::Property::wrap_template(Box::new(template)))
-    });
-    map.insert("config", |language, _diagnostics, _build_ctx, function| {
-        // Dynamic lookup can be implemented if needed. The name is literal
-        // string for now so the error can be reported early.
-        let [name_node] = function.expect_exact_arguments()?;
-        let name: ConfigNamePathBuf =
-            template_parser::expect_string_literal_with(name_node, |name, span| {
-                name.parse().map_err(|err| {
-                    TemplateParseError::expression("Failed to parse config name", span)
-                        .with_source(err)
-                })
-            })?;
-        let value = language.settings().get_value(&name).map_err(|err| {
-            TemplateParseError::expression("Failed to get config value", function.name_span)
-                .with_source(err)
-        })?;
-        // .decorated("", "") to trim leading/trailing whitespace
-        Ok(L::Property::wrap_config_value(
-            Literal(value.decorated("", "")).into_dyn(),
-        ))
+            .collect()
     });
-    map
+    Ok(wrap_enumerated_list(out_property.into_dyn()))
 }
 
-fn new_pad_template<'a, W>(
-    content: Box<dyn Template + 'a>,
-    fill_char: Option<Box<dyn Template + 'a>>,
-    width: BoxedTemplateProperty<'a, usize>,
-    write_padded: W,
-) -> Box<dyn Template + 'a>
+/// Builds method call expression for a list item paired with its position.
+///
+/// `wrap_item()` is the function to wrap the list item.
+pub fn build_list_enumerated_method<'a, P, O>(
+    type_name: &str,
+    self_property: impl TemplateProperty<Output = ListEnumerated<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> P,
+) -> TemplateParseResult<P>
 where
-    W: Fn(&mut dyn Formatter, &FormatRecorder, &FormatRecorder, usize) -> io::Result<()> + 'a,
+    P: CoreTemplatePropertyVar<'a>,
+    O: 'a,
 {
-    let default_fill_char = FormatRecorder::with_data(" ");
-    let template = ReformatTemplate::new(content, move |formatter, recorded| {
-        let width = match width.extract() {
-            Ok(width) => width,
-            Err(err) => return formatter.handle_error(err),
+    let property = match function.name {
+        "index" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|enumerated| enumerated.index);
+            P::wrap_integer(out_property.into_dyn())
+        }
+        "item" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|enumerated| enumerated.item);
+            wrap_item(out_property.into_dyn())
+        }
+        _ => return Err(TemplateParseError::no_such_method(type_name, function)),
+    };
+    Ok(property)
+}
+
+/// Builds method call expression for a lazily evaluated list.
+///
+/// Only the methods that can consume the items one by one are supported.
+/// `wrap_item()` is the function to wrap the list item.
+pub fn build_list_stream_method<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    type_name: &str,
+    self_property: impl TemplateProperty<Output = ListStream<'a, O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Template + Clone + 'a,
+{
+    let property = match function.name {
+        "join" => {
+            let [separator_node] = function.expect_exact_arguments()?;
+            let separator =
+                expect_template_expression(language, diagnostics, build_ctx, separator_node)?;
+            let template =
+                ListPropertyTemplate::new(self_property, separator, |formatter, item| {
+                    item.format(formatter)
+                });
+            L::Property::wrap_template(Box::new(template))
+        }
+        "map" => build_map_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+        )?,
+        _ => return Err(TemplateParseError::no_such_method(type_name, function)),
+    };
+    Ok(property)
+}
+
+/// Builds `unique()` expression that removes duplicated items while
+/// preserving the first occurrences.
+///
+/// Items are compared by `try_into_eq()`, so the item type must support `==`.
+fn build_unique_operation<'a, L, O>(
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+    wrap_list: impl Fn(BoxedTemplateProperty<'a, Vec<O>>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    function.expect_no_arguments()?;
+    let lhs_placeholder = PropertyPlaceholder::new();
+    let rhs_placeholder = PropertyPlaceholder::new();
+    let lhs = wrap_item(lhs_placeholder.clone().into_dyn());
+    let rhs = wrap_item(rhs_placeholder.clone().into_dyn());
+    let item_type = lhs.type_name();
+    let item_eq = lhs.try_into_eq(rhs).ok_or_else(|| {
+        let message = format!("Cannot compare items of type `{item_type}`");
+        TemplateParseError::expression(message, function.name_span)
+    })?;
+    let out_property = self_property.and_then(move |items| {
+        let mut unique_items: Vec<O> = Vec::with_capacity(items.len());
+        for item in items {
+            let mut is_duplicate = false;
+            for seen in &unique_items {
+                let eq = lhs_placeholder.with_value(item.clone(), || {
+                    rhs_placeholder.with_value(seen.clone(), || item_eq.extract())
+                })?;
+                if eq {
+                    is_duplicate = true;
+                    break;
+                }
+            }
+            if !is_duplicate {
+                unique_items.push(item);
+            }
+        }
+        Ok(unique_items)
+    });
+    Ok(wrap_list(out_property.into_dyn()))
+}
+
+/// Key property of `sort()` or `sort_by(|item| key)`.
+enum SortKeyProperty<'a> {
+    String(BoxedTemplateProperty<'a, String>),
+    Integer(BoxedTemplateProperty<'a, i64>),
+    Float(BoxedTemplateProperty<'a, f64>),
+    Duration(BoxedTemplateProperty<'a, TimeDelta>),
+}
+
+impl<'a> SortKeyProperty<'a> {
+    fn from_property<P: CoreTemplatePropertyVar<'a>>(property: P) -> Option<Self> {
+        match property.type_name() {
+            "String" => property.try_into_plain_text().map(SortKeyProperty::String),
+            "Integer" => property.try_into_integer().map(SortKeyProperty::Integer),
+            "Float" => {
+                // NaN can't be compared with `<`, so reject it before sorting
+                let property = property.try_into_float()?.and_then(|v| {
+                    if v.is_nan() {
+                        Err(TemplatePropertyError("Cannot compare NaN".into()))
+                    } else {
+                        Ok(v)
+                    }
+                });
+                Some(SortKeyProperty::Float(property.into_dyn()))
+            }
+            "Duration" => property.try_into_duration().map(SortKeyProperty::Duration),
+            _ => None,
+        }
+    }
+
+    /// Sorts `items` by the keys evaluated once per item.
+    fn sort_items<O: Clone>(
+        &self,
+        item_placeholder: &PropertyPlaceholder<O>,
+        items: Vec<O>,
+    ) -> Result<Vec<O>, TemplatePropertyError> {
+        match self {
+            SortKeyProperty::String(key) => sort_by_keys(item_placeholder, items, key, Ord::cmp),
+            SortKeyProperty::Integer(key) => sort_by_keys(item_placeholder, items, key, Ord::cmp),
+            SortKeyProperty::Float(key) => {
+                sort_by_keys(item_placeholder, items, key, f64::total_cmp)
+            }
+            SortKeyProperty::Duration(key) => sort_by_keys(item_placeholder, items, key, Ord::cmp),
+        }
+    }
+}
+
+fn sort_by_keys<O: Clone, K>(
+    item_placeholder: &PropertyPlaceholder<O>,
+    items: Vec<O>,
+    key_property: &impl TemplateProperty<Output = K>,
+    compare: impl Fn(&K, &K) -> Ordering,
+) -> Result<Vec<O>, TemplatePropertyError> {
+    let keys: Vec<K> = items
+        .iter()
+        .map(|item| item_placeholder.with_value(item.clone(), || key_property.extract()))
+        .try_collect()?;
+    let mut keyed_items = iter::zip(keys, items).collect_vec();
+    // sort_by() is stable
+    keyed_items.sort_by(|(lhs, _), (rhs, _)| compare(lhs, rhs));
+    Ok(keyed_items.into_iter().map(|(_, item)| item).collect())
+}
+
+/// Builds `sort()` or `sort_by(|item| key)` expression.
+///
+/// The item or key type must be `String`, `Integer`, `Float`, or `Duration`.
+/// The key is evaluated once per item. The sort is stable, so items of equal
+/// keys are kept in the original order.
+fn build_sort_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+    wrap_list: impl Fn(BoxedTemplateProperty<'a, Vec<O>>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    let item_placeholder = PropertyPlaceholder::new();
+    let (key, kind, span) = if function.name == "sort_by" {
+        let [lambda_node] = function.expect_exact_arguments()?;
+        let key = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+            build_lambda_expression(
+                build_ctx,
+                lambda,
+                &[&|| wrap_item(item_placeholder.clone().into_dyn())],
+                |build_ctx, body| build_expression(language, diagnostics, build_ctx, body),
+            )
+        })?;
+        (key.property, "expressions", lambda_node.span)
+    } else {
+        function.expect_no_arguments()?;
+        let key = wrap_item(item_placeholder.clone().into_dyn());
+        (key, "items", function.name_span)
+    };
+    let ty = key.type_name();
+    let key = SortKeyProperty::from_property(key).ok_or_else(|| {
+        let message = format!("Cannot compare {kind} of type `{ty}`");
+        TemplateParseError::expression(message, span)
+    })?;
+    let out_property =
+        self_property.and_then(move |items| key.sort_items(&item_placeholder, items));
+    Ok(wrap_list(out_property.into_dyn()))
+}
+
+/// Builds `chunk(size)` expression that splits list into sub-lists of `size`
+/// items.
+///
+/// The last chunk may be shorter if the number of items isn't divisible by
+/// `size`.
+fn build_chunk_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_chunks: impl Fn(BoxedTemplateProperty<'a, Vec<Vec<O>>>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    let [size_node] = function.expect_exact_arguments()?;
+    let size_property = expect_integer_expression(language, diagnostics, build_ctx, size_node)?;
+    let out_property = (self_property, size_property).and_then(|(items, size)| {
+        if size <= 0 {
+            return Err(TemplatePropertyError(
+                "Chunk size must be greater than 0".into(),
+            ));
+        }
+        let size = usize::try_from(size).unwrap_or(usize::MAX);
+        Ok(items.chunks(size).map(|chunk| chunk.to_vec()).collect())
+    });
+    Ok(wrap_chunks(out_property.into_dyn()))
+}
+
+/// Builds `flatten()` expression that concatenates sub-lists into a single
+/// list.
+///
+/// Only one level of nesting is removed.
+fn build_flatten_operation<'a, L, O>(
+    self_property: impl TemplateProperty<Output = Vec<Vec<O>>> + 'a,
+    function: &FunctionCallNode,
+    wrap_list: impl Fn(BoxedTemplateProperty<'a, Vec<O>>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: 'a,
+{
+    function.expect_no_arguments()?;
+    let out_property = self_property.map(|lists| lists.into_iter().flatten().collect());
+    Ok(wrap_list(out_property.into_dyn()))
+}
+
+/// Builds `map_or(default, |value| expression)` expression that extracts
+/// optional property and applies template to the value if present.
+///
+/// The `default` template is rendered if the value is absent.
+///
+/// `wrap_value()` is the function to wrap the value of type `O` as a property.
+fn build_map_or_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Option<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_value: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    let [default_node, lambda_node] = function.expect_exact_arguments()?;
+    let default_template =
+        expect_template_expression(language, diagnostics, build_ctx, default_node)?;
+    let value_placeholder = PropertyPlaceholder::new();
+    let value_template = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+        build_lambda_expression(
+            build_ctx,
+            lambda,
+            &[&|| wrap_value(value_placeholder.clone().into_dyn())],
+            |build_ctx, body| expect_template_expression(language, diagnostics, build_ctx, body),
+        )
+    })?;
+    let template = OptionalPropertyTemplate::new(
+        self_property,
+        move |formatter, value| {
+            value_placeholder.with_value(value, || value_template.format(formatter))
+        },
+        default_template,
+    );
+    Ok(L::Property::wrap_template(Box::new(template)))
+}
+
+/// Builds `first()`, `last()`, or `nth(index)` expression that extracts list
+/// property and picks the item at the position.
+///
+/// Negative index counts from the end. Out-of-range access evaluates to
+/// `None`.
+///
+/// `wrap_item_opt()` is the function to wrap an optional list item of type `O`
+/// as a property.
+pub fn build_index_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item_opt: impl Fn(BoxedTemplateProperty<'a, Option<O>>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: 'a,
+{
+    let index_property = match function.name {
+        "first" => {
+            function.expect_no_arguments()?;
+            Literal(0).into_dyn()
+        }
+        "last" => {
+            function.expect_no_arguments()?;
+            Literal(-1).into_dyn()
+        }
+        "nth" => {
+            let [index_node] = function.expect_exact_arguments()?;
+            expect_integer_expression(language, diagnostics, build_ctx, index_node)?
+        }
+        _ => unreachable!(),
+    };
+    let out_property = (self_property, index_property).map(|(mut items, index)| {
+        let len = items.len();
+        let position = if index < 0 {
+            usize::try_from(index.unsigned_abs())
+                .ok()
+                .and_then(|n| len.checked_sub(n))
+        } else {
+            usize::try_from(index).ok().filter(|&i| i < len)
+        };
+        position.map(|i| items.swap_remove(i))
+    });
+    Ok(wrap_item_opt(out_property.into_dyn()))
+}
+
+/// Builds `take(count)` or `skip(count)` expression that extracts list
+/// property and keeps the first `count` items or the rest.
+///
+/// The `count` is clamped to the list length.
+///
+/// `wrap_list()` is the function to wrap the sliced list.
+fn build_slice_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_list: impl Fn(BoxedTemplateProperty<'a, Vec<O>>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: 'a,
+{
+    let [count_node] = function.expect_exact_arguments()?;
+    let count_property = expect_usize_expression(language, diagnostics, build_ctx, count_node)?;
+    let out_property = match function.name {
+        "take" => (self_property, count_property)
+            .map(|(mut items, count)| {
+                items.truncate(count);
+                items
+            })
+            .into_dyn(),
+        "skip" => (self_property, count_property)
+            .map(|(mut items, count)| {
+                items.drain(..count.min(items.len()));
+                items
+            })
+            .into_dyn(),
+        _ => unreachable!(),
+    };
+    Ok(wrap_list(out_property))
+}
+
+/// Builds expression that extracts iterable property and tests if any (or all
+/// if `all` is true) of its items satisfy the predicate.
+///
+/// The predicate is evaluated lazily, and the evaluation stops as soon as the
+/// result is determined.
+///
+/// `wrap_item()` is the function to wrap a list item of type `O` as a property.
+fn build_any_all_operation<'a, L, O, P>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: P,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+    all: bool,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    P: TemplateProperty + 'a,
+    P::Output: IntoIterator<Item = O>,
+    O: Clone + 'a,
+{
+    let [lambda_node] = function.expect_exact_arguments()?;
+    let item_placeholder = PropertyPlaceholder::new();
+    let item_predicate = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+        build_lambda_expression(
+            build_ctx,
+            lambda,
+            &[&|| wrap_item(item_placeholder.clone().into_dyn())],
+            |build_ctx, body| expect_boolean_expression(language, diagnostics, build_ctx, body),
+        )
+    })?;
+    let out_property = self_property.and_then(move |items| {
+        for item in items {
+            // Evaluate predicate with the current item
+            item_placeholder.set(item);
+            let result = item_predicate.extract();
+            item_placeholder.take();
+            if result? != all {
+                return Ok(!all);
+            }
+        }
+        Ok(all)
+    });
+    Ok(L::Property::wrap_boolean(out_property.into_dyn()))
+}
+
+/// Builds `sum(|item| expression)`, `min(|item| expression)`, or
+/// `max(|item| expression)` expression that aggregates the integer values
+/// mapped from the items.
+///
+/// `sum()` of an empty list is `0`, and `min()`/`max()` of an empty list is
+/// `None`. Overflow is reported as an error.
+fn build_sum_min_max_operation<'a, L, O, P>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: P,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    P: TemplateProperty + 'a,
+    P::Output: IntoIterator<Item = O>,
+    O: Clone + 'a,
+{
+    let [lambda_node] = function.expect_exact_arguments()?;
+    let item_placeholder = PropertyPlaceholder::new();
+    let item_value = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+        build_lambda_expression(
+            build_ctx,
+            lambda,
+            &[&|| wrap_item(item_placeholder.clone().into_dyn())],
+            |build_ctx, body| expect_integer_expression(language, diagnostics, build_ctx, body),
+        )
+    })?;
+    let values_property = self_property.and_then(move |items| {
+        items
+            .into_iter()
+            .map(|item| item_placeholder.with_value(item, || item_value.extract()))
+            .collect::<Result<Vec<_>, _>>()
+    });
+    let property = match function.name {
+        "sum" => {
+            let out_property = values_property.and_then(|values| {
+                values
+                    .into_iter()
+                    .try_fold(0_i64, |acc, v| acc.checked_add(v))
+                    .ok_or_else(|| TemplatePropertyError("Attempt to add with overflow".into()))
+            });
+            L::Property::wrap_integer(out_property.into_dyn())
+        }
+        "min" => {
+            let out_property = values_property.map(|values| values.into_iter().min());
+            L::Property::wrap_integer_opt(out_property.into_dyn())
+        }
+        "max" => {
+            let out_property = values_property.map(|values| values.into_iter().max());
+            L::Property::wrap_integer_opt(out_property.into_dyn())
+        }
+        _ => unreachable!(),
+    };
+    Ok(property)
+}
+
+/// Builds expression that extracts iterable property and applies template to
+/// each item.
+///
+/// `wrap_item()` is the function to wrap a list item of type `O` as a property.
+/// If the lambda takes two parameters, the second parameter is bound to the
+/// 0-based index of the item.
+fn build_map_operation<'a, L, O, P>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: P,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    P: TemplateProperty + 'a,
+    P::Output: IntoIterator<Item = O>,
+    O: Clone + 'a,
+{
+    let [lambda_node] = function.expect_exact_arguments()?;
+    let item_placeholder = PropertyPlaceholder::new();
+    let index_placeholder = PropertyPlaceholder::new();
+    let mut with_index = false;
+    let item_template = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+        let item_fn = || wrap_item(item_placeholder.clone().into_dyn());
+        let index_fn = || L::Property::wrap_integer(index_placeholder.clone().into_dyn());
+        with_index = lambda.params.len() >= 2;
+        let arg_fns: &[&dyn Fn() -> L::Property] = if with_index {
+            &[&item_fn, &index_fn]
+        } else {
+            &[&item_fn]
+        };
+        build_lambda_expression(build_ctx, lambda, arg_fns, |build_ctx, body| {
+            expect_template_expression(language, diagnostics, build_ctx, body)
+        })
+    })?;
+    // Pair items with their indices only if the lambda needs them.
+    let list_template: Box<dyn ListTemplate + 'a> = if with_index {
+        Box::new(ListPropertyTemplate::new(
+            self_property.map(|items| iter::zip(0_i64.., items)),
+            Literal(" "), // separator
+            move |formatter, (index, item)| {
+                index_placeholder.with_value(index, || {
+                    item_placeholder.with_value(item, || item_template.format(formatter))
+                })
+            },
+        ))
+    } else {
+        Box::new(ListPropertyTemplate::new(
+            self_property,
+            Literal(" "), // separator
+            move |formatter, item| {
+                item_placeholder.with_value(item, || item_template.format(formatter))
+            },
+        ))
+    };
+    Ok(L::Property::wrap_list_template(list_template))
+}
+
+/// Builds `table(|item| column, ...)` expression that renders list items as
+/// rows of aligned columns.
+///
+/// Each lambda renders one column of the row. Columns are aligned by display
+/// width.
+fn build_table_operation<'a, L, O, P>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: P,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(BoxedTemplateProperty<'a, O>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    P: TemplateProperty + 'a,
+    P::Output: IntoIterator<Item = O>,
+    O: Clone + 'a,
+{
+    let ([first_lambda_node], other_lambda_nodes) = function.expect_some_arguments()?;
+    let item_placeholder = PropertyPlaceholder::new();
+    let item_fn = || wrap_item(item_placeholder.clone().into_dyn());
+    let column_templates: Vec<_> = iter::once(first_lambda_node)
+        .chain(other_lambda_nodes)
+        .map(|lambda_node| {
+            template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+                build_lambda_expression(build_ctx, lambda, &[&item_fn], |build_ctx, body| {
+                    expect_template_expression(language, diagnostics, build_ctx, body)
+                })
+            })
+        })
+        .try_collect()?;
+    let template = TablePropertyTemplate::new(self_property, move |formatter, item| {
+        let rewrap = formatter.rewrap_fn();
+        item_placeholder.with_value(item, || {
+            column_templates
+                .iter()
+                .map(|template| {
+                    let mut recorder = FormatRecorder::new();
+                    template.format(&mut rewrap(&mut recorder))?;
+                    Ok(recorder)
+                })
+                .collect()
+        })
+    });
+    Ok(L::Property::wrap_template(Box::new(template)))
+}
+
+/// Builds lambda expression to be evaluated with the provided arguments.
+/// `arg_fns` is usually an array of wrapped [`PropertyPlaceholder`]s.
+fn build_lambda_expression<'i, P, T>(
+    build_ctx: &BuildContext<'i, P>,
+    lambda: &LambdaNode<'i>,
+    arg_fns: &[&'i dyn Fn() -> P],
+    build_body: impl FnOnce(&BuildContext<'i, P>, &ExpressionNode<'i>) -> TemplateParseResult<T>,
+) -> TemplateParseResult<T> {
+    if lambda.params.len() != arg_fns.len() {
+        return Err(TemplateParseError::expression(
+            format!("Expected {} lambda parameters", arg_fns.len()),
+            lambda.params_span,
+        ));
+    }
+    let mut local_variables = build_ctx.local_variables.clone();
+    local_variables.extend(iter::zip(&lambda.params, arg_fns));
+    let inner_build_ctx = BuildContext {
+        local_variables,
+        self_variable: build_ctx.self_variable,
+        node_types: build_ctx.node_types,
+    };
+    build_body(&inner_build_ctx, &lambda.body)
+}
+
+/// Builds `let name = value; body` expression.
+///
+/// The `value` is evaluated lazily, and at most once per evaluation of the
+/// `body`.
+fn build_let_binding<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    let_node: &LetNode,
+) -> TemplateParseResult<Box<dyn Template + 'a>> {
+    let value = build_expression(language, diagnostics, build_ctx, &let_node.value)?;
+    let type_name = value.type_name();
+    let scope = PropertyCacheScope::new();
+    let make_value = value.property.try_into_cached(&scope).ok_or_else(|| {
+        TemplateParseError::expression(
+            format!("Expression of type `{type_name}` cannot be bound to a variable"),
+            let_node.value.span,
+        )
+    })?;
+    let mut local_variables = build_ctx.local_variables.clone();
+    local_variables.insert(let_node.name, &*make_value);
+    let inner_build_ctx = BuildContext {
+        local_variables,
+        self_variable: build_ctx.self_variable,
+        node_types: build_ctx.node_types,
+    };
+    let body = expect_template_expression(language, diagnostics, &inner_build_ctx, &let_node.body)?;
+    Ok(Box::new(CacheScopeTemplate::new(scope, body)))
+}
+
+fn builtin_functions<'a, L: TemplateLanguage<'a> + ?Sized>() -> TemplateBuildFunctionFnMap<'a, L> {
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = TemplateBuildFunctionFnMap::<L>::new();
+    map.insert("fill", |language, diagnostics, build_ctx, function| {
+        let ([width_node, content_node], [keep_indent_node]) =
+            function.expect_named_arguments(&["", "", "keep_indent"])?;
+        let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
+        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+        let keep_indent = match keep_indent_node {
+            Some(node) => expect_boolean_expression(language, diagnostics, build_ctx, node)?,
+            None => Literal(false).into_dyn(),
+        };
+        let options = (width, keep_indent).into_dyn();
+        let template = ReformatTemplate::new(content, move |formatter, recorded| {
+            match options.extract() {
+                Ok((width, false)) => text_util::write_wrapped(formatter.as_mut(), recorded, width),
+                Ok((width, true)) => {
+                    text_util::write_wrapped_keeping_indent(formatter.as_mut(), recorded, width)
+                }
+                Err(err) => formatter.handle_error(err),
+            }
+        });
+        Ok(L::Property::wrap_template(Box::new(template)))
+    });
+    map.insert("indent", |language, diagnostics, build_ctx, function| {
+        let [prefix_node, content_node] = function.expect_exact_arguments()?;
+        let prefix = expect_template_expression(language, diagnostics, build_ctx, prefix_node)?;
+        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+        let template = ReformatTemplate::new(content, move |formatter, recorded| {
+            let rewrap = formatter.rewrap_fn();
+            text_util::write_indented(formatter.as_mut(), recorded, |formatter| {
+                prefix.format(&mut rewrap(formatter))
+            })
+        });
+        Ok(L::Property::wrap_template(Box::new(template)))
+    });
+    map.insert("pad_start", |language, diagnostics, build_ctx, function| {
+        let ([width_node, content_node], [fill_char_node]) =
+            function.expect_named_arguments(&["", "", "fill_char"])?;
+        let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
+        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+        let fill_char = fill_char_node
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .transpose()?;
+        let template = new_pad_template(content, fill_char, width, text_util::write_padded_start);
+        Ok(L::Property::wrap_template(template))
+    });
+    map.insert("pad_end", |language, diagnostics, build_ctx, function| {
+        let ([width_node, content_node], [fill_char_node]) =
+            function.expect_named_arguments(&["", "", "fill_char"])?;
+        let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
+        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+        let fill_char = fill_char_node
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .transpose()?;
+        let template = new_pad_template(content, fill_char, width, text_util::write_padded_end);
+        Ok(L::Property::wrap_template(template))
+    });
+    map.insert(
+        "pad_centered",
+        |language, diagnostics, build_ctx, function| {
+            let ([width_node, content_node], [fill_char_node]) =
+                function.expect_named_arguments(&["", "", "fill_char"])?;
+            let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
+            let content =
+                expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+            let fill_char = fill_char_node
+                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+                .transpose()?;
+            let template =
+                new_pad_template(content, fill_char, width, text_util::write_padded_centered);
+            Ok(L::Property::wrap_template(template))
+        },
+    );
+    map.insert(
+        "truncate_start",
+        |language, diagnostics, build_ctx, function| {
+            let ([width_node, content_node], [ellipsis_node]) =
+                function.expect_named_arguments(&["", "", "ellipsis"])?;
+            let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
+            let content =
+                expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+            let ellipsis = ellipsis_node
+                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+                .transpose()?;
+            let template =
+                new_truncate_template(content, ellipsis, width, text_util::write_truncated_start);
+            Ok(L::Property::wrap_template(template))
+        },
+    );
+    map.insert(
+        "truncate_end",
+        |language, diagnostics, build_ctx, function| {
+            let ([width_node, content_node], [ellipsis_node]) =
+                function.expect_named_arguments(&["", "", "ellipsis"])?;
+            let width = expect_usize_expression(language, diagnostics, build_ctx, width_node)?;
+            let content =
+                expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+            let ellipsis = ellipsis_node
+                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+                .transpose()?;
+            let template =
+                new_truncate_template(content, ellipsis, width, text_util::write_truncated_end);
+            Ok(L::Property::wrap_template(template))
+        },
+    );
+    map.insert("label", |language, diagnostics, build_ctx, function| {
+        let [label_node, content_node] = function.expect_exact_arguments()?;
+        let label_property =
+            expect_plain_text_expression(language, diagnostics, build_ctx, label_node)?;
+        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+        let labels =
+            label_property.map(|s| s.split_whitespace().map(ToString::to_string).collect());
+        Ok(L::Property::wrap_template(Box::new(LabelTemplate::new(
+            content, labels,
+        ))))
+    });
+    map.insert(
+        "raw_escape_sequence",
+        |language, diagnostics, build_ctx, function| {
+            let [content_node] = function.expect_exact_arguments()?;
+            let content =
+                expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+            Ok(L::Property::wrap_template(Box::new(
+                RawEscapeSequenceTemplate(content),
+            )))
+        },
+    );
+    map.insert("stringify", |language, diagnostics, build_ctx, function| {
+        let [content_node] = function.expect_exact_arguments()?;
+        let content = expect_plain_text_expression(language, diagnostics, build_ctx, content_node)?;
+        Ok(L::Property::wrap_string(content))
+    });
+    map.insert("if", |language, diagnostics, build_ctx, function| {
+        let ([condition_node, true_node], [false_node]) = function.expect_arguments()?;
+        let condition =
+            expect_boolean_expression(language, diagnostics, build_ctx, condition_node)?;
+        let true_template =
+            expect_template_expression(language, diagnostics, build_ctx, true_node)?;
+        let false_template = false_node
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .transpose()?;
+        let template = ConditionalTemplate::new(condition, true_template, false_template);
+        Ok(L::Property::wrap_template(Box::new(template)))
+    });
+    map.insert(
+        "if_contains_any",
+        |language, diagnostics, build_ctx, function| {
+            let ([haystack_node, needles_node, true_node], [false_node]) =
+                function.expect_arguments()?;
+            let haystack_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, haystack_node)?;
+            let needles_property =
+                expect_string_list_expression(language, diagnostics, build_ctx, needles_node)?;
+            let true_template =
+                expect_template_expression(language, diagnostics, build_ctx, true_node)?;
+            let false_template = false_node
+                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+                .transpose()?;
+            let condition = (haystack_property, needles_property).map(|(haystack, needles)| {
+                needles
+                    .iter()
+                    .any(|needle| haystack.contains(needle.as_str()))
+            });
+            let template =
+                ConditionalTemplate::new(condition.into_dyn(), true_template, false_template);
+            Ok(L::Property::wrap_template(Box::new(template)))
+        },
+    );
+    map.insert("match", |language, diagnostics, build_ctx, function| {
+        let ([value_node, _, _], _) = function.expect_some_arguments()?;
+        let (case_nodes, default_node) = match &function.args[1..] {
+            nodes if nodes.len() % 2 == 0 => (nodes, None),
+            [nodes @ .., default_node] => (nodes, Some(default_node)),
+            [] => unreachable!(),
+        };
+        // The value is evaluated at most once, and shared by the comparisons
+        let value = build_expression(language, diagnostics, build_ctx, value_node)?;
+        let vty = value.type_name();
+        let scope = PropertyCacheScope::new();
+        let make_value = value.property.try_into_cached(&scope).ok_or_else(|| {
+            TemplateParseError::expression(
+                format!("Expression of type `{vty}` cannot be matched"),
+                value_node.span,
+            )
+        })?;
+        let branches: Vec<_> = case_nodes
+            .chunks_exact(2)
+            .map(|nodes| {
+                let [case_node, result_node] = nodes else {
+                    unreachable!()
+                };
+                let case = build_expression(language, diagnostics, build_ctx, case_node)?;
+                let cty = case.type_name();
+                let eq = make_value().try_into_eq(case.property).ok_or_else(|| {
+                    let kind = TemplateParseErrorKind::IncomparableTypes {
+                        lhs: vty.to_owned(),
+                        rhs: cty.to_owned(),
+                    };
+                    TemplateParseError::with_span(kind, case_node.span)
+                })?;
+                let result =
+                    expect_template_expression(language, diagnostics, build_ctx, result_node)?;
+                Ok::<_, TemplateParseError>((eq, result))
+            })
+            .try_collect()?;
+        let default_template = default_node
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .transpose()?;
+        // Equivalent to if(value == case1, result1, if(value == case2, ...))
+        let template = branches
+            .into_iter()
+            .rev()
+            .fold(default_template, |false_template, (eq, result)| {
+                let template = ConditionalTemplate::new(eq, result, false_template);
+                Some(Box::new(template))
+            })
+            .unwrap();
+        Ok(L::Property::wrap_template(Box::new(
+            CacheScopeTemplate::new(scope, template),
+        )))
+    });
+    map.insert("cond", |language, diagnostics, build_ctx, function| {
+        let ([_, _], _) = function.expect_some_arguments()?;
+        let (branch_nodes, default_node) = match &function.args[..] {
+            nodes if nodes.len() % 2 == 0 => (nodes, None),
+            [nodes @ .., default_node] => (nodes, Some(default_node)),
+            [] => unreachable!(),
+        };
+        let branches: Vec<_> = branch_nodes
+            .chunks_exact(2)
+            .map(|nodes| {
+                let [condition_node, result_node] = nodes else {
+                    unreachable!()
+                };
+                let condition =
+                    expect_boolean_expression(language, diagnostics, build_ctx, condition_node)?;
+                let result =
+                    expect_template_expression(language, diagnostics, build_ctx, result_node)?;
+                Ok::<_, TemplateParseError>((condition, result))
+            })
+            .try_collect()?;
+        let default_template = default_node
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .transpose()?;
+        // Equivalent to if(condition1, result1, if(condition2, ...))
+        let template = branches
+            .into_iter()
+            .rev()
+            .fold(default_template, |false_template, (condition, result)| {
+                let template = ConditionalTemplate::new(condition, result, false_template);
+                Some(Box::new(template))
+            })
+            .unwrap();
+        Ok(L::Property::wrap_template(template))
+    });
+    map.insert("coalesce", |language, diagnostics, build_ctx, function| {
+        let contents = function
+            .args
+            .iter()
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .try_collect()?;
+        Ok(L::Property::wrap_template(Box::new(CoalesceTemplate(
+            contents,
+        ))))
+    });
+    map.insert("concat", |language, diagnostics, build_ctx, function| {
+        let contents = function
+            .args
+            .iter()
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .try_collect()?;
+        Ok(L::Property::wrap_template(Box::new(ConcatTemplate(
+            contents,
+        ))))
+    });
+    map.insert("format", |language, diagnostics, build_ctx, function| {
+        let ([format_node], arg_nodes) = function.expect_some_arguments()?;
+        let pieces = template_parser::expect_string_literal_with(format_node, |format, span| {
+            parse_format_string(format)
+                .map_err(|message| TemplateParseError::expression(message, span))
+        })?;
+        let placeholder_count = pieces.len() - 1;
+        if arg_nodes.len() != placeholder_count {
+            return Err(InvalidArguments {
+                name: function.name,
+                message: format!(
+                    "Expected {placeholder_count} arguments for the format string, but got {}",
+                    arg_nodes.len()
+                ),
+                span: function.args_span,
+            }
+            .into());
+        }
+        let args: Vec<_> = arg_nodes
+            .iter()
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .try_collect()?;
+        let mut pieces = pieces.into_iter();
+        let mut contents: Vec<Box<dyn Template>> = vec![Box::new(Literal(pieces.next().unwrap()))];
+        for (arg, piece) in iter::zip(args, pieces) {
+            contents.push(arg);
+            contents.push(Box::new(Literal(piece)));
+        }
+        Ok(L::Property::wrap_template(Box::new(ConcatTemplate(
+            contents,
+        ))))
+    });
+    map.insert("separate", |language, diagnostics, build_ctx, function| {
+        let ([separator_node], content_nodes) = function.expect_some_arguments()?;
+        let separator =
+            expect_template_expression(language, diagnostics, build_ctx, separator_node)?;
+        let contents = content_nodes
+            .iter()
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .try_collect()?;
+        Ok(L::Property::wrap_template(Box::new(SeparateTemplate::new(
+            separator, contents,
+        ))))
+    });
+    map.insert("surround", |language, diagnostics, build_ctx, function| {
+        let [prefix_node, suffix_node, content_node] = function.expect_exact_arguments()?;
+        let prefix = expect_template_expression(language, diagnostics, build_ctx, prefix_node)?;
+        let suffix = expect_template_expression(language, diagnostics, build_ctx, suffix_node)?;
+        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+        let template = build_surround_template(prefix, suffix, content);
+        Ok(L::Property::wrap_template(template))
+    });
+    map.insert("json", |language, diagnostics, build_ctx, function| {
+        let object = build_json_object(language, diagnostics, build_ctx, function)?;
+        Ok(L::Property::wrap_string(object))
+    });
+    map.insert("config", |language, _diagnostics, _build_ctx, function| {
+        // Dynamic lookup can be implemented if needed. The name is literal
+        // string for now so the error can be reported early.
+        let [name_node] = function.expect_exact_arguments()?;
+        let name: ConfigNamePathBuf =
+            template_parser::expect_string_literal_with(name_node, |name, span| {
+                name.parse().map_err(|err| {
+                    TemplateParseError::expression("Failed to parse config name", span)
+                        .with_source(err)
+                })
+            })?;
+        let value = language.settings().get_value(&name).map_err(|err| {
+            TemplateParseError::expression("Failed to get config value", function.name_span)
+                .with_source(err)
+        })?;
+        // .decorated("", "") to trim leading/trailing whitespace
+        Ok(L::Property::wrap_config_value(
+            Literal(value.decorated("", "")).into_dyn(),
+        ))
+    });
+    map.insert(
+        "term_width",
+        |language, _diagnostics, _build_ctx, function| {
+            function.expect_no_arguments()?;
+            // The width doesn't account for the graph or other decorations.
+            let width = match language.term_width() {
+                Some(width) => i64::try_from(width).unwrap_or(i64::MAX),
+                None => language
+                    .settings()
+                    .get_int("ui.default-term-width")
+                    .optional()
+                    .map_err(|err| {
+                        TemplateParseError::expression(
+                            "Failed to get config value",
+                            function.name_span,
+                        )
+                        .with_source(err)
+                    })?
+                    .unwrap_or(80),
+            };
+            Ok(L::Property::wrap_integer(Literal(width).into_dyn()))
+        },
+    );
+    map.insert("now", |language, _diagnostics, _build_ctx, function| {
+        function.expect_no_arguments()?;
+        // The time is fixed when the template is built so all occurrences
+        // agree with each other.
+        let now = language.clock().now();
+        Ok(L::Property::wrap_timestamp(Literal(now).into_dyn()))
+    });
+    map
+}
+
+/// Splits `format` string at `{}` placeholders, unescaping `{{` and `}}`.
+///
+/// The returned list has one more item than the number of placeholders.
+fn parse_format_string(format: &str) -> Result<Vec<String>, &'static str> {
+    let mut pieces = vec![String::new()];
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('{', Some('}')) => pieces.push(String::new()),
+            ('{', Some('{')) => pieces.last_mut().unwrap().push('{'),
+            ('}', Some('}')) => pieces.last_mut().unwrap().push('}'),
+            ('{', _) => return Err("Unmatched `{` in format string (use `{{` to escape)"),
+            ('}', _) => return Err("Unmatched `}` in format string (use `}}` to escape)"),
+            (c, _) => {
+                pieces.last_mut().unwrap().push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    Ok(pieces)
+}
+
+/// Builds `json(key=value, ...)` function which serializes the keyword
+/// arguments as JSON object.
+fn build_json_object<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    function: &FunctionCallNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, String>> {
+    if !function.args.is_empty() {
+        return Err(InvalidArguments {
+            name: function.name,
+            message: "Expected keyword arguments".to_owned(),
+            span: function.args_span,
+        }
+        .into());
+    }
+    if let Some(arg) = function
+        .keyword_args
+        .iter()
+        .duplicates_by(|arg| arg.name)
+        .next()
+    {
+        return Err(InvalidArguments {
+            name: function.name,
+            message: format!(r#"Got multiple values for keyword "{}""#, arg.name),
+            span: arg.name_span.start_pos().span(&arg.value.span.end_pos()),
+        }
+        .into());
+    }
+    let members: Vec<_> = function
+        .keyword_args
+        .iter()
+        .map(|arg| {
+            let key = serde_json::to_string(arg.name).unwrap();
+            let value = expect_json_value_expression(language, diagnostics, build_ctx, &arg.value)?;
+            Ok(value.map(move |value| format!("{key}:{value}")))
+        })
+        .try_collect::<_, _, TemplateParseError>()?;
+    let object = members.map(|members| format!("{{{}}}", members.join(",")));
+    Ok(object.into_dyn())
+}
+
+/// Builds JSON value of the given expression. Boolean and numeric values are
+/// serialized as such, nested `json()` call as object, and the others as
+/// string.
+fn expect_json_value_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, String>> {
+    if let ExpressionKind::FunctionCall(function) = &node.kind {
+        if function.name == "json" {
+            return build_json_object(language, diagnostics, build_ctx, function);
+        }
+    }
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Template",
+        |expression| match expression.type_name() {
+            "Boolean" => Some(
+                expression
+                    .try_into_boolean()?
+                    .map(|b| b.to_string())
+                    .into_dyn(),
+            ),
+            "Integer" => Some(
+                expression
+                    .try_into_integer()?
+                    .map(|i| i.to_string())
+                    .into_dyn(),
+            ),
+            // NaN and infinity are serialized as null
+            "Float" => Some(
+                expression
+                    .try_into_float()?
+                    .map(|f| serde_json::to_string(&f).unwrap())
+                    .into_dyn(),
+            ),
+            _ => Some(
+                expression
+                    .try_into_plain_text()?
+                    .map(|s| serde_json::to_string(&s).unwrap())
+                    .into_dyn(),
+            ),
+        },
+    )
+}
+
+fn new_pad_template<'a, W>(
+    content: Box<dyn Template + 'a>,
+    fill_char: Option<Box<dyn Template + 'a>>,
+    width: BoxedTemplateProperty<'a, usize>,
+    write_padded: W,
+) -> Box<dyn Template + 'a>
+where
+    W: Fn(&mut dyn Formatter, &FormatRecorder, &FormatRecorder, usize) -> io::Result<()> + 'a,
+{
+    let default_fill_char = FormatRecorder::with_data(" ");
+    let template = ReformatTemplate::new(content, move |formatter, recorded| {
+        let width = match width.extract() {
+            Ok(width) => width,
+            Err(err) => return formatter.handle_error(err),
+        };
+        let mut fill_char_recorder;
+        let recorded_fill_char = if let Some(fill_char) = &fill_char {
+            let rewrap = formatter.rewrap_fn();
+            fill_char_recorder = FormatRecorder::new();
+            fill_char.format(&mut rewrap(&mut fill_char_recorder))?;
+            &fill_char_recorder
+        } else {
+            &default_fill_char
+        };
+        write_padded(formatter.as_mut(), recorded, recorded_fill_char, width)
+    });
+    Box::new(template)
+}
+
+fn new_truncate_template<'a, W>(
+    content: Box<dyn Template + 'a>,
+    ellipsis: Option<Box<dyn Template + 'a>>,
+    width: BoxedTemplateProperty<'a, usize>,
+    write_truncated: W,
+) -> Box<dyn Template + 'a>
+where
+    W: Fn(&mut dyn Formatter, &FormatRecorder, &FormatRecorder, usize) -> io::Result<usize> + 'a,
+{
+    let default_ellipsis = FormatRecorder::with_data("");
+    let template = ReformatTemplate::new(content, move |formatter, recorded| {
+        let width = match width.extract() {
+            Ok(width) => width,
+            Err(err) => return formatter.handle_error(err),
+        };
+        let mut ellipsis_recorder;
+        let recorded_ellipsis = if let Some(ellipsis) = &ellipsis {
+            let rewrap = formatter.rewrap_fn();
+            ellipsis_recorder = FormatRecorder::new();
+            ellipsis.format(&mut rewrap(&mut ellipsis_recorder))?;
+            &ellipsis_recorder
+        } else {
+            &default_ellipsis
+        };
+        write_truncated(formatter.as_mut(), recorded, recorded_ellipsis, width)?;
+        Ok(())
+    });
+    Box::new(template)
+}
+
+/// Builds intermediate expression tree from AST nodes.
+pub fn build_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<Expression<L::Property>> {
+    let expression = build_expression_kind(language, diagnostics, build_ctx, node)?;
+    if let Some(node_types) = build_ctx.node_types {
+        let key = std::ptr::from_ref(node).cast();
+        node_types.borrow_mut().insert(key, expression.type_name());
+    }
+    Ok(expression)
+}
+
+fn build_expression_kind<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<Expression<L::Property>> {
+    match &node.kind {
+        ExpressionKind::Identifier(name) => {
+            if let Some(make) = build_ctx.local_variables.get(name) {
+                // Don't label a local variable with its name
+                Ok(Expression::unlabeled(make()))
+            } else if *name == "self" {
+                // "self" is a special variable, so don't label it
+                let make = build_ctx.self_variable;
+                Ok(Expression::unlabeled(make()))
+            } else {
+                let property = build_keyword(language, diagnostics, build_ctx, name, node.span)
+                    .map_err(|err| {
+                        err.extend_keyword_candidates(itertools::chain(
+                            build_ctx.local_variables.keys().copied(),
+                            ["self"],
+                        ))
+                    })?;
+                Ok(Expression::with_label(property, *name))
+            }
+        }
+        ExpressionKind::Boolean(value) => {
+            let property = L::Property::wrap_boolean(Literal(*value).into_dyn());
+            Ok(Expression::unlabeled(property))
+        }
+        ExpressionKind::Integer(value) => {
+            let property = L::Property::wrap_integer(Literal(*value).into_dyn());
+            Ok(Expression::unlabeled(property))
+        }
+        ExpressionKind::Float(value) => {
+            let property = L::Property::wrap_float(Literal(*value).into_dyn());
+            Ok(Expression::unlabeled(property))
+        }
+        ExpressionKind::String(value) => {
+            let property = L::Property::wrap_string(Literal(value.clone()).into_dyn());
+            Ok(Expression::unlabeled(property))
+        }
+        ExpressionKind::Unary(op, arg_node) => {
+            let property = build_unary_operation(language, diagnostics, build_ctx, *op, arg_node)?;
+            Ok(Expression::unlabeled(property))
+        }
+        ExpressionKind::Binary(op, lhs_node, rhs_node) => {
+            let property = build_binary_operation(
+                language,
+                diagnostics,
+                build_ctx,
+                *op,
+                lhs_node,
+                rhs_node,
+                node.span,
+            )?;
+            Ok(Expression::unlabeled(property))
+        }
+        ExpressionKind::Concat(nodes) => {
+            let templates = nodes
+                .iter()
+                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+                .try_collect()?;
+            let property = L::Property::wrap_template(Box::new(ConcatTemplate(templates)));
+            Ok(Expression::unlabeled(property))
+        }
+        ExpressionKind::FunctionCall(function) => {
+            let property = language.build_function(diagnostics, build_ctx, function)?;
+            Ok(Expression::unlabeled(property))
+        }
+        ExpressionKind::MethodCall(method) => {
+            let mut expression =
+                build_expression(language, diagnostics, build_ctx, &method.object)?;
+            expression.property = language.build_method(
+                diagnostics,
+                build_ctx,
+                expression.property,
+                &method.function,
+            )?;
+            expression.labels.push(method.function.name.to_owned());
+            Ok(expression)
+        }
+        ExpressionKind::Lambda(_) => Err(TemplateParseError::expression(
+            "Lambda cannot be defined here",
+            node.span,
+        )),
+        ExpressionKind::Let(let_node) => {
+            let template = build_let_binding(language, diagnostics, build_ctx, let_node)?;
+            Ok(Expression::unlabeled(L::Property::wrap_template(template)))
+        }
+        ExpressionKind::AliasExpanded(id, subst) => {
+            let mut inner_diagnostics = TemplateDiagnostics::new();
+            let expression = build_expression(language, &mut inner_diagnostics, build_ctx, subst)
+                .map_err(|e| e.within_alias_expansion(*id, node.span))?;
+            diagnostics.extend_with(inner_diagnostics, |diag| {
+                diag.within_alias_expansion(*id, node.span)
+            });
+            Ok(expression)
+        }
+    }
+}
+
+/// Default limit of the output size of a single template evaluation.
+const DEFAULT_MAX_OUTPUT_SIZE: HumanByteSize = HumanByteSize(64 << 20);
+
+/// Builds template evaluation tree from AST nodes, with fresh build context.
+///
+/// `wrap_self` specifies the type of the top-level property, which should be
+/// one of the `L::Property::wrap_*()` functions.
+///
+/// The size of the rendered output is limited by `ui.template-max-output-size`.
+/// The nesting depth of the expression should have been checked by the parser.
+pub fn build<'a, C: Clone + 'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    node: &ExpressionNode,
+    // TODO: Generic L: WrapProperty<C> trait might be better. See the
+    // comment in build_formattable_list_method().
+    wrap_self: impl Fn(BoxedTemplateProperty<'a, C>) -> L::Property,
+) -> TemplateParseResult<TemplateRenderer<'a, C>> {
+    build_with_node_types(language, diagnostics, node, wrap_self, None)
+}
+
+fn build_with_node_types<'a, C: Clone + 'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    node: &ExpressionNode,
+    wrap_self: impl Fn(BoxedTemplateProperty<'a, C>) -> L::Property,
+    node_types: Option<&NodeTypesMap>,
+) -> TemplateParseResult<TemplateRenderer<'a, C>> {
+    let self_placeholder = PropertyPlaceholder::new();
+    let build_ctx = BuildContext {
+        local_variables: HashMap::new(),
+        self_variable: &|| wrap_self(self_placeholder.clone().into_dyn()),
+        node_types,
+    };
+    let settings = language.settings();
+    let config_error = |err| {
+        TemplateParseError::expression("Failed to get config value", node.span).with_source(err)
+    };
+    let HumanByteSize(max_output_size) = settings
+        .get_value_with("ui.template-max-output-size", TryInto::try_into)
+        .optional()
+        .map_err(config_error)?
+        .unwrap_or(DEFAULT_MAX_OUTPUT_SIZE);
+    let template = expect_template_expression(language, diagnostics, &build_ctx, node)?;
+    let max_output_bytes = usize::try_from(max_output_size).unwrap_or(usize::MAX);
+    Ok(TemplateRenderer::new(template, self_placeholder).with_max_output_bytes(max_output_bytes))
+}
+
+fn get_max_depth(settings: &UserSettings, span: pest::Span<'_>) -> TemplateParseResult<usize> {
+    let max_depth = settings
+        .get("ui.template-max-depth")
+        .optional()
+        .map_err(|err| {
+            TemplateParseError::expression("Failed to get config value", span).with_source(err)
+        })?
+        .unwrap_or(template_parser::DEFAULT_MAX_DEPTH);
+    Ok(max_depth)
+}
+
+/// Parses text, expands aliases, then builds template evaluation tree.
+pub fn parse<'a, C: Clone + 'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    template_text: &str,
+    aliases_map: &TemplateAliasesMap,
+    wrap_self: impl Fn(BoxedTemplateProperty<'a, C>) -> L::Property,
+) -> TemplateParseResult<TemplateRenderer<'a, C>> {
+    let text_span = pest::Span::new(template_text, 0, template_text.len()).unwrap();
+    let max_depth = get_max_depth(language.settings(), text_span)?;
+    let node = template_parser::parse_with_max_depth(template_text, aliases_map, max_depth)?;
+    build(language, diagnostics, &node, wrap_self)
+        .map_err(|err| err.extend_alias_candidates(aliases_map))
+}
+
+/// Parses text and type-checks the template without rendering it.
+///
+/// This is useful for checking user-provided template before saving it. The
+/// self type `C` and `wrap_self` are needed to resolve keywords, but no value
+/// of `C` is required. Warnings are reported to `diagnostics` even if the
+/// template is valid.
+pub fn validate<'a, C: Clone + 'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    template_text: &str,
+    aliases_map: &TemplateAliasesMap,
+    wrap_self: impl Fn(BoxedTemplateProperty<'a, C>) -> L::Property,
+) -> TemplateParseResult<()> {
+    parse(language, diagnostics, template_text, aliases_map, wrap_self)?;
+    Ok(())
+}
+
+pub fn expect_boolean_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, bool>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Boolean",
+        |expression| expression.try_into_boolean(),
+    )
+}
+
+pub fn expect_integer_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, i64>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Integer",
+        |expression| expression.try_into_integer(),
+    )
+}
+
+/// If the given expression `node` is of `Integer` type, converts it to `isize`.
+pub fn expect_isize_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, isize>> {
+    let i64_property = expect_integer_expression(language, diagnostics, build_ctx, node)?;
+    let isize_property = i64_property.and_then(|v| Ok(isize::try_from(v)?));
+    Ok(isize_property.into_dyn())
+}
+
+/// If the given expression `node` is of `Integer` type, converts it to `usize`.
+pub fn expect_usize_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, usize>> {
+    let i64_property = expect_integer_expression(language, diagnostics, build_ctx, node)?;
+    let usize_property = i64_property.and_then(|v| Ok(usize::try_from(v)?));
+    Ok(usize_property.into_dyn())
+}
+
+pub fn expect_float_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, f64>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Float",
+        |expression| expression.try_into_float(),
+    )
+}
+
+pub fn expect_timestamp_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, Timestamp>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Timestamp",
+        |expression| expression.try_into_timestamp(),
+    )
+}
+
+pub fn expect_string_list_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, Vec<String>>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "List<String>",
+        |expression| expression.try_into_string_list(),
+    )
+}
+
+/// Integer or float property.
+enum NumericProperty<'a> {
+    Integer(BoxedTemplateProperty<'a, i64>),
+    Float(BoxedTemplateProperty<'a, f64>),
+}
+
+impl<'a> NumericProperty<'a> {
+    fn into_float(self) -> BoxedTemplateProperty<'a, f64> {
+        match self {
+            NumericProperty::Integer(property) => property.map(|v| v as f64).into_dyn(),
+            NumericProperty::Float(property) => property,
+        }
+    }
+}
+
+/// Operand of arithmetic operators.
+enum ArithmeticProperty<'a> {
+    Numeric(NumericProperty<'a>),
+    Timestamp(BoxedTemplateProperty<'a, Timestamp>),
+    Duration(BoxedTemplateProperty<'a, TimeDelta>),
+}
+
+impl ArithmeticProperty<'_> {
+    fn type_name(&self) -> &'static str {
+        match self {
+            ArithmeticProperty::Numeric(NumericProperty::Integer(_)) => "Integer",
+            ArithmeticProperty::Numeric(NumericProperty::Float(_)) => "Float",
+            ArithmeticProperty::Timestamp(_) => "Timestamp",
+            ArithmeticProperty::Duration(_) => "Duration",
+        }
+    }
+}
+
+/// If the given expression `node` is of `Timestamp` or `Duration` type,
+/// returns the property of that type. Otherwise the expression is converted to
+/// numeric type.
+fn expect_arithmetic_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<ArithmeticProperty<'a>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Integer",
+        |expression| match expression.type_name() {
+            "Timestamp" => expression
+                .try_into_timestamp()
+                .map(ArithmeticProperty::Timestamp),
+            "Duration" => expression
+                .try_into_duration()
+                .map(ArithmeticProperty::Duration),
+            "Float" => expression
+                .try_into_float()
+                .map(|property| ArithmeticProperty::Numeric(NumericProperty::Float(property))),
+            _ => expression
+                .try_into_integer()
+                .map(|property| ArithmeticProperty::Numeric(NumericProperty::Integer(property))),
+        },
+    )
+}
+
+/// If the given expression `node` is of `Float` type, returns the float
+/// property. Otherwise the expression is converted to `Integer`.
+fn expect_numeric_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<NumericProperty<'a>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Integer",
+        |expression| {
+            if expression.type_name() == "Float" {
+                expression.try_into_float().map(NumericProperty::Float)
+            } else {
+                expression.try_into_integer().map(NumericProperty::Integer)
+            }
+        },
+    )
+}
+
+/// Compiles the string literal `node` as a regular expression.
+fn expect_regex_literal(node: &ExpressionNode) -> TemplateParseResult<regex::Regex> {
+    template_parser::expect_string_literal_with(node, |pattern, span| {
+        regex::Regex::new(pattern).map_err(|err| {
+            TemplateParseError::expression("Invalid regular expression", span).with_source(err)
+        })
+    })
+}
+
+pub fn expect_plain_text_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<BoxedTemplateProperty<'a, String>> {
+    // Since any formattable type can be converted to a string property,
+    // the expected type is not a String, but a Template.
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Template",
+        |expression| expression.try_into_plain_text(),
+    )
+}
+
+pub fn expect_template_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<Box<dyn Template + 'a>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Template",
+        |expression| expression.try_into_template(),
+    )
+}
+
+fn expect_expression_of_type<'a, L: TemplateLanguage<'a> + ?Sized, T>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+    expected_type: &str,
+    f: impl FnOnce(Expression<L::Property>) -> Option<T>,
+) -> TemplateParseResult<T> {
+    if let ExpressionKind::AliasExpanded(id, subst) = &node.kind {
+        let mut inner_diagnostics = TemplateDiagnostics::new();
+        let expression = expect_expression_of_type(
+            language,
+            &mut inner_diagnostics,
+            build_ctx,
+            subst,
+            expected_type,
+            f,
+        )
+        .map_err(|e| e.within_alias_expansion(*id, node.span))?;
+        diagnostics.extend_with(inner_diagnostics, |diag| {
+            diag.within_alias_expansion(*id, node.span)
+        });
+        Ok(expression)
+    } else {
+        let expression = build_expression(language, diagnostics, build_ctx, node)?;
+        let actual_type = expression.type_name();
+        f(expression)
+            .ok_or_else(|| TemplateParseError::expected_type(expected_type, actual_type, node.span))
+    }
+}
+
+pub mod debug {
+    //! Debugging aids for template authors.
+
+    use std::fmt::Write as _;
+    use std::iter;
+
+    use super::*;
+
+    /// Parses text and dumps the expression tree with the type of each node.
+    ///
+    /// This is a debugging aid to see how the template is parsed and typed.
+    /// The output format isn't stable. Aliases are shown as `AliasExpanded`
+    /// nodes. Nodes that aren't built as expressions, such as lambdas, have no
+    /// type annotation.
+    pub fn dump_expression_tree<'a, C: Clone + 'a, L: TemplateLanguage<'a> + ?Sized>(
+        language: &L,
+        diagnostics: &mut TemplateDiagnostics,
+        template_text: &str,
+        aliases_map: &TemplateAliasesMap,
+        wrap_self: impl Fn(BoxedTemplateProperty<'a, C>) -> L::Property,
+    ) -> TemplateParseResult<String> {
+        let text_span = pest::Span::new(template_text, 0, template_text.len()).unwrap();
+        let max_depth = get_max_depth(language.settings(), text_span)?;
+        let node = template_parser::parse_with_max_depth(template_text, aliases_map, max_depth)?;
+        let node_types = NodeTypesMap::default();
+        build_with_node_types(language, diagnostics, &node, wrap_self, Some(&node_types))
+            .map_err(|err| err.extend_alias_candidates(aliases_map))?;
+        let mut out = String::new();
+        write_expression_tree(&node_types.borrow(), &node, "", 0, &mut out);
+        Ok(out)
+    }
+
+    fn write_expression_tree(
+        node_types: &HashMap<*const (), &'static str>,
+        node: &ExpressionNode,
+        prefix: &str,
+        depth: usize,
+        out: &mut String,
+    ) {
+        let (label, children): (_, Vec<(&str, &ExpressionNode)>) = match &node.kind {
+            ExpressionKind::Identifier(name) => (format!("Identifier({name})"), vec![]),
+            ExpressionKind::Boolean(value) => (format!("Boolean({value})"), vec![]),
+            ExpressionKind::Integer(value) => (format!("Integer({value})"), vec![]),
+            ExpressionKind::Float(value) => (format!("Float({value})"), vec![]),
+            ExpressionKind::String(value) => (format!("String({value:?})"), vec![]),
+            ExpressionKind::Unary(op, arg_node) => (format!("Unary({op:?})"), vec![("", arg_node)]),
+            ExpressionKind::Binary(op, lhs_node, rhs_node) => (
+                format!("Binary({op:?})"),
+                vec![("", lhs_node), ("", rhs_node)],
+            ),
+            ExpressionKind::Concat(nodes) => {
+                ("Concat".to_owned(), nodes.iter().map(|n| ("", n)).collect())
+            }
+            ExpressionKind::FunctionCall(function) => {
+                let args = function.args.iter().map(|n| ("", n));
+                let keyword_args = function
+                    .keyword_args
+                    .iter()
+                    .map(|arg| (arg.name, &arg.value));
+                (
+                    format!("FunctionCall({})", function.name),
+                    args.chain(keyword_args).collect(),
+                )
+            }
+            ExpressionKind::MethodCall(method) => {
+                let function = &method.function;
+                let object = iter::once(("", &method.object));
+                let args = function.args.iter().map(|n| ("", n));
+                let keyword_args = function
+                    .keyword_args
+                    .iter()
+                    .map(|arg| (arg.name, &arg.value));
+                (
+                    format!("MethodCall({})", function.name),
+                    object.chain(args).chain(keyword_args).collect(),
+                )
+            }
+            ExpressionKind::Lambda(lambda) => (
+                format!("Lambda({})", lambda.params.join(", ")),
+                vec![("", &lambda.body)],
+            ),
+            ExpressionKind::Let(let_node) => (
+                format!("Let({})", let_node.name),
+                vec![("", &let_node.value), ("", &let_node.body)],
+            ),
+            ExpressionKind::AliasExpanded(id, subst) => {
+                (format!("AliasExpanded({id})"), vec![("", subst.as_ref())])
+            }
+        };
+        let type_name = node_type(node_types, node);
+        let indent = "  ".repeat(depth);
+        let prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{prefix}=")
+        };
+        match type_name {
+            Some(type_name) => writeln!(out, "{indent}{prefix}{label}: {type_name}").unwrap(),
+            None => writeln!(out, "{indent}{prefix}{label}").unwrap(),
+        }
+        for (name, child) in children {
+            write_expression_tree(node_types, child, name, depth + 1, out);
+        }
+    }
+
+    fn node_type(
+        node_types: &HashMap<*const (), &'static str>,
+        node: &ExpressionNode,
+    ) -> Option<&'static str> {
+        let type_name = node_types.get(&std::ptr::from_ref(node).cast()).copied();
+        match &node.kind {
+            // Alias may be unwrapped without building the AliasExpanded node
+            ExpressionKind::AliasExpanded(_, subst) if type_name.is_none() => {
+                node_type(node_types, subst)
+            }
+            _ => type_name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::error::Error as _;
+    use std::iter;
+    use std::rc::Rc;
+
+    use assert_matches::assert_matches;
+    use jj_lib::backend::MillisSinceEpoch;
+    use jj_lib::config::ConfigLayer;
+    use jj_lib::config::ConfigSource;
+    use jj_lib::config::StackedConfig;
+
+    use super::*;
+    use crate::formatter;
+    use crate::formatter::ColorFormatter;
+    use crate::formatter::PlainTextFormatter;
+    use crate::generic_templater::GenericTemplateLanguage;
+    use crate::template_parser::TemplateDiagnosticSeverity;
+    use crate::template_parser::TemplateDiagnosticsExt as _;
+    use crate::templater::constant_property;
+
+    type TestTemplateLanguage = GenericTemplateLanguage<'static, ()>;
+    type P = <TestTemplateLanguage as TemplateLanguage<'static>>::Property;
+
+    /// Helper to set up template evaluation environment.
+    struct TestTemplateEnv {
+        language: TestTemplateLanguage,
+        aliases_map: TemplateAliasesMap,
+        color_rules: Vec<(Vec<String>, formatter::Style)>,
+    }
+
+    impl TestTemplateEnv {
+        fn new() -> Self {
+            Self::with_config(StackedConfig::with_defaults())
+        }
+
+        fn with_config(config: StackedConfig) -> Self {
+            let settings = UserSettings::from_config(config).unwrap();
+            TestTemplateEnv {
+                language: TestTemplateLanguage::new(&settings),
+                aliases_map: TemplateAliasesMap::new(),
+                color_rules: Vec::new(),
+            }
+        }
+    }
+
+    impl TestTemplateEnv {
+        fn add_keyword<F>(&mut self, name: &'static str, build: F)
+        where
+            F: Fn() -> P + 'static,
+        {
+            self.language.add_keyword(name, move |_| Ok(build()));
+        }
+
+        /// Adds Integer keyword that evaluates to the number of times it has
+        /// been evaluated so far.
+        fn add_counting_keyword(&mut self, name: &'static str) -> Rc<Cell<i64>> {
+            let evaluated = Rc::new(Cell::new(0));
+            self.add_keyword(name, {
+                let evaluated = evaluated.clone();
+                move || {
+                    let evaluated = evaluated.clone();
+                    let property = literal(()).map(move |()| {
+                        evaluated.set(evaluated.get() + 1);
+                        evaluated.get()
+                    });
+                    P::wrap_integer(property.into_dyn())
+                }
+            });
+            evaluated
+        }
+
+        fn add_alias(&mut self, decl: impl AsRef<str>, defn: impl Into<String>) {
+            self.aliases_map.insert(decl, defn).unwrap();
+        }
+
+        fn add_color(&mut self, label: &str, fg: crossterm::style::Color) {
+            let labels = label.split_whitespace().map(|s| s.to_owned()).collect();
+            let style = formatter::Style {
+                fg: Some(fg),
+                ..Default::default()
+            };
+            self.color_rules.push((labels, style));
+        }
+
+        fn parse(&self, template: &str) -> TemplateParseResult<TemplateRenderer<'static, ()>> {
+            parse(
+                &self.language,
+                &mut TemplateDiagnostics::new(),
+                template,
+                &self.aliases_map,
+                P::wrap_self,
+            )
+        }
+
+        fn parse_err(&self, template: &str) -> String {
+            let err = self.parse(template).err().unwrap();
+            iter::successors(Some(&err), |e| e.origin()).join("\n")
+        }
+
+        fn render_ok(&self, template: &str) -> String {
+            let template = self.parse(template).unwrap();
+            let mut output = Vec::new();
+            let mut formatter =
+                ColorFormatter::new(&mut output, self.color_rules.clone().into(), false);
+            template.format(&(), &mut formatter).unwrap();
+            drop(formatter);
+            String::from_utf8(output).unwrap()
+        }
+    }
+
+    fn literal<'a, O: Clone + 'a>(value: O) -> BoxedTemplateProperty<'a, O> {
+        Literal(value).into_dyn()
+    }
+
+    fn new_error_property<O>(message: &str) -> BoxedTemplateProperty<'_, O> {
+        Literal(())
+            .and_then(|()| Err(TemplatePropertyError(message.into())))
+            .into_dyn()
+    }
+
+    fn new_signature(name: &str, email: &str) -> Signature {
+        Signature {
+            name: name.to_owned(),
+            email: email.to_owned(),
+            timestamp: new_timestamp(0, 0),
+        }
+    }
+
+    fn new_timestamp(msec: i64, tz_offset: i32) -> Timestamp {
+        Timestamp {
+            timestamp: MillisSinceEpoch(msec),
+            tz_offset,
+        }
+    }
+
+    #[test]
+    fn test_parsed_tree() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("divergent", || P::wrap_boolean(literal(false)));
+        env.add_keyword("empty", || P::wrap_boolean(literal(true)));
+        env.add_keyword("hello", || P::wrap_string(literal("Hello".to_owned())));
+
+        // Empty
+        insta::assert_snapshot!(env.render_ok(r#"  "#), @"");
+
+        // Single term with whitespace
+        insta::assert_snapshot!(env.render_ok(r#"  hello.upper()  "#), @"HELLO");
+
+        // Multiple terms
+        insta::assert_snapshot!(env.render_ok(r#"  hello.upper()  ++ true "#), @"HELLOtrue");
+
+        // Parenthesized single term
+        insta::assert_snapshot!(env.render_ok(r#"(hello.upper())"#), @"HELLO");
+
+        // Parenthesized multiple terms and concatenation
+        insta::assert_snapshot!(env.render_ok(r#"(hello.upper() ++ " ") ++ empty"#), @"HELLO true");
+
+        // Parenthesized "if" condition
+        insta::assert_snapshot!(env.render_ok(r#"if((divergent), "t", "f")"#), @"f");
+
+        // Parenthesized method chaining
+        insta::assert_snapshot!(env.render_ok(r#"(hello).upper()"#), @"HELLO");
+    }
+
+    #[test]
+    fn test_argument_type_error_span() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || P::wrap_string(literal("".to_owned())));
+        env.add_alias("text", r#""abc""#);
+
+        // Errors point at the bad argument, not the whole call
+        insta::assert_snapshot!(env.parse_err(r#"description.substr(0, "2")"#), @r#"
+         --> 1:23
+          |
+        1 | description.substr(0, "2")
+          |                       ^-^
+          |
+          = Expected expression of type `Integer`, but actual type is `String`
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"fill(10, "a", keep_indent = 1)"#), @r#"
+         --> 1:29
+          |
+        1 | fill(10, "a", keep_indent = 1)
+          |                             ^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Integer`
+        "#);
+        // Alias expanded to the bad argument
+        insta::assert_snapshot!(env.parse_err(r#"description.truncate(text)"#), @r#"
+         --> 1:22
+          |
+        1 | description.truncate(text)
+          |                      ^--^
+          |
+          = In alias `text`
+         --> 1:1
+          |
+        1 | "abc"
+          | ^---^
+          |
+          = Expected expression of type `Integer`, but actual type is `String`
+        "#);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || P::wrap_string(literal("".to_owned())));
+        env.add_keyword("empty", || P::wrap_boolean(literal(true)));
+
+        insta::assert_snapshot!(env.parse_err(r#"description ()"#), @r"
+         --> 1:13
+          |
+        1 | description ()
+          |             ^---
+          |
+          = expected <EOI>, `++`, `||`, `&&`, `==`, `!=`, `>=`, `>`, `<=`, `<`, `+`, `-`, `*`, `/`, or `%`
+        ");
+
+        insta::assert_snapshot!(env.parse_err(r#"foo"#), @r"
+         --> 1:1
+          |
+        1 | foo
+          | ^-^
+          |
+          = Keyword `foo` doesn't exist
+        ");
+
+        insta::assert_snapshot!(env.parse_err(r#"foo()"#), @r"
+         --> 1:1
+          |
+        1 | foo()
+          | ^-^
+          |
+          = Function `foo` doesn't exist
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"false()"#), @r"
+         --> 1:1
+          |
+        1 | false()
+          | ^---^
+          |
+          = Expected identifier
+        ");
+
+        insta::assert_snapshot!(env.parse_err(r#"!foo"#), @r"
+         --> 1:2
+          |
+        1 | !foo
+          |  ^-^
+          |
+          = Keyword `foo` doesn't exist
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"true && 123"#), @r"
+         --> 1:9
+          |
+        1 | true && 123
+          |         ^-^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Integer`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"true == 1"#), @r"
+         --> 1:1
+          |
+        1 | true == 1
+          | ^-------^
+          |
+          = Cannot compare expressions of type `Boolean` and `Integer`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"true != 'a'"#), @r"
+         --> 1:1
+          |
+        1 | true != 'a'
+          | ^---------^
+          |
+          = Cannot compare expressions of type `Boolean` and `String`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"1 == true"#), @r"
+         --> 1:1
+          |
+        1 | 1 == true
+          | ^-------^
+          |
+          = Cannot compare expressions of type `Integer` and `Boolean`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"1 != 'a'"#), @r"
+         --> 1:1
+          |
+        1 | 1 != 'a'
+          | ^------^
+          |
+          = Cannot compare expressions of type `Integer` and `String`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"'a' == true"#), @r"
+         --> 1:1
+          |
+        1 | 'a' == true
+          | ^---------^
+          |
+          = Cannot compare expressions of type `String` and `Boolean`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"'a' != 1"#), @r"
+         --> 1:1
+          |
+        1 | 'a' != 1
+          | ^------^
+          |
+          = Cannot compare expressions of type `String` and `Integer`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"'a' == label("", "")"#), @r#"
+         --> 1:1
+          |
+        1 | 'a' == label("", "")
+          | ^------------------^
+          |
+          = Cannot compare expressions of type `String` and `Template`
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"'a' > 1"#), @r"
+         --> 1:1
+          |
+        1 | 'a' > 1
+          | ^-----^
+          |
+          = Cannot compare expressions of type `String` and `Integer`
+        ");
+
+        insta::assert_snapshot!(env.parse_err(r#"description.first_line().foo()"#), @r"
+         --> 1:26
+          |
+        1 | description.first_line().foo()
+          |                          ^-^
+          |
+          = Method `foo` doesn't exist for type `String`
+        ");
+
+        insta::assert_snapshot!(env.parse_err(r#"10000000000000000000"#), @r"
+         --> 1:1
+          |
+        1 | 10000000000000000000
+          | ^------------------^
+          |
+          = Invalid integer literal
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"0x8000000000000000"#), @r"
+         --> 1:1
+          |
+        1 | 0x8000000000000000
+          | ^----------------^
+          |
+          = Invalid integer literal
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"42.foo()"#), @r"
+         --> 1:4
+          |
+        1 | 42.foo()
+          |    ^-^
+          |
+          = Method `foo` doesn't exist for type `Integer`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"(-empty)"#), @r"
+         --> 1:3
+          |
+        1 | (-empty)
+          |   ^---^
+          |
+          = Expected expression of type `Integer`, but actual type is `Boolean`
+        ");
+
+        insta::assert_snapshot!(env.parse_err(r#"("foo" ++ "bar").baz()"#), @r#"
+         --> 1:18
+          |
+        1 | ("foo" ++ "bar").baz()
+          |                  ^-^
+          |
+          = Method `baz` doesn't exist for type `Template`
+        "#);
+
+        insta::assert_snapshot!(env.parse_err(r#"description.contains()"#), @r"
+         --> 1:22
+          |
+        1 | description.contains()
+          |                      ^
+          |
+          = Function `contains`: Expected 1 arguments
+        ");
+
+        insta::assert_snapshot!(env.parse_err(r#"description.first_line("foo")"#), @r#"
+         --> 1:24
+          |
+        1 | description.first_line("foo")
+          |                        ^---^
+          |
+          = Function `first_line`: Expected 0 arguments
+        "#);
+
+        insta::assert_snapshot!(env.parse_err(r#"label()"#), @r"
+         --> 1:7
+          |
+        1 | label()
+          |       ^
+          |
+          = Function `label`: Expected 2 arguments
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"label("foo", "bar", "baz")"#), @r#"
+         --> 1:7
+          |
+        1 | label("foo", "bar", "baz")
+          |       ^-----------------^
+          |
+          = Function `label`: Expected 2 arguments
+        "#);
+
+        insta::assert_snapshot!(env.parse_err(r#"if()"#), @r"
+         --> 1:4
+          |
+        1 | if()
+          |    ^
+          |
+          = Function `if`: Expected 2 to 3 arguments
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"if("foo", "bar", "baz", "quux")"#), @r#"
+         --> 1:4
+          |
+        1 | if("foo", "bar", "baz", "quux")
+          |    ^-------------------------^
+          |
+          = Function `if`: Expected 2 to 3 arguments
+        "#);
+
+        insta::assert_snapshot!(env.parse_err(r#"pad_start("foo", fill_char = "bar", "baz")"#), @r#"
+         --> 1:37
+          |
+        1 | pad_start("foo", fill_char = "bar", "baz")
+          |                                     ^---^
+          |
+          = Function `pad_start`: Positional argument follows keyword argument
+        "#);
+
+        insta::assert_snapshot!(env.parse_err(r#"pad_start(3, "foo", fill="bar")"#), @r#"
+         --> 1:21
+          |
+        1 | pad_start(3, "foo", fill="bar")
+          |                     ^--------^
+          |
+          = Function `pad_start`: Unexpected keyword argument "fill"
+        "#);
+        insta::assert_snapshot!(
+            env.parse_err(r#"pad_start(3, "foo", fill_char="a", fill_char="b")"#),
+            @r#"
+         --> 1:36
+          |
+        1 | pad_start(3, "foo", fill_char="a", fill_char="b")
+          |                                    ^-----------^
+          |
+          = Function `pad_start`: Got multiple values for keyword "fill_char"
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"pad_start(3, "foo", "a", fill_char="b")"#), @r#"
+         --> 1:26
+          |
+        1 | pad_start(3, "foo", "a", fill_char="b")
+          |                          ^-----------^
+          |
+          = Function `pad_start`: Got multiple values for keyword "fill_char"
+        "#);
+
+        insta::assert_snapshot!(env.parse_err(r#"if(label("foo", "bar"), "baz")"#), @r#"
+         --> 1:4
+          |
+        1 | if(label("foo", "bar"), "baz")
+          |    ^-----------------^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Template`
+        "#);
+
+        insta::assert_snapshot!(env.parse_err(r#"|x| description"#), @r"
+         --> 1:1
+          |
+        1 | |x| description
+          | ^-------------^
+          |
+          = Lambda cannot be defined here
+        ");
+    }
+
+    #[test]
+    fn test_self_keyword() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("say_hello", || P::wrap_string(literal("Hello".to_owned())));
+
+        insta::assert_snapshot!(env.render_ok(r#"self.say_hello()"#), @"Hello");
+        insta::assert_snapshot!(env.parse_err(r#"self"#), @r"
+         --> 1:1
+          |
+        1 | self
+          | ^--^
+          |
+          = Expected expression of type `Template`, but actual type is `Self`
+        ");
+    }
+
+    #[test]
+    fn test_self_keyword_candidates() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("say_hello", || P::wrap_string(literal("Hello".to_owned())));
+        env.add_keyword("say_goodbye", || P::wrap_string(literal("Bye".to_owned())));
+        env.add_keyword("unrelated", || P::wrap_boolean(literal(true)));
+        assert_eq!(
+            env.language.keyword_names().sorted().collect_vec(),
+            ["say_goodbye", "say_hello", "unrelated"]
+        );
+
+        assert_matches!(
+            env.parse("say_helo").err().unwrap().kind(),
+            TemplateParseErrorKind::NoSuchKeyword { name, candidates }
+                if name == "say_helo" && *candidates == ["say_hello"]
+        );
+        assert_matches!(
+            env.parse("self.say_goodby()").err().unwrap().kind(),
+            TemplateParseErrorKind::NoSuchMethod { name, candidates, .. }
+                if name == "say_goodby" && *candidates == ["say_goodbye"]
+        );
+    }
+
+    #[test]
+    fn test_method_names() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("say_hello", || P::wrap_string(literal("Hello".to_owned())));
+        env.language.add_method(
+            "greet",
+            |_language, _diagnostics, _build_ctx, _self_property, function| {
+                function.expect_no_arguments()?;
+                Ok(P::wrap_string(literal("Hi".to_owned())))
+            },
+        );
+
+        insta::assert_snapshot!(
+            env.language.method_names("String").unwrap().join("\n"), @r"
+        as_bool
+        byte_len
+        capitalize
+        captures
+        char_count
+        contains
+        contains_ignore_case
+        count_matches
+        count_matches_regex
+        csv_quote
+        default
+        ends_with
+        escape_control
+        escape_json
+        escape_markdown
+        first_line
+        hash_to
+        indent_rest
+        is_empty
+        len
+        lines
+        lower
+        matches
+        normalize_newlines
+        pad_centered
+        pad_end
+        pad_start
+        parse_int
+        parse_timestamp
+        remove_prefix
+        remove_suffix
+        repeat
+        replace
+        replace_regex
+        reverse
+        shell_quote
+        split
+        starts_with
+        strip_ansi
+        substr
+        surround
+        title_case
+        trim
+        trim_end
+        trim_matches
+        trim_start
+        truncate
+        upper
+        word_wrap
+        ");
+        assert_eq!(
+            env.language.method_names("Self").unwrap(),
+            ["greet", "say_hello"]
+        );
+        assert_eq!(
+            env.language.method_names("Bytes").unwrap(),
+            ["base64", "hex", "hexdump", "len"]
+        );
+        insta::assert_snapshot!(
+            env.language.method_names("List<String>").unwrap().join(" "),
+            @"all any chunk enumerate filter first group_by is_empty join last len map max min nth reverse skip sort sort_by sum table take unique");
+        assert_eq!(
+            env.language.method_names("Group<String>").unwrap(),
+            ["items", "key"]
+        );
+        assert_eq!(env.language.method_names("Template").unwrap(), ["surround"]);
+        assert_eq!(env.language.method_names("Unknown"), None);
+
+        // All listed methods should exist
+        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(0))));
+        env.add_keyword("some_stream", || {
+            let out_property = Literal(()).map(|()| Box::new(iter::empty()) as ListStream<_>);
+            P::wrap_string_stream(out_property.into_dyn())
+        });
+        let method_names = |type_name| env.language.method_names(type_name).unwrap();
+        for (type_name, object) in [
+            ("Option<String>", r#""a".lines().first()"#),
+            ("List<String>", r#""a".lines()"#),
+            ("List<List<String>>", r#""a".lines().chunk(1)"#),
+            ("Group<String>", r#""a".lines().group_by(|s| s).first()"#),
+            ("List<Group<String>>", r#""a".lines().group_by(|s| s)"#),
+            ("Enumerated<String>", r#""a".lines().enumerate().first()"#),
+            ("List<Enumerated<String>>", r#""a".lines().enumerate()"#),
+            ("Option<Integer>", "some_i64"),
+            ("Template", r#"label("", "")"#),
+            ("ListTemplate", r#""a".lines().map(|s| s)"#),
+            ("Stream<String>", "some_stream"),
+        ] {
+            for name in method_names(type_name) {
+                let template = format!("{object}.{name}()");
+                let kind = env.parse(&template).err().map(|err| err.kind().clone());
+                assert!(
+                    !matches!(kind, Some(TemplateParseErrorKind::NoSuchMethod { .. })),
+                    "{template}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_keyword_doc() {
+        let mut env = TestTemplateEnv::new();
+        env.language
+            .add_keyword_with_doc("say_hello", "Greets the user", |_self_property| {
+                Ok(P::wrap_string(literal("Hello".to_owned())))
+            });
+        env.language
+            .add_keyword_with_doc("say_goodbye", "Says goodbye", |_self_property| {
+                Ok(P::wrap_string(literal("Bye".to_owned())))
+            });
+        env.add_keyword("say_nothing", || P::wrap_string(literal("".to_owned())));
+
+        assert_eq!(env.language.doc("say_hello"), Some("Greets the user"));
+        assert_eq!(env.language.doc("say_nothing"), None);
+        assert_eq!(env.language.doc("unknown"), None);
+        assert_eq!(
+            env.language.method_names("Self").unwrap(),
+            ["say_goodbye", "say_hello", "say_nothing"]
+        );
+        insta::assert_snapshot!(env.render_ok("say_hello"), @"Hello");
+
+        // Docs of the documented candidates are attached to the error hint
+        let err = env.parse("say_helo").err().unwrap();
+        assert_matches!(
+            err.kind(),
+            TemplateParseErrorKind::NoSuchKeyword { candidates, .. }
+                if *candidates == ["say_hello", "say_nothing"]
+        );
+        insta::assert_snapshot!(err.hint().unwrap(), @"`say_hello`: Greets the user");
+        assert!(err.source().is_none());
+        let err = env.parse("self.say_()").err().unwrap();
+        insta::assert_snapshot!(err.hint().unwrap(), @r"
+        `say_goodbye`: Says goodbye
+        `say_hello`: Greets the user
+        ");
+        let err = env.parse("nothing_at_all").err().unwrap();
+        assert!(err.hint().is_none());
+    }
+
+    #[test]
+    fn test_dump_expression_tree() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || P::wrap_string(literal("foo".to_owned())));
+        env.add_keyword("tags", || {
+            P::wrap_string_list(literal(vec!["a".to_owned(), "b".to_owned()]))
+        });
+        env.add_alias("greet(x)", r#""Hello, " ++ x"#);
+        let dump = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            debug::dump_expression_tree(
+                &env.language,
+                &mut diagnostics,
+                template,
+                &env.aliases_map,
+                P::wrap_self,
+            )
+        };
+
+        insta::assert_snapshot!(
+            dump(r#"description.upper() ++ " " ++ if(!tags, "-", tags.len() + 1)"#).unwrap(),
+            @r#"
+        Concat: Template
+          MethodCall(upper): String
+            Identifier(description): String
+          String(" "): String
+          FunctionCall(if): Template
+            Unary(LogicalNot): Boolean
+              Identifier(tags): List<String>
+            String("-"): String
+            Binary(Add): Integer
+              MethodCall(len): Integer
+                Identifier(tags): List<String>
+              Integer(1): Integer
+        "#);
+        insta::assert_snapshot!(
+            dump(r#"tags.map(|t| t.upper()).join(",") ++ pad_start(5, greet(description), fill_char = "*")"#).unwrap(),
+            @r#"
+        Concat: Template
+          MethodCall(join): Template
+            MethodCall(map): ListTemplate
+              Identifier(tags): List<String>
+              Lambda(t)
+                MethodCall(upper): String
+                  Identifier(t): String
+            String(","): String
+          FunctionCall(pad_start): Template
+            Integer(5): Integer
+            AliasExpanded(greet(x)): Template
+              Concat: Template
+                String("Hello, "): String
+                AliasExpanded(x): String
+                  Identifier(description): String
+            fill_char=String("*"): String
+        "#);
+        assert_matches!(dump("description.foo()"), Err(_));
+    }
+
+    #[test]
+    fn test_self_method_warning() {
+        let mut env = TestTemplateEnv::new();
+        env.language.add_method(
+            "old_hello",
+            |_language, diagnostics, _build_ctx, _self_property, function| {
+                function.expect_no_arguments()?;
+                diagnostics.add_warning(TemplateParseError::expression(
+                    "old_hello is deprecated",
+                    function.name_span,
+                ));
+                Ok(P::wrap_string(literal("Hello".to_owned())))
+            },
+        );
+
+        let mut diagnostics = TemplateDiagnostics::new();
+        parse(
+            &env.language,
+            &mut diagnostics,
+            "old_hello ++ ' ' ++ self.old_hello()",
+            &env.aliases_map,
+            P::wrap_self,
+        )
+        .unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        insta::assert_snapshot!(diagnostics.iter().join("\n"), @r"
+         --> 1:1
+          |
+        1 | old_hello ++ ' ' ++ self.old_hello()
+          | ^-------^
+          |
+          = old_hello is deprecated
+         --> 1:26
+          |
+        1 | old_hello ++ ' ' ++ self.old_hello()
+          |                          ^-------^
+          |
+          = old_hello is deprecated
+        ");
+
+        // Rendering proceeds
+        insta::assert_snapshot!(
+            env.render_ok("old_hello ++ ' ' ++ self.old_hello()"),
+            @"Hello Hello");
+    }
+
+    #[test]
+    fn test_plain_text_renderer() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
+        env.add_color("error", crossterm::style::Color::DarkRed);
+        env.add_color("warning", crossterm::style::Color::DarkYellow);
+        let render = |template: TemplateRenderer<'_, ()>| {
+            let mut output = Vec::new();
+            let mut formatter =
+                ColorFormatter::new(&mut output, env.color_rules.clone().into(), false);
+            template.format(&(), &mut formatter).unwrap();
+            drop(formatter);
+            String::from_utf8(output).unwrap()
+        };
+
+        let text = r#"label("error", "a") ++ " " ++ bad_string"#;
+        insta::assert_snapshot!(
+            render(env.parse(text).unwrap()),
+            @"[38;5;1ma[39m [38;5;1m<Error: Bad>[39m");
+        let output = render(env.parse(text).unwrap().plain_text());
+        assert!(!output.contains('\x1b'));
+        insta::assert_snapshot!(output, @"a <Error: Bad>");
+
+        // Labels attached to the renderer are dropped as well
+        let output = render(env.parse(r#""b""#).unwrap().labeled("warning").plain_text());
+        insta::assert_snapshot!(output, @"b");
+
+        // Labels pushed to the formatter by the caller aren't applied
+        let render_in_label = |template: TemplateRenderer<'_, ()>, debug: bool| {
+            let mut output = Vec::new();
+            let mut formatter =
+                ColorFormatter::new(&mut output, env.color_rules.clone().into(), debug);
+            (&mut formatter as &mut dyn Formatter)
+                .with_label("warning", |formatter| {
+                    write!(formatter, "x")?;
+                    template.format(&(), formatter)?;
+                    write!(formatter, "y")
+                })
+                .unwrap();
+            drop(formatter);
+            String::from_utf8(output).unwrap()
         };
-        let mut fill_char_recorder;
-        let recorded_fill_char = if let Some(fill_char) = &fill_char {
-            let rewrap = formatter.rewrap_fn();
-            fill_char_recorder = FormatRecorder::new();
-            fill_char.format(&mut rewrap(&mut fill_char_recorder))?;
-            &fill_char_recorder
-        } else {
-            &default_fill_char
+        let output = render_in_label(env.parse(text).unwrap().plain_text(), false);
+        insta::assert_snapshot!(output, @"[38;5;3mx[39ma <Error: Bad>[38;5;3my[39m");
+        let output = render_in_label(env.parse(text).unwrap().plain_text(), true);
+        insta::assert_snapshot!(
+            output,
+            @"[38;5;3m<<warning::x>>[39ma <Error: Bad>[38;5;3m<<warning::y>>[39m");
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || P::wrap_string(literal("".to_owned())));
+        env.language.add_method(
+            "old_hello",
+            |_language, diagnostics, _build_ctx, _self_property, function| {
+                function.expect_no_arguments()?;
+                diagnostics.add_warning(TemplateParseError::expression(
+                    "old_hello is deprecated",
+                    function.name_span,
+                ));
+                Ok(P::wrap_string(literal("Hello".to_owned())))
+            },
+        );
+        let validate = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            let result = validate(
+                &env.language,
+                &mut diagnostics,
+                template,
+                &env.aliases_map,
+                P::wrap_self,
+            );
+            (result, diagnostics)
         };
-        write_padded(formatter.as_mut(), recorded, recorded_fill_char, width)
-    });
-    Box::new(template)
-}
 
-fn new_truncate_template<'a, W>(
-    content: Box<dyn Template + 'a>,
-    ellipsis: Option<Box<dyn Template + 'a>>,
-    width: BoxedTemplateProperty<'a, usize>,
-    write_truncated: W,
-) -> Box<dyn Template + 'a>
-where
-    W: Fn(&mut dyn Formatter, &FormatRecorder, &FormatRecorder, usize) -> io::Result<usize> + 'a,
-{
-    let default_ellipsis = FormatRecorder::with_data("");
-    let template = ReformatTemplate::new(content, move |formatter, recorded| {
-        let width = match width.extract() {
-            Ok(width) => width,
-            Err(err) => return formatter.handle_error(err),
+        let (result, diagnostics) = validate("description.first_line() ++ '\n'");
+        assert!(result.is_ok());
+        assert!(diagnostics.is_empty());
+
+        // Valid template with warnings
+        let (result, diagnostics) = validate("self.old_hello() ++ description");
+        assert!(result.is_ok());
+        insta::assert_snapshot!(diagnostics.iter().join("\n"), @r"
+         --> 1:6
+          |
+        1 | self.old_hello() ++ description
+          |      ^-------^
+          |
+          = old_hello is deprecated
+        ");
+
+        // Type error is detected without rendering
+        let (result, diagnostics) = validate("old_hello ++ description.len().upper()");
+        insta::assert_snapshot!(result.unwrap_err(), @r"
+         --> 1:32
+          |
+        1 | old_hello ++ description.len().upper()
+          |                                ^---^
+          |
+          = Method `upper` doesn't exist for type `Integer`
+        ");
+        assert_eq!(diagnostics.len(), 1);
+
+        // Syntax error
+        let (result, diagnostics) = validate("description ++");
+        assert_matches!(
+            result.unwrap_err().kind(),
+            TemplateParseErrorKind::SyntaxError
+        );
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_self_keyword_label() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("say_hello", || P::wrap_string(literal("Hello".to_owned())));
+        env.add_color("say_hello", crossterm::style::Color::Green);
+        env.add_color("greeting", crossterm::style::Color::Red);
+
+        // Self keyword is labeled by its name
+        insta::assert_snapshot!(env.render_ok("say_hello"), @"[38;5;10mHello[39m");
+        insta::assert_snapshot!(env.render_ok("self.say_hello()"), @"[38;5;10mHello[39m");
+        // label() function can be applied to the self keyword, and the inner
+        // label takes precedence
+        insta::assert_snapshot!(
+            env.render_ok("label('greeting', 'Hi, ' ++ say_hello)"),
+            @"[38;5;9mHi, [38;5;10mHello[39m");
+    }
+
+    #[test]
+    fn test_self_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("say_hello", || P::wrap_string(literal("Hello".to_owned())));
+        env.language.add_method(
+            "greet",
+            |language, diagnostics, build_ctx, _self_property, function| {
+                let [name_node] = function.expect_exact_arguments()?;
+                let name_property =
+                    expect_plain_text_expression(language, diagnostics, build_ctx, name_node)?;
+                let out_property = name_property.map(|name| format!("Hello, {name}!"));
+                Ok(P::wrap_string(out_property.into_dyn()))
+            },
+        );
+
+        insta::assert_snapshot!(env.render_ok(r#"self.greet("world")"#), @"Hello, world!");
+        insta::assert_snapshot!(
+            env.render_ok(r#"self.greet(say_hello.lower()).upper()"#),
+            @"HELLO, HELLO!");
+        insta::assert_snapshot!(env.parse_err(r#"self.greet()"#), @r"
+         --> 1:12
+          |
+        1 | self.greet()
+          |            ^
+          |
+          = Function `greet`: Expected 1 arguments
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"greet"#), @r"
+         --> 1:1
+          |
+        1 | greet
+          | ^---^
+          |
+          = Keyword `greet` doesn't exist
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"self.greeet("world")"#), @r#"
+         --> 1:6
+          |
+        1 | self.greeet("world")
+          |      ^----^
+          |
+          = Method `greeet` doesn't exist for type `Self`
+        "#);
+    }
+
+    #[test]
+    fn test_self_alias() {
+        let mut env = TestTemplateEnv::new();
+        env.language
+            .add_alias("greeting", "say_hello ++ '!'")
+            .unwrap();
+        env.language
+            .add_alias("quoted(x)", "'\"' ++ x ++ '\"'")
+            .unwrap();
+        env.language.add_alias("bad", "say_hello ++").unwrap();
+        env.add_keyword("say_hello", || P::wrap_string(literal("Hello".to_owned())));
+        assert!(env.language.add_alias("bad(", "").is_err());
+
+        let parse = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            env.language.parse_template(&mut diagnostics, template)
         };
-        let mut ellipsis_recorder;
-        let recorded_ellipsis = if let Some(ellipsis) = &ellipsis {
-            let rewrap = formatter.rewrap_fn();
-            ellipsis_recorder = FormatRecorder::new();
-            ellipsis.format(&mut rewrap(&mut ellipsis_recorder))?;
-            &ellipsis_recorder
-        } else {
-            &default_ellipsis
+        let render_ok = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            env.language
+                .render_to_string(&mut diagnostics, template, &())
+                .unwrap()
+        };
+        let parse_err = |template: &str| {
+            let err = parse(template).err().unwrap();
+            iter::successors(Some(&err), |e| e.origin()).join("\n")
         };
-        write_truncated(formatter.as_mut(), recorded, recorded_ellipsis, width)?;
-        Ok(())
-    });
-    Box::new(template)
-}
 
-/// Builds intermediate expression tree from AST nodes.
-pub fn build_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    node: &ExpressionNode,
-) -> TemplateParseResult<Expression<L::Property>> {
-    match &node.kind {
-        ExpressionKind::Identifier(name) => {
-            if let Some(make) = build_ctx.local_variables.get(name) {
-                // Don't label a local variable with its name
-                Ok(Expression::unlabeled(make()))
-            } else if *name == "self" {
-                // "self" is a special variable, so don't label it
-                let make = build_ctx.self_variable;
-                Ok(Expression::unlabeled(make()))
-            } else {
-                let property = build_keyword(language, diagnostics, build_ctx, name, node.span)
-                    .map_err(|err| {
-                        err.extend_keyword_candidates(itertools::chain(
-                            build_ctx.local_variables.keys().copied(),
-                            ["self"],
-                        ))
-                    })?;
-                Ok(Expression::with_label(property, *name))
-            }
-        }
-        ExpressionKind::Boolean(value) => {
-            let property = L::Property::wrap_boolean(Literal(*value).into_dyn());
-            Ok(Expression::unlabeled(property))
-        }
-        ExpressionKind::Integer(value) => {
-            let property = L::Property::wrap_integer(Literal(*value).into_dyn());
-            Ok(Expression::unlabeled(property))
-        }
-        ExpressionKind::String(value) => {
-            let property = L::Property::wrap_string(Literal(value.clone()).into_dyn());
-            Ok(Expression::unlabeled(property))
-        }
-        ExpressionKind::Unary(op, arg_node) => {
-            let property = build_unary_operation(language, diagnostics, build_ctx, *op, arg_node)?;
-            Ok(Expression::unlabeled(property))
-        }
-        ExpressionKind::Binary(op, lhs_node, rhs_node) => {
-            let property = build_binary_operation(
-                language,
-                diagnostics,
-                build_ctx,
-                *op,
-                lhs_node,
-                rhs_node,
-                node.span,
-            )?;
-            Ok(Expression::unlabeled(property))
-        }
-        ExpressionKind::Concat(nodes) => {
-            let templates = nodes
-                .iter()
-                .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
-                .try_collect()?;
-            let property = L::Property::wrap_template(Box::new(ConcatTemplate(templates)));
-            Ok(Expression::unlabeled(property))
-        }
-        ExpressionKind::FunctionCall(function) => {
-            let property = language.build_function(diagnostics, build_ctx, function)?;
-            Ok(Expression::unlabeled(property))
-        }
-        ExpressionKind::MethodCall(method) => {
-            let mut expression =
-                build_expression(language, diagnostics, build_ctx, &method.object)?;
-            expression.property = language.build_method(
-                diagnostics,
-                build_ctx,
-                expression.property,
-                &method.function,
-            )?;
-            expression.labels.push(method.function.name.to_owned());
-            Ok(expression)
-        }
-        ExpressionKind::Lambda(_) => Err(TemplateParseError::expression(
-            "Lambda cannot be defined here",
-            node.span,
-        )),
-        ExpressionKind::AliasExpanded(id, subst) => {
-            let mut inner_diagnostics = TemplateDiagnostics::new();
-            let expression = build_expression(language, &mut inner_diagnostics, build_ctx, subst)
-                .map_err(|e| e.within_alias_expansion(*id, node.span))?;
-            diagnostics.extend_with(inner_diagnostics, |diag| {
-                diag.within_alias_expansion(*id, node.span)
-            });
-            Ok(expression)
-        }
+        insta::assert_snapshot!(render_ok(r#"greeting"#), @"Hello!");
+        insta::assert_snapshot!(render_ok(r#"quoted(say_hello.upper())"#), @r#""HELLO""#);
+        insta::assert_snapshot!(parse_err(r#"bad"#), @r"
+         --> 1:1
+          |
+        1 | bad
+          | ^-^
+          |
+          = In alias `bad`
+         --> 1:13
+          |
+        1 | say_hello ++
+          |             ^---
+          |
+          = expected <expression>
+        ");
     }
-}
 
-/// Builds template evaluation tree from AST nodes, with fresh build context.
-///
-/// `wrap_self` specifies the type of the top-level property, which should be
-/// one of the `L::Property::wrap_*()` functions.
-pub fn build<'a, C: Clone + 'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    node: &ExpressionNode,
-    // TODO: Generic L: WrapProperty<C> trait might be better. See the
-    // comment in build_formattable_list_method().
-    wrap_self: impl Fn(BoxedTemplateProperty<'a, C>) -> L::Property,
-) -> TemplateParseResult<TemplateRenderer<'a, C>> {
-    let self_placeholder = PropertyPlaceholder::new();
-    let build_ctx = BuildContext {
-        local_variables: HashMap::new(),
-        self_variable: &|| wrap_self(self_placeholder.clone().into_dyn()),
-    };
-    let template = expect_template_expression(language, diagnostics, &build_ctx, node)?;
-    Ok(TemplateRenderer::new(template, self_placeholder))
-}
+    #[test]
+    fn test_self_context() {
+        type ContextTemplateLanguage = GenericTemplateLanguage<'static, (), Rc<Vec<String>>>;
+        type P = <ContextTemplateLanguage as TemplateLanguage<'static>>::Property;
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        let names = Rc::new(vec!["foo".to_owned(), "bar".to_owned()]);
+        let mut language = ContextTemplateLanguage::with_context(names.clone(), &settings);
+        language.add_context_keyword("names", |names, self_property| {
+            let names = names.clone();
+            let out_property = self_property.map(move |()| names.join(","));
+            Ok(P::wrap_string(out_property.into_dyn()))
+        });
+        language.add_method(
+            "name",
+            |language, diagnostics, build_ctx, self_property, function| {
+                let [index_node] = function.expect_exact_arguments()?;
+                let index_property =
+                    expect_usize_expression(language, diagnostics, build_ctx, index_node)?;
+                let names = language.context().clone();
+                let out_property = (self_property, index_property)
+                    .map(move |((), index)| names.get(index).cloned().unwrap_or_default());
+                Ok(P::wrap_string(out_property.into_dyn()))
+            },
+        );
+        assert!(Rc::ptr_eq(language.context(), &names));
 
-/// Parses text, expands aliases, then builds template evaluation tree.
-pub fn parse<'a, C: Clone + 'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    template_text: &str,
-    aliases_map: &TemplateAliasesMap,
-    wrap_self: impl Fn(BoxedTemplateProperty<'a, C>) -> L::Property,
-) -> TemplateParseResult<TemplateRenderer<'a, C>> {
-    let node = template_parser::parse(template_text, aliases_map)?;
-    build(language, diagnostics, &node, wrap_self)
-        .map_err(|err| err.extend_alias_candidates(aliases_map))
-}
+        let render_ok = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            language
+                .render_to_string(&mut diagnostics, template, &())
+                .unwrap()
+        };
 
-pub fn expect_boolean_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    node: &ExpressionNode,
-) -> TemplateParseResult<BoxedTemplateProperty<'a, bool>> {
-    expect_expression_of_type(
-        language,
-        diagnostics,
-        build_ctx,
-        node,
-        "Boolean",
-        |expression| expression.try_into_boolean(),
-    )
-}
+        insta::assert_snapshot!(render_ok("names"), @"foo,bar");
+        insta::assert_snapshot!(render_ok("self.name(1) ++ self.name(0)"), @"barfoo");
+        insta::assert_snapshot!(render_ok("self.names().upper()"), @"FOO,BAR");
+    }
+
+    #[test]
+    fn test_self_fallback_method() {
+        type Record = HashMap<String, String>;
+        type MapTemplateLanguage = GenericTemplateLanguage<'static, Record, Vec<&'static str>>;
+        type P = <MapTemplateLanguage as TemplateLanguage<'static>>::Property;
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        let mut language = MapTemplateLanguage::with_context(vec!["name", "place"], &settings);
+        language.add_keyword("len", |self_property| {
+            let out_property = self_property.and_then(|record| Ok(i64::try_from(record.len())?));
+            Ok(P::wrap_integer(out_property.into_dyn()))
+        });
+        language.add_fallback_method(
+            |language, _diagnostics, _build_ctx, self_property, function| {
+                if !language.context().contains(&function.name) {
+                    return Ok(None);
+                }
+                function.expect_no_arguments()?;
+                let name = function.name.to_owned();
+                let out_property = self_property.map(move |record| record.get(&name).cloned());
+                Ok(Some(P::wrap_string_opt(out_property.into_dyn())))
+            },
+        );
+        let record = Record::from([("name".to_owned(), "jj".to_owned())]);
+
+        let render_ok = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            language
+                .render_to_string(&mut diagnostics, template, &record)
+                .unwrap()
+        };
+        let parse_err = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            let err = language
+                .render_to_string(&mut diagnostics, template, &record)
+                .unwrap_err();
+            err.kind().to_string()
+        };
+
+        // Registered keywords take precedence
+        insta::assert_snapshot!(render_ok("len"), @"1");
+        // Dynamic fields can be referenced as keywords or methods
+        insta::assert_snapshot!(render_ok("name"), @"jj");
+        insta::assert_snapshot!(render_ok("self.name().upper()"), @"JJ");
+        insta::assert_snapshot!(render_ok("place.map_or('nowhere', |s| s)"), @"nowhere");
+        insta::assert_snapshot!(render_ok("name ++ ' ' ++ len"), @"jj 1");
+
+        // Unresolved names are reported as usual
+        insta::assert_snapshot!(parse_err("unknown"), @"Keyword `unknown` doesn't exist");
+        insta::assert_snapshot!(
+            parse_err("self.unknown()"),
+            @"Method `unknown` doesn't exist for type `Self`");
+        insta::assert_snapshot!(
+            parse_err("self.name(1)"),
+            @"Function `name`: Expected 0 arguments");
+    }
 
-pub fn expect_integer_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    node: &ExpressionNode,
-) -> TemplateParseResult<BoxedTemplateProperty<'a, i64>> {
-    expect_expression_of_type(
-        language,
-        diagnostics,
-        build_ctx,
-        node,
-        "Integer",
-        |expression| expression.try_into_integer(),
-    )
-}
+    #[test]
+    fn test_settings_keyword() {
+        let mut config = StackedConfig::with_defaults();
+        config
+            .add_layer(ConfigLayer::parse(ConfigSource::User, "test.greeting = 'Hello'").unwrap());
+        let settings = UserSettings::from_config(config).unwrap();
+        let mut language = TestTemplateLanguage::new(&settings);
+        language.add_settings_keyword("greeting", |settings, _self_property| {
+            let greeting = settings.get_string("test.greeting").unwrap_or_default();
+            Ok(P::wrap_string(literal(greeting)))
+        });
 
-/// If the given expression `node` is of `Integer` type, converts it to `isize`.
-pub fn expect_isize_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    node: &ExpressionNode,
-) -> TemplateParseResult<BoxedTemplateProperty<'a, isize>> {
-    let i64_property = expect_integer_expression(language, diagnostics, build_ctx, node)?;
-    let isize_property = i64_property.and_then(|v| Ok(isize::try_from(v)?));
-    Ok(isize_property.into_dyn())
-}
+        let render_ok = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            language
+                .render_to_string(&mut diagnostics, template, &())
+                .unwrap()
+        };
+        insta::assert_snapshot!(render_ok("greeting"), @"Hello");
+        insta::assert_snapshot!(render_ok("self.greeting().upper()"), @"HELLO");
+    }
 
-/// If the given expression `node` is of `Integer` type, converts it to `usize`.
-pub fn expect_usize_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    node: &ExpressionNode,
-) -> TemplateParseResult<BoxedTemplateProperty<'a, usize>> {
-    let i64_property = expect_integer_expression(language, diagnostics, build_ctx, node)?;
-    let usize_property = i64_property.and_then(|v| Ok(usize::try_from(v)?));
-    Ok(usize_property.into_dyn())
-}
+    #[test]
+    fn test_constant_keyword() {
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        let mut language = TestTemplateLanguage::new(&settings);
+        language.add_keyword("tool_name", |_self_property| {
+            Ok(P::wrap_string(constant_property("jj".to_owned())))
+        });
+        language.add_keyword("answer", |_self_property| {
+            Ok(P::wrap_integer(constant_property(42)))
+        });
+        language.add_keyword("enabled", |_self_property| {
+            Ok(P::wrap_boolean(constant_property(true)))
+        });
 
-pub fn expect_plain_text_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    node: &ExpressionNode,
-) -> TemplateParseResult<BoxedTemplateProperty<'a, String>> {
-    // Since any formattable type can be converted to a string property,
-    // the expected type is not a String, but a Template.
-    expect_expression_of_type(
-        language,
-        diagnostics,
-        build_ctx,
-        node,
-        "Template",
-        |expression| expression.try_into_plain_text(),
-    )
-}
+        let render_ok = |template: &str| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            language
+                .render_to_string(&mut diagnostics, template, &())
+                .unwrap()
+        };
 
-pub fn expect_template_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    node: &ExpressionNode,
-) -> TemplateParseResult<Box<dyn Template + 'a>> {
-    expect_expression_of_type(
-        language,
-        diagnostics,
-        build_ctx,
-        node,
-        "Template",
-        |expression| expression.try_into_template(),
-    )
-}
+        insta::assert_snapshot!(render_ok("tool_name.upper()"), @"JJ");
+        insta::assert_snapshot!(render_ok("answer + 1"), @"43");
+        insta::assert_snapshot!(render_ok(r#"if(enabled, "on", "off")"#), @"on");
+    }
 
-fn expect_expression_of_type<'a, L: TemplateLanguage<'a> + ?Sized, T>(
-    language: &L,
-    diagnostics: &mut TemplateDiagnostics,
-    build_ctx: &BuildContext<L::Property>,
-    node: &ExpressionNode,
-    expected_type: &str,
-    f: impl FnOnce(Expression<L::Property>) -> Option<T>,
-) -> TemplateParseResult<T> {
-    if let ExpressionKind::AliasExpanded(id, subst) = &node.kind {
-        let mut inner_diagnostics = TemplateDiagnostics::new();
-        let expression = expect_expression_of_type(
-            language,
-            &mut inner_diagnostics,
-            build_ctx,
-            subst,
-            expected_type,
-            f,
-        )
-        .map_err(|e| e.within_alias_expansion(*id, node.span))?;
-        diagnostics.extend_with(inner_diagnostics, |diag| {
-            diag.within_alias_expansion(*id, node.span)
+    #[test]
+    fn test_self_optional_keyword() {
+        type Person = (String, Option<String>);
+        type PersonTemplateLanguage = GenericTemplateLanguage<'static, Person>;
+        type P = <PersonTemplateLanguage as TemplateLanguage<'static>>::Property;
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        let mut language = PersonTemplateLanguage::new(&settings);
+        language.add_keyword("name", |self_property| {
+            let out_property = self_property.map(|(name, _)| name);
+            Ok(P::wrap_string(out_property.into_dyn()))
         });
-        Ok(expression)
-    } else {
-        let expression = build_expression(language, diagnostics, build_ctx, node)?;
-        let actual_type = expression.type_name();
-        f(expression)
-            .ok_or_else(|| TemplateParseError::expected_type(expected_type, actual_type, node.span))
+        language.add_keyword("nickname", |self_property| {
+            let out_property = self_property.map(|(_, nickname)| nickname);
+            Ok(P::wrap_string_opt(out_property.into_dyn()))
+        });
+
+        let render_ok = |template: &str, person: &Person| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            language
+                .render_to_string(&mut diagnostics, template, person)
+                .unwrap()
+        };
+        let alice = ("Alice".to_owned(), Some("Ally".to_owned()));
+        let bob = ("Bob".to_owned(), None);
+        let carol = ("Carol".to_owned(), Some("".to_owned()));
+
+        // Absent value renders as empty
+        insta::assert_snapshot!(render_ok("name ++ '/' ++ nickname", &alice), @"Alice/Ally");
+        insta::assert_snapshot!(render_ok("name ++ '/' ++ nickname", &bob), @"Bob/");
+
+        // Optional value can be tested for presence. Empty string is present.
+        let template = "if(nickname, name ++ ' aka ' ++ nickname, name)";
+        insta::assert_snapshot!(render_ok(template, &alice), @"Alice aka Ally");
+        insta::assert_snapshot!(render_ok(template, &bob), @"Bob");
+        insta::assert_snapshot!(render_ok(template, &carol), @"Carol aka");
+        insta::assert_snapshot!(render_ok("coalesce(nickname, name)", &bob), @"Bob");
+
+        // Methods are applied to the present value
+        insta::assert_snapshot!(render_ok("nickname.upper()", &alice), @"ALLY");
+        insta::assert_snapshot!(
+            render_ok("nickname.upper()", &bob),
+            @"<Error: No String available>");
+        insta::assert_snapshot!(render_ok("name.contains(nickname)", &bob), @"true");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::iter;
+    #[test]
+    fn test_generic_keywords_macro() {
+        #[derive(Clone)]
+        struct Package {
+            name: String,
+            version: String,
+            size: i64,
+            downloads: i64,
+            is_yanked: bool,
+        }
+        type PackageTemplateLanguage = GenericTemplateLanguage<'static, Package>;
+        type P = <PackageTemplateLanguage as TemplateLanguage<'static>>::Property;
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        let mut language = PackageTemplateLanguage::new(&settings);
+        language.add_keywords(crate::generic_keywords!(Package {
+            name: String,
+            version: String,
+            size: i64,
+            downloads: i64,
+            is_yanked: bool,
+        }));
+        language.add_keywords([(
+            "id",
+            crate::generic_templater::new_keyword_fn(|self_property| {
+                let out_property =
+                    self_property.map(|v: Package| format!("{}@{}", v.name, v.version));
+                Ok(P::wrap_string(out_property.into_dyn()))
+            }),
+        )]);
+        let mut names = language.keyword_names().collect_vec();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            ["downloads", "id", "is_yanked", "name", "size", "version"]
+        );
 
-    use jj_lib::backend::MillisSinceEpoch;
-    use jj_lib::config::StackedConfig;
+        let render_ok = |template: &str, package: &Package| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            language
+                .render_to_string(&mut diagnostics, template, package)
+                .unwrap()
+        };
+        let package = Package {
+            name: "foo".to_owned(),
+            version: "1.2.3".to_owned(),
+            size: 4096,
+            downloads: 10,
+            is_yanked: false,
+        };
 
-    use super::*;
-    use crate::formatter;
-    use crate::formatter::ColorFormatter;
-    use crate::generic_templater::GenericTemplateLanguage;
+        insta::assert_snapshot!(render_ok("id", &package), @"foo@1.2.3");
+        insta::assert_snapshot!(
+            render_ok("name.upper() ++ ' ' ++ version", &package),
+            @"FOO 1.2.3");
+        insta::assert_snapshot!(render_ok("size / 1024 ++ 'KiB'", &package), @"4KiB");
+        insta::assert_snapshot!(render_ok("downloads > 5", &package), @"true");
+        insta::assert_snapshot!(render_ok("if(is_yanked, 'yanked', 'ok')", &package), @"ok");
+    }
 
-    type TestTemplateLanguage = GenericTemplateLanguage<'static, ()>;
-    type P = <TestTemplateLanguage as TemplateLanguage<'static>>::Property;
+    #[test]
+    fn test_generic_render_to_string() {
+        type Person = (String, i64);
+        type PersonTemplateLanguage = GenericTemplateLanguage<'static, Person>;
+        type P = <PersonTemplateLanguage as TemplateLanguage<'static>>::Property;
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        let mut language = PersonTemplateLanguage::new(&settings);
+        language.add_keyword("name", |self_property| {
+            let out_property = self_property.map(|(name, _)| name);
+            Ok(P::wrap_string(out_property.into_dyn()))
+        });
+        language.add_keyword("age", |self_property| {
+            let out_property = self_property.map(|(_, age)| age);
+            Ok(P::wrap_integer(out_property.into_dyn()))
+        });
+        language.add_method(
+            "old_name",
+            |_language, diagnostics, _build_ctx, self_property, function| {
+                function.expect_no_arguments()?;
+                diagnostics.add_warning(TemplateParseError::expression(
+                    "old_name is deprecated",
+                    function.name_span,
+                ));
+                let out_property = self_property.map(|(name, _)| name);
+                Ok(P::wrap_string(out_property.into_dyn()))
+            },
+        );
+        language
+            .add_alias("summary", "name ++ ' (' ++ age ++ ')'")
+            .unwrap();
+        let alice = ("Alice".to_owned(), 30);
 
-    /// Helper to set up template evaluation environment.
-    struct TestTemplateEnv {
-        language: TestTemplateLanguage,
-        aliases_map: TemplateAliasesMap,
-        color_rules: Vec<(Vec<String>, formatter::Style)>,
+        let mut diagnostics = TemplateDiagnostics::new();
+        insta::assert_snapshot!(
+            language.render_to_string(&mut diagnostics, "summary", &alice).unwrap(),
+            @"Alice (30)");
+        insta::assert_snapshot!(
+            language.render_to_string(&mut diagnostics, "name.lower() ++ age", &alice).unwrap(),
+            @"alice30");
+        assert!(diagnostics.is_empty());
+
+        // Evaluation errors are rendered inline
+        insta::assert_snapshot!(
+            language.render_to_string(&mut diagnostics, "age / 0", &alice).unwrap(),
+            @"<Error: Attempt to divide by zero>");
+
+        // Warnings are reported to diagnostics
+        insta::assert_snapshot!(
+            language.render_to_string(&mut diagnostics, "self.old_name()", &alice).unwrap(),
+            @"Alice");
+        assert_eq!(diagnostics.len(), 1);
+
+        // Parse errors are returned
+        let mut diagnostics = TemplateDiagnostics::new();
+        let err = language
+            .render_to_string(&mut diagnostics, "nickname", &alice)
+            .unwrap_err();
+        insta::assert_snapshot!(err.kind(), @"Keyword `nickname` doesn't exist");
     }
 
-    impl TestTemplateEnv {
-        fn new() -> Self {
-            Self::with_config(StackedConfig::with_defaults())
+    #[test]
+    fn test_generic_template_streaming() {
+        /// Writer that records the number of bytes and lines, and how many
+        /// list items had been generated ahead of the written lines.
+        struct CountingWriter {
+            generated_count: Rc<Cell<usize>>,
+            total_len: usize,
+            line_count: usize,
+            max_pending_count: usize,
         }
 
-        fn with_config(config: StackedConfig) -> Self {
-            let settings = UserSettings::from_config(config).unwrap();
-            TestTemplateEnv {
-                language: TestTemplateLanguage::new(&settings),
-                aliases_map: TemplateAliasesMap::new(),
-                color_rules: Vec::new(),
+        impl CountingWriter {
+            fn new(generated_count: Rc<Cell<usize>>) -> Self {
+                generated_count.set(0);
+                Self {
+                    generated_count,
+                    total_len: 0,
+                    line_count: 0,
+                    max_pending_count: 0,
+                }
             }
         }
-    }
 
-    impl TestTemplateEnv {
-        fn add_keyword<F>(&mut self, name: &'static str, build: F)
-        where
-            F: Fn() -> P + 'static,
-        {
-            self.language.add_keyword(name, move |_| Ok(build()));
+        impl io::Write for CountingWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let pending_count = self.generated_count.get() - self.line_count;
+                self.max_pending_count = self.max_pending_count.max(pending_count);
+                self.total_len += buf.len();
+                self.line_count += buf.iter().filter(|&&b| b == b'\n').count();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
         }
 
-        fn add_alias(&mut self, decl: impl AsRef<str>, defn: impl Into<String>) {
-            self.aliases_map.insert(decl, defn).unwrap();
+        type ItemTemplateLanguage = GenericTemplateLanguage<'static, usize>;
+        type P = <ItemTemplateLanguage as TemplateLanguage<'static>>::Property;
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        let mut language = ItemTemplateLanguage::new(&settings);
+        let generated_count = Rc::new(Cell::new(0));
+        let generate_items = {
+            let generated_count = generated_count.clone();
+            move |count: usize| {
+                let generated_count = generated_count.clone();
+                (0..count).map(move |i| {
+                    generated_count.set(generated_count.get() + 1);
+                    format!("item {i}")
+                })
+            }
+        };
+        language.add_keyword("items", {
+            let generate_items = generate_items.clone();
+            move |self_property| {
+                let generate_items = generate_items.clone();
+                let out_property = self_property
+                    .map(move |count| Box::new(generate_items(count)) as ListStream<_>);
+                Ok(P::wrap_string_stream(out_property.into_dyn()))
+            }
+        });
+        language.add_keyword("item_list", move |self_property| {
+            let generate_items = generate_items.clone();
+            let out_property = self_property.map(move |count| generate_items(count).collect());
+            Ok(P::wrap_string_list(out_property.into_dyn()))
+        });
+
+        let mut diagnostics = TemplateDiagnostics::new();
+        let render = |template_text: &str, count: usize| {
+            let template = language
+                .parse_template(&mut TemplateDiagnostics::new(), template_text)
+                .unwrap();
+            let mut writer = CountingWriter::new(generated_count.clone());
+            template
+                .format(&count, &mut PlainTextFormatter::new(&mut writer))
+                .unwrap();
+            writer
+        };
+
+        // Stream items are generated while the output is written, not
+        // evaluated ahead of it.
+        let writer = render(r#"items.map(|s| s ++ "\n").join("")"#, 100_000);
+        assert!(writer.total_len > 1_000_000, "{}", writer.total_len);
+        assert_eq!(writer.line_count, 100_000);
+        assert_eq!(writer.max_pending_count, 1);
+        // The next item is generated before the separator is written.
+        let writer = render(r#"items.map(|s, i| i ++ s).join("\n") ++ "\n""#, 100_000);
+        assert_eq!(writer.line_count, 100_000);
+        assert_eq!(writer.max_pending_count, 2);
+        let writer = render(r#"items.join("\n") ++ "\n""#, 100_000);
+        assert_eq!(writer.line_count, 100_000);
+        assert_eq!(writer.max_pending_count, 2);
+
+        // List<String> is evaluated as a whole before the first item is written
+        let writer = render(r#"item_list.map(|s| s ++ "\n").join("")"#, 100_000);
+        assert_eq!(writer.line_count, 100_000);
+        assert_eq!(writer.max_pending_count, 100_000);
+
+        // The renderer can be reused
+        let template = language
+            .parse_template(&mut diagnostics, r#"items.map(|s| s ++ "\n").join("")"#)
+            .unwrap();
+        for count in [3, 2] {
+            let mut output = Vec::new();
+            template
+                .format(&count, &mut PlainTextFormatter::new(&mut output))
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                (0..count).map(|i| format!("item {i}\n")).join("")
+            );
+        }
+
+        // A stream can also be formatted or tested as a whole
+        let template = language
+            .parse_template(&mut diagnostics, r#"if(items, items, "empty")"#)
+            .unwrap();
+        for (count, expected) in [(3, "item 0 item 1 item 2"), (0, "empty")] {
+            let mut output = Vec::new();
+            template
+                .format(&count, &mut PlainTextFormatter::new(&mut output))
+                .unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
         }
 
-        fn add_color(&mut self, label: &str, fg: crossterm::style::Color) {
-            let labels = label.split_whitespace().map(|s| s.to_owned()).collect();
-            let style = formatter::Style {
-                fg: Some(fg),
-                ..Default::default()
-            };
-            self.color_rules.push((labels, style));
-        }
+        // List methods that need all items aren't supported
+        let err = language
+            .parse_template(&mut diagnostics, "items.len()")
+            .err()
+            .unwrap();
+        insta::assert_snapshot!(
+            err.kind(), @"Method `len` doesn't exist for type `Stream<String>`");
+    }
+
+    #[test]
+    fn test_self_list_keyword() {
+        type Package = (String, Vec<String>);
+        type PackageTemplateLanguage = GenericTemplateLanguage<'static, Package>;
+        type P = <PackageTemplateLanguage as TemplateLanguage<'static>>::Property;
+        let settings = UserSettings::from_config(StackedConfig::with_defaults()).unwrap();
+        let mut language = PackageTemplateLanguage::new(&settings);
+        language.add_keyword("name", |self_property| {
+            let out_property = self_property.map(|(name, _)| name);
+            Ok(P::wrap_string(out_property.into_dyn()))
+        });
+        language.add_keyword("authors", |self_property| {
+            let out_property = self_property.map(|(_, authors)| authors);
+            Ok(P::wrap_string_list(out_property.into_dyn()))
+        });
+
+        let render_ok = |template: &str, package: &Package| {
+            let mut diagnostics = TemplateDiagnostics::new();
+            language
+                .render_to_string(&mut diagnostics, template, package)
+                .unwrap()
+        };
+        let package = (
+            "jj".to_owned(),
+            vec!["alice".to_owned(), "bob".to_owned(), "carol".to_owned()],
+        );
+        let orphan = ("orphan".to_owned(), vec![]);
+
+        insta::assert_snapshot!(render_ok("authors", &package), @"alice bob carol");
+        insta::assert_snapshot!(render_ok("authors.join(', ')", &package), @"alice, bob, carol");
+        insta::assert_snapshot!(render_ok("authors.len()", &package), @"3");
+        insta::assert_snapshot!(
+            render_ok("authors.map(|a| name ++ ':' ++ a.upper()).join(' ')", &package),
+            @"jj:ALICE jj:BOB jj:CAROL");
+        insta::assert_snapshot!(
+            render_ok("authors.filter(|a| a.contains('o')).join(',')", &package),
+            @"bob,carol");
+        insta::assert_snapshot!(
+            render_ok("if(authors, 'has authors', 'no authors')", &orphan),
+            @"no authors");
+    }
+
+    #[test]
+    fn test_boolean_cast() {
+        let mut env = TestTemplateEnv::new();
+
+        insta::assert_snapshot!(env.render_ok(r#"if("", true, false)"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"if("a", true, false)"#), @"true");
+
+        env.add_keyword("sl0", || {
+            P::wrap_string_list(literal::<Vec<String>>(vec![]))
+        });
+        env.add_keyword("sl1", || P::wrap_string_list(literal(vec!["".to_owned()])));
+        insta::assert_snapshot!(env.render_ok(r#"if(sl0, true, false)"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"if(sl1, true, false)"#), @"true");
+
+        // No implicit cast of integer
+        insta::assert_snapshot!(env.parse_err(r#"if(0, true, false)"#), @r"
+         --> 1:4
+          |
+        1 | if(0, true, false)
+          |    ^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Integer`
+        ");
+
+        // Optional integer can be converted to boolean, and Some(0) is truthy.
+        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
+        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(0))));
+        insta::assert_snapshot!(env.render_ok(r#"if(none_i64, true, false)"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"if(some_i64, true, false)"#), @"true");
+
+        insta::assert_snapshot!(env.parse_err(r#"if(label("", ""), true, false)"#), @r#"
+         --> 1:4
+          |
+        1 | if(label("", ""), true, false)
+          |    ^-----------^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Template`
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"if(sl0.map(|x| x), true, false)"#), @r"
+         --> 1:4
+          |
+        1 | if(sl0.map(|x| x), true, false)
+          |    ^------------^
+          |
+          = Expected expression of type `Boolean`, but actual type is `ListTemplate`
+        ");
+
+        env.add_keyword("empty_email", || {
+            P::wrap_email(literal(Email("".to_owned())))
+        });
+        env.add_keyword("nonempty_email", || {
+            P::wrap_email(literal(Email("local@domain".to_owned())))
+        });
+        insta::assert_snapshot!(env.render_ok(r#"if(empty_email, true, false)"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"if(nonempty_email, true, false)"#), @"true");
+    }
+
+    #[test]
+    fn test_arithmetic_operation() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
+        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(1))));
+        env.add_keyword("i64_min", || P::wrap_integer(literal(i64::MIN)));
 
-        fn parse(&self, template: &str) -> TemplateParseResult<TemplateRenderer<'static, ()>> {
-            parse(
-                &self.language,
-                &mut TemplateDiagnostics::new(),
-                template,
-                &self.aliases_map,
-                P::wrap_self,
-            )
-        }
+        insta::assert_snapshot!(env.render_ok(r#"-1"#), @"-1");
+        insta::assert_snapshot!(env.render_ok(r#"--2"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#"-(3)"#), @"-3");
+        insta::assert_snapshot!(env.render_ok(r#"-1.5"#), @"-1.5");
+        insta::assert_snapshot!(env.render_ok(r#"--0.25"#), @"0.25");
+        insta::assert_snapshot!(env.render_ok(r#"0xff + 0o10 + 0b11"#), @"266");
 
-        fn parse_err(&self, template: &str) -> String {
-            let err = self.parse(template).err().unwrap();
-            iter::successors(Some(&err), |e| e.origin()).join("\n")
-        }
+        // Since methods of the contained value can be invoked, it makes sense
+        // to apply operators to optional integers as well.
+        insta::assert_snapshot!(env.render_ok(r#"-none_i64"#), @"<Error: No Integer available>");
+        insta::assert_snapshot!(env.render_ok(r#"-some_i64"#), @"-1");
 
-        fn render_ok(&self, template: &str) -> String {
-            let template = self.parse(template).unwrap();
-            let mut output = Vec::new();
-            let mut formatter =
-                ColorFormatter::new(&mut output, self.color_rules.clone().into(), false);
-            template.format(&(), &mut formatter).unwrap();
-            drop(formatter);
-            String::from_utf8(output).unwrap()
-        }
-    }
+        // No panic on integer overflow.
+        insta::assert_snapshot!(
+            env.render_ok(r#"-i64_min"#),
+            @"<Error: Attempt to negate with overflow>");
 
-    fn literal<'a, O: Clone + 'a>(value: O) -> BoxedTemplateProperty<'a, O> {
-        Literal(value).into_dyn()
+        insta::assert_snapshot!(env.render_ok(r#"1 + 2"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#"1 - 2"#), @"-1");
+        insta::assert_snapshot!(env.render_ok(r#"3 * 4"#), @"12");
+        insta::assert_snapshot!(env.render_ok(r#"7 / 2"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#"-7 / 2"#), @"-3");
+        insta::assert_snapshot!(env.render_ok(r#"7 % 3"#), @"1");
+        insta::assert_snapshot!(env.render_ok(r#"-7 % 3"#), @"-1");
+        insta::assert_snapshot!(env.render_ok(r#"1+2*3-4/2"#), @"5");
+        insta::assert_snapshot!(env.render_ok(r#"(1 + 2) * 3"#), @"9");
+        insta::assert_snapshot!(env.render_ok(r#"10 - 2 - 3"#), @"5");
+        insta::assert_snapshot!(env.render_ok(r#"2 - -3"#), @"5");
+        insta::assert_snapshot!(env.render_ok(r#"1 + 2 ++ 3"#), @"33");
+        insta::assert_snapshot!(env.render_ok(r#"1 + 2 == 3"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"some_i64 + 1"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#"none_i64 + 1"#), @"<Error: No Integer available>");
+        insta::assert_snapshot!(env.render_ok(r#""abc".len() * 100 / 4"#), @"75");
+
+        // Integer division by zero
+        insta::assert_snapshot!(env.render_ok(r#"1 / 0"#), @"<Error: Attempt to divide by zero>");
+        insta::assert_snapshot!(env.render_ok(r#"1 % 0"#), @"<Error: Attempt to divide by zero>");
+
+        // Integer overflow
+        insta::assert_snapshot!(
+            env.render_ok(r#"i64_min - 1"#),
+            @"<Error: Attempt to subtract with overflow>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"-(i64_min + 1) + 2"#),
+            @"<Error: Attempt to add with overflow>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"i64_min * 2"#),
+            @"<Error: Attempt to multiply with overflow>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"i64_min / -1"#),
+            @"<Error: Attempt to divide with overflow>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"i64_min % -1"#),
+            @"<Error: Attempt to calculate the remainder with overflow>");
+
+        // Integer is converted to float if either operand is float
+        insta::assert_snapshot!(env.render_ok(r#"1.5 + 1"#), @"2.5");
+        insta::assert_snapshot!(env.render_ok(r#"1 - 0.25"#), @"0.75");
+        insta::assert_snapshot!(env.render_ok(r#"7 / 2.0"#), @"3.5");
+        insta::assert_snapshot!(env.render_ok(r#"7.5 % 2"#), @"1.5");
+        insta::assert_snapshot!(env.render_ok(r#"(2 * 0.5).round()"#), @"1");
+        insta::assert_snapshot!(env.render_ok(r#"(2 / 3 * 100.0).fixed(1)"#), @"0.0");
+        insta::assert_snapshot!(env.render_ok(r#"(2 * 100.0 / 3).fixed(1)"#), @"66.7");
+        insta::assert_snapshot!(env.render_ok(r#"1 / 0.0"#), @"<Error: Attempt to divide by zero>");
+
+        insta::assert_snapshot!(env.parse_err(r#"1 + "a""#), @r#"
+         --> 1:5
+          |
+        1 | 1 + "a"
+          |     ^-^
+          |
+          = Expected expression of type `Integer`, but actual type is `String`
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"true * 2"#), @r"
+         --> 1:1
+          |
+        1 | true * 2
+          | ^--^
+          |
+          = Expected expression of type `Integer`, but actual type is `Boolean`
+        ");
     }
 
-    fn new_error_property<O>(message: &str) -> BoxedTemplateProperty<'_, O> {
-        Literal(())
-            .and_then(|()| Err(TemplatePropertyError(message.into())))
-            .into_dyn()
-    }
+    #[test]
+    fn test_relational_operation() {
+        let env = TestTemplateEnv::new();
 
-    fn new_signature(name: &str, email: &str) -> Signature {
-        Signature {
-            name: name.to_owned(),
-            email: email.to_owned(),
-            timestamp: new_timestamp(0, 0),
-        }
-    }
+        insta::assert_snapshot!(env.render_ok(r#"1 >= 1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"0 >= 1"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"2 > 1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1 > 1"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"1 <= 1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"2 <= 1"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"0 < 1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1 < 1"#), @"false");
 
-    fn new_timestamp(msec: i64, tz_offset: i32) -> Timestamp {
-        Timestamp {
-            timestamp: MillisSinceEpoch(msec),
-            tz_offset,
-        }
+        insta::assert_snapshot!(env.render_ok(r#"1.5 > 1.25"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1.5 > 1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1 < 1.5"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"2.0 >= 2"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"2 <= 1.9"#), @"false");
+
+        let mut env = env;
+        env.add_keyword("nan", || P::wrap_float(literal(f64::NAN)));
+        insta::assert_snapshot!(env.render_ok(r#"nan < 1"#), @"<Error: Cannot compare NaN>");
+        insta::assert_snapshot!(env.render_ok(r#"nan == nan"#), @"false");
     }
 
     #[test]
-    fn test_parsed_tree() {
+    fn test_logical_operation() {
         let mut env = TestTemplateEnv::new();
-        env.add_keyword("divergent", || P::wrap_boolean(literal(false)));
-        env.add_keyword("empty", || P::wrap_boolean(literal(true)));
-        env.add_keyword("hello", || P::wrap_string(literal("Hello".to_owned())));
-
-        // Empty
-        insta::assert_snapshot!(env.render_ok(r#"  "#), @"");
-
-        // Single term with whitespace
-        insta::assert_snapshot!(env.render_ok(r#"  hello.upper()  "#), @"HELLO");
-
-        // Multiple terms
-        insta::assert_snapshot!(env.render_ok(r#"  hello.upper()  ++ true "#), @"HELLOtrue");
+        env.add_keyword("email1", || {
+            P::wrap_email(literal(Email("local-1@domain".to_owned())))
+        });
+        env.add_keyword("email2", || {
+            P::wrap_email(literal(Email("local-2@domain".to_owned())))
+        });
 
-        // Parenthesized single term
-        insta::assert_snapshot!(env.render_ok(r#"(hello.upper())"#), @"HELLO");
+        insta::assert_snapshot!(env.render_ok(r#"!false"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"false || !false"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"false && true"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"true == true"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"true == false"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"true != true"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"true != false"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1 == 1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1 == 2"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"1 != 1"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"1 != 2"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"0.5 == 0.5"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1.0 == 1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1 != 1.5"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"'a' == 'a'"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"'a' == 'b'"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"'a' != 'a'"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"'a' != 'b'"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"email1 == email1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"email1 == email2"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"email1 == 'local-1@domain'"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"email1 != 'local-2@domain'"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"'local-1@domain' == email1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"'local-2@domain' != email1"#), @"true");
 
-        // Parenthesized multiple terms and concatenation
-        insta::assert_snapshot!(env.render_ok(r#"(hello.upper() ++ " ") ++ empty"#), @"HELLO true");
+        insta::assert_snapshot!(env.render_ok(r#"1 < 2 && 3 >= 3 || 4 > 5"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"1 > 2 || !(2 <= 1) && 3 != 3"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"!true || 1 <= 2"#), @"true");
+        insta::assert_snapshot!(env.parse_err(r#"1 < 2 < 3"#), @r"
+         --> 1:1
+          |
+        1 | 1 < 2 < 3
+          | ^-------^
+          |
+          = Cannot compare expressions of type `Boolean` and `Integer`
+        ");
 
-        // Parenthesized "if" condition
-        insta::assert_snapshot!(env.render_ok(r#"if((divergent), "t", "f")"#), @"f");
+        insta::assert_snapshot!(env.render_ok(r#" !"" "#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#" "" || "a".lines() "#), @"true");
 
-        // Parenthesized method chaining
-        insta::assert_snapshot!(env.render_ok(r#"(hello).upper()"#), @"HELLO");
+        // Short-circuiting
+        env.add_keyword("bad_bool", || P::wrap_boolean(new_error_property("Bad")));
+        insta::assert_snapshot!(env.render_ok(r#"false && bad_bool"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"true && bad_bool"#), @"<Error: Bad>");
+        insta::assert_snapshot!(env.render_ok(r#"false || bad_bool"#), @"<Error: Bad>");
+        insta::assert_snapshot!(env.render_ok(r#"true || bad_bool"#), @"true");
     }
 
     #[test]
-    fn test_parse_error() {
+    fn test_list_method() {
         let mut env = TestTemplateEnv::new();
-        env.add_keyword("description", || P::wrap_string(literal("".to_owned())));
         env.add_keyword("empty", || P::wrap_boolean(literal(true)));
+        env.add_keyword("nan", || P::wrap_float(literal(f64::NAN)));
+        env.add_keyword("sep", || P::wrap_string(literal("sep".to_owned())));
+        let evaluated = env.add_counting_keyword("recorded");
 
-        insta::assert_snapshot!(env.parse_err(r#"description ()"#), @r"
-         --> 1:13
+        insta::assert_snapshot!(env.render_ok(r#""".lines().len()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().len()"#), @"3");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c d e f".split(" ").chunk(2).map(|row| row.join(",")).join("|")"#),
+            @"a,b|c,d|e,f");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c d e".split(" ").chunk(2).map(|row| row.join(",")).join("|")"#),
+            @"a,b|c,d|e");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c".split(" ").chunk(5).map(|row| row.len())"#),
+            @"3");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c d e".split(" ").chunk(2).map(|row| row.len())"#),
+            @"2 2 1");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().chunk(2).len()"#), @"0");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c".split(" ").chunk(2).skip(1).map(|row| row.join(","))"#),
+            @"c");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c".split(" ").chunk(1 + 1).filter(|row| row.len() == 2).len()"#),
+            @"1");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b".split(" ").chunk(0).len()"#),
+            @"<Error: Chunk size must be greater than 0>");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b".split(" ").chunk(-1).len()"#),
+            @"<Error: Chunk size must be greater than 0>");
+        insta::assert_snapshot!(env.parse_err(r#""a b".split(" ").chunk()"#), @r#"
+         --> 1:24
           |
-        1 | description ()
-          |             ^---
+        1 | "a b".split(" ").chunk()
+          |                        ^
           |
-          = expected <EOI>, `++`, `||`, `&&`, `==`, `!=`, `>=`, `>`, `<=`, or `<`
-        ");
-
-        insta::assert_snapshot!(env.parse_err(r#"foo"#), @r"
+          = Function `chunk`: Expected 1 arguments
+        "#);
+        // Nested list can't be rendered directly
+        insta::assert_snapshot!(env.parse_err(r#""a b".split(" ").chunk(2)"#), @r#"
          --> 1:1
           |
-        1 | foo
-          | ^-^
+        1 | "a b".split(" ").chunk(2)
+          | ^-----------------------^
           |
-          = Keyword `foo` doesn't exist
-        ");
+          = Expected expression of type `Template`, but actual type is `List<List<String>>`
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#""a b".split(" ").chunk(2).foo()"#), @r#"
+         --> 1:27
+          |
+        1 | "a b".split(" ").chunk(2).foo()
+          |                           ^-^
+          |
+          = Method `foo` doesn't exist for type `List`
+        "#);
 
-        insta::assert_snapshot!(env.parse_err(r#"foo()"#), @r"
-         --> 1:1
+        insta::assert_snapshot!(env.render_ok(r#""".lines().is_empty()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"" ".lines().is_empty()"#), @"false");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb".lines().filter(|s| s.len() > 2).is_empty()"#),
+            @"true");
+
+        insta::assert_snapshot!(env.render_ok(r#""".lines().join("|")"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().reverse()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().reverse().join("|")"#), @"c|b|a");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().filter(|s| s.len() == 1).reverse()"#),
+            @"c a");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().join("|")"#), @"a|b|c");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().unique()"#), @"");
+        insta::assert_snapshot!(
+            env.render_ok(r#""b\na\nb\nc\na".lines().unique().join("|")"#),
+            @"b|a|c");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().unique().join("|")"#),
+            @"a|b|c");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nB\nb\nA".lower().lines().unique().len()"#),
+            @"2");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().sort()"#), @"");
+        insta::assert_snapshot!(
+            env.render_ok(r#""b\nc\na\nb".lines().sort().join("|")"#),
+            @"a|b|b|c");
+        insta::assert_snapshot!(
+            env.render_ok(r#""ccc\nb\naa\nd\ne".lines().sort_by(|s| s.len()).join("|")"#),
+            @"b|d|e|aa|ccc");
+        insta::assert_snapshot!(
+            env.render_ok(r#""c1\na2\nb1".lines().sort_by(|s| s.substr(1, 2)).join("|")"#),
+            @"c1|b1|a2");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".lines().sort_by(|s| s.len() / (s.len() - 1))"#),
+            @"<Error: Attempt to divide by zero>");
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().sort_by(|s| s.lines())"#), @r#"
+         --> 1:21
           |
-        1 | foo()
-          | ^-^
+        1 | "a".lines().sort_by(|s| s.lines())
+          |                     ^-----------^
           |
-          = Function `foo` doesn't exist
+          = Cannot compare expressions of type `List<String>`
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().sort_by(|s, t| s)"#), @r#"
+         --> 1:22
+          |
+        1 | "a".lines().sort_by(|s, t| s)
+          |                      ^--^
+          |
+          = Expected 1 lambda parameters
+        "#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".lines().sort_by(|s| nan)"#),
+            @"<Error: Cannot compare NaN>");
+        // Key should be evaluated once per item
+        insta::assert_snapshot!(
+            env.render_ok(r#""c\na\nb\nd".lines().sort_by(|s| -recorded).join("|")"#),
+            @"d|b|a|c");
+        assert_eq!(evaluated.get(), 4);
+        // Null separator
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().join("\0")"#), @"a\0b\0c");
+        // Keyword as separator
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().join(sep.upper())"#),
+            @"aSEPbSEPc");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().filter(|s| s.len() == 1)"#),
+            @"a c");
+
+        // Map with index
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().map(|s, i| (i + 1) ++ ". " ++ s).join("\n")"#),
+            @r"
+        1. a
+        2. b
+        3. c
         ");
-        insta::assert_snapshot!(env.parse_err(r#"false()"#), @r"
-         --> 1:1
+        insta::assert_snapshot!(env.render_ok(r#""a\nb".lines().map(|_, i| i)"#), @"0 1");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().filter(|s| s.len() == 1).map(|s, i| i ++ s)"#),
+            @"0a 1c");
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(|s, i, j| s)"#), @r#"
+         --> 1:18
+          |
+        1 | "a".lines().map(|s, i, j| s)
+          |                  ^-----^
+          |
+          = Expected 2 lambda parameters
+        "#);
+
+        insta::assert_snapshot!(env.render_ok(r#""".lines().any(|s| true)"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().all(|s| false)"#), @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().any(|s| s.len() == 2)"#),
+            @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().all(|s| s.len() == 1)"#),
+            @"false");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().all(|s| s.len() == 1)"#),
+            @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().filter(|s| s.len() == 1).all(|s| s != "bb")"#),
+            @"true");
+        // Evaluation stops once the result is determined
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".lines().any(|s| s == "a" || 1 / 0 == 0)"#),
+            @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".lines().all(|s| s == "b" && 1 / 0 == 0)"#),
+            @"false");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".lines().any(|s| s == "b" || 1 / 0 == 0)"#),
+            @"<Error: Attempt to divide by zero>");
+        // Type error
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().any(|s| 0)"#), @r#"
+         --> 1:21
+          |
+        1 | "a".lines().any(|s| 0)
+          |                     ^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Integer`
+        "#);
+
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().first()"#), @"a");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().last()"#), @"c");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().nth(1)"#), @"b");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().nth(-3)"#), @"a");
+        // Element type is preserved
+        insta::assert_snapshot!(env.render_ok(r#""a\nbb\nc".lines().nth(1).upper()"#), @"BB");
+        insta::assert_snapshot!(env.render_ok(r#""a\nbb\nc".lines().last().len() + 1"#), @"2");
+        // Out-of-range access evaluates to None
+        insta::assert_snapshot!(env.render_ok(r#""".lines().first()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().last()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb".lines().nth(2)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb".lines().nth(-3)"#), @"");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if("".lines().first(), "some", "none")"#),
+            @"none");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".lines().nth(2).map_or("none", |s| s.upper())"#),
+            @"none");
+        insta::assert_snapshot!(
+            env.render_ok(r#""".lines().first().upper()"#),
+            @"<Error: No String available>");
+        // Lists of item types without optional type
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c".split(" ").chunk(2).last().join(",")"#),
+            @"c");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c".split(" ").chunk(2).nth(2)"#),
+            @"<Error: No List<String> available>");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b a".split(" ").group_by(|s| s).first().key()"#),
+            @"a");
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().nth()"#), @r#"
+         --> 1:17
           |
-        1 | false()
-          | ^---^
+        1 | "a".lines().nth()
+          |                 ^
           |
-          = Expected identifier
-        ");
+          = Function `nth`: Expected 1 arguments
+        "#);
 
-        insta::assert_snapshot!(env.parse_err(r#"!foo"#), @r"
-         --> 1:2
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().take(2)"#), @"a b");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().skip(1)"#), @"b c");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().skip(1).take(1)"#), @"b");
+        // Count is clamped to the list length
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().take(5)"#), @"a b c");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().skip(5).len()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().take(1).len()"#), @"0");
+        // Zero count
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().take(0).len()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().skip(0)"#), @"a b c");
+        // Element type is preserved
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().skip(1).map(|s| s.upper()).join(",")"#),
+            @"B,C");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".lines().take(-1)"#),
+            @"<Error: out of range integral type conversion attempted>");
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().skip()"#), @r#"
+         --> 1:18
           |
-        1 | !foo
-          |  ^-^
+        1 | "a".lines().skip()
+          |                  ^
           |
-          = Keyword `foo` doesn't exist
-        ");
-        insta::assert_snapshot!(env.parse_err(r#"true && 123"#), @r"
-         --> 1:9
+          = Function `skip`: Expected 1 arguments
+        "#);
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().map(|s| s ++ s)"#),
+            @"aa bb cc");
+        // Global keyword in item template
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().map(|s| s ++ empty)"#),
+            @"atrue btrue ctrue");
+        // Global keyword in item template shadowing 'self'
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().map(|self| self ++ empty)"#),
+            @"atrue btrue ctrue");
+        // Override global keyword 'empty'
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().map(|empty| empty)"#),
+            @"a b c");
+        // Nested map operations
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().map(|s| "x\ny".lines().map(|t| s ++ t))"#),
+            @"ax ay bx by cx cy");
+        // Nested map/join operations
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().map(|s| "x\ny".lines().map(|t| s ++ t).join(",")).join(";")"#),
+            @"ax,ay;bx,by;cx,cy");
+        // Nested string operations
+        insta::assert_snapshot!(
+            env.render_ok(r#""!  a\n!b\nc\n   end".remove_suffix("end").trim_end().lines().map(|s| s.remove_prefix("!").trim_start())"#),
+            @"a b c");
+
+        // Lambda expression in alias
+        env.add_alias("identity", "|x| x");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().map(identity)"#), @"a b c");
+
+        // Not a lambda expression
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(empty)"#), @r#"
+         --> 1:17
           |
-        1 | true && 123
-          |         ^-^
+        1 | "a".lines().map(empty)
+          |                 ^---^
           |
-          = Expected expression of type `Boolean`, but actual type is `Integer`
-        ");
-        insta::assert_snapshot!(env.parse_err(r#"true == 1"#), @r"
-         --> 1:1
+          = Expected lambda expression
+        "#);
+        // Bad lambda parameter count
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(|| "")"#), @r#"
+         --> 1:18
           |
-        1 | true == 1
-          | ^-------^
+        1 | "a".lines().map(|| "")
+          |                  ^
           |
-          = Cannot compare expressions of type `Boolean` and `Integer`
-        ");
-        insta::assert_snapshot!(env.parse_err(r#"true != 'a'"#), @r"
-         --> 1:1
+          = Expected 1 lambda parameters
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().filter(|a, b| true)"#), @r#"
+         --> 1:21
           |
-        1 | true != 'a'
-          | ^---------^
+        1 | "a".lines().filter(|a, b| true)
+          |                     ^--^
           |
-          = Cannot compare expressions of type `Boolean` and `String`
-        ");
-        insta::assert_snapshot!(env.parse_err(r#"1 == true"#), @r"
-         --> 1:1
+          = Expected 1 lambda parameters
+        "#);
+        // Bad lambda output
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().filter(|s| s ++ "\n")"#), @r#"
+         --> 1:24
           |
-        1 | 1 == true
-          | ^-------^
+        1 | "a".lines().filter(|s| s ++ "\n")
+          |                        ^-------^
           |
-          = Cannot compare expressions of type `Integer` and `Boolean`
-        ");
-        insta::assert_snapshot!(env.parse_err(r#"1 != 'a'"#), @r"
-         --> 1:1
+          = Expected expression of type `Boolean`, but actual type is `Template`
+        "#);
+        // Error in lambda expression
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(|s| s.unknown())"#), @r#"
+         --> 1:23
           |
-        1 | 1 != 'a'
-          | ^------^
+        1 | "a".lines().map(|s| s.unknown())
+          |                       ^-----^
           |
-          = Cannot compare expressions of type `Integer` and `String`
-        ");
-        insta::assert_snapshot!(env.parse_err(r#"'a' == true"#), @r"
-         --> 1:1
+          = Method `unknown` doesn't exist for type `String`
+        "#);
+        // Error in lambda alias
+        env.add_alias("too_many_params", "|x, y| x");
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().filter(too_many_params)"#), @r#"
+         --> 1:20
           |
-        1 | 'a' == true
-          | ^---------^
+        1 | "a".lines().filter(too_many_params)
+          |                    ^-------------^
           |
-          = Cannot compare expressions of type `String` and `Boolean`
-        ");
-        insta::assert_snapshot!(env.parse_err(r#"'a' != 1"#), @r"
-         --> 1:1
+          = In alias `too_many_params`
+         --> 1:2
           |
-        1 | 'a' != 1
-          | ^------^
+        1 | |x, y| x
+          |  ^--^
           |
-          = Cannot compare expressions of type `String` and `Integer`
-        ");
-        insta::assert_snapshot!(env.parse_err(r#"'a' == label("", "")"#), @r#"
-         --> 1:1
+          = Expected 1 lambda parameters
+        "#);
+    }
+
+    #[test]
+    fn test_list_flatten_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("rows", || {
+            P::wrap_string_list_list(literal(vec![
+                vec!["a".to_owned(), "b".to_owned()],
+                vec![],
+                vec!["c".to_owned()],
+            ]))
+        });
+
+        insta::assert_snapshot!(env.render_ok("rows.flatten()"), @"a b c");
+        insta::assert_snapshot!(env.render_ok("rows.flatten().len()"), @"3");
+        insta::assert_snapshot!(env.render_ok(r#"rows.flatten().join(",")"#), @"a,b,c");
+        // Composes with other list methods
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c d e".split(" ").chunk(2).flatten().join(",")"#),
+            @"a,b,c,d,e");
+        insta::assert_snapshot!(
+            env.render_ok(
+                r#""a b c d e".split(" ").chunk(2).filter(|row| row.len() == 2).flatten().join(",")"#
+            ),
+            @"a,b,c,d");
+        insta::assert_snapshot!(
+            env.render_ok(r#"rows.flatten().map(|s| s.upper()).join(",")"#),
+            @"A,B,C");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().chunk(2).flatten().len()"#), @"0");
+
+        // Elements must be lists
+        insta::assert_snapshot!(env.parse_err(r#""a b".split(" ").flatten()"#), @r#"
+         --> 1:18
           |
-        1 | 'a' == label("", "")
-          | ^------------------^
+        1 | "a b".split(" ").flatten()
+          |                  ^-----^
           |
-          = Cannot compare expressions of type `String` and `Template`
+          = Method `flatten` doesn't exist for type `List`
         "#);
-        insta::assert_snapshot!(env.parse_err(r#"'a' > 1"#), @r"
-         --> 1:1
+        // map() renders items as templates, so list-valued items can't be
+        // flattened
+        insta::assert_snapshot!(
+            env.parse_err(r#""a,b c,d".split(" ").map(|s| s.split(",")).flatten()"#),
+            @r#"
+         --> 1:44
           |
-        1 | 'a' > 1
-          | ^-----^
+        1 | "a,b c,d".split(" ").map(|s| s.split(",")).flatten()
+          |                                            ^-----^
           |
-          = Cannot compare expressions of type `String` and `Integer`
+          = Method `flatten` doesn't exist for type `ListTemplate`
+        "#);
+        insta::assert_snapshot!(env.parse_err("rows.flatten().flatten()"), @r"
+         --> 1:16
+          |
+        1 | rows.flatten().flatten()
+          |                ^-----^
+          |
+          = Method `flatten` doesn't exist for type `List`
         ");
-
-        insta::assert_snapshot!(env.parse_err(r#"description.first_line().foo()"#), @r"
-         --> 1:26
+        insta::assert_snapshot!(env.parse_err("rows.flatten(1)"), @r"
+         --> 1:14
           |
-        1 | description.first_line().foo()
-          |                          ^-^
+        1 | rows.flatten(1)
+          |              ^
           |
-          = Method `foo` doesn't exist for type `String`
+          = Function `flatten`: Expected 0 arguments
         ");
+    }
 
-        insta::assert_snapshot!(env.parse_err(r#"10000000000000000000"#), @r"
-         --> 1:1
+    #[test]
+    fn test_list_sum_min_max_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("words", || {
+            P::wrap_string_list(literal(vec![
+                "ab".to_owned(),
+                "c".to_owned(),
+                "defg".to_owned(),
+            ]))
+        });
+        env.add_keyword("empty", || P::wrap_string_list(literal(vec![])));
+        env.add_keyword("bad_i64", || P::wrap_integer(new_error_property("Bad")));
+
+        insta::assert_snapshot!(env.render_ok("words.sum(|s| s.len())"), @"7");
+        insta::assert_snapshot!(env.render_ok("words.min(|s| s.len())"), @"1");
+        insta::assert_snapshot!(env.render_ok("words.max(|s| s.len())"), @"4");
+        insta::assert_snapshot!(
+            env.render_ok(r#""3,-1,2".split(",").sum(|s| s.parse_int())"#),
+            @"4");
+        insta::assert_snapshot!(
+            env.render_ok(r#""3,-1,2".split(",").min(|s| s.parse_int())"#),
+            @"-1");
+        // Unformattable list
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b c d e".split(" ").chunk(2).sum(|row| row.len())"#),
+            @"5");
+
+        // Empty list
+        insta::assert_snapshot!(env.render_ok("empty.sum(|s| s.len())"), @"0");
+        insta::assert_snapshot!(env.render_ok("empty.min(|s| s.len())"), @"");
+        insta::assert_snapshot!(env.render_ok("empty.max(|s| s.len())"), @"");
+        insta::assert_snapshot!(
+            env.render_ok("if(empty.max(|s| s.len()), 'some', 'none')"),
+            @"none");
+        insta::assert_snapshot!(
+            env.render_ok("empty.max(|s| s.len()).map_or('-', |n| n)"),
+            @"-");
+
+        // Overflow, including intermediate overflow
+        insta::assert_snapshot!(
+            env.render_ok(r#""9223372036854775807,1".split(",").sum(|s| s.parse_int())"#),
+            @"<Error: Attempt to add with overflow>");
+        insta::assert_snapshot!(
+            env.render_ok(r#""9223372036854775807,1,-1".split(",").sum(|s| s.parse_int())"#),
+            @"<Error: Attempt to add with overflow>");
+        insta::assert_snapshot!(
+            env.render_ok(
+                r#""9223372036854775807,-9223372036854775808".split(",").sum(|s| s.parse_int())"#
+            ),
+            @"-1");
+        insta::assert_snapshot!(
+            env.render_ok(
+                r#""9223372036854775807,-9223372036854775808".split(",").max(|s| s.parse_int())"#
+            ),
+            @"9223372036854775807");
+        insta::assert_snapshot!(env.render_ok("words.sum(|s| bad_i64)"), @"<Error: Bad>");
+
+        insta::assert_snapshot!(env.parse_err("words.sum()"), @r"
+         --> 1:11
           |
-        1 | 10000000000000000000
-          | ^------------------^
+        1 | words.sum()
+          |           ^
           |
-          = Invalid integer literal
+          = Function `sum`: Expected 1 arguments
         ");
-        insta::assert_snapshot!(env.parse_err(r#"42.foo()"#), @r"
-         --> 1:4
+        insta::assert_snapshot!(env.parse_err("words.min(|s| s)"), @r"
+         --> 1:15
           |
-        1 | 42.foo()
-          |    ^-^
+        1 | words.min(|s| s)
+          |               ^
           |
-          = Method `foo` doesn't exist for type `Integer`
+          = Expected expression of type `Integer`, but actual type is `String`
         ");
-        insta::assert_snapshot!(env.parse_err(r#"(-empty)"#), @r"
-         --> 1:3
+    }
+
+    #[test]
+    fn test_list_group_by_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("words", || {
+            let words = ["banana", "apple", "blueberry", "cherry", "avocado"];
+            P::wrap_string_list(literal(words.map(|s| s.to_owned()).to_vec()))
+        });
+
+        // Groups are ordered by the first occurrence of the key
+        insta::assert_snapshot!(
+            env.render_ok(
+                r#"words.group_by(|s| s.substr(0, 1)).map(|g| g.key() ++ ": " ++ g.items().join(","))"#
+            ),
+            @"b: banana,blueberry a: apple,avocado c: cherry");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.group_by(|s| s.len() > 6).map(|g| g.key()).join(",")"#),
+            @"false,true");
+        insta::assert_snapshot!(env.render_ok(r#"words.group_by(|s| s).len()"#), @"5");
+        // Key is the rendered template
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.group_by(|s| label("x", s.len())).map(|g| g.key()).join(",")"#),
+            @"6,5,9,7");
+        // Groups can be processed as a list
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.group_by(|s| s.substr(0, 1)).filter(|g| g.items().len() > 1).len()"#),
+            @"2");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if("".lines().group_by(|s| s), "groups", "no groups")"#),
+            @"no groups");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.group_by(|s| if(s == "cherry", 1 / 0)).len()"#),
+            @"<Error: Attempt to divide by zero>");
+        insta::assert_snapshot!(env.parse_err(r#"words.group_by(|s| s).map(|g| g.foo())"#), @r"
+         --> 1:33
           |
-        1 | (-empty)
-          |   ^---^
+        1 | words.group_by(|s| s).map(|g| g.foo())
+          |                                 ^-^
           |
-          = Expected expression of type `Integer`, but actual type is `Boolean`
+          = Method `foo` doesn't exist for type `Group<String>`
         ");
-
-        insta::assert_snapshot!(env.parse_err(r#"("foo" ++ "bar").baz()"#), @r#"
-         --> 1:18
+        insta::assert_snapshot!(env.parse_err(r#"words.group_by("a")"#), @r#"
+         --> 1:16
           |
-        1 | ("foo" ++ "bar").baz()
-          |                  ^-^
+        1 | words.group_by("a")
+          |                ^-^
           |
-          = Method `baz` doesn't exist for type `Template`
+          = Expected lambda expression
         "#);
+    }
 
-        insta::assert_snapshot!(env.parse_err(r#"description.contains()"#), @r"
-         --> 1:22
-          |
-        1 | description.contains()
-          |                      ^
-          |
-          = Function `contains`: Expected 1 arguments
+    #[test]
+    fn test_list_enumerate_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("words", || {
+            P::wrap_string_list(literal(vec![
+                "apple".to_owned(),
+                "banana".to_owned(),
+                "cherry".to_owned(),
+            ]))
+        });
+        env.add_keyword("max_i64", || P::wrap_integer(literal(i64::MAX)));
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.enumerate().map(|e| e.index() ++ ":" ++ e.item()).join(",")"#),
+            @"0:apple,1:banana,2:cherry");
+        insta::assert_snapshot!(
+            env.render_ok(
+                r#"words.enumerate(1).map(|e| e.index() ++ ". " ++ e.item()).join("\n")"#),
+            @r"
+        1. apple
+        2. banana
+        3. cherry
         ");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.enumerate(-1).map(|e| e.index()).join(",")"#),
+            @"-1,0,1");
 
-        insta::assert_snapshot!(env.parse_err(r#"description.first_line("foo")"#), @r#"
-         --> 1:24
+        // Pairs can be processed by any list method
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.enumerate().filter(|e| e.index() != 1).map(|e| e.item())"#),
+            @"apple cherry");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.enumerate().any(|e| e.index() == 2 && e.item() == "cherry")"#),
+            @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.enumerate(1).sum(|e| e.index() * e.item().len())"#),
+            @"35");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.enumerate(1).last().index()"#),
+            @"3");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.enumerate().nth(3).item()"#),
+            @"<Error: No Enumerated<String> available>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.enumerate().sort_by(|e| -e.index()).map(|e| e.item())"#),
+            @"cherry banana apple");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().enumerate().len()"#), @"0");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if("".lines().enumerate(), "items", "no items")"#),
+            @"no items");
+
+        insta::assert_snapshot!(
+            env.render_ok("words.enumerate(max_i64).len()"),
+            @"<Error: Attempt to add with overflow>");
+        insta::assert_snapshot!(env.parse_err(r#"words.enumerate("a")"#), @r#"
+         --> 1:17
           |
-        1 | description.first_line("foo")
-          |                        ^---^
+        1 | words.enumerate("a")
+          |                 ^-^
           |
-          = Function `first_line`: Expected 0 arguments
-        "#);
-
-        insta::assert_snapshot!(env.parse_err(r#"label()"#), @r"
-         --> 1:7
+          = Expected expression of type `Integer`, but actual type is `String`
+        "#);
+        insta::assert_snapshot!(env.parse_err("words.enumerate().map(|e| e.foo())"), @r"
+         --> 1:29
           |
-        1 | label()
-          |       ^
+        1 | words.enumerate().map(|e| e.foo())
+          |                             ^-^
           |
-          = Function `label`: Expected 2 arguments
+          = Method `foo` doesn't exist for type `Enumerated<String>`
         ");
-        insta::assert_snapshot!(env.parse_err(r#"label("foo", "bar", "baz")"#), @r#"
-         --> 1:7
+        insta::assert_snapshot!(env.parse_err(r#"words.enumerate().join(",")"#), @r#"
+         --> 1:19
           |
-        1 | label("foo", "bar", "baz")
-          |       ^-----------------^
+        1 | words.enumerate().join(",")
+          |                   ^--^
           |
-          = Function `label`: Expected 2 arguments
+          = Method `join` doesn't exist for type `List`
         "#);
+    }
 
-        insta::assert_snapshot!(env.parse_err(r#"if()"#), @r"
-         --> 1:4
+    #[test]
+    fn test_list_table_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("words", || {
+            let words = ["a", "日本語", "💩x", "wide"];
+            P::wrap_string_list(literal(words.map(|s| s.to_owned()).to_vec()))
+        });
+        env.add_keyword("empty_words", || {
+            P::wrap_string_list(literal::<Vec<String>>(vec![]))
+        });
+        env.add_color("red", crossterm::style::Color::Red);
+
+        // Columns are aligned by display width
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.table(|s| s, |s| s.len())"#), @r"
+        a      1
+        日本語 9
+        💩x    5
+        wide   4
+        ");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.table(|s| s.len(), |s| s, |s| "|")"#), @r"
+        1 a      |
+        9 日本語 |
+        5 💩x    |
+        4 wide   |
+        ");
+        insta::assert_snapshot!(env.render_ok(r#"words.table(|s| s)"#), @r"
+        a
+        日本語
+        💩x
+        wide
+        ");
+        insta::assert_snapshot!(env.render_ok(r#"empty_words.table(|s| s)"#), @"");
+        // Labels are preserved, and don't contribute to the width
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.table(|s| label("red", s), |s| s.len())"#), @r"
+        [38;5;9ma[39m      1
+        [38;5;9m日本語[39m 9
+        [38;5;9m💩x[39m    5
+        [38;5;9mwide[39m   4
+        ");
+        insta::assert_snapshot!(
+            env.render_ok(r#"words.filter(|s| s.len() > 1).table(|s| s.len(), |s| s)"#), @r"
+        9 日本語
+        5 💩x
+        4 wide
+        ");
+
+        insta::assert_snapshot!(env.parse_err(r#"words.table()"#), @r"
+         --> 1:13
           |
-        1 | if()
-          |    ^
+        1 | words.table()
+          |             ^
           |
-          = Function `if`: Expected 2 to 3 arguments
+          = Function `table`: Expected at least 1 arguments
         ");
-        insta::assert_snapshot!(env.parse_err(r#"if("foo", "bar", "baz", "quux")"#), @r#"
-         --> 1:4
+        insta::assert_snapshot!(env.parse_err(r#"words.table(|s| s, "a")"#), @r#"
+         --> 1:20
           |
-        1 | if("foo", "bar", "baz", "quux")
-          |    ^-------------------------^
+        1 | words.table(|s| s, "a")
+          |                    ^-^
           |
-          = Function `if`: Expected 2 to 3 arguments
+          = Expected lambda expression
         "#);
+    }
 
-        insta::assert_snapshot!(env.parse_err(r#"pad_start("foo", fill_char = "bar", "baz")"#), @r#"
-         --> 1:37
+    #[test]
+    fn test_string_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || {
+            P::wrap_string(literal("description 1".to_owned()))
+        });
+        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
+
+        insta::assert_snapshot!(env.render_ok(r#""".len()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#""foo".len()"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#""💩".len()"#), @"4");
+        // "e" followed by U+0301 (combining acute accent)
+        insta::assert_snapshot!(env.render_ok("\"e\u{301}\".len()"), @"3");
+
+        insta::assert_snapshot!(env.render_ok(r#""".is_empty()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"" ".is_empty()"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""\n".is_empty()"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""foo".is_empty()"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""foo".remove_prefix("foo").is_empty()"#), @"true");
+
+        insta::assert_snapshot!(env.render_ok(r#""".char_count()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#""foo".char_count()"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#""💩".char_count()"#), @"1");
+        insta::assert_snapshot!(env.render_ok(r#""日本語".char_count()"#), @"3");
+        // Combining character is counted separately
+        insta::assert_snapshot!(env.render_ok("\"e\u{301}\".char_count()"), @"2");
+
+        // byte_len() is an explicit alias of len()
+        insta::assert_snapshot!(env.render_ok(r#""".byte_len()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#""foo".byte_len()"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#""ü".byte_len() ++ " " ++ "ü".char_count()"#), @"2 1");
+        insta::assert_snapshot!(
+            env.render_ok(r#""日本語".byte_len() ++ " " ++ "日本語".char_count()"#),
+            @"9 3");
+        insta::assert_snapshot!(
+            env.render_ok(r#""💩".byte_len() ++ " " ++ "💩".char_count()"#),
+            @"4 1");
+        insta::assert_snapshot!(env.render_ok(r#""💩".byte_len() == "💩".len()"#), @"true");
+
+        insta::assert_snapshot!(env.render_ok(r#""fooo".contains("foo")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""foo".contains("fooo")"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"description.contains("description")"#), @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#""description 123".contains(description.first_line())"#),
+            @"true");
+
+        // inner template error should propagate
+        insta::assert_snapshot!(env.render_ok(r#""foo".contains(bad_string)"#), @"<Error: Bad>");
+        insta::assert_snapshot!(
+            env.render_ok(r#""foo".contains("f" ++ bad_string) ++ "bar""#), @"<Error: Bad>bar");
+        insta::assert_snapshot!(
+            env.render_ok(r#""foo".contains(separate("o", "f", bad_string))"#), @"<Error: Bad>");
+
+        insta::assert_snapshot!(env.render_ok(r#""foo".contains("")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""foo".contains("FOO")"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""fOoo".contains_ignore_case("FOO")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""foo".contains_ignore_case("")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""foo".contains_ignore_case("fooo")"#), @"false");
+        insta::assert_snapshot!(
+            env.render_ok(r#""[Skip CI] fix".contains_ignore_case("[skip ci]")"#),
+            @"true");
+        // Non-ASCII characters are compared case-insensitively, too
+        insta::assert_snapshot!(env.render_ok(r#""Äpfel".contains_ignore_case("ä")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""ÄB".contains_ignore_case("äb")"#), @"true");
+        // Lowercase mapping isn't full case folding
+        insta::assert_snapshot!(env.render_ok(r#""straße".contains_ignore_case("SSE")"#), @"false");
+
+        insta::assert_snapshot!(env.render_ok(r#""".first_line()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""foo\nbar".first_line()"#), @"foo");
+
+        insta::assert_snapshot!(env.render_ok(r#""".lines()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc\n".lines()"#), @"a b c");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().join("|")"#), @"a|b|c");
+        insta::assert_snapshot!(env.render_ok(r#""a\r\nb\r\nc\r\n".lines().join("|")"#), @"a|b|c");
+        insta::assert_snapshot!(env.render_ok(r#""a\n\nb\n\n".lines().join("|")"#), @"a||b|");
+        insta::assert_snapshot!(env.render_ok(r#""\n".lines().len()"#), @"1");
+        // Lone CR isn't a line separator
+        insta::assert_snapshot!(env.render_ok(r#""a\rb\r".lines().len()"#), @"1");
+
+        insta::assert_snapshot!(env.render_ok(r#""a,b,c".split(",").join("|")"#), @"a|b|c");
+        insta::assert_snapshot!(env.render_ok(r#""a, b".split(", ").len()"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#""a,,b".split(",").join("|")"#), @"a||b");
+        insta::assert_snapshot!(env.render_ok(r#""a,b,".split(",").join("|")"#), @"a|b|");
+        insta::assert_snapshot!(env.render_ok(r#"",a".split(",").len()"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#""abc".split(",").join("|")"#), @"abc");
+        insta::assert_snapshot!(env.render_ok(r#""".split(",").len()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#""a,b,c".split(",", 2).join("|")"#), @"a|b,c");
+        insta::assert_snapshot!(env.render_ok(r#""a,b,c".split(",", 5).join("|")"#), @"a|b|c");
+        insta::assert_snapshot!(env.render_ok(r#""a,b,c".split(",", 0).len()"#), @"0");
+        insta::assert_snapshot!(
+            env.render_ok(r#""A, B ,C".split(",").map(|s| s.trim()).join("|")"#),
+            @"A|B|C");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a,b".split(",", -1)"#),
+            @"<Error: out of range integral type conversion attempted>");
+
+        insta::assert_snapshot!(env.render_ok(r#""".starts_with("")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""everything".starts_with("")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""".starts_with("foo")"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""foo".starts_with("foo")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""foobar".starts_with("foo")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""foobar".starts_with("bar")"#), @"false");
+
+        insta::assert_snapshot!(env.render_ok(r#""".ends_with("")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""everything".ends_with("")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""".ends_with("foo")"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""foo".ends_with("foo")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""foobar".ends_with("foo")"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""foobar".ends_with("bar")"#), @"true");
+
+        // Multibyte characters are compared as a whole
+        insta::assert_snapshot!(env.render_ok(r#""💩foo".starts_with("💩")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""💩foo".starts_with("\xf0")"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""日本語".starts_with("日本")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""日本語".ends_with("語")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""日本語".ends_with("本")"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""café".ends_with("é")"#), @"true");
+        // "e" followed by U+0301 (combining acute accent) isn't normalized
+        insta::assert_snapshot!(env.render_ok("\"cafe\u{301}\".ends_with(\"é\")"), @"false");
+        insta::assert_snapshot!(env.render_ok("\"cafe\u{301}\".ends_with(\"\u{301}\")"), @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if("release/1.0".starts_with("release/"), "yes", "no")"#),
+            @"yes");
+
+        insta::assert_snapshot!(env.render_ok(r#""".remove_prefix("wip: ")"#), @"");
+        insta::assert_snapshot!(
+            env.render_ok(r#""wip: testing".remove_prefix("wip: ")"#),
+            @"testing");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""bar@my.example.com".remove_suffix("@other.example.com")"#),
+            @"bar@my.example.com");
+        insta::assert_snapshot!(
+            env.render_ok(r#""bar@other.example.com".remove_suffix("@other.example.com")"#),
+            @"bar");
+
+        insta::assert_snapshot!(env.render_ok(r#""".replace("a", "b")"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""foo bar".replace("o", "0")"#), @"f00 bar");
+        insta::assert_snapshot!(env.render_ok(r#""foo bar".replace("baz", "0")"#), @"foo bar");
+        insta::assert_snapshot!(env.render_ok(r#""foo bar".replace("", "-")"#), @"foo bar");
+        insta::assert_snapshot!(env.render_ok(r#""foo bar".replace(" ", "")"#), @"foobar");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\n".replace("\n", " ")"#), @"a b ");
+        // Non-overlapping matches are replaced from left to right
+        insta::assert_snapshot!(env.render_ok(r#""aaaaa".replace("aa", "b")"#), @"bba");
+        // Original string isn't modified
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.replace("1", "2") ++ " " ++ description"#),
+            @"description 2 description 1");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""foo  bar\tbaz".replace_regex('\s+', " ")"#),
+            @"foo bar baz");
+        insta::assert_snapshot!(
+            env.render_ok(r#""2024-01-31".replace_regex('(\d+)-(\d+)-(\d+)', "$3/$2/$1")"#),
+            @"31/01/2024");
+        insta::assert_snapshot!(env.render_ok(r#""foo".replace_regex('x', "y")"#), @"foo");
+        insta::assert_snapshot!(env.render_ok(r#""foo".replace_regex('', "-")"#), @"-f-o-o-");
+        insta::assert_snapshot!(env.parse_err(r#""foo".replace_regex('(', "")"#), @r#"
+         --> 1:21
           |
-        1 | pad_start("foo", fill_char = "bar", "baz")
-          |                                     ^---^
+        1 | "foo".replace_regex('(', "")
+          |                     ^-^
           |
-          = Function `pad_start`: Positional argument follows keyword argument
+          = Invalid regular expression
         "#);
-
-        insta::assert_snapshot!(env.parse_err(r#"if(label("foo", "bar"), "baz")"#), @r#"
-         --> 1:4
+        insta::assert_snapshot!(env.parse_err(r#""foo".replace_regex(description, "")"#), @r#"
+         --> 1:21
           |
-        1 | if(label("foo", "bar"), "baz")
-          |    ^-----------------^
+        1 | "foo".replace_regex(description, "")
+          |                     ^---------^
           |
-          = Expected expression of type `Boolean`, but actual type is `Template`
+          = Expected string literal
         "#);
 
-        insta::assert_snapshot!(env.parse_err(r#"|x| description"#), @r"
-         --> 1:1
-          |
-        1 | |x| description
-          | ^-------------^
-          |
-          = Lambda cannot be defined here
-        ");
+        insta::assert_snapshot!(env.render_ok(r#"" \n \r    \t \r ".trim()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"" \n \r foo  bar \t \r ".trim()"#), @"foo  bar");
+
+        insta::assert_snapshot!(env.render_ok(r#"" \n \r    \t \r ".trim_start()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"" \n \r foo  bar \t \r ".trim_start()"#), @"foo  bar");
+
+        insta::assert_snapshot!(env.render_ok(r#"" \n \r    \t \r ".trim_end()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"" \n \r foo  bar \t \r ".trim_end()"#), @" foo  bar");
+        // Already trimmed
+        insta::assert_snapshot!(env.render_ok(r#""foo  bar".trim()"#), @"foo  bar");
+        insta::assert_snapshot!(env.render_ok(r#""foo  bar".trim_start()"#), @"foo  bar");
+        insta::assert_snapshot!(env.render_ok(r#""foo  bar".trim_end()"#), @"foo  bar");
+        // Unicode whitespace
+        insta::assert_snapshot!(
+            env.render_ok("\"\u{3000}\u{a0}foo\u{2003}\n\".trim() ++ '|'"),
+            @"foo|");
+
+        insta::assert_snapshot!(env.render_ok(r#""".trim_matches("-")"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""--foo-bar--".trim_matches("-")"#), @"foo-bar");
+        insta::assert_snapshot!(env.render_ok(r#""-=foo=-".trim_matches("=-")"#), @"foo");
+        insta::assert_snapshot!(
+            env.render_ok(r#""\t\nfoo\n\t".trim_matches("\t").escape_json()"#),
+            @r#""\nfoo\n""#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""\t\nfoo\n\t".trim_matches("\n\t").escape_json()"#),
+            @r#""foo""#);
+        insta::assert_snapshot!(env.render_ok(r#""foo".trim_matches("")"#), @"foo");
+        insta::assert_snapshot!(env.render_ok(r#""★foo★".trim_matches("★")"#), @"foo");
+
+        insta::assert_snapshot!(env.render_ok(r#""".reverse()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""abc".reverse()"#), @"cba");
+        // Reversed by grapheme cluster
+        insta::assert_snapshot!(env.render_ok("\"cafe\u{301}s\".reverse()"), @"séfac");
+        insta::assert_snapshot!(
+            env.render_ok("\"a\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}b\".reverse()"),
+            @"b👨‍👩‍👧a");
+        insta::assert_snapshot!(
+            env.render_ok("\"a\r\nb\".reverse().escape_json()"),
+            @r#""b\r\na""#);
+
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".truncate(3)"#), @"abc");
+        insta::assert_snapshot!(env.render_ok(r#""abc".truncate(3)"#), @"abc");
+        insta::assert_snapshot!(env.render_ok(r#""abc".truncate(5, "...")"#), @"abc");
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".truncate(5, "...")"#), @"ab...");
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".truncate(0)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""".truncate(0, "...")"#), @"");
+        // Ellipsis longer than the budget is truncated
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".truncate(2, "...")"#), @"..");
+        // Counted by grapheme cluster, not by display width
+        insta::assert_snapshot!(env.render_ok(r#""日本語の文章".truncate(3)"#), @"日本語");
+        insta::assert_snapshot!(env.render_ok(r#""日本語の文章".truncate(4, "…")"#), @"日本語…");
+        insta::assert_snapshot!(env.render_ok("\"cafe\u{301}s\".truncate(4)"), @"café");
+        insta::assert_snapshot!(
+            env.render_ok("\"\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}ab\".truncate(2)"),
+            @"👨‍👩‍👧a");
+        insta::assert_snapshot!(
+            env.render_ok("\"a\u{1f1ef}\u{1f1f5}b\".truncate(2, \"\")"),
+            @"a🇯🇵");
+        insta::assert_snapshot!(
+            env.render_ok(r#""abc".truncate(-1)"#),
+            @"<Error: out of range integral type conversion attempted>");
+
+        insta::assert_snapshot!(env.render_ok(r#""Hello World".upper()"#), @"HELLO WORLD");
+        insta::assert_snapshot!(env.render_ok(r#""Hello World".lower()"#), @"hello world");
+        insta::assert_snapshot!(
+            env.render_ok(r#""hello  wORLD\tfoo-bar".title_case()"#),
+            @"Hello  WORLD	Foo-bar");
+        insta::assert_snapshot!(env.render_ok(r#""".title_case()"#), @"");
+        // Unicode-aware, but locale-independent case mapping
+        insta::assert_snapshot!(env.render_ok(r#""straße".upper()"#), @"STRASSE");
+        insta::assert_snapshot!(env.render_ok(r#""STRASSE ÄÖÜ".lower()"#), @"strasse äöü");
+        insta::assert_snapshot!(env.render_ok(r#""ße ärger".title_case()"#), @"SSe Ärger");
+        insta::assert_snapshot!(env.render_ok(r#""hello wORLD".capitalize()"#), @"Hello wORLD");
+        insta::assert_snapshot!(env.render_ok(r#""".capitalize()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""élan vital".capitalize()"#), @"Élan vital");
+        insta::assert_snapshot!(env.render_ok(r#""ßa".capitalize()"#), @"SSa");
+        // "e" followed by U+0301 (combining acute accent) is kept together
+        insta::assert_snapshot!(env.render_ok("\"e\u{301}t\u{e9}\".capitalize()"), @"Été");
+        // Leading non-letter characters are left unchanged
+        insta::assert_snapshot!(env.render_ok(r#""(foo) bar".capitalize()"#), @"(foo) bar");
+        insta::assert_snapshot!(env.render_ok(r#"" foo".capitalize()"#), @" foo");
+        insta::assert_snapshot!(env.render_ok(r#""istanbul".upper()"#), @"ISTANBUL");
+        // "İ" is lowercased to "i" followed by U+0307 COMBINING DOT ABOVE
+        insta::assert_snapshot!(env.render_ok(r#""İSTANBUL".lower().len()"#), @"10");
+        insta::assert_snapshot!(env.render_ok(r#""ıi".upper()"#), @"II");
+
+        insta::assert_snapshot!(env.render_ok(r#""foo".substr(0, 0)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""foo".substr(0, 1)"#), @"f");
+        insta::assert_snapshot!(env.render_ok(r#""foo".substr(0, 3)"#), @"foo");
+        insta::assert_snapshot!(env.render_ok(r#""foo".substr(0, 4)"#), @"foo");
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(2, -1)"#), @"cde");
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(-3, 99)"#), @"def");
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(-6, 99)"#), @"abcdef");
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(-7, 1)"#), @"a");
+
+        // non-ascii characters
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(2, -1)"#), @"c💩");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, -3)"#), @"💩");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, -4)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(6, -3)"#), @"💩");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(7, -3)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, 4)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, 6)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, 7)"#), @"💩");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(-1, 7)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(-3, 7)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(-4, 7)"#), @"💩");
+
+        // ranges with end > start are empty
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(4, 2)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(-2, -4)"#), @"");
+
+        insta::assert_snapshot!(env.render_ok(r#""hello".escape_json()"#), @r#""hello""#);
+        insta::assert_snapshot!(env.render_ok(r#""he \n ll \n \" o".escape_json()"#), @r#""he \n ll \n \" o""#);
+        insta::assert_snapshot!(env.render_ok(r#"'a"b\c'.escape_json()"#), @r#""a\"b\\c""#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""\0\x01\x1f\t\r".escape_json()"#),
+            @r#""\u0000\u0001\u001f\t\r""#);
+        // Non-BMP characters are emitted as is, not as surrogate pairs
+        insta::assert_snapshot!(env.render_ok(r#""日本💩".escape_json()"#), @r#""日本💩""#);
     }
 
     #[test]
-    fn test_self_keyword() {
+    fn test_string_matches_method() {
         let mut env = TestTemplateEnv::new();
-        env.add_keyword("say_hello", || P::wrap_string(literal("Hello".to_owned())));
+        env.add_keyword("description", || {
+            P::wrap_string(literal("feat(cli): add foo\n\nCloses #123\n".to_owned()))
+        });
 
-        insta::assert_snapshot!(env.render_ok(r#"self.say_hello()"#), @"Hello");
-        insta::assert_snapshot!(env.parse_err(r#"self"#), @r"
-         --> 1:1
+        // Unanchored patterns match anywhere
+        insta::assert_snapshot!(env.render_ok(r#"description.matches('foo')"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"description.matches('#\d+')"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"description.matches('bar')"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"description.matches('')"#), @"true");
+
+        // Anchored patterns
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.matches('^(feat|fix)(\(.+\))?: ')"#),
+            @"true");
+        insta::assert_snapshot!(env.render_ok(r#"description.matches('^add')"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"description.matches('(?m)^Closes')"#), @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.first_line().matches('^feat.*foo$')"#),
+            @"true");
+        insta::assert_snapshot!(env.render_ok(r#"description.matches('^feat.*foo$')"#), @"false");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"if(description.matches('^feat'), "feature", "other")"#),
+            @"feature");
+
+        insta::assert_snapshot!(env.parse_err(r#""foo".matches('(')"#), @r#"
+         --> 1:15
           |
-        1 | self
-          | ^--^
+        1 | "foo".matches('(')
+          |               ^-^
           |
-          = Expected expression of type `Template`, but actual type is `Self`
-        ");
+          = Invalid regular expression
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#""foo".matches(description)"#), @r#"
+         --> 1:15
+          |
+        1 | "foo".matches(description)
+          |               ^---------^
+          |
+          = Expected string literal
+        "#);
     }
 
     #[test]
-    fn test_boolean_cast() {
+    fn test_string_captures_method() {
         let mut env = TestTemplateEnv::new();
-
-        insta::assert_snapshot!(env.render_ok(r#"if("", true, false)"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"if("a", true, false)"#), @"true");
-
-        env.add_keyword("sl0", || {
-            P::wrap_string_list(literal::<Vec<String>>(vec![]))
+        env.add_keyword("description", || {
+            P::wrap_string(literal("fix: crash (#123, #456)\n".to_owned()))
         });
-        env.add_keyword("sl1", || P::wrap_string_list(literal(vec!["".to_owned()])));
-        insta::assert_snapshot!(env.render_ok(r#"if(sl0, true, false)"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"if(sl1, true, false)"#), @"true");
 
-        // No implicit cast of integer
-        insta::assert_snapshot!(env.parse_err(r#"if(0, true, false)"#), @r"
-         --> 1:4
-          |
-        1 | if(0, true, false)
-          |    ^
-          |
-          = Expected expression of type `Boolean`, but actual type is `Integer`
-        ");
+        // Whole match followed by each group
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.first_line().captures('^(\w+): (.*)$').join("|")"#),
+            @"fix: crash (#123, #456)|fix|crash (#123, #456)");
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.first_line().captures('^(\w+): (.*)$').len()"#),
+            @"3");
+        insta::assert_snapshot!(env.render_ok(r#"description.captures('#(\d+)').nth(1)"#), @"123");
+        // Named groups are numbered along with the others
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.captures('(?<kind>\w+): (?<title>\w+)').join("|")"#),
+            @"fix: crash|fix|crash");
+        // Unmatched optional groups are empty
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.captures('(fix|feat)(\(\w+\))?:').map(|s| "[" ++ s ++ "]")"#),
+            @"[fix:] [fix] []");
 
-        // Optional integer can be converted to boolean, and Some(0) is truthy.
-        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
-        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(0))));
-        insta::assert_snapshot!(env.render_ok(r#"if(none_i64, true, false)"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"if(some_i64, true, false)"#), @"true");
+        // No match
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.captures('^feat: (.*)').len()"#),
+            @"0");
+        insta::assert_snapshot!(env.render_ok(r#"description.captures('^feat: (.*)')"#), @"");
 
-        insta::assert_snapshot!(env.parse_err(r#"if(label("", ""), true, false)"#), @r#"
-         --> 1:4
+        insta::assert_snapshot!(env.parse_err(r#""foo".captures('(')"#), @r#"
+         --> 1:16
           |
-        1 | if(label("", ""), true, false)
-          |    ^-----------^
+        1 | "foo".captures('(')
+          |                ^-^
           |
-          = Expected expression of type `Boolean`, but actual type is `Template`
+          = Invalid regular expression
         "#);
-        insta::assert_snapshot!(env.parse_err(r#"if(sl0.map(|x| x), true, false)"#), @r"
-         --> 1:4
+        insta::assert_snapshot!(env.parse_err(r#""foo".captures(description)"#), @r#"
+         --> 1:16
           |
-        1 | if(sl0.map(|x| x), true, false)
-          |    ^------------^
+        1 | "foo".captures(description)
+          |                ^---------^
           |
-          = Expected expression of type `Boolean`, but actual type is `ListTemplate`
-        ");
-
-        env.add_keyword("empty_email", || {
-            P::wrap_email(literal(Email("".to_owned())))
-        });
-        env.add_keyword("nonempty_email", || {
-            P::wrap_email(literal(Email("local@domain".to_owned())))
-        });
-        insta::assert_snapshot!(env.render_ok(r#"if(empty_email, true, false)"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"if(nonempty_email, true, false)"#), @"true");
+          = Expected string literal
+        "#);
     }
 
     #[test]
-    fn test_arithmetic_operation() {
+    fn test_string_count_matches_method() {
         let mut env = TestTemplateEnv::new();
-        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
-        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(1))));
-        env.add_keyword("i64_min", || P::wrap_integer(literal(i64::MIN)));
-
-        insta::assert_snapshot!(env.render_ok(r#"-1"#), @"-1");
-        insta::assert_snapshot!(env.render_ok(r#"--2"#), @"2");
-        insta::assert_snapshot!(env.render_ok(r#"-(3)"#), @"-3");
+        env.add_keyword("description", || {
+            P::wrap_string(literal("TODO: foo\nTODO: bar\nDone: baz\n".to_owned()))
+        });
 
-        // Since methods of the contained value can be invoked, it makes sense
-        // to apply operators to optional integers as well.
-        insta::assert_snapshot!(env.render_ok(r#"-none_i64"#), @"<Error: No Integer available>");
-        insta::assert_snapshot!(env.render_ok(r#"-some_i64"#), @"-1");
+        insta::assert_snapshot!(env.render_ok(r#"description.count_matches("TODO")"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#"description.count_matches("todo")"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"description.count_matches("\n")"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#""".count_matches("a")"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#""日本日本".count_matches("本")"#), @"2");
+        // Non-overlapping matches are counted from left to right
+        insta::assert_snapshot!(env.render_ok(r#""aaaaa".count_matches("aa")"#), @"2");
+        insta::assert_snapshot!(
+            env.render_ok(r#""foo".count_matches("")"#),
+            @"<Error: Pattern must not be empty>");
 
-        // No panic on integer overflow.
         insta::assert_snapshot!(
-            env.render_ok(r#"-i64_min"#),
-            @"<Error: Attempt to negate with overflow>");
+            env.render_ok(r#"description.count_matches_regex('(?m)^TODO:')"#),
+            @"2");
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.count_matches_regex('\b[a-z]{3}\b')"#),
+            @"3");
+        insta::assert_snapshot!(env.render_ok(r#""aaaaa".count_matches_regex('aa')"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#""foo".count_matches_regex('x')"#), @"0");
+        insta::assert_snapshot!(env.parse_err(r#""foo".count_matches_regex('')"#), @r#"
+         --> 1:27
+          |
+        1 | "foo".count_matches_regex('')
+          |                           ^^
+          |
+          = Pattern must not be empty
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#""foo".count_matches_regex('(')"#), @r#"
+         --> 1:27
+          |
+        1 | "foo".count_matches_regex('(')
+          |                           ^-^
+          |
+          = Invalid regular expression
+        "#);
+        insta::assert_snapshot!(
+            env.parse_err(r#""foo".count_matches_regex(description)"#),
+            @r#"
+         --> 1:27
+          |
+        1 | "foo".count_matches_regex(description)
+          |                           ^---------^
+          |
+          = Expected string literal
+        "#);
     }
 
     #[test]
-    fn test_relational_operation() {
+    fn test_string_shell_quote_method() {
         let env = TestTemplateEnv::new();
 
-        insta::assert_snapshot!(env.render_ok(r#"1 >= 1"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"0 >= 1"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"2 > 1"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"1 > 1"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"1 <= 1"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"2 <= 1"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"0 < 1"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"1 < 1"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""foo".shell_quote()"#), @"'foo'");
+        insta::assert_snapshot!(env.render_ok(r#""".shell_quote()"#), @"''");
+        insta::assert_snapshot!(env.render_ok(r#""foo bar $HOME".shell_quote()"#), @"'foo bar $HOME'");
+        insta::assert_snapshot!(env.render_ok(r#""it's".shell_quote()"#), @r"'it'\''s'");
+        insta::assert_snapshot!(env.render_ok(r#""'".shell_quote()"#), @r"''\'''");
+        insta::assert_snapshot!(env.render_ok(r#""\"a\" \\".shell_quote()"#), @r#"'"a" \'"#);
+        insta::assert_snapshot!(env.render_ok(r#""a\nb".shell_quote()"#), @r"
+        'a
+        b'
+        ");
     }
 
     #[test]
-    fn test_logical_operation() {
+    fn test_string_csv_quote_method() {
+        let env = TestTemplateEnv::new();
+
+        insta::assert_snapshot!(env.render_ok(r#""foo".csv_quote()"#), @r#""foo""#);
+        insta::assert_snapshot!(env.render_ok(r#""".csv_quote()"#), @r#""""#);
+        insta::assert_snapshot!(env.render_ok(r#""foo, bar".csv_quote()"#), @r#""foo, bar""#);
+        insta::assert_snapshot!(env.render_ok(r#""say \"hi\"".csv_quote()"#), @r#""say ""hi""""#);
+        insta::assert_snapshot!(env.render_ok(r#""it's".csv_quote()"#), @r#""it's""#);
+        insta::assert_snapshot!(env.render_ok(r#""a\nb".csv_quote()"#), @r#"
+        "a
+        b"
+        "#);
+    }
+
+    #[test]
+    fn test_string_escape_control_method() {
         let mut env = TestTemplateEnv::new();
-        env.add_keyword("email1", || {
-            P::wrap_email(literal(Email("local-1@domain".to_owned())))
-        });
-        env.add_keyword("email2", || {
-            P::wrap_email(literal(Email("local-2@domain".to_owned())))
+        env.add_keyword("description", || {
+            P::wrap_string(literal(
+                "\x1b[1;31mred\x1b[0m\ttab\r\nnext\x07\x7f".to_owned(),
+            ))
         });
 
-        insta::assert_snapshot!(env.render_ok(r#"!false"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"false || !false"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"false && true"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"true == true"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"true == false"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"true != true"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"true != false"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"1 == 1"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"1 == 2"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"1 != 1"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"1 != 2"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"'a' == 'a'"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"'a' == 'b'"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"'a' != 'a'"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"'a' != 'b'"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"email1 == email1"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"email1 == email2"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"email1 == 'local-1@domain'"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"email1 != 'local-2@domain'"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"'local-1@domain' == email1"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#"'local-2@domain' != email1"#), @"true");
-
-        insta::assert_snapshot!(env.render_ok(r#" !"" "#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#" "" || "a".lines() "#), @"true");
+        insta::assert_snapshot!(env.render_ok("description.escape_control()"), @r"
+        \x1b[1;31mred\x1b[0m	tab\x0d
+        next\x07\x7f
+        ");
+        insta::assert_snapshot!(
+            env.render_ok(r#""\e]8;;http://a\e\\".escape_control()"#),
+            @r"\x1b]8;;http://a\x1b\");
+        insta::assert_snapshot!(
+            env.render_ok(r#""\0\x01\x1f".escape_control()"#),
+            @r"\x00\x01\x1f");
 
-        // Short-circuiting
-        env.add_keyword("bad_bool", || P::wrap_boolean(new_error_property("Bad")));
-        insta::assert_snapshot!(env.render_ok(r#"false && bad_bool"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"true && bad_bool"#), @"<Error: Bad>");
-        insta::assert_snapshot!(env.render_ok(r#"false || bad_bool"#), @"<Error: Bad>");
-        insta::assert_snapshot!(env.render_ok(r#"true || bad_bool"#), @"true");
+        // Normal whitespace and non-ASCII characters are preserved
+        insta::assert_snapshot!(env.render_ok(r#""a\tb\nc ü 日本".escape_control()"#), @r"
+        a	b
+        c ü 日本
+        ");
+        insta::assert_snapshot!(env.render_ok(r#""".escape_control()"#), @"");
     }
 
     #[test]
-    fn test_list_method() {
+    fn test_string_normalize_newlines_method() {
         let mut env = TestTemplateEnv::new();
-        env.add_keyword("empty", || P::wrap_boolean(literal(true)));
-        env.add_keyword("sep", || P::wrap_string(literal("sep".to_owned())));
-
-        insta::assert_snapshot!(env.render_ok(r#""".lines().len()"#), @"0");
-        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().len()"#), @"3");
+        env.add_keyword("description", || {
+            P::wrap_string(literal("summary\r\n\r\nbody\rmore\nend\r\n\n\r".to_owned()))
+        });
 
-        insta::assert_snapshot!(env.render_ok(r#""".lines().join("|")"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().join("|")"#), @"a|b|c");
-        // Null separator
-        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().join("\0")"#), @"a\0b\0c");
-        // Keyword as separator
+        // CRLF
         insta::assert_snapshot!(
-            env.render_ok(r#""a\nb\nc".lines().join(sep.upper())"#),
-            @"aSEPbSEPc");
+            env.render_ok(r#""a\r\nb\r\n".normalize_newlines().escape_json()"#),
+            @r#""a\nb\n""#);
+        // Lone CR
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\rb\r".normalize_newlines().escape_json()"#),
+            @r#""a\nb\n""#);
+        // Mixed line endings
+        insta::assert_snapshot!(
+            env.render_ok("description.normalize_newlines().escape_json()"),
+            @r#""summary\n\nbody\nmore\nend\n\n\n""#);
+        insta::assert_snapshot!(
+            env.render_ok("description.normalize_newlines().lines().len()"), @"7");
+        insta::assert_snapshot!(
+            env.render_ok("description.normalize_newlines(collapse_trailing = true).escape_json()"),
+            @r#""summary\n\nbody\nmore\nend\n""#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\r\n\r\nb".normalize_newlines(true).escape_json()"#),
+            @r#""a\n\nb""#);
+        // Text without trailing newline is unchanged
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".normalize_newlines(true).escape_json()"#), @r#""a\nb""#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""\r\n\r\n".normalize_newlines(true).escape_json()"#), @r#""\n""#);
+        insta::assert_snapshot!(env.render_ok(r#""".normalize_newlines(true)"#), @"");
+    }
+
+    #[test]
+    fn test_string_escape_markdown_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || {
+            P::wrap_string(literal(
+                "Rename `foo_bar` to *baz_qux* [docs](http://a.b/c_d)".to_owned(),
+            ))
+        });
 
         insta::assert_snapshot!(
-            env.render_ok(r#""a\nbb\nc".lines().filter(|s| s.len() == 1)"#),
-            @"a c");
+            env.render_ok("description.escape_markdown()"),
+            @r"Rename \`foo\_bar\` to \*baz\_qux\* \[docs\]\(http://a\.b/c\_d\)");
+        insta::assert_snapshot!(
+            env.render_ok("description.escape_markdown(link_text = true)"),
+            @r"Rename `foo_bar` to *baz_qux* \[docs\](http://a.b/c_d)");
 
+        // Underscores in identifiers
         insta::assert_snapshot!(
-            env.render_ok(r#""a\nb\nc".lines().map(|s| s ++ s)"#),
-            @"aa bb cc");
-        // Global keyword in item template
+            env.render_ok(r#""snake_case_name".escape_markdown()"#),
+            @r"snake\_case\_name");
         insta::assert_snapshot!(
-            env.render_ok(r#""a\nb\nc".lines().map(|s| s ++ empty)"#),
-            @"atrue btrue ctrue");
-        // Global keyword in item template shadowing 'self'
+            env.render_ok(r#""snake_case_name".escape_markdown(link_text = true)"#),
+            @"snake_case_name");
+        // Backticks and backslashes
+        insta::assert_snapshot!(env.render_ok(r#""`a\\b`".escape_markdown()"#), @r"\`a\\b\`");
+        // Headings, lists, and tables
         insta::assert_snapshot!(
-            env.render_ok(r#""a\nb\nc".lines().map(|self| self ++ empty)"#),
-            @"atrue btrue ctrue");
-        // Override global keyword 'empty'
+            env.render_ok(r##""# 1. a | b - c!".escape_markdown()"##),
+            @r"\# 1\. a \| b \- c\!");
+        // Link text only escapes brackets and backslashes
         insta::assert_snapshot!(
-            env.render_ok(r#""a\nb\nc".lines().map(|empty| empty)"#),
-            @"a b c");
-        // Nested map operations
+            env.render_ok(r#""[a] \\ *b* `c`".escape_markdown(link_text = true)"#),
+            @r"\[a\] \\ *b* `c`");
+        // Non-ASCII characters are preserved
+        insta::assert_snapshot!(env.render_ok(r#""ü 日本".escape_markdown()"#), @"ü 日本");
+        insta::assert_snapshot!(env.render_ok(r#""".escape_markdown()"#), @"");
+
+        // The flag can be passed positionally
+        insta::assert_snapshot!(env.render_ok(r#""*a*".escape_markdown(true)"#), @"*a*");
         insta::assert_snapshot!(
-            env.render_ok(r#""a\nb\nc".lines().map(|s| "x\ny".lines().map(|t| s ++ t))"#),
-            @"ax ay bx by cx cy");
-        // Nested map/join operations
+            env.parse_err(r#""a".escape_markdown(link_text = 1)"#), @r#"
+         --> 1:33
+          |
+        1 | "a".escape_markdown(link_text = 1)
+          |                                 ^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Integer`
+        "#);
+    }
+
+    #[test]
+    fn test_string_strip_ansi_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || {
+            P::wrap_string(literal(
+                "\x1b[1;31mred\x1b[0m \x1b[38;5;10mgreen\x1b[39m".to_owned(),
+            ))
+        });
+
+        // SGR color codes
+        insta::assert_snapshot!(env.render_ok("description.strip_ansi()"), @"red green");
         insta::assert_snapshot!(
-            env.render_ok(r#""a\nb\nc".lines().map(|s| "x\ny".lines().map(|t| s ++ t).join(",")).join(";")"#),
-            @"ax,ay;bx,by;cx,cy");
-        // Nested string operations
+            env.render_ok(r#""\e[m\e[1mbold\e[22m".strip_ansi()"#), @"bold");
+        // Cursor movements and erasures
         insta::assert_snapshot!(
-            env.render_ok(r#""!  a\n!b\nc\n   end".remove_suffix("end").trim_end().lines().map(|s| s.remove_prefix("!").trim_start())"#),
-            @"a b c");
+            env.render_ok(r#""a\e[2Ab\e[10;20Hc\e[K\e[?25ld".strip_ansi()"#), @"abcd");
+        insta::assert_snapshot!(env.render_ok(r#""\e7saved\e8".strip_ansi()"#), @"saved");
+        // OSC hyperlinks terminated by ST or BEL
+        insta::assert_snapshot!(
+            env.render_ok(r#""\e]8;;http://a\e\\link\e]8;;\e\\".strip_ansi()"#), @"link");
+        insta::assert_snapshot!(env.render_ok(r#""\e]0;title\x07text".strip_ansi()"#), @"text");
+        // Character set selection
+        insta::assert_snapshot!(env.render_ok(r#""\e(Bascii".strip_ansi()"#), @"ascii");
+
+        // Unterminated sequences are removed up to the end
+        insta::assert_snapshot!(env.render_ok(r#""a\e[1".strip_ansi()"#), @"a");
+        insta::assert_snapshot!(env.render_ok(r#""a\e".strip_ansi()"#), @"a");
+        // Other characters are preserved
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\tb [c] ü 日本".strip_ansi()"#),
+            @"a	b [c] ü 日本");
+    }
 
-        // Lambda expression in alias
-        env.add_alias("identity", "|x| x");
-        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().map(identity)"#), @"a b c");
+    #[test]
+    fn test_string_repeat_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("width", || P::wrap_integer(literal(12)));
+        env.add_keyword("i64_max", || P::wrap_integer(literal(i64::MAX)));
 
-        // Not a lambda expression
-        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(empty)"#), @r#"
-         --> 1:17
+        insta::assert_snapshot!(env.render_ok(r#""-".repeat(width)"#), @"------------");
+        insta::assert_snapshot!(env.render_ok(r#""ab".repeat(3)"#), @"ababab");
+        insta::assert_snapshot!(env.render_ok(r#""日本".repeat(2)"#), @"日本日本");
+        insta::assert_snapshot!(env.render_ok(r#""".repeat(100)"#), @"");
+
+        // Zero count yields empty
+        insta::assert_snapshot!(env.render_ok(r#""abc".repeat(0) ++ "|""#), @"|");
+
+        // Large count
+        insta::assert_snapshot!(env.render_ok(r#""=".repeat(10000).len()"#), @"10000");
+        insta::assert_snapshot!(
+            env.render_ok(r#""==".repeat(i64_max)"#),
+            @"<Error: Attempt to repeat with overflow>");
+        insta::assert_snapshot!(env.render_ok(r#""".repeat(i64_max)"#), @"");
+
+        // Negative count
+        insta::assert_snapshot!(
+            env.render_ok(r#""a".repeat(-1)"#),
+            @"<Error: out of range integral type conversion attempted>");
+
+        insta::assert_snapshot!(env.parse_err(r#""a".repeat()"#), @r#"
+         --> 1:12
           |
-        1 | "a".lines().map(empty)
-          |                 ^---^
+        1 | "a".repeat()
+          |            ^
           |
-          = Expected lambda expression
+          = Function `repeat`: Expected 1 arguments
         "#);
-        // Bad lambda parameter count
-        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(|| "")"#), @r#"
-         --> 1:18
+    }
+
+    #[test]
+    fn test_boolean_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_color("error", crossterm::style::Color::DarkRed);
+
+        insta::assert_snapshot!(env.render_ok(r#"true.yes_no()"#), @"yes");
+        insta::assert_snapshot!(env.render_ok(r#"false.yes_no()"#), @"no");
+        insta::assert_snapshot!(env.render_ok(r#"true.on_off()"#), @"on");
+        insta::assert_snapshot!(env.render_ok(r#"false.on_off().upper()"#), @"OFF");
+        insta::assert_snapshot!(env.render_ok(r#"true.check_mark()"#), @"✓");
+        insta::assert_snapshot!(env.render_ok(r#"(1 > 2).check_mark()"#), @"✗");
+
+        insta::assert_snapshot!(env.render_ok(r#"true.then_else("a", "b")"#), @"a");
+        insta::assert_snapshot!(env.render_ok(r#"false.then_else("a", "b")"#), @"b");
+        insta::assert_snapshot!(
+            env.render_ok(r#"(1 < 2).then_else(label("error", "a"), "b")"#),
+            @"[38;5;1ma[39m");
+        insta::assert_snapshot!(env.parse_err(r#"true.then_else("a")"#), @r#"
+         --> 1:16
           |
-        1 | "a".lines().map(|| "")
-          |                  ^
+        1 | true.then_else("a")
+          |                ^-^
           |
-          = Expected 1 lambda parameters
+          = Function `then_else`: Expected 2 arguments
         "#);
-        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(|a, b| "")"#), @r#"
-         --> 1:18
+        insta::assert_snapshot!(env.parse_err(r#"true.then_else(1 == "a", "b")"#), @r#"
+         --> 1:16
           |
-        1 | "a".lines().map(|a, b| "")
-          |                  ^--^
+        1 | true.then_else(1 == "a", "b")
+          |                ^------^
           |
-          = Expected 1 lambda parameters
+          = Cannot compare expressions of type `Integer` and `String`
         "#);
-        // Bad lambda output
-        insta::assert_snapshot!(env.parse_err(r#""a".lines().filter(|s| s ++ "\n")"#), @r#"
-         --> 1:24
+    }
+
+    #[test]
+    fn test_to_string_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
+        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(42))));
+        env.add_keyword("t0", || P::wrap_timestamp(literal(new_timestamp(0, 0))));
+
+        insta::assert_snapshot!(env.render_ok(r#"true.to_string()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"(1 > 2).to_string().upper()"#), @"FALSE");
+        insta::assert_snapshot!(env.render_ok(r#"(-12).to_string()"#), @"-12");
+        insta::assert_snapshot!(env.render_ok(r#"42.to_string().pad_start(5)"#), @"   42");
+        insta::assert_snapshot!(env.render_ok(r#"42.to_string().pad_start(5, "0")"#), @"00042");
+        insta::assert_snapshot!(env.render_ok(r#"some_i64.to_string().len()"#), @"2");
+        insta::assert_snapshot!(
+            env.render_ok(r#"none_i64.to_string()"#),
+            @"<Error: No Integer available>");
+        insta::assert_snapshot!(env.render_ok(r#"1.5.to_string().pad_end(5, "0")"#), @"1.500");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t0.to_string()"#),
+            @"1970-01-01 00:00:00.000 +00:00");
+        insta::assert_snapshot!(env.render_ok(r#"t0.to_string() == stringify(t0)"#), @"true");
+
+        insta::assert_snapshot!(env.parse_err(r#"1.to_string(2)"#), @r"
+         --> 1:13
           |
-        1 | "a".lines().filter(|s| s ++ "\n")
-          |                        ^-------^
+        1 | 1.to_string(2)
+          |             ^
           |
-          = Expected expression of type `Boolean`, but actual type is `Template`
-        "#);
-        // Error in lambda expression
-        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(|s| s.unknown())"#), @r#"
-         --> 1:23
+          = Function `to_string`: Expected 0 arguments
+        ");
+    }
+
+    #[test]
+    fn test_integer_format_size_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("i64_min", || P::wrap_integer(literal(i64::MIN)));
+        env.add_keyword("i64_max", || P::wrap_integer(literal(i64::MAX)));
+
+        // Sizes under one kilobyte are printed as is
+        insta::assert_snapshot!(env.render_ok("0.format_size()"), @"0 B");
+        insta::assert_snapshot!(env.render_ok("1.format_size()"), @"1 B");
+        insta::assert_snapshot!(env.render_ok("1023.format_size()"), @"1023 B");
+        insta::assert_snapshot!(env.render_ok("999.format_size(false)"), @"999 B");
+
+        // Binary units
+        insta::assert_snapshot!(env.render_ok("1024.format_size()"), @"1.0 KiB");
+        insta::assert_snapshot!(env.render_ok("1228.format_size()"), @"1.2 KiB");
+        insta::assert_snapshot!(env.render_ok("1280.format_size()"), @"1.2 KiB");
+        insta::assert_snapshot!(env.render_ok("1048524.format_size()"), @"1023.9 KiB");
+        insta::assert_snapshot!(env.render_ok("1048576.format_size(true)"), @"1.0 MiB");
+        insta::assert_snapshot!(env.render_ok("3565158.format_size()"), @"3.4 MiB");
+        insta::assert_snapshot!(env.render_ok("i64_max.format_size()"), @"8.0 EiB");
+
+        // Decimal units
+        insta::assert_snapshot!(env.render_ok("1000.format_size(false)"), @"1.0 KB");
+        insta::assert_snapshot!(env.render_ok("1024.format_size(false)"), @"1.0 KB");
+        insta::assert_snapshot!(env.render_ok("999949.format_size(false)"), @"999.9 KB");
+        insta::assert_snapshot!(env.render_ok("999950.format_size(false)"), @"1.0 MB");
+        insta::assert_snapshot!(env.render_ok("3400000.format_size(false)"), @"3.4 MB");
+        insta::assert_snapshot!(env.render_ok("i64_max.format_size(false)"), @"9.2 EB");
+
+        // Negative sizes
+        insta::assert_snapshot!(env.render_ok("(-1).format_size()"), @"-1 B");
+        insta::assert_snapshot!(env.render_ok("(-1536).format_size()"), @"-1.5 KiB");
+        insta::assert_snapshot!(env.render_ok("i64_min.format_size()"), @"-8.0 EiB");
+
+        insta::assert_snapshot!(env.parse_err("1024.format_size(true, 1)"), @r"
+         --> 1:18
           |
-        1 | "a".lines().map(|s| s.unknown())
-          |                       ^-----^
+        1 | 1024.format_size(true, 1)
+          |                  ^-----^
           |
-          = Method `unknown` doesn't exist for type `String`
-        "#);
-        // Error in lambda alias
-        env.add_alias("too_many_params", "|x, y| x");
-        insta::assert_snapshot!(env.parse_err(r#""a".lines().map(too_many_params)"#), @r#"
-         --> 1:17
+          = Function `format_size`: Expected 0 to 1 arguments
+        ");
+    }
+
+    #[test]
+    fn test_integer_abs_signum_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("i64_min", || P::wrap_integer(literal(i64::MIN)));
+        env.add_keyword("i64_max", || P::wrap_integer(literal(i64::MAX)));
+
+        insta::assert_snapshot!(env.render_ok(r#"(-3).abs()"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#"0.abs()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"5.abs()"#), @"5");
+        insta::assert_snapshot!(env.render_ok(r#"i64_max.abs()"#), @"9223372036854775807");
+        insta::assert_snapshot!(env.render_ok(r#"(i64_min + 1).abs()"#), @"9223372036854775807");
+        insta::assert_snapshot!(
+            env.render_ok(r#"i64_min.abs()"#),
+            @"<Error: Attempt to take absolute value with overflow>");
+
+        insta::assert_snapshot!(env.render_ok(r#"(-3).signum()"#), @"-1");
+        insta::assert_snapshot!(env.render_ok(r#"0.signum()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"5.signum()"#), @"1");
+        insta::assert_snapshot!(env.render_ok(r#"i64_min.signum()"#), @"-1");
+
+        // Format a signed difference
+        insta::assert_snapshot!(
+            env.render_ok(r#"if((-3).signum() < 0, "-", "+") ++ (-3).abs()"#),
+            @"-3");
+    }
+
+    #[test]
+    fn test_integer_clamp_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
+        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(42))));
+
+        insta::assert_snapshot!(env.render_ok(r#"(-5).clamp(0, 10)"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"0.clamp(0, 10)"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"5.clamp(0, 10)"#), @"5");
+        insta::assert_snapshot!(env.render_ok(r#"10.clamp(0, 10)"#), @"10");
+        insta::assert_snapshot!(env.render_ok(r#"15.clamp(0, 10)"#), @"10");
+        insta::assert_snapshot!(env.render_ok(r#"5.clamp(5, 5)"#), @"5");
+        insta::assert_snapshot!(env.render_ok(r#"(1 + 2 * 50).clamp(-10, 90 + 1)"#), @"91");
+        insta::assert_snapshot!(env.render_ok(r#"some_i64.clamp(0, 10)"#), @"10");
+        insta::assert_snapshot!(
+            env.render_ok(r#"none_i64.clamp(0, 10)"#),
+            @"<Error: No Integer available>");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"5.clamp(10, 0)"#),
+            @"<Error: Minimum 10 is greater than maximum 0>");
+        insta::assert_snapshot!(env.parse_err(r#"5.clamp(0)"#), @r"
+         --> 1:9
           |
-        1 | "a".lines().map(too_many_params)
-          |                 ^-------------^
+        1 | 5.clamp(0)
+          |         ^
           |
-          = In alias `too_many_params`
-         --> 1:2
+          = Function `clamp`: Expected 2 arguments
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"5.clamp(0, "a")"#), @r#"
+         --> 1:12
+          |
+        1 | 5.clamp(0, "a")
+          |            ^-^
+          |
+          = Expected expression of type `Integer`, but actual type is `String`
+        "#);
+    }
+
+    #[test]
+    fn test_as_bool_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
+
+        // Empty, "0", and "false" strings are falsy
+        insta::assert_snapshot!(env.render_ok(r#""".as_bool()"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""0".as_bool()"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""false".as_bool()"#), @"false");
+        // Anything else is truthy, including look-alikes
+        insta::assert_snapshot!(env.render_ok(r#""1".as_bool()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""true".as_bool()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""no".as_bool()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""False".as_bool()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#""00".as_bool()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"" ".as_bool()"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"" false".as_bool()"#), @"true");
+        // Unlike implicit conversion, "0" and "false" are falsy
+        insta::assert_snapshot!(env.render_ok(r#"if("0", "yes", "no")"#), @"yes");
+        insta::assert_snapshot!(env.render_ok(r#"if("0".as_bool(), "yes", "no")"#), @"no");
+
+        // Zero is falsy
+        insta::assert_snapshot!(env.render_ok("0.as_bool()"), @"false");
+        insta::assert_snapshot!(env.render_ok("1.as_bool()"), @"true");
+        insta::assert_snapshot!(env.render_ok("(-1).as_bool()"), @"true");
+        insta::assert_snapshot!(env.render_ok("(3 - 3).as_bool()"), @"false");
+        insta::assert_snapshot!(
+            env.render_ok("none_i64.as_bool()"),
+            @"<Error: No Integer available>");
+
+        insta::assert_snapshot!(env.parse_err(r#""a".as_bool(1)"#), @r#"
+         --> 1:13
           |
-        1 | |x, y| x
-          |  ^--^
+        1 | "a".as_bool(1)
+          |             ^
           |
-          = Expected 1 lambda parameters
+          = Function `as_bool`: Expected 0 arguments
         "#);
     }
 
     #[test]
-    fn test_string_method() {
+    fn test_integer_pluralize_method() {
         let mut env = TestTemplateEnv::new();
-        env.add_keyword("description", || {
-            P::wrap_string(literal("description 1".to_owned()))
-        });
-        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
-
-        insta::assert_snapshot!(env.render_ok(r#""".len()"#), @"0");
-        insta::assert_snapshot!(env.render_ok(r#""foo".len()"#), @"3");
-        insta::assert_snapshot!(env.render_ok(r#""💩".len()"#), @"4");
+        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
+        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(3))));
 
-        insta::assert_snapshot!(env.render_ok(r#""fooo".contains("foo")"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#""foo".contains("fooo")"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#"description.contains("description")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"0.pluralize("file", "files")"#), @"files");
+        insta::assert_snapshot!(env.render_ok(r#"1.pluralize("file", "files")"#), @"file");
+        insta::assert_snapshot!(env.render_ok(r#"3.pluralize("file", "files")"#), @"files");
+        insta::assert_snapshot!(env.render_ok(r#"(-1).pluralize("file", "files")"#), @"file");
         insta::assert_snapshot!(
-            env.render_ok(r#""description 123".contains(description.first_line())"#),
-            @"true");
-
-        // inner template error should propagate
-        insta::assert_snapshot!(env.render_ok(r#""foo".contains(bad_string)"#), @"<Error: Bad>");
+            env.render_ok(r#"0.pluralize("file", "files", "no files")"#), @"no files");
         insta::assert_snapshot!(
-            env.render_ok(r#""foo".contains("f" ++ bad_string) ++ "bar""#), @"<Error: Bad>bar");
+            env.render_ok(r#"1.pluralize("file", "files", "no files")"#), @"file");
         insta::assert_snapshot!(
-            env.render_ok(r#""foo".contains(separate("o", "f", bad_string))"#), @"<Error: Bad>");
-
-        insta::assert_snapshot!(env.render_ok(r#""".first_line()"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""foo\nbar".first_line()"#), @"foo");
+            env.render_ok(r#"some_i64 ++ " " ++ some_i64.pluralize("file", "files")"#), @"3 files");
+        insta::assert_snapshot!(
+            env.render_ok(r#"none_i64.pluralize("file", "files")"#),
+            @"<Error: No Integer available>");
 
-        insta::assert_snapshot!(env.render_ok(r#""".lines()"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc\n".lines()"#), @"a b c");
+        insta::assert_snapshot!(env.parse_err(r#"1.pluralize("file")"#), @r#"
+         --> 1:13
+          |
+        1 | 1.pluralize("file")
+          |             ^----^
+          |
+          = Function `pluralize`: Expected 2 to 3 arguments
+        "#);
+    }
 
-        insta::assert_snapshot!(env.render_ok(r#""".starts_with("")"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#""everything".starts_with("")"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#""".starts_with("foo")"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#""foo".starts_with("foo")"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#""foobar".starts_with("foo")"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#""foobar".starts_with("bar")"#), @"false");
+    #[test]
+    fn test_integer_format_number_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("i64_min", || P::wrap_integer(literal(i64::MIN)));
 
-        insta::assert_snapshot!(env.render_ok(r#""".ends_with("")"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#""everything".ends_with("")"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#""".ends_with("foo")"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#""foo".ends_with("foo")"#), @"true");
-        insta::assert_snapshot!(env.render_ok(r#""foobar".ends_with("foo")"#), @"false");
-        insta::assert_snapshot!(env.render_ok(r#""foobar".ends_with("bar")"#), @"true");
+        insta::assert_snapshot!(env.render_ok("1234567.format_number()"), @"1,234,567");
+        insta::assert_snapshot!(env.render_ok("123456.format_number()"), @"123,456");
+        insta::assert_snapshot!(env.render_ok("1000.format_number()"), @"1,000");
 
-        insta::assert_snapshot!(env.render_ok(r#""".remove_prefix("wip: ")"#), @"");
-        insta::assert_snapshot!(
-            env.render_ok(r#""wip: testing".remove_prefix("wip: ")"#),
-            @"testing");
+        // Numbers under 1000 have no separator
+        insta::assert_snapshot!(env.render_ok("999.format_number()"), @"999");
+        insta::assert_snapshot!(env.render_ok("0.format_number()"), @"0");
+        insta::assert_snapshot!(env.render_ok("(-999).format_number()"), @"-999");
 
+        // Negative numbers
+        insta::assert_snapshot!(env.render_ok("(-1234567).format_number()"), @"-1,234,567");
         insta::assert_snapshot!(
-            env.render_ok(r#""bar@my.example.com".remove_suffix("@other.example.com")"#),
-            @"bar@my.example.com");
+            env.render_ok("i64_min.format_number()"),
+            @"-9,223,372,036,854,775,808");
+
+        // Custom separator and group size
+        insta::assert_snapshot!(env.render_ok("1234567.format_number('_')"), @"1_234_567");
+        insta::assert_snapshot!(env.render_ok("1234567.format_number(' ', 4)"), @"123 4567");
+        insta::assert_snapshot!(env.render_ok("(-1234567).format_number('.', 2)"), @"-1.23.45.67");
+        insta::assert_snapshot!(env.render_ok("1234567.format_number(',', 10)"), @"1234567");
         insta::assert_snapshot!(
-            env.render_ok(r#""bar@other.example.com".remove_suffix("@other.example.com")"#),
-            @"bar");
-
-        insta::assert_snapshot!(env.render_ok(r#"" \n \r    \t \r ".trim()"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#"" \n \r foo  bar \t \r ".trim()"#), @"foo  bar");
-
-        insta::assert_snapshot!(env.render_ok(r#"" \n \r    \t \r ".trim_start()"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#"" \n \r foo  bar \t \r ".trim_start()"#), @"foo  bar");
+            env.render_ok("1234567.format_number(',', 0)"),
+            @"<Error: Group size must be greater than 0>");
 
-        insta::assert_snapshot!(env.render_ok(r#"" \n \r    \t \r ".trim_end()"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#"" \n \r foo  bar \t \r ".trim_end()"#), @" foo  bar");
-
-        insta::assert_snapshot!(env.render_ok(r#""foo".substr(0, 0)"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""foo".substr(0, 1)"#), @"f");
-        insta::assert_snapshot!(env.render_ok(r#""foo".substr(0, 3)"#), @"foo");
-        insta::assert_snapshot!(env.render_ok(r#""foo".substr(0, 4)"#), @"foo");
-        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(2, -1)"#), @"cde");
-        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(-3, 99)"#), @"def");
-        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(-6, 99)"#), @"abcdef");
-        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(-7, 1)"#), @"a");
+        insta::assert_snapshot!(env.parse_err("1234567.format_number(',', 3, 1)"), @r"
+         --> 1:23
+          |
+        1 | 1234567.format_number(',', 3, 1)
+          |                       ^-------^
+          |
+          = Function `format_number`: Expected 0 to 2 arguments
+        ");
+        insta::assert_snapshot!(
+            env.render_ok("1234567.format_number(',', -1)"),
+            @"<Error: out of range integral type conversion attempted>");
+    }
 
-        // non-ascii characters
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(2, -1)"#), @"c💩");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, -3)"#), @"💩");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, -4)"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(6, -3)"#), @"💩");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(7, -3)"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, 4)"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, 6)"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(3, 7)"#), @"💩");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(-1, 7)"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(-3, 7)"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""abc💩".substr(-4, 7)"#), @"💩");
+    #[test]
+    fn test_float_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("big", || P::wrap_float(literal(1e20)));
+        env.add_keyword("nan", || P::wrap_float(literal(f64::NAN)));
+
+        insta::assert_snapshot!(env.render_ok(r#"1.5"#), @"1.5");
+        insta::assert_snapshot!(env.render_ok(r#"1.0"#), @"1");
+        insta::assert_snapshot!(env.render_ok(r#"0.1"#), @"0.1");
+
+        insta::assert_snapshot!(env.render_ok(r#"2.5.round()"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#"(-2.5).round()"#), @"-3");
+        insta::assert_snapshot!(env.render_ok(r#"2.7.floor()"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#"(-2.2).floor()"#), @"-3");
+        insta::assert_snapshot!(env.render_ok(r#"2.2.ceil()"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#"(-2.7).ceil()"#), @"-2");
+        insta::assert_snapshot!(
+            env.render_ok(r#"big.round()"#),
+            @"<Error: Float 100000000000000000000 is out of range for Integer>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"nan.floor()"#),
+            @"<Error: Float NaN is out of range for Integer>");
 
-        // ranges with end > start are empty
-        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(4, 2)"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#""abcdef".substr(-2, -4)"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"3.14159.fixed(2)"#), @"3.14");
+        insta::assert_snapshot!(env.render_ok(r#"0.125.fixed(1)"#), @"0.1");
+        insta::assert_snapshot!(env.render_ok(r#"2.5.fixed(0)"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#"1.5.fixed(3)"#), @"1.500");
+        insta::assert_snapshot!(
+            env.render_ok(r#"1.5.fixed(-1)"#),
+            @"<Error: out of range integral type conversion attempted>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"1.5.fixed(65536)"#),
+            @"<Error: Precision 65536 is out of range>");
 
-        insta::assert_snapshot!(env.render_ok(r#""hello".escape_json()"#), @r#""hello""#);
-        insta::assert_snapshot!(env.render_ok(r#""he \n ll \n \" o".escape_json()"#), @r#""he \n ll \n \" o""#);
+        // Integer isn't implicitly converted to float for method calls
+        insta::assert_snapshot!(env.parse_err(r#"1.fixed(2)"#), @r"
+         --> 1:3
+          |
+        1 | 1.fixed(2)
+          |   ^---^
+          |
+          = Method `fixed` doesn't exist for type `Integer`
+        ");
     }
 
     #[test]
@@ -2835,6 +8864,38 @@ mod tests {
         insta::assert_snapshot!(env.render_ok(r#"author.username()"#), @"");
     }
 
+    #[test]
+    fn test_diagnostics_location() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("author", || {
+            P::wrap_signature(literal(new_signature("Test User", "test.user@example.com")))
+        });
+
+        let mut diagnostics = TemplateDiagnostics::new();
+        let template = "author.username() ++ author.username()";
+        parse(
+            &env.language,
+            &mut diagnostics,
+            template,
+            &env.aliases_map,
+            P::wrap_self,
+        )
+        .unwrap();
+        let warnings = diagnostics
+            .located()
+            .map(|diag| (diag.byte_range, diag.severity, diag.message))
+            .collect_vec();
+        let message = "username() is deprecated; use email().local() instead";
+        let severity = TemplateDiagnosticSeverity::Warning;
+        assert_eq!(
+            warnings,
+            [
+                (7..15, severity, message.into()),
+                (28..36, severity, message.into())
+            ]
+        );
+    }
+
     #[test]
     fn test_size_hint_method() {
         let mut env = TestTemplateEnv::new();
@@ -2845,76 +8906,535 @@ mod tests {
         insta::assert_snapshot!(env.render_ok(r#"unbounded.exact()"#), @"");
         insta::assert_snapshot!(env.render_ok(r#"unbounded.zero()"#), @"false");
 
-        env.add_keyword("bounded", || P::wrap_size_hint(literal((0, Some(10)))));
-        insta::assert_snapshot!(env.render_ok(r#"bounded.lower()"#), @"0");
-        insta::assert_snapshot!(env.render_ok(r#"bounded.upper()"#), @"10");
-        insta::assert_snapshot!(env.render_ok(r#"bounded.exact()"#), @"");
-        insta::assert_snapshot!(env.render_ok(r#"bounded.zero()"#), @"false");
+        env.add_keyword("bounded", || P::wrap_size_hint(literal((0, Some(10)))));
+        insta::assert_snapshot!(env.render_ok(r#"bounded.lower()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"bounded.upper()"#), @"10");
+        insta::assert_snapshot!(env.render_ok(r#"bounded.exact()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"bounded.zero()"#), @"false");
+
+        env.add_keyword("zero", || P::wrap_size_hint(literal((0, Some(0)))));
+        insta::assert_snapshot!(env.render_ok(r#"zero.lower()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"zero.upper()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"zero.exact()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"zero.zero()"#), @"true");
+    }
+
+    #[test]
+    fn test_timestamp_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("t0", || P::wrap_timestamp(literal(new_timestamp(0, 0))));
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"t0.format("%Y%m%d %H:%M:%S")"#),
+            @"19700101 00:00:00");
+
+        // The original timezone offset should be preserved
+        env.add_keyword("t1", || {
+            P::wrap_timestamp(literal(new_timestamp(981_173_107_000, 9 * 60 + 30)))
+        });
+        env.add_keyword("t2", || {
+            P::wrap_timestamp(literal(new_timestamp(981_173_107_000, -8 * 60)))
+        });
+        insta::assert_snapshot!(
+            env.render_ok(r#"t1.format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-03 13:35 +09:30");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t2.format("%Y-%m-%d %H:%M %z")"#),
+            @"2001-02-02 20:05 -0800");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t2.utc().format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-03 04:05 +00:00");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t1.utc().format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-03 04:05 +00:00");
+
+        // Conversion to fixed offset or named zone. Most named zones depend on
+        // the system time zone database, but "UTC" is always available.
+        insta::assert_snapshot!(
+            env.render_ok(r#"t1.in_timezone("UTC").format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-03 04:05 +00:00");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t1.in_timezone("-03:30").format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-03 00:35 -03:30");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t2.in_timezone("+0530").format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-03 09:35 +05:30");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t2.in_timezone("+00:00").format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-03 04:05 +00:00");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t1.in_timezone("Mars/Olympus_Mons")"#),
+            @r#"<Error: Invalid time zone "Mars/Olympus_Mons": failed to find time zone `Mars/Olympus_Mons` in time zone database>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#"t1.in_timezone("+25:00")"#),
+            @r#"<Error: Invalid time zone "+25:00">"#);
+
+        // Out-of-range timestamp
+        env.add_keyword("t_max", || {
+            P::wrap_timestamp(literal(new_timestamp(i64::MAX, 0)))
+        });
+        insta::assert_snapshot!(
+            env.render_ok(r#"t_max.format("%Y")"#),
+            @"<Error: Out-of-range date>");
+
+        // Invalid format string
+        insta::assert_snapshot!(env.parse_err(r#"t0.format("%_")"#), @r#"
+         --> 1:11
+          |
+        1 | t0.format("%_")
+          |           ^--^
+          |
+          = Invalid time format
+        "#);
+
+        // Invalid type
+        insta::assert_snapshot!(env.parse_err(r#"t0.format(0)"#), @r"
+         --> 1:11
+          |
+        1 | t0.format(0)
+          |           ^
+          |
+          = Expected string literal
+        ");
+
+        // Dynamic string isn't supported yet
+        insta::assert_snapshot!(env.parse_err(r#"t0.format("%Y" ++ "%m")"#), @r#"
+         --> 1:11
+          |
+        1 | t0.format("%Y" ++ "%m")
+          |           ^----------^
+          |
+          = Expected string literal
+        "#);
+
+        // Literal alias expansion
+        env.add_alias("time_format", r#""%Y-%m-%d""#);
+        env.add_alias("bad_time_format", r#""%_""#);
+        insta::assert_snapshot!(env.render_ok(r#"t0.format(time_format)"#), @"1970-01-01");
+        insta::assert_snapshot!(env.parse_err(r#"t0.format(bad_time_format)"#), @r#"
+         --> 1:11
+          |
+        1 | t0.format(bad_time_format)
+          |           ^-------------^
+          |
+          = In alias `bad_time_format`
+         --> 1:1
+          |
+        1 | "%_"
+          | ^--^
+          |
+          = Invalid time format
+        "#);
+    }
+
+    #[test]
+    fn test_template_clock() {
+        let mut env = TestTemplateEnv::new();
+        // 2001-02-03 04:05:07 UTC
+        env.language.set_clock(
+            TemplateClock::SYSTEM
+                .with_now(new_timestamp(981_173_107_000, 0))
+                .with_tz_offset(-5 * 60),
+        );
+        env.add_keyword("t1", || {
+            P::wrap_timestamp(literal(new_timestamp(
+                981_173_107_000 - 3 * 3600 * 1000,
+                9 * 60,
+            )))
+        });
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"now().format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-02 23:05 -05:00");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t1.local().format("%Y-%m-%d %H:%M %:z")"#),
+            @"2001-02-02 20:05 -05:00");
+        insta::assert_snapshot!(env.render_ok("t1.ago()"), @"3 hours ago");
+        insta::assert_snapshot!(env.render_ok(r#"now().after("2001-02-02")"#), @"true");
+        // Relative dates are resolved against the frozen clock
+        insta::assert_snapshot!(env.render_ok(r#"t1.after("5 hours ago")"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"t1.after("1 hour ago")"#), @"false");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t1.after("yesterday") && t1.is_today()"#),
+            @"true");
+        insta::assert_snapshot!(env.render_ok(r#"now().before("tomorrow")"#), @"true");
+    }
+
+    #[test]
+    fn test_timestamp_same_day_method() {
+        let new_env = |tz_offset: i32| {
+            let mut env = TestTemplateEnv::new();
+            // 2001-02-03 04:05:07 UTC
+            env.language.set_clock(
+                TemplateClock::SYSTEM
+                    .with_now(new_timestamp(981_173_107_000, 0))
+                    .with_tz_offset(tz_offset),
+            );
+            // 2001-02-02 23:30 +09:00
+            env.add_keyword("t1", || {
+                P::wrap_timestamp(literal(new_timestamp(981_124_200_000, 9 * 60)))
+            });
+            // 2001-02-03 00:30 +09:00, stored as 2001-02-02 07:30 -08:00
+            env.add_keyword("t2", || {
+                P::wrap_timestamp(literal(new_timestamp(981_127_800_000, -8 * 60)))
+            });
+            env
+        };
+
+        // Straddles midnight in +09:00
+        let env = new_env(9 * 60);
+        insta::assert_snapshot!(env.render_ok("t1.same_day(t2)"), @"false");
+        insta::assert_snapshot!(env.render_ok("t2.same_day(t1)"), @"false");
+        insta::assert_snapshot!(env.render_ok("t1.same_day(t1)"), @"true");
+        insta::assert_snapshot!(env.render_ok("t1.is_today()"), @"false");
+        insta::assert_snapshot!(env.render_ok("t2.is_today()"), @"true");
+
+        // The original time zones of the timestamps don't matter
+        let env = new_env(0);
+        insta::assert_snapshot!(env.render_ok("t1.same_day(t2)"), @"true");
+        insta::assert_snapshot!(env.render_ok("t1.is_today()"), @"false");
+        insta::assert_snapshot!(env.render_ok("t2.is_today()"), @"false");
+
+        let env = new_env(-5 * 60);
+        insta::assert_snapshot!(env.render_ok("t1.same_day(t2)"), @"true");
+        insta::assert_snapshot!(env.render_ok("t1.is_today()"), @"true");
+        insta::assert_snapshot!(env.render_ok("now().is_today()"), @"true");
+
+        insta::assert_snapshot!(env.parse_err("t1.same_day('2001-02-02')"), @r"
+         --> 1:13
+          |
+        1 | t1.same_day('2001-02-02')
+          |             ^----------^
+          |
+          = Expected expression of type `Timestamp`, but actual type is `String`
+        ");
+        insta::assert_snapshot!(env.parse_err("t1.is_today(t2)"), @r"
+         --> 1:13
+          |
+        1 | t1.is_today(t2)
+          |             ^^
+          |
+          = Function `is_today`: Expected 0 arguments
+        ");
+    }
+
+    #[test]
+    fn test_term_width_function() {
+        let mut env = TestTemplateEnv::new();
+        insta::assert_snapshot!(env.render_ok("term_width()"), @"80");
+        env.language.set_term_width(Some(120));
+        insta::assert_snapshot!(env.render_ok("term_width() - 20"), @"100");
+
+        let mut config = StackedConfig::with_defaults();
+        config.add_layer(
+            ConfigLayer::parse(ConfigSource::User, "ui.default-term-width = 50").unwrap(),
+        );
+        let env = TestTemplateEnv::with_config(config);
+        insta::assert_snapshot!(env.render_ok("term_width()"), @"50");
+    }
+
+    #[test]
+    fn test_template_limits() {
+        let new_env = |text: &str| {
+            let mut config = StackedConfig::with_defaults();
+            config.add_layer(ConfigLayer::parse(ConfigSource::User, text).unwrap());
+            TestTemplateEnv::with_config(config)
+        };
+        // Each alias doubles the size and adds one nesting level.
+        let add_explosive_aliases = |env: &mut TestTemplateEnv, n: usize| {
+            env.add_alias("x0", "'0123456789'");
+            for i in 1..=n {
+                env.add_alias(format!("x{i}"), format!("x{j} ++ x{j}", j = i - 1));
+            }
+        };
+
+        let mut env = new_env("ui.template-max-depth = 5");
+        add_explosive_aliases(&mut env, 8);
+        insta::assert_snapshot!(
+            env.render_ok("x3"),
+            @"01234567890123456789012345678901234567890123456789012345678901234567890123456789");
+        insta::assert_snapshot!(env.parse_err("x8"), @r"
+         --> 1:1
+          |
+        1 | x8
+          | ^^
+          |
+          = In alias `x8`
+         --> 1:1
+          |
+        1 | x7 ++ x7
+          | ^^
+          |
+          = In alias `x7`
+         --> 1:1
+          |
+        1 | x6 ++ x6
+          | ^^
+          |
+          = In alias `x6`
+         --> 1:1
+          |
+        1 | x5 ++ x5
+          | ^^
+          |
+          = In alias `x5`
+         --> 1:1
+          |
+        1 | x4 ++ x4
+          | ^^
+          |
+          = In alias `x4`
+         --> 1:1
+          |
+        1 | x3 ++ x3
+          | ^^
+          |
+          = Template expression is nested too deeply (limit: 5)
+        ");
+        insta::assert_snapshot!(env.parse_err("'a'.upper().upper().upper().upper().upper()"), @r"
+         --> 1:1
+          |
+        1 | 'a'.upper().upper().upper().upper().upper()
+          | ^-^
+          |
+          = Template expression is nested too deeply (limit: 5)
+        ");
+
+        // Depth of the parsed expression is checked before building it
+        let parse_err_message = |env: &TestTemplateEnv, template: &str| {
+            env.parse(template).err().unwrap().message().into_owned()
+        };
+        let nested_calls = |n| format!("{}'a'{}", "if(true, ".repeat(n), ")".repeat(n));
+        let nested_parens = |n| format!("{}'a'{}", "(".repeat(n), ")".repeat(n));
+        let chained_methods = |n| format!("'a'{}", ".upper()".repeat(n));
+        let env = new_env("");
+        insta::assert_snapshot!(
+            parse_err_message(&env, &chained_methods(1000)),
+            @"Template expression is nested too deeply (limit: 256)");
+        let env = new_env("ui.template-max-depth = 20");
+        insta::assert_snapshot!(env.render_ok(&nested_calls(19)), @"a");
+        insta::assert_snapshot!(env.render_ok(&chained_methods(19)), @"A");
+        insta::assert_snapshot!(
+            parse_err_message(&env, &nested_calls(20)),
+            @"Template expression is nested too deeply (limit: 20)");
+        insta::assert_snapshot!(
+            parse_err_message(&env, &chained_methods(20)),
+            @"Template expression is nested too deeply (limit: 20)");
+        // Parentheses don't add nesting level
+        insta::assert_snapshot!(env.render_ok(&nested_parens(30)), @"a");
+
+        let mut env = new_env("ui.template-max-output-size = 100");
+        add_explosive_aliases(&mut env, 8);
+        let render_err = |template: &str| {
+            let template = env.parse(template).unwrap();
+            let mut output = Vec::new();
+            let mut formatter = PlainTextFormatter::new(&mut output);
+            template
+                .format(&(), &mut formatter)
+                .unwrap_err()
+                .to_string()
+        };
+        insta::assert_snapshot!(
+            env.render_ok("x3"),
+            @"01234567890123456789012345678901234567890123456789012345678901234567890123456789");
+        insta::assert_snapshot!(
+            render_err("x8"),
+            @"Template output exceeds the limit of 100 bytes");
 
-        env.add_keyword("zero", || P::wrap_size_hint(literal((0, Some(0)))));
-        insta::assert_snapshot!(env.render_ok(r#"zero.lower()"#), @"0");
-        insta::assert_snapshot!(env.render_ok(r#"zero.upper()"#), @"0");
-        insta::assert_snapshot!(env.render_ok(r#"zero.exact()"#), @"0");
-        insta::assert_snapshot!(env.render_ok(r#"zero.zero()"#), @"true");
+        let env = new_env("ui.template-max-output-size = 'bad'");
+        insta::assert_snapshot!(env.parse_err("''"), @r"
+         --> 1:1
+          |
+        1 | ''
+          | ^^
+          |
+          = Failed to get config value
+        ");
     }
 
     #[test]
-    fn test_timestamp_method() {
+    fn test_duration_method() {
         let mut env = TestTemplateEnv::new();
         env.add_keyword("t0", || P::wrap_timestamp(literal(new_timestamp(0, 0))));
+        env.add_keyword("t1", || {
+            P::wrap_timestamp(literal(new_timestamp(250, 9 * 60)))
+        });
+        env.add_keyword("t2", || {
+            let msec = ((2 * 24 + 3) * 60 + 4) * 60_000 + 5_500;
+            P::wrap_timestamp(literal(new_timestamp(msec, -8 * 60)))
+        });
+        env.add_keyword("t_max", || {
+            P::wrap_timestamp(literal(new_timestamp(i64::MAX, 0)))
+        });
+        env.add_keyword("t_min", || {
+            P::wrap_timestamp(literal(new_timestamp(i64::MIN, 0)))
+        });
 
+        // Sub-second span
+        insta::assert_snapshot!(env.render_ok(r#"t1 - t0"#), @"0.25s");
+        insta::assert_snapshot!(env.render_ok(r#"(t1 - t0).seconds()"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"(t1 - t0).humanize()"#), @"0 seconds");
+        insta::assert_snapshot!(env.render_ok(r#"t0 - t0"#), @"0s");
+
+        // Multi-day span. The timezone offsets don't matter.
+        insta::assert_snapshot!(env.render_ok(r#"(t2 - t0).format()"#), @"2d 3h 4m 5.5s");
+        insta::assert_snapshot!(env.render_ok(r#"(t2 - t0).seconds()"#), @"183845");
+        insta::assert_snapshot!(env.render_ok(r#"(t2 - t0).humanize()"#), @"2 days");
+
+        // Negative span
+        insta::assert_snapshot!(env.render_ok(r#"t0 - t2"#), @"-2d 3h 4m 5.5s");
+        insta::assert_snapshot!(env.render_ok(r#"(t0 - t2).seconds()"#), @"-183845");
+        insta::assert_snapshot!(env.render_ok(r#"(t0 - t2).humanize()"#), @"-2 days");
+        insta::assert_snapshot!(env.render_ok(r#"(t0 - t1).seconds()"#), @"0");
+
+        // Arithmetic and comparison
+        insta::assert_snapshot!(env.render_ok(r#"(t2 - t1) + (t1 - t0) == t2 - t0"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"(t2 - t1) - (t2 - t0)"#), @"-0.25s");
+        insta::assert_snapshot!(env.render_ok(r#"t2 - t0 > t1 - t0"#), @"true");
         insta::assert_snapshot!(
-            env.render_ok(r#"t0.format("%Y%m%d %H:%M:%S")"#),
-            @"19700101 00:00:00");
+            env.render_ok(r#"(t0 + (t2 - t1)).format("%Y-%m-%d %H:%M:%S%.3f")"#),
+            @"1970-01-03 03:04:05.250");
+        insta::assert_snapshot!(
+            env.render_ok(r#"((t1 - t0) + t1).format("%H:%M:%S%.3f %:z")"#),
+            @"09:00:00.500 +09:00");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t_max - t_min"#),
+            @"<Error: Attempt to subtract with overflow>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"t_max + (t1 - t0)"#),
+            @"<Error: Attempt to add with overflow>");
 
-        // Invalid format string
-        insta::assert_snapshot!(env.parse_err(r#"t0.format("%_")"#), @r#"
-         --> 1:11
+        insta::assert_snapshot!(env.parse_err(r#"t0 + t0"#), @r"
+         --> 1:1
           |
-        1 | t0.format("%_")
-          |           ^--^
+        1 | t0 + t0
+          | ^-----^
           |
-          = Invalid time format
-        "#);
-
-        // Invalid type
-        insta::assert_snapshot!(env.parse_err(r#"t0.format(0)"#), @r"
-         --> 1:11
+          = Cannot apply arithmetic operator to expressions of type `Timestamp` and `Timestamp`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"t0 - 1"#), @r"
+         --> 1:1
           |
-        1 | t0.format(0)
-          |           ^
+        1 | t0 - 1
+          | ^----^
           |
-          = Expected string literal
+          = Cannot apply arithmetic operator to expressions of type `Timestamp` and `Integer`
         ");
-
-        // Dynamic string isn't supported yet
-        insta::assert_snapshot!(env.parse_err(r#"t0.format("%Y" ++ "%m")"#), @r#"
-         --> 1:11
+        insta::assert_snapshot!(env.parse_err(r#"(t1 - t0) * 2"#), @r"
+         --> 1:2
           |
-        1 | t0.format("%Y" ++ "%m")
-          |           ^----------^
+        1 | (t1 - t0) * 2
+          |  ^----------^
           |
-          = Expected string literal
+          = Cannot apply arithmetic operator to expressions of type `Duration` and `Integer`
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"t0 - "a""#), @r#"
+         --> 1:6
+          |
+        1 | t0 - "a"
+          |      ^-^
+          |
+          = Expected expression of type `Integer`, but actual type is `String`
         "#);
-
-        // Literal alias expansion
-        env.add_alias("time_format", r#""%Y-%m-%d""#);
-        env.add_alias("bad_time_format", r#""%_""#);
-        insta::assert_snapshot!(env.render_ok(r#"t0.format(time_format)"#), @"1970-01-01");
-        insta::assert_snapshot!(env.parse_err(r#"t0.format(bad_time_format)"#), @r#"
-         --> 1:11
+        insta::assert_snapshot!(env.parse_err(r#"(t1 - t0) == 0"#), @r"
+         --> 1:2
           |
-        1 | t0.format(bad_time_format)
-          |           ^-------------^
+        1 | (t1 - t0) == 0
+          |  ^-----------^
           |
-          = In alias `bad_time_format`
+          = Cannot compare expressions of type `Duration` and `Integer`
+        ");
+    }
+
+    #[test]
+    fn test_bytes_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("empty_bytes", || P::wrap_bytes(literal(vec![])));
+        env.add_keyword("ascii_bytes", || P::wrap_bytes(literal(b"jj".to_vec())));
+        env.add_keyword("high_bytes", || {
+            P::wrap_bytes(literal(vec![0x00, 0x7f, 0x80, 0xfb, 0xff]))
+        });
+
+        insta::assert_snapshot!(env.render_ok("empty_bytes.len()"), @"0");
+        insta::assert_snapshot!(env.render_ok("empty_bytes.hex() ++ '|'"), @"|");
+        insta::assert_snapshot!(env.render_ok("empty_bytes.base64() ++ '|'"), @"|");
+        insta::assert_snapshot!(env.render_ok("if(empty_bytes, 'true', 'false')"), @"false");
+
+        insta::assert_snapshot!(env.render_ok("ascii_bytes.len()"), @"2");
+        insta::assert_snapshot!(env.render_ok("ascii_bytes.hex()"), @"6a6a");
+        insta::assert_snapshot!(env.render_ok("ascii_bytes.base64()"), @"amo=");
+        insta::assert_snapshot!(env.render_ok("if(ascii_bytes, 'true', 'false')"), @"true");
+
+        insta::assert_snapshot!(env.render_ok("high_bytes.len()"), @"5");
+        insta::assert_snapshot!(env.render_ok("high_bytes.hex()"), @"007f80fbff");
+        insta::assert_snapshot!(env.render_ok("high_bytes.hex(uppercase = true)"), @"007F80FBFF");
+        insta::assert_snapshot!(env.render_ok("high_bytes.base64()"), @"AH+A+/8=");
+        insta::assert_snapshot!(env.render_ok("high_bytes.base64(url_safe = true)"), @"AH-A-_8=");
+
+        insta::assert_snapshot!(env.render_ok("ascii_bytes == ascii_bytes"), @"true");
+        insta::assert_snapshot!(env.render_ok("ascii_bytes != high_bytes"), @"true");
+
+        // Bytes can't be rendered as is
+        insta::assert_snapshot!(env.parse_err("ascii_bytes"), @r"
          --> 1:1
           |
-        1 | "%_"
-          | ^--^
+        1 | ascii_bytes
+          | ^---------^
           |
-          = Invalid time format
-        "#);
+          = Expected expression of type `Template`, but actual type is `Bytes`
+        ");
+        insta::assert_snapshot!(env.parse_err("ascii_bytes.hex(uppercase = 1)"), @r"
+         --> 1:29
+          |
+        1 | ascii_bytes.hex(uppercase = 1)
+          |                             ^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Integer`
+        ");
+    }
+
+    #[test]
+    fn test_bytes_hexdump_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("empty_bytes", || P::wrap_bytes(literal(vec![])));
+        env.add_keyword("bytes17", || {
+            P::wrap_bytes(literal(b"Hello, world!\x00\x7f\x80\n".to_vec()))
+        });
+
+        // The last partial line is padded
+        insta::assert_snapshot!(env.render_ok("bytes17.hexdump()"), @r"
+        00000000  48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 00 7f 80  |Hello, world!...|
+        00000010  0a                                               |.|
+        ");
+        insta::assert_snapshot!(env.render_ok("bytes17.hexdump(16)"), @r"
+        00000000  48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 00 7f 80  |Hello, world!...|
+        00000010  0a                                               |.|
+        ");
+        insta::assert_snapshot!(env.render_ok("bytes17.hexdump(8)"), @r"
+        00000000  48 65 6c 6c 6f 2c 20 77  |Hello, w|
+        00000008  6f 72 6c 64 21 00 7f 80  |orld!...|
+        00000010  0a                       |.|
+        ");
+        insta::assert_snapshot!(
+            env.render_ok("bytes17.hexdump(17)"),
+            @"00000000  48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 00 7f 80 0a  |Hello, world!....|");
+        // Huge width shouldn't be padded
+        insta::assert_snapshot!(
+            env.render_ok("bytes17.hexdump(9223372036854775807)"),
+            @"00000000  48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 00 7f 80 0a  |Hello, world!....|");
+        insta::assert_snapshot!(env.render_ok("empty_bytes.hexdump() ++ '|'"), @"|");
+
+        insta::assert_snapshot!(
+            env.render_ok("bytes17.hexdump(0)"),
+            @"<Error: Bytes per line must be greater than 0>");
+        insta::assert_snapshot!(env.parse_err("bytes17.hexdump(uppercase = true)"), @r"
+         --> 1:17
+          |
+        1 | bytes17.hexdump(uppercase = true)
+          |                 ^--------------^
+          |
+          = Function `hexdump`: Unexpected keyword arguments
+        ");
     }
 
     #[test]
@@ -3004,6 +9524,38 @@ mod tests {
         jumps over the [38;5;1mlazy[39m
         dog
         ");
+
+        // Word-wrap indented, keeping the indentation
+        insta::assert_snapshot!(
+            env.render_ok(r#""START marker to help insta\n" ++
+                             fill(20, indent("    ", "The quick fox jumps over the " ++
+                                             label("error", "lazy") ++ " dog\n"),
+                                  keep_indent = true)"#),
+            @r"
+        START marker to help insta
+            The quick fox
+            jumps over the
+            [38;5;1mlazy[39m dog
+        ");
+
+        // Each wrapped line keeps the label
+        insta::assert_snapshot!(
+            env.render_ok(r#"fill(10, label("error", "  The quick fox jumps") ++ "\nover the dog",
+                                  keep_indent = true)"#),
+            @r"
+        [38;5;1m  The[39m
+          [38;5;1mquick[39m
+          [38;5;1mfox[39m
+          [38;5;1mjumps[39m
+        over the
+        dog
+        ");
+        insta::assert_snapshot!(
+            env.render_ok(r#"fill(10, "  foo bar baz", keep_indent = false)"#),
+            @r"
+          foo bar
+        baz
+        ");
     }
 
     #[test]
@@ -3083,17 +9635,312 @@ mod tests {
             env.render_ok(r"pad_centered(5, label('red', 'foo'), fill_char=label('cyan', '='))"),
             @"[38;5;6m=[39m[38;5;9mfoo[39m[38;5;6m=[39m");
 
-        // Error in fill char: the output looks odd (because the error message
-        // isn't 1-width character), but is still readable.
+        // Error in fill char: the output looks odd (because the error message
+        // isn't 1-width character), but is still readable.
+        insta::assert_snapshot!(
+            env.render_ok(r"pad_start(3, 'foo', fill_char=bad_string)"),
+            @"foo");
+        insta::assert_snapshot!(
+            env.render_ok(r"pad_end(5, 'foo', fill_char=bad_string)"),
+            @"foo<<Error: Error: Bad>Bad>");
+        insta::assert_snapshot!(
+            env.render_ok(r"pad_centered(5, 'foo', fill_char=bad_string)"),
+            @"<Error: Bad>foo<Error: Bad>");
+    }
+
+    #[test]
+    fn test_string_pad_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
+
+        // Default fill_char is ' '
+        insta::assert_snapshot!(env.render_ok(r"'{' ++ 'foo'.pad_start(5) ++ '}'"), @"{  foo}");
+        insta::assert_snapshot!(env.render_ok(r"'{' ++ 'foo'.pad_end(5) ++ '}'"), @"{foo  }");
+        insta::assert_snapshot!(
+            env.render_ok(r"'{' ++ 'foo'.pad_centered(6) ++ '}'"),
+            @"{ foo  }");
+
+        // Custom fill_char
+        insta::assert_snapshot!(env.render_ok(r"'foo'.pad_start(5, fill_char='=')"), @"==foo");
+        insta::assert_snapshot!(env.render_ok(r"'foo'.pad_end(5, fill_char='=')"), @"foo==");
+        insta::assert_snapshot!(
+            env.render_ok(r"'foo'.pad_centered(5, fill_char='=')"),
+            @"=foo=");
+
+        // No-op if the string is already wide enough
+        insta::assert_snapshot!(env.render_ok(r"'foobar'.pad_start(3)"), @"foobar");
+        insta::assert_snapshot!(env.render_ok(r"'foobar'.pad_end(3)"), @"foobar");
+        insta::assert_snapshot!(env.render_ok(r"'foobar'.pad_centered(6)"), @"foobar");
+
+        // Width is measured in terminal columns, not in bytes or chars
+        insta::assert_snapshot!(
+            env.render_ok(r"'|' ++ '漢字'.pad_start(6) ++ '|' ++ 'abcd'.pad_start(6) ++ '|'"),
+            @"|  漢字|  abcd|");
+        insta::assert_snapshot!(
+            env.render_ok(r"'|' ++ '🦀'.pad_end(4, fill_char='-') ++ '|' ++ 'ab'.pad_end(4)"),
+            @"|🦀--|ab  ");
+        insta::assert_snapshot!(
+            env.render_ok("'|' ++ \"e\u{301}\".pad_centered(3) ++ '|'"),
+            @"| é |");
+
+        // Method chaining
+        insta::assert_snapshot!(env.render_ok(r"'foo'.pad_start(5).len()"), @"5");
+
+        // Error in width or fill char
+        insta::assert_snapshot!(
+            env.render_ok(r"'foo'.pad_start(-1)"),
+            @"<Error: out of range integral type conversion attempted>");
+        insta::assert_snapshot!(
+            env.render_ok(r"'foo'.pad_end(5, fill_char=bad_string)"),
+            @"<Error: Bad>");
+        // Fill char must occupy exactly one column
+        insta::assert_snapshot!(
+            env.render_ok(r"'foo'.pad_start(5, fill_char='')"),
+            @r#"<Error: Fill char must be 1 column wide: "">"#);
+        insta::assert_snapshot!(
+            env.render_ok(r"'foo'.pad_end(5, fill_char='ab')"),
+            @r#"<Error: Fill char must be 1 column wide: "ab">"#);
+        insta::assert_snapshot!(
+            env.render_ok(r"'foo'.pad_centered(5, fill_char='日')"),
+            @r#"<Error: Fill char must be 1 column wide: "日">"#);
+        // Huge width shouldn't abort the process
+        insta::assert_snapshot!(
+            env.render_ok(r"'a'.pad_start(9000000000000000000)"),
+            @"<Error: memory allocation failed because the memory allocator returned an error>");
+        insta::assert_snapshot!(
+            env.render_ok(r"'a'.pad_end(9223372036854775807, fill_char='é')"),
+            @"<Error: Attempt to pad with overflow>");
+
+        insta::assert_snapshot!(env.parse_err(r"'foo'.pad_start()"), @r"
+         --> 1:17
+          |
+        1 | 'foo'.pad_start()
+          |                 ^
+          |
+          = Function `pad_start`: Expected 1 to 2 arguments
+        ");
+    }
+
+    #[test]
+    fn test_string_indent_rest_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || {
+            P::wrap_string(literal("summary\n\nbody 1\nbody 2\n".to_owned()))
+        });
+
+        // Not using insta here because we test whitespace existence.
+        assert_eq!(env.render_ok(r#""".indent_rest("__")"#), "");
+        assert_eq!(env.render_ok(r#""\n".indent_rest("__")"#), "\n");
+
+        // Single line
+        assert_eq!(env.render_ok(r#""a".indent_rest("__")"#), "a");
+        assert_eq!(env.render_ok(r#""a\n".indent_rest("__")"#), "a\n");
+
+        // Multiple lines: empty line and trailing newline aren't prefixed
+        assert_eq!(env.render_ok(r#""a\nb".indent_rest("__")"#), "a\n__b");
+        assert_eq!(
+            env.render_ok(r#""a\n\nb\nc\n".indent_rest("__")"#),
+            "a\n\n__b\n__c\n"
+        );
+        assert_eq!(env.render_ok(r#""\nb".indent_rest("__")"#), "\n__b");
+        insta::assert_snapshot!(
+            env.render_ok(r#""Description: " ++ description.indent_rest(" ".repeat(13))"#),
+            @r"
+        Description: summary
+
+                     body 1
+                     body 2
+        ");
+    }
+
+    #[test]
+    fn test_string_parse_int_method() {
+        let env = TestTemplateEnv::new();
+
+        insta::assert_snapshot!(env.render_ok(r#""42".parse_int()"#), @"42");
+        insta::assert_snapshot!(env.render_ok(r#""-42".parse_int() + 1"#), @"-41");
+        insta::assert_snapshot!(env.render_ok(r#""+7".parse_int()"#), @"7");
+        insta::assert_snapshot!(env.render_ok(r#""ff".parse_int(16)"#), @"255");
+        insta::assert_snapshot!(env.render_ok(r#""FF".parse_int(16)"#), @"255");
+        insta::assert_snapshot!(env.render_ok(r#""101".parse_int(2)"#), @"5");
+        insta::assert_snapshot!(env.render_ok(r#""zz".parse_int(36)"#), @"1295");
+        insta::assert_snapshot!(
+            env.render_ok(r#""9223372036854775807".parse_int()"#),
+            @"9223372036854775807");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""".parse_int()"#),
+            @r#"<Error: Failed to parse "" as integer: cannot parse integer from empty string>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#"" 1".parse_int()"#),
+            @r#"<Error: Failed to parse " 1" as integer: invalid digit found in string>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""1.5".parse_int()"#),
+            @r#"<Error: Failed to parse "1.5" as integer: invalid digit found in string>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""12".parse_int(2)"#),
+            @r#"<Error: Failed to parse "12" as integer: invalid digit found in string>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""9223372036854775808".parse_int()"#),
+            @r#"<Error: Failed to parse "9223372036854775808" as integer: number too large to fit in target type>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""1".parse_int(1)"#),
+            @"<Error: Radix must be between 2 and 36>");
+        insta::assert_snapshot!(
+            env.render_ok(r#""1".parse_int(37)"#),
+            @"<Error: Radix must be between 2 and 36>");
+    }
+
+    #[test]
+    fn test_string_parse_timestamp_method() {
+        let env = TestTemplateEnv::new();
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""2024-01-02 03:04:05 +0900".parse_timestamp("%Y-%m-%d %H:%M:%S %z")"#),
+            @"2024-01-02 03:04:05.000 +09:00");
+        // Time zone defaults to UTC, and time defaults to midnight
+        insta::assert_snapshot!(
+            env.render_ok(r#""2024-01-02 03:04".parse_timestamp("%Y-%m-%d %H:%M")"#),
+            @"2024-01-02 03:04:00.000 +00:00");
+        insta::assert_snapshot!(
+            env.render_ok(r#""2024-01-02".parse_timestamp("%Y-%m-%d")"#),
+            @"2024-01-02 00:00:00.000 +00:00");
+        insta::assert_snapshot!(
+            env.render_ok(r#""1700000000".parse_timestamp("%s")"#),
+            @"2023-11-14 22:13:20.000 +00:00");
+        // Parsed timestamp can be formatted
+        insta::assert_snapshot!(
+            env.render_ok(r#""02/01/2024".parse_timestamp("%d/%m/%Y").format("%b %e, %Y")"#),
+            @"Jan  2, 2024");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""2024-01-02".parse_timestamp("%Y-%m-%d %H:%M")"#),
+            @r#"<Error: Failed to parse "2024-01-02" as timestamp: premature end of input>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""Jan 2".parse_timestamp("%Y-%m-%d")"#),
+            @r#"<Error: Failed to parse "Jan 2" as timestamp: input contains invalid characters>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""2024-13-01".parse_timestamp("%Y-%m-%d")"#),
+            @r#"<Error: Failed to parse "2024-13-01" as timestamp: input is out of range>"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#""2024-01-02 03".parse_timestamp("%Y-%m-%d %H")"#),
+            @r#"<Error: Failed to parse "2024-01-02 03" as timestamp: input is not enough for unique date and time>"#);
+
+        insta::assert_snapshot!(env.parse_err(r#""2024".parse_timestamp("%_")"#), @r#"
+         --> 1:24
+          |
+        1 | "2024".parse_timestamp("%_")
+          |                        ^--^
+          |
+          = Invalid time format
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#""2024".parse_timestamp(fmt)"#), @r#"
+         --> 1:24
+          |
+        1 | "2024".parse_timestamp(fmt)
+          |                        ^-^
+          |
+          = Expected string literal
+        "#);
+    }
+
+    #[test]
+    fn test_string_word_wrap_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || {
+            P::wrap_string(literal(
+                "Lorem ipsum dolor sit amet,\nconsectetur adipiscing elit.\n".to_owned(),
+            ))
+        });
+
+        insta::assert_snapshot!(env.render_ok("description.word_wrap(12)"), @r"
+        Lorem ipsum
+        dolor sit
+        amet,
+        consectetur
+        adipiscing
+        elit.
+        ");
+        insta::assert_snapshot!(env.render_ok("description.word_wrap(100)"), @r"
+        Lorem ipsum dolor sit amet,
+        consectetur adipiscing elit.
+        ");
+
+        // Not using insta here because we test whitespace existence.
+        assert_eq!(env.render_ok(r#""".word_wrap(5)"#), "");
+        assert_eq!(env.render_ok(r#""\n".word_wrap(5)"#), "\n");
+        assert_eq!(
+            env.render_ok(r#""a b\n\nc d\n".word_wrap(1)"#),
+            "a\nb\n\nc\nd\n"
+        );
+        assert_eq!(env.render_ok(r#""  a b".word_wrap(3)"#), "  a\nb");
+        assert_eq!(env.render_ok(r#""a   b".word_wrap(3)"#), "a\nb");
+
+        // Words longer than the width are broken
+        insta::assert_snapshot!(env.render_ok(r#""abcdefghij kl".word_wrap(4)"#), @r"
+        abcd
+        efgh
+        ij
+        kl
+        ");
+        insta::assert_snapshot!(env.render_ok(r#""abc".word_wrap(0)"#), @r"
+        a
+        b
+        c
+        ");
+
+        // CJK characters are 2 columns wide
+        insta::assert_snapshot!(env.render_ok(r#""日本語 の 文章".word_wrap(6)"#), @r"
+        日本語
+        の
+        文章
+        ");
+        insta::assert_snapshot!(env.render_ok(r#""日本語の文章".word_wrap(5)"#), @r"
+        日本
+        語の
+        文章
+        ");
+        insta::assert_snapshot!(env.render_ok(r#""a 日本 b".word_wrap(4)"#), @r"
+        a
+        日本
+        b
+        ");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""a".word_wrap(-1)"#),
+            @"<Error: out of range integral type conversion attempted>");
+    }
+
+    #[test]
+    fn test_string_hash_to_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("email", || {
+            P::wrap_string(literal("test.user@example.com".to_owned()))
+        });
+        env.add_keyword("i64_max", || P::wrap_integer(literal(i64::MAX)));
+
+        // The hash must be stable across runs, versions, and platforms. These
+        // values are the 64-bit FNV-1a hashes modulo n.
+        assert_eq!(fnv1a_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_hash(b"foobar"), 0x85944171f73967e8);
+        insta::assert_snapshot!(env.render_ok("email.hash_to(8)"), @"7");
+        insta::assert_snapshot!(env.render_ok("email.hash_to(1000)"), @"687");
+        insta::assert_snapshot!(env.render_ok("'foobar'.hash_to(i64_max)"), @"402018224477661161");
+        insta::assert_snapshot!(env.render_ok("''.hash_to(7)"), @"2");
+        insta::assert_snapshot!(env.render_ok("'a'.hash_to(1)"), @"0");
+
+        // Same input maps to the same bucket
         insta::assert_snapshot!(
-            env.render_ok(r"pad_start(3, 'foo', fill_char=bad_string)"),
-            @"foo");
+            env.render_ok("email.hash_to(16) == 'test.user@example.com'.hash_to(16)"),
+            @"true");
+
         insta::assert_snapshot!(
-            env.render_ok(r"pad_end(5, 'foo', fill_char=bad_string)"),
-            @"foo<<Error: Error: Bad>Bad>");
+            env.render_ok("email.hash_to(0)"),
+            @"<Error: Bucket count must be greater than 0>");
         insta::assert_snapshot!(
-            env.render_ok(r"pad_centered(5, 'foo', fill_char=bad_string)"),
-            @"<Error: Bad>foo<Error: Bad>");
+            env.render_ok("email.hash_to(-1)"),
+            @"<Error: Bucket count must be greater than 0>");
     }
 
     #[test]
@@ -3183,6 +10030,191 @@ mod tests {
         insta::assert_snapshot!(env.render_ok("stringify(label('error', 'text'))"), @"text");
     }
 
+    #[test]
+    fn test_match_function() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("status", || P::wrap_string(literal("modified".to_owned())));
+        env.add_keyword("count", || P::wrap_integer(literal(2)));
+        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
+        env.add_color("error", crossterm::style::Color::DarkRed);
+        let evaluated = env.add_counting_keyword("recorded");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(status, "added", "A", "modified", "M", "deleted", "D")"#),
+            @"M");
+        insta::assert_snapshot!(env.render_ok(r#"match(count, 1, "one", 2, "two")"#), @"two");
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(count, 1, "one", 2, "two", 2, "dup")"#),
+            @"two");
+
+        // Default fallthrough
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(status, "added", "A", "deleted", "D", "?")"#),
+            @"?");
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(status, "modified", "M", "?")"#),
+            @"M");
+
+        // No match without default renders nothing
+        insta::assert_snapshot!(env.render_ok(r#"match(status, "added", "A")"#), @"");
+
+        // Results of unmatched cases and the remaining cases aren't evaluated
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(count, 1, bad_string, 2, "two", bad_string)"#),
+            @"two");
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(status, "modified", "M", bad_string, "bad")"#),
+            @"M");
+        insta::assert_snapshot!(env.render_ok(r#"match(bad_string, "a", "b")"#), @"[38;5;1m<Error: Bad>[39m");
+
+        // The value is evaluated once per render
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(recorded, 5, "five", 6, "six", 1, "one")"#),
+            @"one");
+        assert_eq!(evaluated.get(), 1);
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(recorded, 5, "five", 2, "two")"#),
+            @"two");
+        assert_eq!(evaluated.get(), 2);
+
+        // Result is a template
+        insta::assert_snapshot!(
+            env.render_ok(r#"match(count, 2, label("error", "two"))"#),
+            @"[38;5;1mtwo[39m");
+
+        insta::assert_snapshot!(env.parse_err(r#"match(status, "a")"#), @r#"
+         --> 1:7
+          |
+        1 | match(status, "a")
+          |       ^---------^
+          |
+          = Function `match`: Expected at least 3 arguments
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"match(status, 1, "one")"#), @r#"
+         --> 1:15
+          |
+        1 | match(status, 1, "one")
+          |               ^
+          |
+          = Cannot compare expressions of type `String` and `Integer`
+        "#);
+    }
+
+    #[test]
+    fn test_if_contains_any_function() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || {
+            P::wrap_string(literal("fix: typo [skip ci]\n".to_owned()))
+        });
+        env.add_keyword("markers", || {
+            P::wrap_string_list(literal(vec![
+                "[skip ci]".to_owned(),
+                "[ci skip]".to_owned(),
+                "[no ci]".to_owned(),
+            ]))
+        });
+        env.add_keyword("empty_list", || P::wrap_string_list(literal(vec![])));
+        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"if_contains_any(description, markers, "skip", "build")"#),
+            @"skip");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if_contains_any("feat: add", markers, "skip", "build")"#),
+            @"build");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if_contains_any("a_b", "x,_,y".split(","), "yes", "no")"#),
+            @"yes");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if_contains_any("feat: add", markers, "skip")"#), @"");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if_contains_any(description, empty_list, "yes", "no")"#), @"no");
+        // Only the selected branch is evaluated
+        insta::assert_snapshot!(
+            env.render_ok(r#"if_contains_any(description, markers, "skip", bad_string)"#),
+            @"skip");
+
+        insta::assert_snapshot!(
+            env.parse_err(r#"if_contains_any(description, "[skip ci]", "skip")"#), @r#"
+         --> 1:30
+          |
+        1 | if_contains_any(description, "[skip ci]", "skip")
+          |                              ^---------^
+          |
+          = Expected expression of type `List<String>`, but actual type is `String`
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"if_contains_any(description, markers)"#), @r"
+         --> 1:17
+          |
+        1 | if_contains_any(description, markers)
+          |                 ^------------------^
+          |
+          = Function `if_contains_any`: Expected 3 to 4 arguments
+        ");
+    }
+
+    #[test]
+    fn test_cond_function() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("count", || P::wrap_integer(literal(2)));
+        env.add_keyword("bad_bool", || P::wrap_boolean(new_error_property("Bad")));
+        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
+        env.add_color("error", crossterm::style::Color::DarkRed);
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"cond(count == 1, "one", count == 2, "two", count > 2, "many")"#),
+            @"two");
+        // The first matching branch wins
+        insta::assert_snapshot!(
+            env.render_ok(r#"cond(count > 0, "positive", count == 2, "two")"#),
+            @"positive");
+
+        // Default fallthrough
+        insta::assert_snapshot!(
+            env.render_ok(r#"cond(count == 0, "zero", count == 1, "one", "other")"#),
+            @"other");
+        insta::assert_snapshot!(env.render_ok(r#"cond(true, "yes", "no")"#), @"yes");
+
+        // No match without default renders nothing
+        insta::assert_snapshot!(env.render_ok(r#"cond(count == 0, "zero")"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"cond(false, "a", false, "b")"#), @"");
+
+        // Conditions and results after the matched branch aren't evaluated
+        insta::assert_snapshot!(
+            env.render_ok(r#"cond(count == 1, bad_string, count == 2, "two", bad_bool, "x")"#),
+            @"two");
+        insta::assert_snapshot!(
+            env.render_ok(r#"cond(bad_bool, "a", "b")"#),
+            @"[38;5;1m<Error: Bad>[39m");
+
+        // Conditions are implicitly converted to Boolean
+        insta::assert_snapshot!(
+            env.render_ok(r#"cond("", "empty", "x", "nonempty")"#),
+            @"nonempty");
+
+        // Result is a template
+        insta::assert_snapshot!(
+            env.render_ok(r#"cond(true, label("error", "two"))"#),
+            @"[38;5;1mtwo[39m");
+
+        insta::assert_snapshot!(env.parse_err(r#"cond(true)"#), @r"
+         --> 1:6
+          |
+        1 | cond(true)
+          |      ^--^
+          |
+          = Function `cond`: Expected at least 2 arguments
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"cond(count, "a")"#), @r#"
+         --> 1:6
+          |
+        1 | cond(count, "a")
+          |      ^---^
+          |
+          = Expected expression of type `Boolean`, but actual type is `Integer`
+        "#);
+    }
+
     #[test]
     fn test_coalesce_function() {
         let mut env = TestTemplateEnv::new();
@@ -3205,6 +10237,255 @@ mod tests {
         insta::assert_snapshot!(env.render_ok(r#"coalesce(bad_string, "a")"#), @"<Error: Bad>");
         // but can be short-circuited
         insta::assert_snapshot!(env.render_ok(r#"coalesce("a", bad_string)"#), @"a");
+
+        // Whitespace-only string can be omitted by trimming
+        insta::assert_snapshot!(
+            env.render_ok(r#"coalesce(" ".trim(), non_empty_string)"#), @"a");
+    }
+
+    #[test]
+    fn test_coalesce_function_lazy_evaluation() {
+        let mut env = TestTemplateEnv::new();
+        let evaluated = env.add_counting_keyword("recorded");
+
+        // Later arguments shouldn't be evaluated once non-empty content is found
+        insta::assert_snapshot!(env.render_ok(r#"coalesce("a", recorded, recorded)"#), @"a");
+        assert_eq!(evaluated.get(), 0);
+        insta::assert_snapshot!(env.render_ok(r#"coalesce("", recorded, recorded)"#), @"1");
+        assert_eq!(evaluated.get(), 1);
+    }
+
+    #[test]
+    fn test_let_binding() {
+        let mut env = TestTemplateEnv::new();
+        let evaluated = env.add_counting_keyword("recorded");
+        env.add_color("error", crossterm::style::Color::DarkRed);
+
+        // Bound value should be evaluated once per evaluation of the body
+        insta::assert_snapshot!(env.render_ok(r#"let x = recorded; x ++ " " ++ x"#), @"1 1");
+        assert_eq!(evaluated.get(), 1);
+        insta::assert_snapshot!(
+            env.render_ok(r#"let x = recorded; (x + 1) ++ " " ++ x.to_string().len()"#),
+            @"3 1");
+        assert_eq!(evaluated.get(), 2);
+        insta::assert_snapshot!(env.render_ok(r#"recorded ++ " " ++ recorded"#), @"3 4");
+        assert_eq!(evaluated.get(), 4);
+
+        // Unused value shouldn't be evaluated
+        insta::assert_snapshot!(env.render_ok(r#"let x = recorded; "a""#), @"a");
+        assert_eq!(evaluated.get(), 4);
+
+        // Body is re-evaluated per list item
+        insta::assert_snapshot!(
+            env.render_ok(r#""a b".split(" ").map(|s| let n = recorded; s ++ n ++ n)"#),
+            @"a55 b66");
+        assert_eq!(evaluated.get(), 6);
+        insta::assert_snapshot!(
+            env.render_ok(r#"let n = recorded; "a b".split(" ").map(|s| s ++ n)"#),
+            @"a7 b7");
+        assert_eq!(evaluated.get(), 7);
+
+        // Template value is recorded with labels
+        insta::assert_snapshot!(
+            env.render_ok(r#"let x = label("error", recorded); x ++ x"#),
+            @"[38;5;1m8[39m[38;5;1m8[39m");
+        assert_eq!(evaluated.get(), 8);
+
+        // Nested and shadowed bindings
+        insta::assert_snapshot!(
+            env.render_ok(r#"let x = "a"; let y = x ++ "b"; let x = x.upper(); x ++ y"#),
+            @"Aab");
+
+        // Variable isn't visible outside of the body
+        insta::assert_snapshot!(env.parse_err(r#"(let x = "a"; x) ++ x"#), @r#"
+         --> 1:21
+          |
+        1 | (let x = "a"; x) ++ x
+          |                     ^
+          |
+          = Keyword `x` doesn't exist
+        "#);
+
+        // Unsupported type
+        insta::assert_snapshot!(
+            env.parse_err(r#"let x = "a b".split(" ").map(|s| s); x"#),
+            @r#"
+         --> 1:9
+          |
+        1 | let x = "a b".split(" ").map(|s| s); x
+          |         ^-------------------------^
+          |
+          = Expression of type `ListTemplate` cannot be bound to a variable
+        "#);
+    }
+
+    #[test]
+    fn test_string_default_method() {
+        let mut env = TestTemplateEnv::new();
+        let evaluated = env.add_counting_keyword("recorded");
+        env.add_keyword("empty", || {
+            P::wrap_string(literal(String::new()).into_dyn())
+        });
+        env.add_color("error", crossterm::style::Color::DarkRed);
+
+        insta::assert_snapshot!(env.render_ok(r#""a".default("b")"#), @"a");
+        insta::assert_snapshot!(env.render_ok(r#"empty.default("b")"#), @"b");
+        insta::assert_snapshot!(
+            env.render_ok(r#"empty.default(label("error", "b"))"#),
+            @"[38;5;1mb[39m");
+        insta::assert_snapshot!(env.render_ok(r#"empty.default(empty)"#), @"");
+
+        // Whitespace isn't considered empty
+        insta::assert_snapshot!(env.render_ok(r#""[" ++ " ".default("b") ++ "]""#), @"[ ]");
+
+        // The fallback shouldn't be evaluated if the value isn't empty
+        insta::assert_snapshot!(env.render_ok(r#""a".default(recorded)"#), @"a");
+        assert_eq!(evaluated.get(), 0);
+        insta::assert_snapshot!(env.render_ok(r#"empty.default(recorded)"#), @"1");
+        assert_eq!(evaluated.get(), 1);
+
+        insta::assert_snapshot!(env.parse_err(r#""a".default()"#), @r#"
+         --> 1:13
+          |
+        1 | "a".default()
+          |             ^
+          |
+          = Function `default`: Expected 1 arguments
+        "#);
+    }
+
+    #[test]
+    fn test_optional_map_or_method() {
+        let mut env = TestTemplateEnv::new();
+        let evaluated = env.add_counting_keyword("recorded");
+        env.add_keyword("none_i64", || P::wrap_integer_opt(literal(None)));
+        env.add_keyword("some_i64", || P::wrap_integer_opt(literal(Some(42))));
+        env.add_keyword("none_str", || P::wrap_string_opt(literal(None)));
+        env.add_keyword("some_str", || {
+            P::wrap_string_opt(literal(Some("foo".to_owned())))
+        });
+        env.add_keyword("empty_str", || {
+            P::wrap_string_opt(literal(Some(String::new())))
+        });
+        env.add_keyword("bad_i64", || {
+            P::wrap_integer_opt(new_error_property::<Option<i64>>("Bad"))
+        });
+
+        insta::assert_snapshot!(env.render_ok(r#"some_i64.map_or("none", |x| x + 1)"#), @"43");
+        insta::assert_snapshot!(env.render_ok(r#"none_i64.map_or("none", |x| x + 1)"#), @"none");
+        insta::assert_snapshot!(env.render_ok(r#"some_str.map_or("none", |s| s.upper())"#), @"FOO");
+        insta::assert_snapshot!(
+            env.render_ok(r#"none_str.map_or("none", |s| s.upper())"#),
+            @"none");
+        // Empty string is a present value
+        insta::assert_snapshot!(
+            env.render_ok(r#"empty_str.map_or("none", |s| "[" ++ s ++ "]")"#),
+            @"[]");
+        insta::assert_snapshot!(env.render_ok(r#"bad_i64.map_or("none", |x| x)"#), @"<Error: Bad>");
+
+        // The transform only runs if the value is present
+        insta::assert_snapshot!(env.render_ok(r#"none_str.map_or("none", |s| recorded)"#), @"none");
+        assert_eq!(evaluated.get(), 0);
+        insta::assert_snapshot!(
+            env.render_ok(r#"some_str.map_or("none", |s| s ++ recorded)"#),
+            @"foo1");
+        assert_eq!(evaluated.get(), 1);
+        // The default is evaluated only if the value is absent
+        insta::assert_snapshot!(env.render_ok(r#"some_str.map_or(recorded, |s| s)"#), @"foo");
+        assert_eq!(evaluated.get(), 1);
+
+        insta::assert_snapshot!(env.parse_err(r#"some_str.map_or("none")"#), @r#"
+         --> 1:17
+          |
+        1 | some_str.map_or("none")
+          |                 ^----^
+          |
+          = Function `map_or`: Expected 2 arguments
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"some_str.map_or("none", "x")"#), @r#"
+         --> 1:25
+          |
+        1 | some_str.map_or("none", "x")
+          |                         ^-^
+          |
+          = Expected lambda expression
+        "#);
+    }
+
+    #[test]
+    fn test_list_any_all_lazy_evaluation() {
+        let mut env = TestTemplateEnv::new();
+        let evaluated = env.add_counting_keyword("recorded");
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().any(|s| recorded > 0)"#),
+            @"true");
+        assert_eq!(evaluated.replace(0), 1);
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().all(|s| recorded < 0)"#),
+            @"false");
+        assert_eq!(evaluated.replace(0), 1);
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb\nc".lines().all(|s| recorded > 0)"#),
+            @"true");
+        assert_eq!(evaluated.replace(0), 3);
+    }
+
+    #[test]
+    fn test_json_function() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
+        env.add_keyword("description", || {
+            P::wrap_string(literal("quoted \"text\"\n".to_owned()))
+        });
+        env.add_keyword("sl0", || P::wrap_string_list(literal(vec![])));
+        env.add_keyword("sl1", || {
+            P::wrap_string_list(literal(vec!["a".to_owned(), "b".to_owned()]))
+        });
+        env.add_color("red", crossterm::style::Color::Red);
+
+        insta::assert_snapshot!(env.render_ok(r#"json()"#), @"{}");
+        insta::assert_snapshot!(
+            env.render_ok(r#"json(int=-1, float=1.5, bool=true, str="a", empty="")"#),
+            @r#"{"int":-1,"float":1.5,"bool":true,"str":"a","empty":""}"#);
+
+        // Strings are escaped
+        insta::assert_snapshot!(
+            env.render_ok(r#"json(description=description)"#),
+            @r#"{"description":"quoted \"text\"\n"}"#);
+        insta::assert_snapshot!(
+            env.render_ok(r#"json(tab="\t", esc="\e")"#),
+            @r#"{"tab":"\t","esc":"\u001b"}"#);
+
+        // Non-primitive values are stringified, and labels are stripped
+        insta::assert_snapshot!(
+            env.render_ok(r#"json(list=sl1, empty_list=sl0, label=label("red", "a"))"#),
+            @r#"{"list":"a b","empty_list":"","label":"a"}"#);
+
+        // Nested object
+        insta::assert_snapshot!(
+            env.render_ok(r#"json(a=json(b=json(), c=1), d=2)"#),
+            @r#"{"a":{"b":{},"c":1},"d":2}"#);
+
+        // Error in value
+        insta::assert_snapshot!(env.render_ok(r#"json(s=bad_string)"#), @"<Error: Bad>");
+
+        insta::assert_snapshot!(env.parse_err(r#"json("a")"#), @r#"
+         --> 1:6
+          |
+        1 | json("a")
+          |      ^-^
+          |
+          = Function `json`: Expected keyword arguments
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"json(a=1, a=2)"#), @r#"
+         --> 1:11
+          |
+        1 | json(a=1, a=2)
+          |           ^-^
+          |
+          = Function `json`: Got multiple values for keyword "a"
+        "#);
     }
 
     #[test]
@@ -3223,6 +10504,101 @@ mod tests {
         insta::assert_snapshot!(
             env.render_ok(r#"concat(label("error", ""), label("warning", "a"), "b")"#),
             @"[38;5;3ma[39mb");
+
+        // Concat operator preserves labels of each part
+        insta::assert_snapshot!(
+            env.render_ok(r#"label("error", "a") ++ "b" ++ label("warning", "c")"#),
+            @"[38;5;1ma[39mb[38;5;3mc[39m");
+        insta::assert_snapshot!(
+            env.render_ok(r#"label("error", "a" ++ label("warning", "b") ++ "c")"#),
+            @"[38;5;1ma[38;5;3mb[38;5;1mc[39m");
+
+        // Error in one part doesn't prevent the others from being rendered
+        env.add_keyword("bad_string", || P::wrap_string(new_error_property("Bad")));
+        insta::assert_snapshot!(
+            env.render_ok(r#"concat("a", bad_string, label("warning", "b"))"#),
+            @"a[38;5;1m<Error: Bad>[39m[38;5;3mb[39m");
+    }
+
+    #[test]
+    fn test_format_function() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("empty", || P::wrap_boolean(literal(true)));
+        env.add_keyword("name", || P::wrap_string(literal("foo".to_owned())));
+        env.add_color("error", crossterm::style::Color::DarkRed);
+
+        insta::assert_snapshot!(env.render_ok(r#"format("")"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"format("plain")"#), @"plain");
+        insta::assert_snapshot!(env.render_ok(r#"format("{}", name)"#), @"foo");
+        insta::assert_snapshot!(
+            env.render_ok(r#"format("{} is {} ({}).", name, empty, 1 + 2)"#),
+            @"foo is true (3).");
+        insta::assert_snapshot!(env.render_ok(r#"format("{}{}", "a", "")"#), @"a");
+        insta::assert_snapshot!(
+            env.render_ok(r#"format("[{}]", label("error", name))"#),
+            @"[[38;5;1mfoo[39m]");
+
+        // Escaped braces
+        insta::assert_snapshot!(env.render_ok(r#"format("{{}}")"#), @"{}");
+        insta::assert_snapshot!(env.render_ok(r#"format("{{{}}}", name)"#), @"{foo}");
+        insta::assert_snapshot!(env.render_ok(r#"format("}}{{ {} {{x}}", name)"#), @"}{ foo {x}");
+
+        insta::assert_snapshot!(env.parse_err(r#"format()"#), @r"
+         --> 1:8
+          |
+        1 | format()
+          |        ^
+          |
+          = Function `format`: Expected at least 1 arguments
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"format("{}")"#), @r#"
+         --> 1:8
+          |
+        1 | format("{}")
+          |        ^--^
+          |
+          = Function `format`: Expected 1 arguments for the format string, but got 0
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"format("{}", "a", "b")"#), @r#"
+         --> 1:8
+          |
+        1 | format("{}", "a", "b")
+          |        ^------------^
+          |
+          = Function `format`: Expected 1 arguments for the format string, but got 2
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"format("a}", "b")"#), @r#"
+         --> 1:8
+          |
+        1 | format("a}", "b")
+          |        ^--^
+          |
+          = Unmatched `}` in format string (use `}}` to escape)
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"format("{", "b")"#), @r#"
+         --> 1:8
+          |
+        1 | format("{", "b")
+          |        ^-^
+          |
+          = Unmatched `{` in format string (use `{{` to escape)
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"format("{0}", "b")"#), @r#"
+         --> 1:8
+          |
+        1 | format("{0}", "b")
+          |        ^---^
+          |
+          = Unmatched `{` in format string (use `{{` to escape)
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"format(name, "b")"#), @r#"
+         --> 1:8
+          |
+        1 | format(name, "b")
+          |        ^--^
+          |
+          = Expected string literal
+        "#);
     }
 
     #[test]
@@ -3247,6 +10623,11 @@ mod tests {
         insta::assert_snapshot!(
             env.render_ok(r#"separate(" ", label("error", ""), label("warning", "a"), "b")"#),
             @"[38;5;3ma[39m b");
+        insta::assert_snapshot!(
+            env.render_ok(
+                r#"separate(", ", label("error", "a"), "", label("warning", ""), description,
+                                 label("warning", "b"), "c", "")"#),
+            @"[38;5;1ma[39m, [38;5;3mb[39m, c");
 
         // List template
         insta::assert_snapshot!(env.render_ok(r#"separate(" ", "a", ("" ++ ""))"#), @"a");
@@ -3315,5 +10696,32 @@ mod tests {
         insta::assert_snapshot!(
             env.render_ok(r#"surround(lt, gt, if(empty_content, "not empty", ""))"#),
             @"");
+
+        // Method on String
+        insta::assert_snapshot!(env.render_ok(r#"content.surround("[", "]")"#), @"[content]");
+        insta::assert_snapshot!(env.render_ok(r#"empty_content.surround("[", "]")"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"content.upper().surround(lt, gt)"#), @"<CONTENT>");
+
+        // Method on Template
+        insta::assert_snapshot!(
+            env.render_ok(r#"label("error", content).surround(label("paren", "("), ")")"#),
+            @"[38;5;14m([38;5;1mcontent[39m)");
+        insta::assert_snapshot!(
+            env.render_ok(r#"label("error", empty_content).surround("(", ")")"#),
+            @"");
+        insta::assert_snapshot!(
+            env.render_ok(r#"(lt ++ content).surround("(", ")").surround("[", "]")"#),
+            @"[(<content)]");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if(empty_content, "x").surround("(", ")")"#),
+            @"");
+        insta::assert_snapshot!(env.parse_err(r#"content.surround("[")"#), @r#"
+         --> 1:18
+          |
+        1 | content.surround("[")
+          |                  ^-^
+          |
+          = Function `surround`: Expected 2 arguments
+        "#);
     }
 }
@@ -12,9 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::error;
 use std::mem;
+use std::ops::Range;
 
 use itertools::Itertools as _;
 use jj_lib::dsl_util;
@@ -31,6 +33,7 @@ use jj_lib::dsl_util::ExpressionFolder;
 use jj_lib::dsl_util::FoldableExpression;
 use jj_lib::dsl_util::FunctionCallParser;
 use jj_lib::dsl_util::InvalidArguments;
+use jj_lib::dsl_util::InvalidStringEscape;
 use jj_lib::dsl_util::StringLiteralParser;
 use once_cell::sync::Lazy;
 use pest::iterators::Pair;
@@ -64,12 +67,14 @@ impl Rule {
             Rule::EOI => None,
             Rule::whitespace => None,
             Rule::string_escape => None,
+            Rule::invalid_string_escape => None,
             Rule::string_content_char => None,
             Rule::string_content => None,
             Rule::string_literal => None,
             Rule::raw_string_content => None,
             Rule::raw_string_literal => None,
             Rule::integer_literal => None,
+            Rule::float_literal => None,
             Rule::identifier => None,
             Rule::concat_op => Some("++"),
             Rule::logical_or_op => Some("||"),
@@ -80,6 +85,11 @@ impl Rule {
             Rule::gt_op => Some(">"),
             Rule::le_op => Some("<="),
             Rule::lt_op => Some("<"),
+            Rule::add_op => Some("+"),
+            Rule::sub_op => Some("-"),
+            Rule::mul_op => Some("*"),
+            Rule::div_op => Some("/"),
+            Rule::rem_op => Some("%"),
             Rule::logical_not_op => Some("!"),
             Rule::negate_op => Some("-"),
             Rule::prefix_ops => None,
@@ -89,6 +99,8 @@ impl Rule {
             Rule::argument => None,
             Rule::function_arguments => None,
             Rule::lambda => None,
+            Rule::let_keyword => None,
+            Rule::let_binding => None,
             Rule::formal_parameters => None,
             Rule::primary => None,
             Rule::term => None,
@@ -102,8 +114,39 @@ impl Rule {
 }
 
 /// Manages diagnostic messages emitted during template parsing and building.
+///
+/// The collected messages are warnings. Errors are reported as
+/// `TemplateParseResult::Err`.
 pub type TemplateDiagnostics = Diagnostics<TemplateParseError>;
 
+/// Extension methods for `TemplateDiagnostics`.
+pub trait TemplateDiagnosticsExt {
+    /// Iterates over the collected messages with their locations.
+    fn located(&self) -> impl Iterator<Item = TemplateDiagnostic<'_>>;
+}
+
+impl TemplateDiagnosticsExt for TemplateDiagnostics {
+    fn located(&self) -> impl Iterator<Item = TemplateDiagnostic<'_>> {
+        self.iter()
+            .map(|err| err.to_diagnostic(TemplateDiagnosticSeverity::Warning))
+    }
+}
+
+/// Diagnostic message with the location in the source text, which is
+/// suitable for editor integration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TemplateDiagnostic<'a> {
+    pub byte_range: Range<usize>,
+    pub severity: TemplateDiagnosticSeverity,
+    pub message: Cow<'a, str>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TemplateDiagnosticSeverity {
+    Error,
+    Warning,
+}
+
 pub type TemplateParseResult<T> = Result<T, TemplateParseError>;
 
 #[derive(Debug, Error)]
@@ -112,6 +155,7 @@ pub struct TemplateParseError {
     kind: TemplateParseErrorKind,
     pest_error: Box<pest::error::Error<Rule>>,
     source: Option<Box<dyn error::Error + Send + Sync>>,
+    hint: Option<String>,
 }
 
 #[derive(Clone, Debug, Eq, Error, PartialEq)]
@@ -136,6 +180,8 @@ pub enum TemplateParseErrorKind {
     },
     #[error("Function `{name}`: {message}")]
     InvalidArguments { name: String, message: String },
+    #[error("Cannot compare expressions of type `{lhs}` and `{rhs}`")]
+    IncomparableTypes { lhs: String, rhs: String },
     #[error("Redefinition of function parameter")]
     RedefinedFunctionParameter,
     #[error("{0}")]
@@ -159,6 +205,7 @@ impl TemplateParseError {
             kind,
             pest_error,
             source: None,
+            hint: None,
         }
     }
 
@@ -167,6 +214,21 @@ impl TemplateParseError {
         self
     }
 
+    /// Attaches additional `hint` message, which is displayed along with the
+    /// hint derived from the error kind.
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Replaces the error kind and span, but keeps the hint.
+    pub(crate) fn with_kind(self, kind: TemplateParseErrorKind, span: pest::Span<'_>) -> Self {
+        TemplateParseError {
+            hint: self.hint,
+            ..TemplateParseError::with_span(kind, span)
+        }
+    }
+
     // TODO: migrate all callers to table-based lookup_method()
     pub(crate) fn no_such_method(
         type_name: impl Into<String>,
@@ -225,6 +287,25 @@ impl TemplateParseError {
         self
     }
 
+    /// If this is a `NoSuchMethod` error, expands the candidates list with the
+    /// given `other_methods`.
+    pub fn extend_method_candidates<I>(mut self, other_methods: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        if let TemplateParseErrorKind::NoSuchMethod {
+            name, candidates, ..
+        } = &mut self.kind
+        {
+            let other_candidates = collect_similar(name, other_methods);
+            *candidates = itertools::merge(mem::take(candidates), other_candidates)
+                .dedup()
+                .collect();
+        }
+        self
+    }
+
     /// Expands keyword/function candidates with the given aliases.
     pub fn extend_alias_candidates(self, aliases_map: &TemplateAliasesMap) -> Self {
         self.extend_keyword_candidates(aliases_map.symbol_names())
@@ -235,6 +316,37 @@ impl TemplateParseError {
         &self.kind
     }
 
+    /// Error message without the source location.
+    pub fn message(&self) -> Cow<'_, str> {
+        self.pest_error.variant.message()
+    }
+
+    /// Byte range in the source text where this error occurred.
+    ///
+    /// If the error occurred in an alias expression, the range points to the
+    /// alias reference in the source text. The error in the alias definition
+    /// can be obtained by `origin()`.
+    pub fn byte_range(&self) -> Range<usize> {
+        match self.pest_error.location {
+            pest::error::InputLocation::Pos(pos) => pos..pos,
+            pest::error::InputLocation::Span((start, end)) => start..end,
+        }
+    }
+
+    /// Converts this error to diagnostic message of the given `severity`.
+    pub fn to_diagnostic(&self, severity: TemplateDiagnosticSeverity) -> TemplateDiagnostic<'_> {
+        TemplateDiagnostic {
+            byte_range: self.byte_range(),
+            severity,
+            message: self.message(),
+        }
+    }
+
+    /// Additional hint message attached by `with_hint()`.
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
     /// Original parsing error which typically occurred in an alias expression.
     pub fn origin(&self) -> Option<&Self> {
         self.source.as_ref().and_then(|e| e.downcast_ref())
@@ -267,6 +379,7 @@ impl From<pest::error::Error<Rule>> for TemplateParseError {
             kind: TemplateParseErrorKind::SyntaxError,
             pest_error: Box::new(rename_rules_in_pest_error(err)),
             source: None,
+            hint: None,
         }
     }
 }
@@ -281,6 +394,12 @@ impl From<InvalidArguments<'_>> for TemplateParseError {
     }
 }
 
+impl From<InvalidStringEscape<'_>> for TemplateParseError {
+    fn from(err: InvalidStringEscape<'_>) -> Self {
+        Self::expression(err.message, err.span)
+    }
+}
+
 fn rename_rules_in_pest_error(err: pest::error::Error<Rule>) -> pest::error::Error<Rule> {
     err.renamed_rules(|rule| {
         rule.to_symbol()
@@ -294,6 +413,7 @@ pub enum ExpressionKind<'i> {
     Identifier(&'i str),
     Boolean(bool),
     Integer(i64),
+    Float(f64),
     String(String),
     Unary(UnaryOp, Box<ExpressionNode<'i>>),
     Binary(BinaryOp, Box<ExpressionNode<'i>>, Box<ExpressionNode<'i>>),
@@ -301,6 +421,7 @@ pub enum ExpressionKind<'i> {
     FunctionCall(Box<FunctionCallNode<'i>>),
     MethodCall(Box<MethodCallNode<'i>>),
     Lambda(Box<LambdaNode<'i>>),
+    Let(Box<LetNode<'i>>),
     /// Identity node to preserve the span in the source template text.
     AliasExpanded(AliasId<'i>, Box<ExpressionNode<'i>>),
 }
@@ -312,9 +433,10 @@ impl<'i> FoldableExpression<'i> for ExpressionKind<'i> {
     {
         match self {
             ExpressionKind::Identifier(name) => folder.fold_identifier(name, span),
-            ExpressionKind::Boolean(_) | ExpressionKind::Integer(_) | ExpressionKind::String(_) => {
-                Ok(self)
-            }
+            ExpressionKind::Boolean(_)
+            | ExpressionKind::Integer(_)
+            | ExpressionKind::Float(_)
+            | ExpressionKind::String(_) => Ok(self),
             ExpressionKind::Unary(op, arg) => {
                 let arg = Box::new(folder.fold_expression(*arg)?);
                 Ok(ExpressionKind::Unary(op, arg))
@@ -344,6 +466,15 @@ impl<'i> FoldableExpression<'i> for ExpressionKind<'i> {
                 });
                 Ok(ExpressionKind::Lambda(lambda))
             }
+            ExpressionKind::Let(let_node) => {
+                let let_node = Box::new(LetNode {
+                    name: let_node.name,
+                    name_span: let_node.name_span,
+                    value: folder.fold_expression(let_node.value)?,
+                    body: folder.fold_expression(let_node.body)?,
+                });
+                Ok(ExpressionKind::Let(let_node))
+            }
             ExpressionKind::AliasExpanded(id, subst) => {
                 let subst = Box::new(folder.fold_expression(*subst)?);
                 Ok(ExpressionKind::AliasExpanded(id, subst))
@@ -392,6 +523,16 @@ pub enum BinaryOp {
     Le,
     /// `<`
     Lt,
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Rem,
 }
 
 pub type ExpressionNode<'i> = dsl_util::ExpressionNode<'i, ExpressionKind<'i>>;
@@ -410,6 +551,15 @@ pub struct LambdaNode<'i> {
     pub body: ExpressionNode<'i>,
 }
 
+/// `let name = value; body` expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LetNode<'i> {
+    pub name: &'i str,
+    pub name_span: pest::Span<'i>,
+    pub value: ExpressionNode<'i>,
+    pub body: ExpressionNode<'i>,
+}
+
 fn parse_identifier_or_literal(pair: Pair<Rule>) -> ExpressionKind {
     assert_eq!(pair.as_rule(), Rule::identifier);
     match pair.as_str() {
@@ -460,6 +610,36 @@ fn parse_lambda_node(pair: Pair<Rule>) -> TemplateParseResult<LambdaNode> {
     })
 }
 
+fn parse_let_node(pair: Pair<Rule>) -> TemplateParseResult<LetNode> {
+    assert_eq!(pair.as_rule(), Rule::let_binding);
+    let mut inner = pair.into_inner();
+    let name_pair = inner.next().unwrap();
+    let name_span = name_pair.as_span();
+    let value_pair = inner.next().unwrap();
+    let body_pair = inner.next().unwrap();
+    let name = parse_identifier_name(name_pair)?;
+    let value = parse_template_node(value_pair)?;
+    let body = parse_template_node(body_pair)?;
+    Ok(LetNode {
+        name,
+        name_span,
+        value,
+        body,
+    })
+}
+
+/// Reports invalid escape sequences which the string literal parser can't
+/// process.
+fn check_string_escapes(mut pairs: Pairs<Rule>) -> TemplateParseResult<()> {
+    match pairs.find(|part| part.as_rule() == Rule::invalid_string_escape) {
+        Some(part) => Err(TemplateParseError::expression(
+            "Invalid escape sequence",
+            part.as_span(),
+        )),
+        None => Ok(()),
+    }
+}
+
 fn parse_term_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode> {
     assert_eq!(pair.as_rule(), Rule::term);
     let mut inner = pair.into_inner();
@@ -467,7 +647,8 @@ fn parse_term_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode> {
     let span = expr.as_span();
     let primary = match expr.as_rule() {
         Rule::string_literal => {
-            let text = STRING_LITERAL_PARSER.parse(expr.into_inner());
+            check_string_escapes(expr.clone().into_inner())?;
+            let text = STRING_LITERAL_PARSER.try_parse(expr.into_inner())?;
             ExpressionNode::new(ExpressionKind::String(text), span)
         }
         Rule::raw_string_literal => {
@@ -477,11 +658,27 @@ fn parse_term_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode> {
             ExpressionNode::new(ExpressionKind::String(text), span)
         }
         Rule::integer_literal => {
-            let value = expr.as_str().parse().map_err(|err| {
+            let text = expr.as_str();
+            let (digits, radix) = if let Some(digits) = text.strip_prefix("0x") {
+                (digits, 16)
+            } else if let Some(digits) = text.strip_prefix("0o") {
+                (digits, 8)
+            } else if let Some(digits) = text.strip_prefix("0b") {
+                (digits, 2)
+            } else {
+                (text, 10)
+            };
+            let value = i64::from_str_radix(digits, radix).map_err(|err| {
                 TemplateParseError::expression("Invalid integer literal", span).with_source(err)
             })?;
             ExpressionNode::new(ExpressionKind::Integer(value), span)
         }
+        Rule::float_literal => {
+            let value = expr.as_str().parse().map_err(|err| {
+                TemplateParseError::expression("Invalid float literal", span).with_source(err)
+            })?;
+            ExpressionNode::new(ExpressionKind::Float(value), span)
+        }
         Rule::identifier => ExpressionNode::new(parse_identifier_or_literal(expr), span),
         Rule::function => {
             let function = Box::new(FUNCTION_CALL_PARSER.parse(
@@ -495,6 +692,10 @@ fn parse_term_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode> {
             let lambda = Box::new(parse_lambda_node(expr)?);
             ExpressionNode::new(ExpressionKind::Lambda(lambda), span)
         }
+        Rule::let_binding => {
+            let let_node = Box::new(parse_let_node(expr)?);
+            ExpressionNode::new(ExpressionKind::Let(let_node), span)
+        }
         Rule::template => parse_template_node(expr)?,
         other => panic!("unexpected term: {other:?}"),
     };
@@ -527,6 +728,10 @@ fn parse_expression_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode
                 | Op::infix(Rule::gt_op, Assoc::Left)
                 | Op::infix(Rule::le_op, Assoc::Left)
                 | Op::infix(Rule::lt_op, Assoc::Left))
+            .op(Op::infix(Rule::add_op, Assoc::Left) | Op::infix(Rule::sub_op, Assoc::Left))
+            .op(Op::infix(Rule::mul_op, Assoc::Left)
+                | Op::infix(Rule::div_op, Assoc::Left)
+                | Op::infix(Rule::rem_op, Assoc::Left))
             .op(Op::prefix(Rule::logical_not_op) | Op::prefix(Rule::negate_op))
     });
     PRATT
@@ -552,6 +757,11 @@ fn parse_expression_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode
                 Rule::gt_op => BinaryOp::Gt,
                 Rule::le_op => BinaryOp::Le,
                 Rule::lt_op => BinaryOp::Lt,
+                Rule::add_op => BinaryOp::Add,
+                Rule::sub_op => BinaryOp::Sub,
+                Rule::mul_op => BinaryOp::Mul,
+                Rule::div_op => BinaryOp::Div,
+                Rule::rem_op => BinaryOp::Rem,
                 r => panic!("unexpected infix operator rule {r:?}"),
             };
             let lhs = Box::new(lhs?);
@@ -581,6 +791,11 @@ fn parse_template_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode>
     }
 }
 
+/// Default limit of the expression nesting depth. The alias expansion, the
+/// template builder, and the evaluator are recursive, so deeply nested
+/// expressions could overflow the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
 /// Parses text into AST nodes. No type/name checking is made at this stage.
 pub fn parse_template(template_text: &str) -> TemplateParseResult<ExpressionNode> {
     let mut pairs: Pairs<Rule> = TemplateParser::parse(Rule::program, template_text)?;
@@ -636,13 +851,87 @@ impl AliasDefinitionParser for TemplateAliasParser {
 
 /// Parses text into AST nodes, and expands aliases.
 ///
-/// No type/name checking is made at this stage.
+/// No type/name checking is made at this stage. Expressions nested deeper than
+/// [`DEFAULT_MAX_DEPTH`] are rejected.
 pub fn parse<'i>(
     template_text: &'i str,
     aliases_map: &'i TemplateAliasesMap,
+) -> TemplateParseResult<ExpressionNode<'i>> {
+    parse_with_max_depth(template_text, aliases_map, DEFAULT_MAX_DEPTH)
+}
+
+/// Parses text into AST nodes, and expands aliases. Expressions nested deeper
+/// than `max_depth` are rejected.
+///
+/// The depth is checked before and after expanding aliases, so the expansion
+/// and the later stages won't recurse deeper than the limit.
+pub fn parse_with_max_depth<'i>(
+    template_text: &'i str,
+    aliases_map: &'i TemplateAliasesMap,
+    max_depth: usize,
 ) -> TemplateParseResult<ExpressionNode<'i>> {
     let node = parse_template(template_text)?;
-    dsl_util::expand_aliases(node, aliases_map)
+    check_expression_depth(&node, max_depth)?;
+    let node = dsl_util::expand_aliases(node, aliases_map)?;
+    check_expression_depth(&node, max_depth)?;
+    Ok(node)
+}
+
+/// Checks that the expression tree is not nested deeper than `max_depth`.
+///
+/// Alias expansion nodes don't count towards the depth.
+fn check_expression_depth(node: &ExpressionNode, max_depth: usize) -> TemplateParseResult<()> {
+    check_expression_depth_rec(node, max_depth, max_depth)
+}
+
+fn check_expression_depth_rec(
+    node: &ExpressionNode,
+    remaining: usize,
+    max_depth: usize,
+) -> TemplateParseResult<()> {
+    let Some(remaining_children) = remaining.checked_sub(1) else {
+        let message = format!("Template expression is nested too deeply (limit: {max_depth})");
+        return Err(TemplateParseError::expression(message, node.span));
+    };
+    let check = |node| check_expression_depth_rec(node, remaining_children, max_depth);
+    match &node.kind {
+        ExpressionKind::Identifier(_)
+        | ExpressionKind::Boolean(_)
+        | ExpressionKind::Integer(_)
+        | ExpressionKind::Float(_)
+        | ExpressionKind::String(_) => Ok(()),
+        ExpressionKind::Unary(_, arg_node) => check(arg_node),
+        ExpressionKind::Binary(_, lhs_node, rhs_node) => {
+            check(lhs_node)?;
+            check(rhs_node)
+        }
+        ExpressionKind::Concat(nodes) => nodes.iter().try_for_each(check),
+        ExpressionKind::FunctionCall(function) => {
+            function.args.iter().try_for_each(check)?;
+            function
+                .keyword_args
+                .iter()
+                .try_for_each(|arg| check(&arg.value))
+        }
+        ExpressionKind::MethodCall(method) => {
+            check(&method.object)?;
+            method.function.args.iter().try_for_each(check)?;
+            method
+                .function
+                .keyword_args
+                .iter()
+                .try_for_each(|arg| check(&arg.value))
+        }
+        ExpressionKind::Lambda(lambda) => check(&lambda.body),
+        ExpressionKind::Let(let_node) => {
+            check(&let_node.value)?;
+            check(&let_node.body)
+        }
+        ExpressionKind::AliasExpanded(id, subst) => {
+            check_expression_depth_rec(subst, remaining, max_depth)
+                .map_err(|e| e.within_alias_expansion(*id, node.span))
+        }
+    }
 }
 
 /// Applies the given function if the `node` is a string literal.
@@ -655,12 +944,14 @@ pub fn expect_string_literal_with<'a, 'i, T>(
         ExpressionKind::Identifier(_)
         | ExpressionKind::Boolean(_)
         | ExpressionKind::Integer(_)
+        | ExpressionKind::Float(_)
         | ExpressionKind::Unary(..)
         | ExpressionKind::Binary(..)
         | ExpressionKind::Concat(_)
         | ExpressionKind::FunctionCall(_)
         | ExpressionKind::MethodCall(_)
-        | ExpressionKind::Lambda(_) => Err(TemplateParseError::expression(
+        | ExpressionKind::Lambda(_)
+        | ExpressionKind::Let(_) => Err(TemplateParseError::expression(
             "Expected string literal",
             node.span,
         )),
@@ -679,12 +970,14 @@ pub fn expect_lambda_with<'a, 'i, T>(
         ExpressionKind::Identifier(_)
         | ExpressionKind::Boolean(_)
         | ExpressionKind::Integer(_)
+        | ExpressionKind::Float(_)
         | ExpressionKind::String(_)
         | ExpressionKind::Unary(..)
         | ExpressionKind::Binary(..)
         | ExpressionKind::Concat(_)
         | ExpressionKind::FunctionCall(_)
-        | ExpressionKind::MethodCall(_) => Err(TemplateParseError::expression(
+        | ExpressionKind::MethodCall(_)
+        | ExpressionKind::Let(_) => Err(TemplateParseError::expression(
             "Expected lambda expression",
             node.span,
         )),
@@ -806,6 +1099,7 @@ mod tests {
             ExpressionKind::Identifier(_)
             | ExpressionKind::Boolean(_)
             | ExpressionKind::Integer(_)
+            | ExpressionKind::Float(_)
             | ExpressionKind::String(_) => node.kind,
             ExpressionKind::Unary(op, arg) => {
                 let arg = Box::new(normalize_tree(*arg));
@@ -836,6 +1130,15 @@ mod tests {
                 });
                 ExpressionKind::Lambda(lambda)
             }
+            ExpressionKind::Let(let_node) => {
+                let let_node = Box::new(LetNode {
+                    name: let_node.name,
+                    name_span: empty_span(),
+                    value: normalize_tree(let_node.value),
+                    body: normalize_tree(let_node.body),
+                });
+                ExpressionKind::Let(let_node)
+            }
             ExpressionKind::AliasExpanded(_, subst) => normalize_tree(*subst).kind,
         };
         ExpressionNode {
@@ -860,6 +1163,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_nesting_depth() {
+        let aliases_map = TemplateAliasesMap::new();
+        let parse = |text, max_depth| parse_with_max_depth(text, &aliases_map, max_depth);
+        let parse_err = |text, max_depth| parse(text, max_depth).unwrap_err().kind;
+        assert!(parse("x", 1).is_ok());
+        assert!(parse("f(x) ++ g(y)", 3).is_ok());
+        assert!(parse("f(x, y)", 2).is_ok());
+        assert_matches!(
+            parse_err("f(x) ++ g(y)", 2),
+            TemplateParseErrorKind::Expression(_)
+        );
+        assert_matches!(
+            parse_err("f(g(x))", 2),
+            TemplateParseErrorKind::Expression(_)
+        );
+        assert_matches!(
+            parse_err("x.f().g()", 2),
+            TemplateParseErrorKind::Expression(_)
+        );
+        assert_matches!(parse_err("!!x", 2), TemplateParseErrorKind::Expression(_));
+        assert_matches!(
+            parse_err("|x| |y| x", 2),
+            TemplateParseErrorKind::Expression(_)
+        );
+        // Parentheses don't add nesting level to the expression
+        assert!(parse("((((x))))", 1).is_ok());
+        assert!(parse("(f((x)))", 2).is_ok());
+
+        // Depth is checked after expanding aliases, but alias nodes don't count
+        let mut aliases_map = TemplateAliasesMap::new();
+        aliases_map.insert("a", "f(x)").unwrap();
+        aliases_map.insert("b", "a").unwrap();
+        aliases_map.insert("g(x)", "f(x)").unwrap();
+        let parse = |text, max_depth| parse_with_max_depth(text, &aliases_map, max_depth);
+        assert!(parse("b", 2).is_ok());
+        assert!(parse("g(y)", 2).is_ok());
+        assert_matches!(
+            parse("f(b)", 2).unwrap_err().kind,
+            TemplateParseErrorKind::InAliasExpansion(_)
+        );
+        assert_matches!(
+            parse("g(b)", 2).unwrap_err().kind,
+            TemplateParseErrorKind::InAliasExpansion(_)
+        );
+    }
+
     #[test]
     fn test_parse_whitespace() {
         let ascii_whitespaces: String = ('\x00'..='\x7f')
@@ -901,6 +1251,42 @@ mod tests {
             parse_normalized("x == y || y != z && !z"),
             parse_normalized("(x == y) || ((y != z) && (!z))"),
         );
+        assert_eq!(
+            parse_normalized("!x < y && z >= w || !v"),
+            parse_normalized("(((!x) < y) && (z >= w)) || (!v)"),
+        );
+        assert_eq!(
+            parse_normalized("x < y || y <= z && z > x"),
+            parse_normalized("(x < y) || ((y <= z) && (z > x))"),
+        );
+        // Comparison operators are left-associative
+        assert_eq!(
+            parse_normalized("x < y < z"),
+            parse_normalized("(x < y) < z"),
+        );
+        assert_eq!(
+            parse_normalized("x + y * z - x / y % z"),
+            parse_normalized("(x + (y * z)) - ((x / y) % z)"),
+        );
+        assert_eq!(
+            parse_normalized("x - y - z"),
+            parse_normalized("(x - y) - z"),
+        );
+        assert_eq!(parse_normalized("-x * -y"), parse_normalized("(-x) * (-y)"),);
+        assert_eq!(parse_normalized("x-y"), parse_normalized("x - y"),);
+        assert_eq!(parse_normalized("x - -y"), parse_normalized("x - (-y)"),);
+        assert_eq!(
+            parse_normalized("x + y > z * x"),
+            parse_normalized("(x + y) > (z * x)"),
+        );
+        assert_eq!(
+            parse_normalized("(x + y) * z"),
+            parse_normalized("(x + y) * z"),
+        );
+        assert_ne!(
+            parse_normalized("(x + y) * z"),
+            parse_normalized("x + y * z"),
+        );
 
         // Logical operator bounds more tightly than concatenation. This might
         // not be so intuitive, but should be harmless.
@@ -920,6 +1306,16 @@ mod tests {
             parse_normalized(r"x != y ++ z"),
             parse_normalized(r"(x != y) ++ z"),
         );
+        assert_eq!(
+            parse_normalized(r"x + y ++ z"),
+            parse_normalized(r"(x + y) ++ z"),
+        );
+        assert_eq!(
+            parse_normalized(r"x ++ y + z"),
+            parse_normalized(r"x ++ (y + z)"),
+        );
+        assert_eq!(parse_normalized(r"x++y"), parse_normalized(r"x ++ y"),);
+        assert!(parse_template(r"x +++ y").is_err());
 
         // Expression span
         assert_eq!(parse_template(" ! x ").unwrap().span.as_str(), "! x");
@@ -947,6 +1343,29 @@ mod tests {
         assert!(parse_template("f(foo = bar)").is_ok());
         assert!(parse_template("f( foo=bar )").is_ok());
         assert!(parse_template("x.f(foo, bar=0, baz=1)").is_ok());
+        assert!(parse_template("f(foo = )").is_err());
+        assert!(parse_template("f(= bar)").is_err());
+        assert!(parse_template(r#"f("foo" = bar)"#).is_err());
+        assert!(parse_template("f(foo.bar = baz)").is_err());
+        assert_matches!(
+            parse_template("f(foo = bar, baz)").unwrap_err().kind,
+            TemplateParseErrorKind::InvalidArguments { .. }
+        );
+        let function = assert_matches!(
+            parse_into_kind("f(a, b = 1, c = x.g())"),
+            Ok(ExpressionKind::FunctionCall(function)) => function
+        );
+        assert_eq!(function.name, "f");
+        assert_eq!(function.args.len(), 1);
+        assert_eq!(
+            function
+                .keyword_args
+                .iter()
+                .map(|arg| (arg.name, arg.name_span.as_str(), arg.value.span.as_str()))
+                .collect_vec(),
+            [("b", "b", "1"), ("c", "c", "x.g()")]
+        );
+        assert_eq!(function.arity(), 3);
 
         // Boolean literal cannot be used as a function name
         assert!(parse_template("false()").is_err());
@@ -1027,6 +1446,55 @@ mod tests {
         assert!(parse_template("|false| a").is_err());
     }
 
+    #[test]
+    fn test_let_binding_syntax() {
+        fn unwrap_let(node: ExpressionNode<'_>) -> Box<LetNode<'_>> {
+            match node.kind {
+                ExpressionKind::Let(let_node) => let_node,
+                _ => panic!("unexpected expression: {node:?}"),
+            }
+        }
+
+        let let_node = unwrap_let(parse_template("let x = a; x").unwrap());
+        assert_eq!(let_node.name, "x");
+        assert_eq!(let_node.name_span.as_str(), "x");
+        assert_eq!(let_node.value.kind, ExpressionKind::Identifier("a"));
+        assert_eq!(let_node.body.kind, ExpressionKind::Identifier("x"));
+        assert!(parse_template("let\tx=a;x").is_ok());
+
+        // Binding
+        assert_eq!(
+            parse_normalized("let x = a ++ b; x ++ y"),
+            parse_normalized("let x = (a ++ b); (x ++ y)"),
+        );
+        assert_eq!(
+            parse_normalized("f(let x = a; x, y)"),
+            parse_normalized("f((let x = a; x), y)"),
+        );
+        assert_eq!(
+            parse_normalized("let x = a; let y = b; x ++ y"),
+            parse_normalized("let x = a; (let y = b; (x ++ y))"),
+        );
+
+        // Not a let binding
+        assert_eq!(
+            parse_into_kind("let"),
+            Ok(ExpressionKind::Identifier("let"))
+        );
+        assert_eq!(
+            parse_into_kind("letx"),
+            Ok(ExpressionKind::Identifier("letx")),
+        );
+        assert!(parse_template("letx = a; x").is_err());
+
+        // No body
+        assert!(parse_template("let x = a").is_err());
+        assert!(parse_template("let x = a;").is_err());
+
+        // Boolean literal cannot be used as a variable name
+        assert!(parse_template("let true = a; b").is_err());
+    }
+
     #[test]
     fn test_keyword_literal() {
         assert_eq!(parse_into_kind("false"), Ok(ExpressionKind::Boolean(false)));
@@ -1050,9 +1518,24 @@ mod tests {
             Ok(ExpressionKind::String("\t\r\n\"\\\0\u{1b}".to_owned())),
         );
 
+        assert_eq!(
+            parse_into_kind(r#" "a\tb\nc" "#),
+            Ok(ExpressionKind::String("a\tb\nc".to_owned())),
+        );
+
         // Invalid "\<char>" escape
         assert_eq!(
             parse_into_kind(r#" "\y" "#),
+            Err(TemplateParseErrorKind::Expression(
+                "Invalid escape sequence".to_owned()
+            )),
+        );
+        let err = parse_template(r#" "foo\ybar" "#).unwrap_err();
+        assert_eq!(err.byte_range(), 5..7);
+        assert_eq!(err.message(), "Invalid escape sequence");
+        // Unterminated escape
+        assert_eq!(
+            parse_into_kind(r#" "\" "#),
             Err(TemplateParseErrorKind::SyntaxError),
         );
 
@@ -1085,16 +1568,58 @@ mod tests {
         );
         assert_eq!(
             parse_into_kind(r#""\x""#),
-            Err(TemplateParseErrorKind::SyntaxError),
+            Err(TemplateParseErrorKind::Expression(
+                "Invalid escape sequence".to_owned()
+            )),
         );
         assert_eq!(
             parse_into_kind(r#""\xf""#),
-            Err(TemplateParseErrorKind::SyntaxError),
+            Err(TemplateParseErrorKind::Expression(
+                "Invalid escape sequence".to_owned()
+            )),
         );
         assert_eq!(
             parse_into_kind(r#""\xgg""#),
-            Err(TemplateParseErrorKind::SyntaxError),
+            Err(TemplateParseErrorKind::Expression(
+                "Invalid escape sequence".to_owned()
+            )),
+        );
+
+        // Unicode code points
+        assert_eq!(
+            parse_into_kind(r#""\u{61}\u{e9}\u{65e5}\u{1F600}\u{0}""#),
+            Ok(ExpressionKind::String(
+                "a\u{e9}\u{65e5}\u{1f600}\0".to_owned()
+            )),
         );
+        assert_eq!(
+            parse_into_kind(r#""\u{10FFFF}""#),
+            Ok(ExpressionKind::String("\u{10ffff}".to_owned())),
+        );
+        let err = parse_template(r#""a\u{110000}b""#).unwrap_err();
+        assert_eq!(err.byte_range(), 2..12);
+        assert_eq!(err.message(), "Invalid Unicode code point");
+        assert_eq!(
+            parse_into_kind(r#""\u{d800}""#),
+            Err(TemplateParseErrorKind::Expression(
+                "Invalid Unicode code point".to_owned()
+            )),
+        );
+        for text in [
+            r#""\u""#,
+            r#""\u{}""#,
+            r#""\u{1234567}""#,
+            r#""\u{zz}""#,
+            r#""\u61""#,
+        ] {
+            assert_eq!(
+                parse_into_kind(text),
+                Err(TemplateParseErrorKind::Expression(
+                    "Invalid escape sequence".to_owned()
+                )),
+                "{text}"
+            );
+        }
     }
 
     #[test]
@@ -1114,6 +1639,79 @@ mod tests {
             parse_into_kind(&format!("{}", (i64::MAX as u64) + 1)),
             Err(TemplateParseErrorKind::Expression(_))
         );
+
+        // Hexadecimal, octal, and binary
+        assert_eq!(parse_into_kind("0x0"), Ok(ExpressionKind::Integer(0)));
+        assert_eq!(parse_into_kind("0xff"), Ok(ExpressionKind::Integer(255)));
+        assert_eq!(parse_into_kind("0xFf"), Ok(ExpressionKind::Integer(255)));
+        assert_eq!(parse_into_kind("0x0010"), Ok(ExpressionKind::Integer(16)));
+        assert_eq!(parse_into_kind("0o17"), Ok(ExpressionKind::Integer(15)));
+        assert_eq!(parse_into_kind("0b101"), Ok(ExpressionKind::Integer(5)));
+        assert_eq!(
+            parse_into_kind(&format!("{:#x}", i64::MAX)),
+            Ok(ExpressionKind::Integer(i64::MAX)),
+        );
+        assert_matches!(
+            parse_into_kind(&format!("{:#x}", (i64::MAX as u64) + 1)),
+            Err(TemplateParseErrorKind::Expression(_))
+        );
+        assert_matches!(
+            parse_into_kind(&format!("{:#o}", u64::MAX)),
+            Err(TemplateParseErrorKind::Expression(_))
+        );
+        assert_matches!(
+            parse_into_kind(&format!("{:#b}", u64::MAX)),
+            Err(TemplateParseErrorKind::Expression(_))
+        );
+        assert_eq!(
+            parse_into_kind("0x"),
+            Err(TemplateParseErrorKind::SyntaxError),
+        );
+        assert_eq!(
+            parse_into_kind("0xg"),
+            Err(TemplateParseErrorKind::SyntaxError),
+        );
+        assert_eq!(
+            parse_into_kind("0o8"),
+            Err(TemplateParseErrorKind::SyntaxError),
+        );
+        assert_eq!(
+            parse_into_kind("0b2"),
+            Err(TemplateParseErrorKind::SyntaxError),
+        );
+        assert_eq!(
+            parse_into_kind("0X1"),
+            Err(TemplateParseErrorKind::SyntaxError),
+        );
+    }
+
+    #[test]
+    fn test_float_literal() {
+        assert_eq!(parse_into_kind("0.0"), Ok(ExpressionKind::Float(0.0)));
+        assert_eq!(parse_into_kind("(1.25)"), Ok(ExpressionKind::Float(1.25)));
+        assert_eq!(parse_into_kind("10.05"), Ok(ExpressionKind::Float(10.05)));
+        assert_eq!(
+            parse_into_kind("00.5"),
+            Err(TemplateParseErrorKind::SyntaxError),
+        );
+        assert_eq!(
+            parse_into_kind("1."),
+            Err(TemplateParseErrorKind::SyntaxError),
+        );
+        assert_eq!(
+            parse_into_kind(".5"),
+            Err(TemplateParseErrorKind::SyntaxError),
+        );
+
+        // Method call on integer literal is not a float literal.
+        assert_matches!(
+            parse_into_kind("1.round()"),
+            Ok(ExpressionKind::MethodCall(_))
+        );
+        assert_matches!(
+            parse_into_kind("1.5.round()"),
+            Ok(ExpressionKind::MethodCall(_))
+        );
     }
 
     #[test]
@@ -1347,4 +1945,34 @@ mod tests {
             TemplateParseErrorKind::InAliasExpansion("F(x)".to_owned())
         );
     }
+
+    #[test]
+    fn test_parse_error_location() {
+        let err = parse_template("a ++ ").unwrap_err();
+        assert_eq!(err.byte_range(), 5..5);
+        assert_eq!(err.message(), "expected <expression>");
+
+        let err = with_aliases([("F(x)", "x")])
+            .parse("a ++ F(b, c)")
+            .unwrap_err();
+        assert_eq!(err.byte_range(), 7..11);
+        assert_eq!(err.message(), "Function `F`: Expected 1 arguments");
+
+        // Error in alias definition is reported at the alias reference
+        let err = with_aliases([("A", "x ++")]).parse("b ++ A").unwrap_err();
+        assert_eq!(err.byte_range(), 5..6);
+        assert_eq!(err.message(), "In alias `A`");
+        let origin = err.origin().unwrap();
+        assert_eq!(origin.byte_range(), 4..4);
+        assert_eq!(origin.message(), "expected <expression>");
+
+        assert_eq!(
+            origin.to_diagnostic(TemplateDiagnosticSeverity::Error),
+            TemplateDiagnostic {
+                byte_range: 4..4,
+                severity: TemplateDiagnosticSeverity::Error,
+                message: "expected <expression>".into(),
+            }
+        );
+    }
 }
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::Cell;
 use std::cell::RefCell;
 use std::error;
 use std::fmt;
@@ -22,9 +23,11 @@ use std::rc::Rc;
 
 use bstr::BStr;
 use bstr::BString;
+use chrono::TimeDelta;
 use jj_lib::backend::Signature;
 use jj_lib::backend::Timestamp;
 use jj_lib::config::ConfigValue;
+use unicode_width::UnicodeWidthStr as _;
 
 use crate::formatter::FormatRecorder;
 use crate::formatter::Formatter;
@@ -182,6 +185,33 @@ impl Template for TimestampRange {
     }
 }
 
+/// List items sharing the same `key`, as produced by `group_by()`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListGroup<T> {
+    pub key: String,
+    pub items: Vec<T>,
+}
+
+/// List item paired with its position, as produced by `enumerate()`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListEnumerated<T> {
+    pub index: i64,
+    pub item: T,
+}
+
+/// List items generated one by one while the list is being iterated over.
+///
+/// Unlike `Vec<T>`, the items aren't held in memory at once, so a huge list
+/// can be rendered by `map()`/`join()` without evaluating it up front. A new
+/// iterator is created each time the property is extracted.
+pub type ListStream<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+
+impl Template for TimeDelta {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        write!(formatter, "{}", time_util::format_duration_compact(*self))
+    }
+}
+
 impl Template for Vec<String> {
     fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
         format_joined(formatter, self, " ")
@@ -201,6 +231,12 @@ impl Template for i64 {
     }
 }
 
+impl Template for f64 {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        write!(formatter, "{self}")
+    }
+}
+
 pub struct LabelTemplate<T, L> {
     content: T,
     labels: L,
@@ -372,6 +408,14 @@ impl<P: TemplateProperty> TemplateProperty for Option<P> {
     }
 }
 
+impl<P: TemplateProperty> TemplateProperty for Vec<P> {
+    type Output = Vec<P::Output>;
+
+    fn extract(&self) -> Result<Self::Output, TemplatePropertyError> {
+        self.iter().map(|property| property.extract()).collect()
+    }
+}
+
 // Implement TemplateProperty for tuples
 macro_rules! tuple_impls {
     ($( ( $($n:tt $T:ident),+ ) )+) => {
@@ -466,6 +510,21 @@ impl<O: Clone> TemplateProperty for Literal<O> {
     }
 }
 
+/// Creates a boxed property that always evaluates to the given `value`.
+///
+/// This is a shorthand for `Literal(value).into_dyn()`, which is useful for
+/// keywords that don't depend on the self object.
+///
+/// ```ignore
+/// language.add_keyword("tool_name", |_self_property| {
+///     let out_property = constant_property("jj".to_owned());
+///     Ok(GenericTemplateLanguage::wrap_string(out_property))
+/// });
+/// ```
+pub fn constant_property<'a, O: Clone + 'a>(value: O) -> BoxedTemplateProperty<'a, O> {
+    Literal(value).into_dyn()
+}
+
 /// Adapter to extract template value from property for displaying.
 pub struct FormattablePropertyTemplate<P> {
     property: P,
@@ -520,6 +579,8 @@ impl<T: Template> TemplateProperty for PlainTextFormattedProperty<T> {
 /// Renders template property of list type with the given separator.
 ///
 /// Each list item will be formatted by the given `format_item()` function.
+/// If the property value is a lazy iterator such as [`ListStream`], items are
+/// formatted as they are generated.
 pub struct ListPropertyTemplate<P, S, F> {
     property: P,
     separator: S,
@@ -586,6 +647,117 @@ where
     }
 }
 
+/// Renders template property of list type as rows of aligned columns.
+///
+/// Each list item will be formatted to a row of cells by the given
+/// `format_row()` function. Cells are single-line texts. Cells except for the
+/// last column are padded to the maximum display width of the column. Columns
+/// are separated by a space, and rows are separated by a newline.
+pub struct TablePropertyTemplate<P, F> {
+    property: P,
+    format_row: F,
+}
+
+impl<P, F> TablePropertyTemplate<P, F> {
+    pub fn new<O>(property: P, format_row: F) -> Self
+    where
+        P: TemplateProperty,
+        P::Output: IntoIterator<Item = O>,
+        F: Fn(&mut TemplateFormatter, O) -> io::Result<Vec<FormatRecorder>>,
+    {
+        TablePropertyTemplate {
+            property,
+            format_row,
+        }
+    }
+}
+
+impl<O, P, F> Template for TablePropertyTemplate<P, F>
+where
+    P: TemplateProperty,
+    P::Output: IntoIterator<Item = O>,
+    F: Fn(&mut TemplateFormatter, O) -> io::Result<Vec<FormatRecorder>>,
+{
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        let contents = match self.property.extract() {
+            Ok(contents) => contents,
+            Err(err) => return formatter.handle_error(err),
+        };
+        let rows: Vec<_> = contents
+            .into_iter()
+            .map(|item| (self.format_row)(formatter, item))
+            .collect::<io::Result<_>>()?;
+        let mut column_widths: Vec<usize> = Vec::new();
+        for row in &rows {
+            if column_widths.len() < row.len() {
+                column_widths.resize(row.len(), 0);
+            }
+            for (width, cell) in iter::zip(&mut column_widths, row) {
+                // We don't care about the width of non-UTF-8 bytes, but should
+                // not panic.
+                *width = (*width).max(String::from_utf8_lossy(cell.data()).width());
+            }
+        }
+        let fill_char = FormatRecorder::with_data(" ");
+        for (i, row) in rows.iter().enumerate() {
+            if i > 0 {
+                writeln!(formatter)?;
+            }
+            for (j, (cell, &width)) in iter::zip(row, &column_widths).enumerate() {
+                if j > 0 {
+                    write!(formatter, " ")?;
+                }
+                if j + 1 < row.len() {
+                    text_util::write_padded_end(formatter.as_mut(), cell, &fill_char, width)?;
+                } else {
+                    cell.replay(formatter.as_mut())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders template property of optional type.
+///
+/// The value will be formatted by the given `format_value()` function if
+/// present. Otherwise, the `fallback` template will be rendered.
+pub struct OptionalPropertyTemplate<P, F, U> {
+    property: P,
+    format_value: F,
+    fallback: U,
+}
+
+impl<P, F, U> OptionalPropertyTemplate<P, F, U> {
+    pub fn new<O>(property: P, format_value: F, fallback: U) -> Self
+    where
+        P: TemplateProperty<Output = Option<O>>,
+        F: Fn(&mut TemplateFormatter, O) -> io::Result<()>,
+        U: Template,
+    {
+        OptionalPropertyTemplate {
+            property,
+            format_value,
+            fallback,
+        }
+    }
+}
+
+impl<O, P, F, U> Template for OptionalPropertyTemplate<P, F, U>
+where
+    P: TemplateProperty<Output = Option<O>>,
+    F: Fn(&mut TemplateFormatter, O) -> io::Result<()>,
+    U: Template,
+{
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        match self.property.extract() {
+            Ok(Some(value)) => (self.format_value)(formatter, value),
+            Ok(None) => self.fallback.format(formatter),
+            Err(err) => formatter.handle_error(err),
+        }
+    }
+}
+
 pub struct ConditionalTemplate<P, T, U> {
     pub condition: P,
     pub true_template: T,
@@ -704,11 +876,149 @@ impl<O: Clone> TemplateProperty for PropertyPlaceholder<O> {
     }
 }
 
+/// Scope in which values cached by [`CachedProperty`] and [`CachedTemplate`]
+/// are shared.
+#[derive(Clone, Debug, Default)]
+pub struct PropertyCacheScope {
+    generation: Rc<Cell<u64>>,
+}
+
+impl PropertyCacheScope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Invalidates values cached in the previous evaluation.
+    pub fn invalidate(&self) {
+        self.generation.set(self.generation.get() + 1);
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.get()
+    }
+}
+
+/// Property which evaluates the underlying property at most once per scope.
+///
+/// Cloned properties share the cached value. Errors aren't cached.
+pub struct CachedProperty<'a, O> {
+    property: Rc<dyn TemplateProperty<Output = O> + 'a>,
+    scope: PropertyCacheScope,
+    cache: Rc<RefCell<Option<(u64, O)>>>,
+}
+
+impl<'a, O> CachedProperty<'a, O> {
+    pub fn new(
+        property: impl TemplateProperty<Output = O> + 'a,
+        scope: PropertyCacheScope,
+    ) -> Self {
+        CachedProperty {
+            property: Rc::new(property),
+            scope,
+            cache: Rc::new(RefCell::new(None)),
+        }
+    }
+}
+
+impl<O> Clone for CachedProperty<'_, O> {
+    fn clone(&self) -> Self {
+        CachedProperty {
+            property: self.property.clone(),
+            scope: self.scope.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<O: Clone> TemplateProperty for CachedProperty<'_, O> {
+    type Output = O;
+
+    fn extract(&self) -> Result<Self::Output, TemplatePropertyError> {
+        let generation = self.scope.generation();
+        if let Some((cached_generation, value)) = self.cache.borrow().as_ref() {
+            if *cached_generation == generation {
+                return Ok(value.clone());
+            }
+        }
+        let value = self.property.extract()?;
+        *self.cache.borrow_mut() = Some((generation, value.clone()));
+        Ok(value)
+    }
+}
+
+/// Template which records the output of the underlying template at most once
+/// per scope, and replays it.
+///
+/// Cloned templates share the recorded output.
+pub struct CachedTemplate<'a> {
+    template: Rc<dyn Template + 'a>,
+    scope: PropertyCacheScope,
+    cache: Rc<RefCell<Option<(u64, FormatRecorder)>>>,
+}
+
+impl<'a> CachedTemplate<'a> {
+    pub fn new(template: impl Template + 'a, scope: PropertyCacheScope) -> Self {
+        CachedTemplate {
+            template: Rc::new(template),
+            scope,
+            cache: Rc::new(RefCell::new(None)),
+        }
+    }
+}
+
+impl Clone for CachedTemplate<'_> {
+    fn clone(&self) -> Self {
+        CachedTemplate {
+            template: self.template.clone(),
+            scope: self.scope.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl Template for CachedTemplate<'_> {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        let generation = self.scope.generation();
+        if let Some((cached_generation, recorder)) = self.cache.borrow().as_ref() {
+            if *cached_generation == generation {
+                return recorder.replay(formatter.as_mut());
+            }
+        }
+        let rewrap = formatter.rewrap_fn();
+        let mut recorder = FormatRecorder::new();
+        self.template.format(&mut rewrap(&mut recorder))?;
+        recorder.replay(formatter.as_mut())?;
+        *self.cache.borrow_mut() = Some((generation, recorder));
+        Ok(())
+    }
+}
+
+/// Template which evaluates `content` in a fresh cache `scope`.
+pub struct CacheScopeTemplate<T> {
+    scope: PropertyCacheScope,
+    content: T,
+}
+
+impl<T: Template> CacheScopeTemplate<T> {
+    pub fn new(scope: PropertyCacheScope, content: T) -> Self {
+        CacheScopeTemplate { scope, content }
+    }
+}
+
+impl<T: Template> Template for CacheScopeTemplate<T> {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        self.scope.invalidate();
+        self.content.format(formatter)
+    }
+}
+
 /// Adapter that renders compiled `template` with the `placeholder` value set.
 pub struct TemplateRenderer<'a, C> {
     template: Box<dyn Template + 'a>,
     placeholder: PropertyPlaceholder<C>,
     labels: Vec<String>,
+    max_output_bytes: Option<usize>,
+    plain_text: bool,
 }
 
 impl<'a, C: Clone> TemplateRenderer<'a, C> {
@@ -717,9 +1027,29 @@ impl<'a, C: Clone> TemplateRenderer<'a, C> {
             template,
             placeholder,
             labels: Vec::new(),
+            max_output_bytes: None,
+            plain_text: false,
         }
     }
 
+    /// Returns renderer that will fail if the output of a single `format()`
+    /// call exceeds `max_output_bytes`.
+    pub fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = Some(max_output_bytes);
+        self
+    }
+
+    /// Returns renderer that will drop all labels and write plain text to the
+    /// formatter passed to `format()`.
+    ///
+    /// This is useful for generating plain-text output from the template used
+    /// for colored output, regardless of the formatter type. Content written by
+    /// `raw_escape_sequence()` is still emitted as is.
+    pub fn plain_text(mut self) -> Self {
+        self.plain_text = true;
+        self
+    }
+
     /// Returns renderer that will format template with the given `label`.
     ///
     /// This is equivalent to wrapping the content template with `label()`
@@ -731,6 +1061,20 @@ impl<'a, C: Clone> TemplateRenderer<'a, C> {
     }
 
     pub fn format(&self, context: &C, formatter: &mut dyn Formatter) -> io::Result<()> {
+        let mut plain_text_formatter;
+        let formatter = if self.plain_text {
+            plain_text_formatter = PlainTextFormatter::new(formatter.raw_unlabeled()?);
+            &mut plain_text_formatter as &mut dyn Formatter
+        } else {
+            formatter
+        };
+        let mut limited_formatter;
+        let formatter = if let Some(max_bytes) = self.max_output_bytes {
+            limited_formatter = LimitedFormatter::new(formatter, max_bytes);
+            &mut limited_formatter as &mut dyn Formatter
+        } else {
+            formatter
+        };
         let mut wrapper = TemplateFormatter::new(formatter, format_property_error_inline);
         self.placeholder.with_value(context.clone(), || {
             format_labeled(&mut wrapper, &self.template, &self.labels)
@@ -738,6 +1082,91 @@ impl<'a, C: Clone> TemplateRenderer<'a, C> {
     }
 }
 
+/// `Formatter` wrapper that fails once the total output exceeds the limit.
+struct LimitedFormatter<'a> {
+    formatter: &'a mut dyn Formatter,
+    max_bytes: usize,
+    remaining: usize,
+}
+
+impl<'a> LimitedFormatter<'a> {
+    fn new(formatter: &'a mut dyn Formatter, max_bytes: usize) -> Self {
+        LimitedFormatter {
+            formatter,
+            max_bytes,
+            remaining: max_bytes,
+        }
+    }
+}
+
+fn consume_output_budget(remaining: &mut usize, max_bytes: usize, len: usize) -> io::Result<()> {
+    *remaining = remaining.checked_sub(len).ok_or_else(|| {
+        io::Error::other(format!(
+            "Template output exceeds the limit of {max_bytes} bytes"
+        ))
+    })?;
+    Ok(())
+}
+
+impl Write for LimitedFormatter<'_> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        consume_output_budget(&mut self.remaining, self.max_bytes, data.len())?;
+        self.formatter.write_all(data)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.formatter.flush()
+    }
+}
+
+impl Formatter for LimitedFormatter<'_> {
+    fn raw(&mut self) -> io::Result<Box<dyn Write + '_>> {
+        let output = self.formatter.raw()?;
+        Ok(Box::new(LimitedWriter {
+            output,
+            max_bytes: self.max_bytes,
+            remaining: &mut self.remaining,
+        }))
+    }
+
+    fn raw_unlabeled(&mut self) -> io::Result<Box<dyn Write + '_>> {
+        let output = self.formatter.raw_unlabeled()?;
+        Ok(Box::new(LimitedWriter {
+            output,
+            max_bytes: self.max_bytes,
+            remaining: &mut self.remaining,
+        }))
+    }
+
+    fn push_label(&mut self, label: &str) -> io::Result<()> {
+        self.formatter.push_label(label)
+    }
+
+    fn pop_label(&mut self) -> io::Result<()> {
+        self.formatter.pop_label()
+    }
+}
+
+/// Raw writer sharing the output budget with the `LimitedFormatter`.
+struct LimitedWriter<'a> {
+    output: Box<dyn Write + 'a>,
+    max_bytes: usize,
+    remaining: &'a mut usize,
+}
+
+impl Write for LimitedWriter<'_> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        consume_output_budget(self.remaining, self.max_bytes, data.len())?;
+        self.output.write_all(data)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
 /// Wrapper to pass around `Formatter` and error handler.
 pub struct TemplateFormatter<'a> {
     formatter: &'a mut dyn Formatter,
@@ -501,6 +501,33 @@ pub fn wrap_bytes(text: &[u8], width: usize) -> Vec<&[u8]> {
     split_lines
 }
 
+/// Like [`wrap_bytes()`], but continuation lines are indented by the leading
+/// spaces of the original line.
+///
+/// Returns pairs of the indentation to be inserted and the line. The
+/// indentation is empty for the first line split from each original line,
+/// which includes its own leading spaces.
+pub fn wrap_bytes_keeping_indent(text: &[u8], width: usize) -> Vec<(&[u8], &[u8])> {
+    let mut split_lines = Vec::new();
+    for line in text.split(|&c| c == b'\n') {
+        let indent_len = line.iter().take_while(|&&c| c == b' ').count();
+        let (indent, content) = line.split_at(indent_len);
+        let words = split_byte_line_to_words(content);
+        let content_width = width.saturating_sub(indent_len);
+        let split = textwrap::wrap_algorithms::wrap_first_fit(&words, &[content_width as f64]);
+        split_lines.extend(split.iter().enumerate().map(|(i, words)| {
+            let (Some(a), Some(b)) = (words.first(), words.last()) else {
+                return (&indent[..0], &line[..0]); // Empty line
+            };
+            let start = if i == 0 { 0 } else { a.offset_in(line) };
+            let end = b.offset_in(line) + b.word.len();
+            let line_indent = if i == 0 { &indent[..0] } else { indent };
+            (line_indent, &line[start..end])
+        }));
+    }
+    split_lines
+}
+
 /// Wraps lines at the given width preserving labels.
 ///
 /// `textwrap::wrap()` can also process text containing ANSI escape sequences.
@@ -511,19 +538,41 @@ pub fn write_wrapped(
     formatter: &mut dyn Formatter,
     recorded_content: &FormatRecorder,
     width: usize,
+) -> io::Result<()> {
+    let lines = wrap_bytes(recorded_content.data(), width)
+        .into_iter()
+        .map(|line| (&line[..0], line));
+    write_wrapped_lines(formatter, recorded_content, lines)
+}
+
+/// Like [`write_wrapped()`], but continuation lines are indented by the
+/// leading spaces of the original line.
+pub fn write_wrapped_keeping_indent(
+    formatter: &mut dyn Formatter,
+    recorded_content: &FormatRecorder,
+    width: usize,
+) -> io::Result<()> {
+    let lines = wrap_bytes_keeping_indent(recorded_content.data(), width);
+    write_wrapped_lines(formatter, recorded_content, lines)
+}
+
+fn write_wrapped_lines<'a>(
+    formatter: &mut dyn Formatter,
+    recorded_content: &'a FormatRecorder,
+    lines: impl IntoIterator<Item = (&'a [u8], &'a [u8])>,
 ) -> io::Result<()> {
     let data = recorded_content.data();
-    let mut line_ranges = wrap_bytes(data, width)
+    let mut line_ranges = lines
         .into_iter()
-        .map(|line| {
+        .map(|(indent, line)| {
             let start = byte_offset_from(data, line);
-            start..start + line.len()
+            (indent, start..start + line.len())
         })
         .peekable();
     // The recorded data ranges are contiguous, and the line ranges are increasing
     // sequence (with some holes.) Both ranges should start from data[0].
-    recorded_content.replay_with(formatter, |formatter, data_range| {
-        while let Some(line_range) = line_ranges.peek() {
+    recorded_content.replay_with_labels(formatter, |formatter, labels, data_range| {
+        while let Some((_, line_range)) = line_ranges.peek() {
             let start = cmp::max(data_range.start, line_range.start);
             let end = cmp::min(data_range.end, line_range.end);
             if start < end {
@@ -533,8 +582,19 @@ pub fn write_wrapped(
                 break; // No more lines in this data range
             }
             line_ranges.next().unwrap();
-            if line_ranges.peek().is_some() {
+            if let Some((indent, _)) = line_ranges.peek() {
                 writeln!(formatter)?; // Not the last line
+                if !indent.is_empty() {
+                    // The indent belongs to the first line, not to the labels
+                    // of the wrapped text
+                    for _ in labels {
+                        formatter.pop_label()?;
+                    }
+                    formatter.write_all(indent)?;
+                    for label in labels {
+                        formatter.push_label(label)?;
+                    }
+                }
             }
         }
         Ok(())
@@ -1401,6 +1461,41 @@ mod tests {
         assert_eq!(lines[4].as_ptr(), text[14..].as_ptr());
     }
 
+    #[test]
+    fn test_wrap_bytes_keeping_indent() {
+        assert_eq!(
+            wrap_bytes_keeping_indent(b"foo bar baz", 7),
+            [(b"".as_ref(), b"foo bar".as_ref()), (b"", b"baz")],
+        );
+        // The first line includes the indentation, and the continuation lines
+        // need to be indented
+        assert_eq!(
+            wrap_bytes_keeping_indent(b"  foo bar baz", 9),
+            [(b"".as_ref(), b"  foo bar".as_ref()), (b"  ", b"baz")],
+        );
+        assert_eq!(
+            wrap_bytes_keeping_indent(b"  foo bar baz\nqux\n    x y", 5),
+            [
+                (b"".as_ref(), b"  foo".as_ref()),
+                (b"  ", b"bar"),
+                (b"  ", b"baz"),
+                (b"", b"qux"),
+                (b"", b"    x"),
+                (b"    ", b"y"),
+            ],
+        );
+        // Indentation wider than the width doesn't remove words
+        assert_eq!(
+            wrap_bytes_keeping_indent(b"    foo bar", 2),
+            [(b"".as_ref(), b"    foo".as_ref()), (b"    ", b"bar")],
+        );
+        // Whitespace-only lines are empty
+        assert_eq!(
+            wrap_bytes_keeping_indent(b"\n  \n", 10),
+            [(b"".as_ref(), b"".as_ref()), (b"", b""), (b"", b"")],
+        );
+    }
+
     #[test]
     fn test_write_wrapped() {
         // Split single label chunk
@@ -1539,6 +1634,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_write_wrapped_keeping_indent() {
+        // Each continuation line is indented, and keeps the label after the
+        // indent
+        let mut recorder = FormatRecorder::new();
+        write!(recorder, "  ").unwrap();
+        recorder.push_label("red").unwrap();
+        write!(recorder, "foo bar baz").unwrap();
+        recorder.pop_label().unwrap();
+        write!(recorder, " qux\nquux").unwrap();
+        insta::assert_snapshot!(
+            format_colored(|formatter| write_wrapped_keeping_indent(formatter, &recorder, 9)),
+            @r"
+          [38;5;1mfoo bar[39m
+          [38;5;1mbaz[39m qux
+        quux
+        ");
+        insta::assert_snapshot!(
+            format_plain_text(|formatter| write_wrapped_keeping_indent(formatter, &recorder, 9)),
+            @r"
+          foo bar
+          baz qux
+        quux
+        ");
+    }
+
     #[test]
     fn test_parse_author() {
         let expected_name = "Example";
@@ -1,10 +1,14 @@
+use chrono::format::Parsed;
 use chrono::format::StrftimeItems;
 use chrono::DateTime;
 use chrono::FixedOffset;
 use chrono::LocalResult;
+use chrono::TimeDelta;
 use chrono::TimeZone as _;
 use chrono::Utc;
 use jj_lib::backend::Timestamp;
+use jj_lib::time_util::DatePattern;
+use jj_lib::time_util::DatePatternParseError;
 use once_cell::sync::Lazy;
 use thiserror::Error;
 
@@ -84,6 +88,174 @@ pub fn format_absolute_timestamp_with(
     Ok(datetime.format_with_items(format.items.iter()).to_string())
 }
 
+/// Returns true if `lhs` and `rhs` fall on the same calendar date in the time
+/// zone at `tz_offset` minutes from UTC.
+///
+/// The original time zone offsets of the timestamps are ignored. Since the
+/// offset is fixed, a day is always 24 hours long, even across DST changes.
+/// Callers usually pass the current local offset, which may differ from the
+/// offset that was in effect at the timestamps.
+pub fn is_same_day(
+    lhs: &Timestamp,
+    rhs: &Timestamp,
+    tz_offset: i32,
+) -> Result<bool, TimestampOutOfRange> {
+    let to_date = |timestamp: &Timestamp| {
+        let timestamp = Timestamp {
+            timestamp: timestamp.timestamp,
+            tz_offset,
+        };
+        datetime_from_timestamp(&timestamp).map(|datetime| datetime.date_naive())
+    };
+    Ok(to_date(lhs)? == to_date(rhs)?)
+}
+
+/// Parses `text` as a timestamp in the given strftime-like `format`.
+///
+/// If the format has no time zone, the timestamp is assumed to be in UTC. If
+/// the format has no time of day, it is assumed to be midnight.
+pub fn parse_timestamp_with(
+    text: &str,
+    format: &FormattingItems,
+) -> Result<Timestamp, chrono::ParseError> {
+    let mut parsed = Parsed::new();
+    chrono::format::parse(&mut parsed, text, format.items.iter())?;
+    if parsed.offset().is_none() {
+        parsed.set_offset(0)?;
+    }
+    let has_time = parsed.hour_div_12().is_some() || parsed.hour_mod_12().is_some();
+    if !has_time && parsed.timestamp().is_none() {
+        parsed.set_hour(0)?;
+        parsed.set_minute(0)?;
+    }
+    Ok(Timestamp::from_datetime(parsed.to_datetime()?))
+}
+
+/// Time zone specified by name or offset.
+#[derive(Clone, Debug)]
+pub enum TimeZoneSpec {
+    /// Fixed offset in minutes.
+    Fixed(i32),
+    /// Named zone looked up in the time zone database.
+    Named(jiff::tz::TimeZone),
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid time zone {name:?}")]
+pub struct InvalidTimeZone {
+    name: String,
+    #[source]
+    source: Option<jiff::Error>,
+}
+
+impl TimeZoneSpec {
+    /// Parses `name` as a UTC offset such as `+09:00` or `-0500`, or looks it
+    /// up in the time zone database.
+    pub fn parse(name: &str) -> Result<Self, InvalidTimeZone> {
+        if name.starts_with(['+', '-']) {
+            let offset: FixedOffset = name.parse().map_err(|_| InvalidTimeZone {
+                name: name.to_owned(),
+                source: None,
+            })?;
+            return Ok(TimeZoneSpec::Fixed(offset.local_minus_utc() / 60));
+        }
+        let tz = jiff::tz::TimeZone::get(name).map_err(|err| InvalidTimeZone {
+            name: name.to_owned(),
+            source: Some(err),
+        })?;
+        Ok(TimeZoneSpec::Named(tz))
+    }
+
+    /// Returns the same instant with the offset of this zone.
+    pub fn convert(&self, timestamp: &Timestamp) -> Result<Timestamp, TimestampOutOfRange> {
+        let tz_offset = match self {
+            TimeZoneSpec::Fixed(offset) => *offset,
+            TimeZoneSpec::Named(tz) => {
+                let instant = jiff::Timestamp::from_millisecond(timestamp.timestamp.0)
+                    .map_err(|_| TimestampOutOfRange)?;
+                tz.to_offset(instant).seconds() / 60
+            }
+        };
+        Ok(Timestamp {
+            timestamp: timestamp.timestamp,
+            tz_offset,
+        })
+    }
+}
+
+/// Source of the current time and the local time zone.
+///
+/// The system clock is used by default. Either can be frozen to render
+/// templates deterministically.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TemplateClock {
+    now: Option<Timestamp>,
+    tz_offset: Option<i32>,
+}
+
+impl TemplateClock {
+    /// Clock reading the system time and time zone.
+    pub const SYSTEM: Self = TemplateClock {
+        now: None,
+        tz_offset: None,
+    };
+
+    /// Freezes the current time at `now`.
+    pub fn with_now(self, now: Timestamp) -> Self {
+        TemplateClock {
+            now: Some(now),
+            ..self
+        }
+    }
+
+    /// Freezes the local time zone at the given offset in minutes.
+    pub fn with_tz_offset(self, tz_offset: i32) -> Self {
+        TemplateClock {
+            tz_offset: Some(tz_offset),
+            ..self
+        }
+    }
+
+    /// Current time in the local time zone.
+    pub fn now(&self) -> Timestamp {
+        let timestamp = self.now.as_ref().map_or_else(Timestamp::now, Clone::clone);
+        Timestamp {
+            timestamp: timestamp.timestamp,
+            tz_offset: self.local_tz_offset(),
+        }
+    }
+
+    /// Parses `date_pattern` of the given `kind` relative to the current time.
+    ///
+    /// If neither the time nor the time zone is frozen, the pattern is
+    /// interpreted in the system time zone, taking DST changes into account.
+    pub fn parse_date_pattern(
+        &self,
+        date_pattern: &str,
+        kind: &str,
+    ) -> Result<DatePattern, DatePatternParseError> {
+        if self == &Self::SYSTEM {
+            return DatePattern::from_str_kind(date_pattern, kind, chrono::Local::now());
+        }
+        // The frozen time should be representable
+        let now = datetime_from_timestamp(&self.now()).unwrap_or_else(|_| Utc::now().into());
+        DatePattern::from_str_kind(date_pattern, kind, now)
+    }
+
+    /// Offset of the local time zone in minutes.
+    ///
+    /// If not frozen, `$JJ_TZ_OFFSET_MINS` takes precedence over the system
+    /// time zone.
+    pub fn local_tz_offset(&self) -> i32 {
+        self.tz_offset.unwrap_or_else(|| {
+            std::env::var("JJ_TZ_OFFSET_MINS")
+                .ok()
+                .and_then(|tz_string| tz_string.parse::<i32>().ok())
+                .unwrap_or_else(|| chrono::Local::now().offset().local_minus_utc() / 60)
+        })
+    }
+}
+
 pub fn format_duration(
     from: &Timestamp,
     to: &Timestamp,
@@ -95,3 +267,164 @@ pub fn format_duration(
         .map_err(|_: chrono::OutOfRangeError| TimestampOutOfRange)?;
     Ok(format.convert(duration))
 }
+
+/// Formats the `timestamp` relative to the reference time `now`, such as
+/// "3 hours ago" or "in 2 days".
+pub fn format_timestamp_relative_to(
+    timestamp: &Timestamp,
+    now: &Timestamp,
+) -> Result<String, TimestampOutOfRange> {
+    let duration =
+        datetime_from_timestamp(now)?.signed_duration_since(datetime_from_timestamp(timestamp)?);
+    if let Ok(duration) = duration.to_std() {
+        return Ok(timeago::Formatter::new().convert(duration));
+    }
+    let duration = (-duration)
+        .to_std()
+        .map_err(|_: chrono::OutOfRangeError| TimestampOutOfRange)?;
+    let mut format = timeago::Formatter::new();
+    format.ago("");
+    let text = format.convert(duration);
+    if text == "now" {
+        Ok(text)
+    } else {
+        Ok(format!("in {text}"))
+    }
+}
+
+/// Formats the signed `duration` in compact form, such as "1d 2h 3m 4.5s".
+///
+/// Zero units are omitted. Negative duration is prefixed with "-".
+pub fn format_duration_compact(duration: TimeDelta) -> String {
+    let sign = if duration < TimeDelta::zero() {
+        "-"
+    } else {
+        ""
+    };
+    let duration = duration.abs();
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+    let seconds = duration.num_seconds() % 60;
+    let millis = duration.subsec_nanos() / 1_000_000;
+    let mut parts = Vec::new();
+    if days > 0 {
+        parts.push(format!("{days}d"));
+    }
+    if hours > 0 {
+        parts.push(format!("{hours}h"));
+    }
+    if minutes > 0 {
+        parts.push(format!("{minutes}m"));
+    }
+    if millis > 0 {
+        let fraction = format!("{millis:03}");
+        let fraction = fraction.trim_end_matches('0');
+        parts.push(format!("{seconds}.{fraction}s"));
+    } else if seconds > 0 || parts.is_empty() {
+        parts.push(format!("{seconds}s"));
+    }
+    format!("{sign}{}", parts.join(" "))
+}
+
+/// Formats the signed `duration` in human-readable form, such as "3 hours".
+///
+/// Only the largest unit is printed. Negative duration is prefixed with "-".
+pub fn format_duration_humanized(duration: TimeDelta) -> String {
+    const TOO_LOW: &str = "0 seconds";
+    let mut format = timeago::Formatter::new();
+    format.ago("").too_low(TOO_LOW);
+    // abs() of TimeDelta should never exceed the range of std Duration
+    let text = format.convert(duration.abs().to_std().unwrap());
+    if duration < TimeDelta::zero() && text != TOO_LOW {
+        format!("-{text}")
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jj_lib::backend::MillisSinceEpoch;
+
+    use super::*;
+
+    fn new_timestamp(msec: i64) -> Timestamp {
+        Timestamp {
+            timestamp: MillisSinceEpoch(msec),
+            tz_offset: 0,
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp_relative_to() {
+        const SECOND: i64 = 1000;
+        const MINUTE: i64 = 60 * SECOND;
+        const HOUR: i64 = 60 * MINUTE;
+        const DAY: i64 = 24 * HOUR;
+        let now = new_timestamp(1_000_000 * DAY);
+        let format = |delta: i64| {
+            let timestamp = new_timestamp(now.timestamp.0 + delta);
+            format_timestamp_relative_to(&timestamp, &now).unwrap()
+        };
+
+        // Past
+        assert_eq!(format(0), "now");
+        assert_eq!(format(-500), "now");
+        assert_eq!(format(-SECOND), "1 second ago");
+        assert_eq!(format(-59 * SECOND), "59 seconds ago");
+        assert_eq!(format(-MINUTE), "1 minute ago");
+        assert_eq!(format(-3 * HOUR - 5 * MINUTE), "3 hours ago");
+        assert_eq!(format(-2 * DAY), "2 days ago");
+        assert_eq!(format(-14 * DAY), "2 weeks ago");
+        assert_eq!(format(-60 * DAY), "1 month ago");
+        assert_eq!(format(-800 * DAY), "2 years ago");
+
+        // Future
+        assert_eq!(format(500), "now");
+        assert_eq!(format(SECOND), "in 1 second");
+        assert_eq!(format(5 * MINUTE), "in 5 minutes");
+        assert_eq!(format(2 * DAY + HOUR), "in 2 days");
+        assert_eq!(format(400 * DAY), "in 1 year");
+
+        // The timezone offset doesn't matter
+        let timestamp = Timestamp {
+            timestamp: MillisSinceEpoch(now.timestamp.0 - HOUR),
+            tz_offset: -9 * 60,
+        };
+        assert_eq!(
+            format_timestamp_relative_to(&timestamp, &now).unwrap(),
+            "1 hour ago"
+        );
+
+        // Out-of-range timestamp
+        assert!(format_timestamp_relative_to(&new_timestamp(i64::MAX), &now).is_err());
+    }
+
+    #[test]
+    fn test_format_duration_compact() {
+        let format = |msec: i64| format_duration_compact(TimeDelta::milliseconds(msec));
+        assert_eq!(format(0), "0s");
+        assert_eq!(format(1), "0.001s");
+        assert_eq!(format(250), "0.25s");
+        assert_eq!(format(1_500), "1.5s");
+        assert_eq!(format(60_000), "1m");
+        assert_eq!(format(3_723_000), "1h 2m 3s");
+        assert_eq!(format(86_400_000), "1d");
+        assert_eq!(format(3 * 86_400_000 + 5_000), "3d 5s");
+        assert_eq!(format(-1_500), "-1.5s");
+        assert_eq!(format(-(2 * 86_400_000 + 3_600_000)), "-2d 1h");
+    }
+
+    #[test]
+    fn test_format_duration_humanized() {
+        let format = |msec: i64| format_duration_humanized(TimeDelta::milliseconds(msec));
+        assert_eq!(format(0), "0 seconds");
+        assert_eq!(format(500), "0 seconds");
+        assert_eq!(format(-500), "0 seconds");
+        assert_eq!(format(1_000), "1 second");
+        assert_eq!(format(3 * 3_600_000 + 5 * 60_000), "3 hours");
+        assert_eq!(format(2 * 86_400_000), "2 days");
+        assert_eq!(format(-2 * 86_400_000), "-2 days");
+    }
+}
@@ -689,7 +689,13 @@ impl Ui {
     }
 
     pub fn term_width(&self) -> usize {
-        term_width().unwrap_or(80).into()
+        term_width(true).unwrap_or(80).into()
+    }
+
+    /// Terminal width if stdout is connected to a terminal or `$COLUMNS` is
+    /// set.
+    pub fn stdout_term_width(&self) -> Option<usize> {
+        term_width(io::stdout().is_terminal()).map(usize::from)
     }
 }
 
@@ -718,7 +724,7 @@ impl<W> ProgressOutput<W> {
 
     pub fn term_width(&self) -> Option<u16> {
         // Terminal can be resized while progress is displayed, so don't cache it.
-        self.term_width.or_else(term_width)
+        self.term_width.or_else(|| term_width(true))
     }
 
     /// Construct a guard object which writes `text` when dropped. Useful for
@@ -770,10 +776,15 @@ fn format_error_with_sources(err: &dyn error::Error) -> impl fmt::Display + use<
     iter::successors(Some(err), |&err| err.source()).format(": ")
 }
 
-fn term_width() -> Option<u16> {
+/// Detects the terminal width. `$COLUMNS` takes precedence if set.
+///
+/// If `is_terminal` is false, the terminal isn't queried.
+fn term_width(is_terminal: bool) -> Option<u16> {
     if let Some(cols) = env::var("COLUMNS").ok().and_then(|s| s.parse().ok()) {
         Some(cols)
-    } else {
+    } else if is_terminal {
         crossterm::terminal::size().ok().map(|(cols, _)| cols)
+    } else {
+        None
     }
 }
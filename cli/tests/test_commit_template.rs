@@ -57,6 +57,37 @@ fn test_log_parents() {
     [EOF]
     ");
 
+    // Filtered and mapped lists can be chained
+    let template = r#"
+    "P: " ++ parents.filter(|c| !c.root()).map(|c| c.change_id().short(8)).join(", ") ++ "\n"
+    "#;
+    let output = work_dir.run_jj(["log", "-T", template]);
+    insta::assert_snapshot!(output, @r"
+    @    P: kkmpptxz, qpvuntsm
+    ├─╮
+    ○ │  P: qpvuntsm
+    ├─╯
+    ○  P:
+    ◆  P:
+    [EOF]
+    ");
+
+    // Individual items can be picked, and the item type is preserved
+    let template = r#"
+    if(parents, "P: " ++ parents.first().change_id().short(8) ++ " "
+                      ++ parents.last().change_id().short(8)) ++ "\n"
+    "#;
+    let output = work_dir.run_jj(["log", "-T", template]);
+    insta::assert_snapshot!(output, @r"
+    @    P: kkmpptxz qpvuntsm
+    ├─╮
+    ○ │  P: qpvuntsm qpvuntsm
+    ├─╯
+    ○  P: zzzzzzzz zzzzzzzz
+    ◆
+    [EOF]
+    ");
+
     let template = r#"parents.map(|c| c.commit_id().shortest(4))"#;
     let output = work_dir.run_jj(["log", "-T", template, "-r@", "--color=always"]);
     insta::assert_snapshot!(output, @r"
@@ -174,6 +205,36 @@ fn test_log_author_timestamp_local() {
     ");
 }
 
+#[test]
+fn test_log_author_timestamp_format() {
+    let mut test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let template = r#"
+    separate(" | ",
+      author.timestamp().format("%Y-%m-%d %H:%M %:z"),
+      author.timestamp().utc().format("%Y-%m-%d %H:%M %:z"),
+      author.timestamp().local().format("%Y-%m-%d %H:%M %:z"),
+    ) ++ "\n"
+    "#;
+
+    let work_dir = test_env.work_dir("repo");
+    let output = work_dir.run_jj(["log", "--no-graph", "-T", template]);
+    insta::assert_snapshot!(output, @r"
+    2001-02-03 04:05 +07:00 | 2001-02-02 21:05 +00:00 | 2001-02-03 08:05 +11:00
+    1970-01-01 00:00 +00:00 | 1970-01-01 00:00 +00:00 | 1970-01-01 11:00 +11:00
+    [EOF]
+    ");
+
+    test_env.add_env_var("JJ_TZ_OFFSET_MINS", "-210");
+    let work_dir = test_env.work_dir("repo");
+    let output = work_dir.run_jj(["log", "--no-graph", "-T", template]);
+    insta::assert_snapshot!(output, @r"
+    2001-02-03 04:05 +07:00 | 2001-02-02 21:05 +00:00 | 2001-02-02 17:35 -03:30
+    1970-01-01 00:00 +00:00 | 1970-01-01 00:00 +00:00 | 1969-12-31 20:30 -03:30
+    [EOF]
+    ");
+}
+
 #[test]
 fn test_log_author_timestamp_after_before() {
     let test_env = TestEnvironment::default();
@@ -1727,4 +1788,16 @@ fn test_log_format_trailers() {
         "-r@",
     ]);
     insta::assert_snapshot!(output, @"false[EOF]");
+
+    let output = work_dir.run_jj([
+        "log",
+        "--no-graph",
+        "-T",
+        "trailers.first().key() ++ ' ' ++ trailers.nth(-1).key()",
+        "-r@",
+    ]);
+    insta::assert_snapshot!(output, @"Signed-off-by Change-Id[EOF]");
+
+    let output = work_dir.run_jj(["log", "--no-graph", "-T", "trailers.nth(2)", "-r@"]);
+    insta::assert_snapshot!(output, @"<Error: No Trailer available>[EOF]");
 }
@@ -764,6 +764,14 @@ fn test_log_short_shortest_length_parameter() {
     ◆  0000000000000000000000000000000000000000|0000000000000000000000000000000000000000
     [EOF]
     ");
+
+    // Change ids are abbreviated in the same way
+    insta::assert_snapshot!(
+        render(r#"change_id.short() ++ "|" ++ change_id.short(0) ++ "|" ++ change_id.short(100)"#), @r"
+    @  qpvuntsmwlqt||qpvuntsmwlqtpsluzzsnyyzlmlwvmlnu
+    ◆  zzzzzzzzzzzz||zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz
+    [EOF]
+    ");
 }
 
 #[test]
@@ -33,7 +33,7 @@ fn test_templater_parse_error() {
     1 | description ()
       |             ^---
       |
-      = expected <EOI>, `++`, `||`, `&&`, `==`, `!=`, `>=`, `>`, `<=`, or `<`
+      = expected <EOI>, `++`, `||`, `&&`, `==`, `!=`, `>=`, `>`, `<=`, `<`, `+`, `-`, `*`, `/`, or `%`
     [EOF]
     [exit status: 1]
     ");
@@ -83,7 +83,7 @@ fn test_templater_parse_error() {
       | ^---^
       |
       = Function `oncat` doesn't exist
-    Hint: Did you mean `concat`, `socat`?
+    Hint: Did you mean `concat`, `format`, `socat`?
     [EOF]
     [exit status: 1]
     ");
@@ -150,6 +150,46 @@ fn test_templater_parse_error() {
     [EOF]
     [exit status: 1]
     ");
+
+    // Mixed String and Integer comparison
+    insta::assert_snapshot!(render(r#"description.first_line() < 1"#), @r"
+    ------- stderr -------
+    Error: Failed to parse template: Cannot compare expressions of type `String` and `Integer`
+    Caused by:  --> 1:1
+      |
+    1 | description.first_line() < 1
+      | ^--------------------------^
+      |
+      = Cannot compare expressions of type `String` and `Integer`
+    Hint: Use `.parse_int()` to convert the String to Integer, or `.to_string()` to convert the Integer to String
+    [EOF]
+    [exit status: 1]
+    ");
+    insta::assert_snapshot!(render(r#"1 == "1""#), @r#"
+    ------- stderr -------
+    Error: Failed to parse template: Cannot compare expressions of type `Integer` and `String`
+    Caused by:  --> 1:1
+      |
+    1 | 1 == "1"
+      | ^------^
+      |
+      = Cannot compare expressions of type `Integer` and `String`
+    Hint: Use `.parse_int()` to convert the String to Integer, or `.to_string()` to convert the Integer to String
+    [EOF]
+    [exit status: 1]
+    "#);
+    insta::assert_snapshot!(render(r#"empty == "1""#), @r#"
+    ------- stderr -------
+    Error: Failed to parse template: Cannot compare expressions of type `Boolean` and `String`
+    Caused by:  --> 1:1
+      |
+    1 | empty == "1"
+      | ^----------^
+      |
+      = Cannot compare expressions of type `Boolean` and `String`
+    [EOF]
+    [exit status: 1]
+    "#);
 }
 
 #[test]
@@ -195,6 +235,221 @@ fn test_templater_upper_lower() {
         render(r#""Hello".upper() ++ "Hello".lower()"#), @"HELLOhello[EOF]");
 }
 
+#[test]
+fn test_templater_list_unique() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    let render = |template| get_template_output(&work_dir, "@", template);
+
+    // Commits can't be compared
+    insta::assert_snapshot!(render(r#"parents.unique().len()"#), @r"
+    ------- stderr -------
+    Error: Failed to parse template: Cannot compare items of type `Commit`
+    Caused by:  --> 1:9
+      |
+    1 | parents.unique().len()
+      |         ^----^
+      |
+      = Cannot compare items of type `Commit`
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[test]
+fn test_templater_list_is_empty() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+
+    let template = r#"parents.is_empty() ++ " " ++ parents.filter(|c| c.root()).is_empty()"#;
+    insta::assert_snapshot!(get_template_output(&work_dir, "@", template), @"false false[EOF]");
+    insta::assert_snapshot!(get_template_output(&work_dir, "root()", template), @"true true[EOF]");
+}
+
+#[test]
+fn test_templater_list_take_skip() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    work_dir.run_jj(["describe", "-m", "a"]).success();
+    work_dir.run_jj(["new", "root()", "-m", "b"]).success();
+    work_dir.run_jj(["new", "root()", "-m", "c"]).success();
+    work_dir
+        .run_jj(["new", "description(a)", "description(b)", "@"])
+        .success();
+    let render = |template| get_template_output(&work_dir, "@", template);
+
+    insta::assert_snapshot!(
+        render(r#"parents.take(2).map(|c| c.description().first_line()).join(",")"#), @"a,b[EOF]");
+    insta::assert_snapshot!(
+        render(r#"parents.skip(1).map(|c| c.description().first_line()).join(",")"#), @"b,c[EOF]");
+    insta::assert_snapshot!(
+        render(r#"parents.take(0).len() ++ " " ++ parents.skip(10).len()"#), @"0 0[EOF]");
+}
+
+#[test]
+fn test_templater_list_group_by() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    work_dir
+        .run_jj(["describe", "-m", "a1", "--author", "A <a@example.com>"])
+        .success();
+    work_dir.run_jj(["new", "root()", "-m", "b1"]).success();
+    work_dir.run_jj(["new", "root()"]).success();
+    work_dir
+        .run_jj(["describe", "-m", "a2", "--author", "A <a@example.com>"])
+        .success();
+    work_dir
+        .run_jj(["new", "description(a1)", "description(b1)", "@"])
+        .success();
+    let render = |template| get_template_output(&work_dir, "@", template);
+
+    let template = r#"
+        parents.group_by(|c| c.author().name()).map(|g| g.key() ++ ": " ++ g.items().map(|c| c.description().first_line()).join(",")).join("\n")
+    "#;
+    insta::assert_snapshot!(render(template.trim()), @r"
+    A: a1,a2
+    Test User: b1[EOF]
+    ");
+    insta::assert_snapshot!(render(r#"parents.group_by(|c| c.author().email()).len()"#), @"2[EOF]");
+}
+
+#[test]
+fn test_templater_list_enumerate() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    work_dir.run_jj(["describe", "-m", "a"]).success();
+    work_dir.run_jj(["new", "root()", "-m", "b"]).success();
+    work_dir.run_jj(["new", "root()", "-m", "c"]).success();
+    work_dir
+        .run_jj(["new", "description(a)", "description(b)", "@"])
+        .success();
+    let render = |template| get_template_output(&work_dir, "@", template);
+
+    let template = r#"
+        parents.enumerate(1).map(|e| e.index() ++ ". " ++ e.item().description().first_line()).join("\n")
+    "#;
+    insta::assert_snapshot!(render(template.trim()), @r"
+    1. a
+    2. b
+    3. c[EOF]
+    ");
+    insta::assert_snapshot!(
+        render(r#"parents.enumerate().filter(|e| e.index() > 0).map(|e| e.item().description().first_line())"#),
+        @"b c[EOF]");
+}
+
+#[test]
+fn test_templater_let_binding() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    work_dir
+        .run_jj(["describe", "-m", "first\nsecond"])
+        .success();
+    let render = |template| get_template_output(&work_dir, "@", template);
+
+    insta::assert_snapshot!(
+        render(r#"let c = self; c.description().first_line() ++ " " ++ c.parents().len()"#),
+        @"first 1[EOF]");
+    insta::assert_snapshot!(
+        render(r#"let p = parents; p.map(|c| c.root()) ++ " " ++ p.len()"#),
+        @"true 1[EOF]");
+    insta::assert_snapshot!(
+        render(r#"let d = description.lines(); d.len() ++ ":" ++ d.join(",")"#),
+        @"2:first,second[EOF]");
+}
+
+#[test]
+fn test_templater_list_sort() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    work_dir.run_jj(["describe", "-m", "ccc"]).success();
+    work_dir.run_jj(["new", "root()", "-m", "a"]).success();
+    work_dir.run_jj(["new", "root()", "-m", "bb"]).success();
+    work_dir
+        .run_jj(["new", "description(ccc)", "description(a)", "@"])
+        .success();
+    let render = |template| get_template_output(&work_dir, "@", template);
+
+    insta::assert_snapshot!(
+        render(r#"parents.map(|c| c.description().first_line()).join(",")"#), @"ccc,a,bb[EOF]");
+    let template = r#"
+        parents.sort_by(|c| c.description().len()).map(|c| c.description().first_line()).join(",")
+    "#;
+    insta::assert_snapshot!(render(template.trim()), @"a,bb,ccc[EOF]");
+    // Commits can't be compared
+    insta::assert_snapshot!(render(r#"parents.sort().len()"#), @r"
+    ------- stderr -------
+    Error: Failed to parse template: Cannot compare items of type `Commit`
+    Caused by:  --> 1:9
+      |
+    1 | parents.sort().len()
+      |         ^--^
+      |
+      = Cannot compare items of type `Commit`
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[test]
+fn test_templater_limits() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    let render = |template: &str| get_template_output(&work_dir, "@-", template);
+
+    // Deeply nested expression
+    let render_with_max_depth = |depth: usize| {
+        let template = format!("{}0{}", "(1 + ".repeat(depth - 1), ")".repeat(depth - 1));
+        work_dir.run_jj([
+            "log",
+            "--no-graph",
+            "-r@-",
+            "--config=ui.template-max-depth=20",
+            "-T",
+            &template,
+        ])
+    };
+    insta::assert_snapshot!(render_with_max_depth(20), @"19[EOF]");
+    insta::assert_snapshot!(render_with_max_depth(21), @r"
+    ------- stderr -------
+    Error: Failed to parse template: Template expression is nested too deeply (limit: 20)
+    Caused by:  --> 1:97
+      |
+    1 | (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + (1 + 0))))))))))))))))))))
+      |                                                                                                 ^
+      |
+      = Template expression is nested too deeply (limit: 20)
+    [EOF]
+    [exit status: 1]
+    ");
+
+    // Explosive alias
+    test_env.add_config(indoc! {r#"
+        ui.template-max-output-size = 10
+        [template-aliases]
+        x0 = "'abc'"
+        x1 = "x0 ++ x0"
+        x2 = "x1 ++ x1"
+        x3 = "x2 ++ x2"
+    "#});
+    insta::assert_snapshot!(render("x1"), @"abcabc[EOF]");
+    insta::assert_snapshot!(render("x3"), @r"
+    abcabcabc[EOF]
+    ------- stderr -------
+    Error: Template output exceeds the limit of 10 bytes
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
 #[test]
 fn test_templater_alias() {
     let test_env = TestEnvironment::default();
@@ -530,6 +785,33 @@ fn test_templater_config_function() {
     ");
 }
 
+#[test]
+fn test_templater_term_width_function() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    let render = |template| get_template_output(&work_dir, "@-", template);
+    let render_with_columns = |template, columns: Option<&str>| {
+        work_dir.run_jj_with(|cmd| {
+            match columns {
+                Some(columns) => cmd.env("COLUMNS", columns),
+                None => cmd.env_remove("COLUMNS"),
+            };
+            cmd.args(["log", "--no-graph", "-r", "@-", "-T", template])
+        })
+    };
+
+    // $COLUMNS is set to 100 by the test environment
+    insta::assert_snapshot!(render("term_width()"), @"100[EOF]");
+    insta::assert_snapshot!(render("'-'.repeat(term_width() - 90)"), @"----------[EOF]");
+    insta::assert_snapshot!(render_with_columns("term_width()", Some("40")), @"40[EOF]");
+
+    // Falls back to the configured width since the output isn't a terminal
+    insta::assert_snapshot!(render_with_columns("term_width()", None), @"80[EOF]");
+    test_env.add_config("ui.default-term-width = 50");
+    insta::assert_snapshot!(render_with_columns("term_width()", None), @"50[EOF]");
+}
+
 #[must_use]
 fn get_template_output(work_dir: &TestWorkDir, rev: &str, template: &str) -> CommandOutput {
     work_dir.run_jj(["log", "--no-graph", "-r", rev, "-T", template])
@@ -547,3 +829,22 @@ fn get_colored_template_output(work_dir: &TestWorkDir, rev: &str, template: &str
         template,
     ])
 }
+
+#[test]
+fn test_templater_list_table() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    work_dir.run_jj(["describe", "-m", "first"]).success();
+    work_dir.run_jj(["new", "root()", "-m", "日本語"]).success();
+    work_dir
+        .run_jj(["new", "description(first)", "@", "-m", "merge"])
+        .success();
+    let render = |template| get_template_output(&work_dir, "@", template);
+
+    let template = r#"parents.table(|c| c.description().first_line(), |c| c.change_id().short(8))"#;
+    insta::assert_snapshot!(render(template), @r"
+    first  qpvuntsm
+    日本語 kkmpptxz[EOF]
+    ");
+}
@@ -397,9 +397,32 @@ pub struct StringLiteralParser<R> {
     pub escape_rule: R,
 }
 
+/// Escape sequence that doesn't represent a valid character.
+///
+/// This error is supposed to be converted to language-specific parse error
+/// type, where lifetime `'i` will be eliminated.
+#[derive(Clone, Debug)]
+pub struct InvalidStringEscape<'i> {
+    /// Error message.
+    pub message: String,
+    /// Span of the bad escape sequence.
+    pub span: pest::Span<'i>,
+}
+
 impl<R: RuleType> StringLiteralParser<R> {
     /// Parses the given string literal `pairs` into string.
+    ///
+    /// Panics if the string contains an invalid escape sequence such as
+    /// `\u{d800}`. Use [`Self::try_parse()`] if the grammar accepts escape
+    /// sequences which may not represent valid characters.
     pub fn parse(&self, pairs: Pairs<R>) -> String {
+        self.try_parse(pairs)
+            .unwrap_or_else(|err| panic!("{}: {:?}", err.message, err.span.as_str()))
+    }
+
+    /// Parses the given string literal `pairs` into string, reporting escape
+    /// sequences which don't represent valid characters.
+    pub fn try_parse<'i>(&self, pairs: Pairs<'i, R>) -> Result<String, InvalidStringEscape<'i>> {
         let mut result = String::new();
         for part in pairs {
             if part.as_rule() == self.content_rule {
@@ -418,13 +441,24 @@ impl<R: RuleType> StringLiteralParser<R> {
                             u8::from_str_radix(&hex[1..], 16).expect("hex characters"),
                         ));
                     }
+                    unicode if unicode.starts_with("u{") => {
+                        let hex = &unicode[2..unicode.len() - 1];
+                        let c = u32::from_str_radix(hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| InvalidStringEscape {
+                                message: "Invalid Unicode code point".to_owned(),
+                                span: part.as_span(),
+                            })?;
+                        result.push(c);
+                    }
                     char => panic!("invalid escape: \\{char:?}"),
                 }
             } else {
                 panic!("unexpected part of string: {part:?}");
             }
         }
-        result
+        Ok(result)
     }
 }
 